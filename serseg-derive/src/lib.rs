@@ -0,0 +1,139 @@
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{format_ident, quote};
+use syn::{Data, DeriveInput, Field, Fields, Ident, Type, parse_macro_input, spanned::Spanned};
+
+/// Types written in a single byte order, so they can't have an endianness
+const BYTE_ORDERLESS: &[&str] = &["u8", "i8"];
+
+/// Types with a builder method of the same name
+const SUPPORTED: &[&str] = &[
+    "u8", "i8", "u16", "i16", "u24", "u32", "i32", "u64", "i64", "u128", "i128", "f32", "f64",
+];
+
+/// Generates `into_sector`, which adds every field to a sector in declaration order.
+///
+/// Fields can be marked with:
+/// - `#[serseg(skip)]` to leave them out
+/// - `#[serseg(le)]` or `#[serseg(be)]` to ignore the builder's endianness
+#[proc_macro_derive(SerialSector, attributes(serseg))]
+pub fn derive_serial_sector(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new(
+            Span::call_site(),
+            "SerialSector can only be derived for structs",
+        ));
+    };
+
+    let calls = match &data.fields {
+        Fields::Named(fields) => fields
+            .named
+            .iter()
+            .filter_map(|field| {
+                let name = field.ident.clone()?;
+                field_call(field, quote!(#name)).transpose()
+            })
+            .collect::<syn::Result<Vec<_>>>()?,
+        Fields::Unnamed(fields) => fields
+            .unnamed
+            .iter()
+            .enumerate()
+            .filter_map(|(index, field)| {
+                let index = syn::Index::from(index);
+                field_call(field, quote!(#index)).transpose()
+            })
+            .collect::<syn::Result<Vec<_>>>()?,
+        Fields::Unit => Vec::new(),
+    };
+
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics #name #type_generics #where_clause {
+            /// Adds every field to a new sector, in declaration order
+            pub fn into_sector<S>(self) -> ::serseg::prelude::SerialSectorBuilder<S>
+            where
+                S: ::std::hash::Hash + ::std::cmp::Eq + ::std::clone::Clone + ::std::fmt::Debug,
+            {
+                ::serseg::prelude::SerialSectorBuilder::<S>::default()
+                    #(#calls)*
+            }
+        }
+    })
+}
+
+/// The builder call for the field, or `None` if it's skipped
+fn field_call(
+    field: &Field,
+    access: proc_macro2::TokenStream,
+) -> syn::Result<Option<proc_macro2::TokenStream>> {
+    let mut skip = false;
+    let mut suffix = None;
+
+    for attribute in field
+        .attrs
+        .iter()
+        .filter(|attribute| attribute.path().is_ident("serseg"))
+    {
+        attribute.parse_nested_meta(|meta| {
+            if meta.path.is_ident("skip") {
+                skip = true;
+            } else if meta.path.is_ident("le") {
+                suffix = Some("le");
+            } else if meta.path.is_ident("be") {
+                suffix = Some("be");
+            } else {
+                return Err(meta.error("Expected `skip`, `le`, or `be`"));
+            }
+
+            Ok(())
+        })?;
+    }
+
+    if skip {
+        return Ok(None);
+    }
+
+    let ty = type_name(&field.ty)
+        .filter(|ty| SUPPORTED.contains(&ty.to_string().as_str()))
+        .ok_or_else(|| {
+            syn::Error::new(
+                field.ty.span(),
+                "Unsupported field type for SerialSector; use `#[serseg(skip)]` to leave it out",
+            )
+        })?;
+
+    let method = match suffix {
+        Some(_) if BYTE_ORDERLESS.contains(&ty.to_string().as_str()) => {
+            return Err(syn::Error::new(
+                field.ty.span(),
+                format!("`{ty}` has no endianness"),
+            ));
+        }
+        Some(suffix) => format_ident!("{ty}_{suffix}"),
+        None => ty,
+    };
+
+    Ok(Some(quote!(.#method(self.#access))))
+}
+
+/// The last segment of a plain type path, like `u24` in `u24::u24`
+fn type_name(ty: &Type) -> Option<Ident> {
+    match ty {
+        Type::Path(path) if path.qself.is_none() => path
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident.clone()),
+        _ => None,
+    }
+}
@@ -0,0 +1,176 @@
+//! Fields are serialized once against these traits, then run either through tokio or with
+//! blocking std calls.
+
+use std::{
+    future::Future,
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::Path,
+    pin::pin,
+    task::{Context, Poll, Waker},
+};
+
+/// The most bytes of an external file held in memory at once
+const COPY_CHUNK_SIZE: usize = 8192;
+
+/// Where built bytes are written
+pub(crate) trait SectorWrite {
+    async fn write_all(&mut self, bytes: &[u8]) -> io::Result<()>;
+
+    async fn flush(&mut self) -> io::Result<()>;
+}
+
+/// How external files are read
+pub(crate) trait Files {
+    async fn len(path: &Path) -> io::Result<u64>;
+
+    /// Writes up to `len` bytes of the file from `start`, returning how many were written
+    async fn copy(
+        path: &Path,
+        start: u64,
+        len: u64,
+        buffer: &mut impl SectorWrite,
+    ) -> io::Result<u64>;
+}
+
+/// Blocking writes, which are done by the time they're first polled
+pub(crate) struct StdWriter<W>(pub(crate) W);
+
+impl<W: Write> SectorWrite for StdWriter<W> {
+    async fn write_all(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.0.write_all(bytes)
+    }
+
+    async fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+/// Counts the bytes written through to the inner writer
+pub(crate) struct Counted<'a, W> {
+    inner: &'a mut W,
+    pub(crate) written: usize,
+}
+
+impl<'a, W> Counted<'a, W> {
+    pub(crate) fn new(inner: &'a mut W) -> Self {
+        Self { inner, written: 0 }
+    }
+}
+
+impl<W: SectorWrite> SectorWrite for Counted<'_, W> {
+    async fn write_all(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.inner.write_all(bytes).await?;
+        self.written += bytes.len();
+
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush().await
+    }
+}
+
+/// Blocking file reads
+pub(crate) struct StdFiles;
+
+impl Files for StdFiles {
+    async fn len(path: &Path) -> io::Result<u64> {
+        Ok(std::fs::metadata(path)?.len())
+    }
+
+    async fn copy(
+        path: &Path,
+        start: u64,
+        len: u64,
+        buffer: &mut impl SectorWrite,
+    ) -> io::Result<u64> {
+        let mut file = std::fs::File::open(path)?;
+        file.seek(SeekFrom::Start(start))?;
+
+        let mut file = file.take(len);
+        let mut chunk = vec![0; COPY_CHUNK_SIZE];
+        let mut copied = 0;
+
+        loop {
+            let read = file.read(&mut chunk)?;
+
+            if read == 0 {
+                return Ok(copied);
+            }
+
+            buffer.write_all(&chunk[..read]).await?;
+            copied += read as u64;
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+pub(crate) use self::tokio::{TokioFiles, TokioWriter};
+
+#[cfg(feature = "tokio")]
+mod tokio {
+    use std::{io, path::Path};
+
+    use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWrite, AsyncWriteExt, SeekFrom};
+
+    use super::{COPY_CHUNK_SIZE, Files, SectorWrite};
+
+    pub(crate) struct TokioWriter<W>(pub(crate) W);
+
+    impl<W: AsyncWrite + Unpin> SectorWrite for TokioWriter<W> {
+        async fn write_all(&mut self, bytes: &[u8]) -> io::Result<()> {
+            self.0.write_all(bytes).await
+        }
+
+        async fn flush(&mut self) -> io::Result<()> {
+            self.0.flush().await
+        }
+    }
+
+    pub(crate) struct TokioFiles;
+
+    impl Files for TokioFiles {
+        async fn len(path: &Path) -> io::Result<u64> {
+            Ok(tokio::fs::metadata(path).await?.len())
+        }
+
+        async fn copy(
+            path: &Path,
+            start: u64,
+            len: u64,
+            buffer: &mut impl SectorWrite,
+        ) -> io::Result<u64> {
+            let mut file = tokio::fs::File::open(path).await?;
+            file.seek(SeekFrom::Start(start)).await?;
+
+            let mut file = file.take(len);
+            let mut chunk = vec![0; COPY_CHUNK_SIZE];
+            let mut copied = 0;
+
+            loop {
+                let read = file.read(&mut chunk).await?;
+
+                if read == 0 {
+                    return Ok(copied);
+                }
+
+                buffer.write_all(&chunk[..read]).await?;
+                copied += read as u64;
+            }
+        }
+    }
+}
+
+/// Files are read through tokio when it's enabled, for when there's no writer to follow
+#[cfg(feature = "tokio")]
+pub(crate) type DefaultFiles = TokioFiles;
+#[cfg(not(feature = "tokio"))]
+pub(crate) type DefaultFiles = StdFiles;
+
+/// Runs a future that only makes blocking calls, so it's finished the first time it's polled
+pub(crate) fn block_on<T>(future: impl Future<Output = T>) -> T {
+    match pin!(future).poll(&mut Context::from_waker(Waker::noop())) {
+        Poll::Ready(value) => value,
+        Poll::Pending => unreachable!("Blocking futures never wait"),
+    }
+}
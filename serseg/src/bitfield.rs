@@ -0,0 +1,166 @@
+use anyhow::anyhow;
+
+/// Which end of the bitfield the first bit goes in
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum BitOrder {
+    /// The first bit is the least significant, as in most flag bytes
+    #[default]
+    LsbFirst,
+    /// The first bit is the most significant, as read left to right
+    MsbFirst,
+}
+
+/// Packs flags and small values into an integer, in the order they're added.
+/// Values keep their own bit order, so `value(3, 0b110)` reads as `110` either way.
+///
+/// Errors are only reported once it's finished, so calls can be chained.
+#[derive(Debug)]
+pub struct BitfieldBuilder {
+    order: BitOrder,
+    /// In bits
+    width: usize,
+    /// The number of bits added so far
+    len: usize,
+    value: u64,
+    error: Option<anyhow::Error>,
+}
+
+impl BitfieldBuilder {
+    /// An empty bitfield `width` bits wide, which can't be wider than 64 bits
+    pub fn new(order: BitOrder, width: usize) -> Self {
+        Self {
+            order,
+            width,
+            len: 0,
+            value: 0,
+            error: (width > 64).then(|| anyhow!("Bitfield can't be wider than 64 bits: {width}")),
+        }
+    }
+
+    pub fn flag(self, set: bool) -> Self {
+        self.value(1, set.into())
+    }
+
+    /// Adds the lowest `bits` bits of the value, which can't have any higher bits set
+    pub fn value(mut self, bits: usize, value: u64) -> Self {
+        if self.error.is_some() {
+            return self;
+        }
+
+        if bits < 64 && value >> bits != 0 {
+            self.error = Some(anyhow!(
+                "Bitfield value doesn't fit in {bits} bits: {value:#b}"
+            ));
+            return self;
+        }
+
+        let Some(len) = self.len.checked_add(bits) else {
+            self.error = Some(anyhow!(
+                "Bitfield is wider than {} bits: {} + {bits} bits were added",
+                self.width,
+                self.len
+            ));
+            return self;
+        };
+
+        if len > self.width {
+            self.error = Some(anyhow!(
+                "Bitfield is wider than {} bits: {len} bits were added",
+                self.width
+            ));
+            return self;
+        }
+
+        let shift = match self.order {
+            BitOrder::LsbFirst => self.len,
+            BitOrder::MsbFirst => self.width - self.len - bits,
+        };
+
+        if bits != 0 {
+            self.value |= value << shift;
+        }
+
+        self.len = len;
+        self
+    }
+
+    /// The packed bits, with any that weren't added left unset
+    pub fn finish(self) -> anyhow::Result<u64> {
+        match self.error {
+            Some(error) => Err(error),
+            None => Ok(self.value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lsb_first() {
+        let bits = BitfieldBuilder::new(BitOrder::LsbFirst, 8)
+            .flag(true)
+            .flag(false)
+            .value(3, 0b101)
+            .finish();
+
+        assert_eq!(bits.unwrap(), 0b0001_0101);
+    }
+
+    #[test]
+    fn msb_first() {
+        let bits = BitfieldBuilder::new(BitOrder::MsbFirst, 8)
+            .flag(true)
+            .flag(false)
+            .value(3, 0b101)
+            .finish();
+
+        assert_eq!(bits.unwrap(), 0b1010_1000);
+    }
+
+    #[test]
+    fn too_wide() {
+        let error = BitfieldBuilder::new(BitOrder::LsbFirst, 8)
+            .value(6, 0)
+            .value(3, 0)
+            .flag(true)
+            .finish()
+            .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Bitfield is wider than 8 bits: 9 bits were added"
+        );
+    }
+
+    #[test]
+    fn too_wide_overflow() {
+        let error = BitfieldBuilder::new(BitOrder::LsbFirst, 8)
+            .flag(true)
+            .value(usize::MAX, 0)
+            .finish()
+            .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Bitfield is wider than 8 bits: 1 + {} bits were added",
+                usize::MAX
+            )
+        );
+    }
+
+    #[test]
+    fn value_too_wide() {
+        let error = BitfieldBuilder::new(BitOrder::LsbFirst, 8)
+            .value(2, 0b100)
+            .finish()
+            .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Bitfield value doesn't fit in 2 bits: 0b100"
+        );
+    }
+}
@@ -1,18 +1,40 @@
-use std::{hash::Hash, path::PathBuf};
+use std::{collections::HashMap, hash::Hash, io::Write, ops::Range, path::PathBuf, sync::Arc};
 
+use anyhow::{Context, bail};
 use indexmap::IndexMap;
-use log::debug;
-use tokio::io::{AsyncSeek, AsyncWrite, AsyncWriteExt};
+use log::{Level, debug, log_enabled, trace};
+#[cfg(feature = "tokio")]
+use tokio::io::{AsyncWrite, AsyncWriteExt};
 use u24::u24;
 
 use crate::{
-    field::{Scale, ScaleRounding, SerialField},
-    tracker::SerialTracker,
+    backend::{self, Counted, DefaultFiles, Files, SectorWrite, StdFiles, StdWriter},
+    bitfield::{BitOrder, BitfieldBuilder},
+    checksum::ChecksumAlgorithm,
+    compression::{self, Compression},
+    field::{DeferredValue, Endianness, Scale, ScaleRounding, SerialField},
+    hexdump::{self, Tee},
+    issue::{SersegIssue, field_name},
+    report::{SectorStats, SerialReport},
+    tracker::{self, SerialTracker},
+};
+#[cfg(feature = "tokio")]
+use crate::{
+    backend::{TokioFiles, TokioWriter},
+    symbols,
 };
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SerialBuilder<S: Hash + Eq + Clone + std::fmt::Debug> {
     sectors: IndexMap<S, SerialSectorBuilder<S>>,
+    /// Used by multi-byte integers, floats, and pointers without an explicit endianness
+    endianness: Endianness,
+    /// The most bytes the output can be
+    max_size: Option<usize>,
+    /// Kept as the last sector, whatever's added after it
+    trailer: Option<S>,
+    /// Checks sectors are written where they were tracked to, even in release builds
+    strict: bool,
 }
 
 // Default macro requires S to implement default
@@ -21,6 +43,10 @@ impl<S: Hash + Eq + Clone + std::fmt::Debug> Default for SerialBuilder<S> {
     fn default() -> Self {
         Self {
             sectors: IndexMap::default(),
+            endianness: Endianness::default(),
+            max_size: None,
+            trailer: None,
+            strict: false,
         }
     }
 }
@@ -28,6 +54,17 @@ impl<S: Hash + Eq + Clone + std::fmt::Debug> Default for SerialBuilder<S> {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SerialSectorBuilder<S: Hash + Eq> {
     pub(crate) fields: Vec<SerialField<S>>,
+    /// The sector starts on a multiple of this many bytes
+    pub(crate) alignment: usize,
+    /// Written before the sector to align it
+    pub(crate) pad: u8,
+    pub(crate) compression: Option<Compression>,
+    /// Labels for errors and hexdumps, by the index of their field
+    pub(crate) labels: HashMap<usize, String>,
+    /// Strings fail to build if they have any character that isn't ASCII
+    pub(crate) ascii_only: bool,
+    /// Padded at the end with the pad byte to exactly this many bytes
+    pub(crate) fixed_size: Option<(usize, u8)>,
 }
 
 // Default macro requires S to implement default
@@ -36,13 +73,184 @@ impl<S: Hash + Eq + std::fmt::Debug> Default for SerialSectorBuilder<S> {
     fn default() -> Self {
         Self {
             fields: Vec::default(),
+            alignment: 1,
+            pad: 0,
+            compression: None,
+            labels: HashMap::new(),
+            ascii_only: false,
+            fixed_size: None,
         }
     }
 }
 
 impl<S: Hash + Eq + Clone + std::fmt::Debug> SerialBuilder<S> {
+    /// Integer fields default to little-endian, the same as [`SerialBuilder::default`]
+    pub fn little_endian() -> Self {
+        Self::default()
+    }
+
+    /// Every multi-byte integer, float, and pointer defaults to big-endian.
+    /// LEB128 values, TI-OS reals, and checksums keep the byte order of their format.
+    pub fn big_endian() -> Self {
+        Self {
+            endianness: Endianness::Big,
+            ..Self::default()
+        }
+    }
+
+    /// Fails tracking, before anything is written, if the output would be over `max_size` bytes.
+    /// Trailing fills aren't written, so they don't count, like [`SerialBuilder::total_size`].
+    pub fn with_max_size(mut self, max_size: usize) -> Self {
+        self.max_size = Some(max_size);
+        self
+    }
+
+    /// Fails to build if any sector isn't written exactly where it was laid out,
+    /// rather than writing a corrupted output.
+    /// Always checked in debug builds.
+    pub fn strict(mut self) -> Self {
+        self.strict = true;
+        self
+    }
+
+    /// Appends the sector
+    ///
+    /// # Panics
+    /// In debug builds, if the key is already used. Otherwise, the existing sector is replaced but
+    /// keeps its position; use [`SerialBuilder::try_sector`] to handle duplicates.
     pub fn sector(mut self, key: S, builder: SerialSectorBuilder<S>) -> Self {
+        self.add_sector(key, builder);
+        self
+    }
+
+    /// Appends the sector, failing if the key is already used
+    pub fn try_sector(mut self, key: S, builder: SerialSectorBuilder<S>) -> anyhow::Result<Self> {
+        if self.sectors.contains_key(&key) {
+            bail!("Duplicate sector: {key:?}");
+        }
+
         self.sectors.insert(key, builder);
+        self.keep_trailer_last();
+        Ok(self)
+    }
+
+    /// Appends the sector in place, for adding sectors in a loop
+    ///
+    /// # Panics
+    /// The same as [`SerialBuilder::sector`]
+    pub fn add_sector(&mut self, key: S, builder: SerialSectorBuilder<S>) {
+        debug_assert!(
+            !self.sectors.contains_key(&key),
+            "Duplicate sector: {key:?}"
+        );
+        self.sectors.insert(key, builder);
+        self.keep_trailer_last();
+    }
+
+    /// Appends the sector and keeps it last, even as more sectors are added, such as for a
+    /// checksum that has to end the output.
+    /// Fails if there's already a trailer or the key is already used.
+    pub fn trailer(mut self, key: S, builder: SerialSectorBuilder<S>) -> anyhow::Result<Self> {
+        if let Some(trailer) = &self.trailer {
+            bail!("Trailer is already set: {trailer:?}");
+        }
+
+        self = self.try_sector(key.clone(), builder)?;
+        self.trailer = Some(key);
+        Ok(self)
+    }
+
+    fn keep_trailer_last(&mut self) {
+        if let Some(index) = self
+            .trailer
+            .as_ref()
+            .and_then(|trailer| self.sectors.get_index_of(trailer))
+        {
+            self.sectors.move_index(index, self.sectors.len() - 1);
+        }
+    }
+
+    /// Appends the sector, padding with `pad` before it so it starts on a multiple of `alignment`
+    /// bytes
+    ///
+    /// # Panics
+    /// The same as [`SerialBuilder::sector`]
+    pub fn sector_aligned(
+        self,
+        key: S,
+        builder: SerialSectorBuilder<S>,
+        alignment: usize,
+        pad: u8,
+    ) -> Self {
+        self.sector(
+            key,
+            SerialSectorBuilder {
+                alignment,
+                pad,
+                ..builder
+            },
+        )
+    }
+
+    /// Appends the sector, padded at the end with `pad` to exactly `size` bytes.
+    /// Fails to build if its fields take up more than that.
+    ///
+    /// # Panics
+    /// The same as [`SerialBuilder::sector`]
+    pub fn sector_sized(
+        self,
+        key: S,
+        builder: SerialSectorBuilder<S>,
+        size: usize,
+        pad: u8,
+    ) -> Self {
+        self.sector(
+            key,
+            SerialSectorBuilder {
+                fixed_size: Some((size, pad)),
+                ..builder
+            },
+        )
+    }
+
+    /// Appends the sector, compressed once it's built on its own.
+    ///
+    /// Pointers in the sector can only point within it, as it's laid out separately from the
+    /// rest of the output. Pointers from outside can only point to its start, where the
+    /// compressed data is.
+    ///
+    /// # Panics
+    /// The same as [`SerialBuilder::sector`]
+    pub fn sector_compressed(
+        self,
+        key: S,
+        builder: SerialSectorBuilder<S>,
+        compression: Compression,
+    ) -> Self {
+        self.sector(
+            key,
+            SerialSectorBuilder {
+                compression: Some(compression),
+                ..builder
+            },
+        )
+    }
+
+    /// Appends every sector in order, reserving space for them all up front
+    ///
+    /// # Panics
+    /// The same as [`SerialBuilder::sector`]
+    pub fn sectors_from_iter(
+        mut self,
+        sectors: impl IntoIterator<Item = (S, SerialSectorBuilder<S>)>,
+    ) -> Self {
+        let sectors = sectors.into_iter();
+        self.sectors.reserve(sectors.size_hint().0);
+
+        for (key, builder) in sectors {
+            self.add_sector(key, builder);
+        }
+
         self
     }
 
@@ -50,34 +258,952 @@ impl<S: Hash + Eq + Clone + std::fmt::Debug> SerialBuilder<S> {
         self.sector(key, SerialSectorBuilder::<S>::default())
     }
 
-    pub async fn build(
+    /// Inserts the sector directly before `anchor`, moving it if it already exists
+    pub fn sector_before(
+        self,
+        anchor: &S,
+        key: S,
+        builder: SerialSectorBuilder<S>,
+    ) -> anyhow::Result<Self> {
+        self.sector_at(anchor, 0, key, builder)
+    }
+
+    /// Inserts the sector directly after `anchor`, moving it if it already exists
+    pub fn sector_after(
+        self,
+        anchor: &S,
+        key: S,
+        builder: SerialSectorBuilder<S>,
+    ) -> anyhow::Result<Self> {
+        self.sector_at(anchor, 1, key, builder)
+    }
+
+    fn sector_at(
+        mut self,
+        anchor: &S,
+        shift: usize,
+        key: S,
+        builder: SerialSectorBuilder<S>,
+    ) -> anyhow::Result<Self> {
+        if key == *anchor {
+            bail!("Can't insert sector relative to itself: {key:?}");
+        }
+
+        if shift != 0 && self.trailer.as_ref() == Some(anchor) {
+            bail!("Can't insert sector after the trailer: {key:?}");
+        }
+
+        if self.trailer.as_ref() == Some(&key) {
+            bail!("Can't move the trailer: {key:?}");
+        }
+
+        self.sectors.shift_remove(&key);
+        let index = self
+            .sectors
+            .get_index_of(anchor)
+            .with_context(|| format!("Failed to find sector to insert next to: {anchor:?}"))?;
+        self.sectors.shift_insert(index + shift, key, builder);
+
+        Ok(self)
+    }
+
+    /// Adds every sector of the inner builder, in order, with their keys mapped to this builder's.
+    /// References between the inner sectors are mapped too, so they still resolve.
+    ///
+    /// Integer fields without an explicit endianness use this builder's.
+    /// The inner trailer becomes this builder's trailer.
+    /// Fails if both builders have a trailer or a mapped key is already used.
+    ///
+    /// Fails on deferred fields, as their closures would have to hold onto the inner keys;
+    /// use [`SerialBuilder::sector_nested_deferred`] for those.
+    pub fn sector_nested<T: Hash + Eq + Clone + std::fmt::Debug>(
         self,
-        buffer: &mut (impl AsyncWrite + Unpin + AsyncSeek),
+        map: impl Fn(T) -> S,
+        inner: SerialBuilder<T>,
+    ) -> anyhow::Result<Self> {
+        self.nest(&map, inner, &|_| {
+            bail!("Deferred fields can only be nested with `sector_nested_deferred`")
+        })
+    }
+
+    /// Nests like [`SerialBuilder::sector_nested`], along with deferred fields.
+    /// Their closures are given a report of the inner sectors under the inner keys.
+    pub fn sector_nested_deferred<T: Hash + Eq + Clone + std::fmt::Debug + Send + Sync + 'static>(
+        self,
+        map: impl Fn(T) -> S,
+        inner: SerialBuilder<T>,
+    ) -> anyhow::Result<Self>
+    where
+        S: Send + Sync + 'static,
+    {
+        let keys = inner
+            .sectors
+            .keys()
+            .map(|key| (key.clone(), map(key.clone())))
+            .collect::<Arc<[_]>>();
+
+        self.nest(&map, inner, &|value: DeferredValue<T>| {
+            Ok(value.map_keys(&keys))
+        })
+    }
+
+    fn nest<T: Hash + Eq + Clone + std::fmt::Debug>(
+        mut self,
+        map: &impl Fn(T) -> S,
+        inner: SerialBuilder<T>,
+        deferred: &impl Fn(DeferredValue<T>) -> anyhow::Result<DeferredValue<S>>,
+    ) -> anyhow::Result<Self> {
+        let trailer = inner.trailer.map(map);
+
+        if let (Some(_), Some(existing)) = (&trailer, &self.trailer) {
+            bail!("Trailer is already set: {existing:?}");
+        }
+
+        for (key, sector) in inner.sectors {
+            let fields = sector
+                .fields
+                .into_iter()
+                .map(|field| field.map_keys(map, deferred))
+                .collect::<anyhow::Result<_>>()
+                .with_context(|| format!("Failed to nest sector: {key:?}"))?;
+
+            self = self.try_sector(
+                map(key),
+                SerialSectorBuilder {
+                    fields,
+                    alignment: sector.alignment,
+                    pad: sector.pad,
+                    compression: sector.compression,
+                    labels: sector.labels,
+                    ascii_only: sector.ascii_only,
+                    fixed_size: sector.fixed_size,
+                },
+            )?;
+        }
+
+        if self.trailer.is_none() {
+            self.trailer = trailer;
+        }
+
+        Ok(self)
+    }
+
+    /// Writes every sector in order. Padding is written rather than seeked over,
+    /// so the buffer doesn't need to be seekable.
+    ///
+    /// Fills are only written once more data follows them, so a trailing fill doesn't extend the
+    /// output, and isn't counted in its total size.
+    #[cfg(feature = "tokio")]
+    pub async fn build(&self, buffer: &mut (impl AsyncWrite + Unpin)) -> anyhow::Result<()> {
+        let tracker = self.track::<TokioFiles>().await?;
+        self.build_tracked::<TokioFiles>(&mut TokioWriter(buffer), &tracker)
+            .await
+    }
+
+    /// Builds like [`SerialBuilder::build`], but with blocking writes and file reads, so it
+    /// doesn't need an async runtime
+    pub fn build_std(&self, buffer: &mut impl Write) -> anyhow::Result<()> {
+        backend::block_on(async {
+            let tracker = self.track::<StdFiles>().await?;
+            self.build_tracked::<StdFiles>(&mut StdWriter(buffer), &tracker)
+                .await
+        })
+    }
+
+    async fn track<F: Files>(&self) -> anyhow::Result<SerialTracker<S>> {
+        SerialTracker::new::<F>(&self.sectors, self.max_size, self.endianness).await
+    }
+
+    /// The size of the output in bytes, without building it.
+    /// External files aren't read, though those without a declared size are checked.
+    ///
+    /// The maximum size isn't enforced, so oversized outputs can still be measured.
+    pub async fn total_size(&self) -> anyhow::Result<usize> {
+        Ok(
+            SerialTracker::new::<DefaultFiles>(&self.sectors, None, self.endianness)
+                .await?
+                .total_size(),
+        )
+    }
+
+    /// The size and makeup of every sector, in output order, without building anything.
+    /// External file sizes are read with blocking calls, and compressed sectors are compressed.
+    ///
+    /// The maximum size isn't enforced, so oversized outputs can still be measured.
+    pub fn stats(&self) -> anyhow::Result<Vec<(S, SectorStats)>> {
+        let tracker = backend::block_on(SerialTracker::new::<StdFiles>(
+            &self.sectors,
+            None,
+            self.endianness,
+        ))?;
+
+        self.sectors
+            .iter()
+            .map(|(key, sector)| {
+                let count = |matches: fn(&SerialField<S>) -> bool| {
+                    sector.fields.iter().filter(|field| matches(field)).count()
+                };
+
+                Ok((
+                    key.clone(),
+                    SectorStats {
+                        size: tracker.sector_size(key)?,
+                        field_count: sector.fields.len(),
+                        pointers: count(|field| {
+                            matches!(
+                                field,
+                                SerialField::Dynamic { .. }
+                                    | SerialField::DynamicEnd { .. }
+                                    | SerialField::DynamicSelf { .. }
+                                    | SerialField::DynamicSigned { .. }
+                                    | SerialField::DynamicAuto { .. }
+                            )
+                        }),
+                        externals: count(|field| matches!(field, SerialField::External { .. })),
+                    },
+                ))
+            })
+            .collect()
+    }
+
+    /// The layout as text, with a line for each sector and an indented line for each of its
+    /// fields. Pointers also show the field they point to and the value written for them.
+    /// Compressed sectors are shown as their compressed bytes.
+    ///
+    /// Laid out like [`SerialBuilder::stats`], without building anything.
+    /// External file sizes are read with blocking calls; use [`SerialBuilder::describe_async`]
+    /// inside an async runtime.
+    pub fn describe(&self) -> anyhow::Result<String> {
+        let tracker = backend::block_on(SerialTracker::new::<StdFiles>(
+            &self.sectors,
+            None,
+            self.endianness,
+        ))?;
+
+        self.describe_tracked(&tracker)
+    }
+
+    /// Describes the layout like [`SerialBuilder::describe`], reading external file sizes
+    /// asynchronously
+    #[cfg(feature = "tokio")]
+    pub async fn describe_async(&self) -> anyhow::Result<String> {
+        let tracker =
+            SerialTracker::new::<TokioFiles>(&self.sectors, None, self.endianness).await?;
+
+        self.describe_tracked(&tracker)
+    }
+
+    fn describe_tracked(&self, tracker: &SerialTracker<S>) -> anyhow::Result<String> {
+        let mut description = String::new();
+
+        for (sector_id, sector) in &self.sectors {
+            let mut offset = tracker.offset_from_origin(sector_id)?;
+
+            description.push_str(&format!(
+                "Sector {sector_id:?} at {offset:06X}, {} bytes",
+                tracker.sector_size(sector_id)?
+            ));
+
+            match tracker.sector_padding(sector_id) {
+                0 => {}
+                padding => description.push_str(&format!(", after {padding} bytes of padding")),
+            }
+
+            if let Some(compression) = sector.compression {
+                description.push_str(&format!(", compressed with {compression:?}"));
+            }
+
+            description.push('\n');
+
+            for (index, field) in tracker.fields(sector_id, sector).iter().enumerate() {
+                let size = field.calculate_size(offset, tracker)?;
+
+                description.push_str(&format!(
+                    "  Field {} at {offset:06X}: {}, {size} bytes",
+                    field_name(index, sector.field_label(index)),
+                    field.kind()
+                ));
+
+                match field.resolve_pointer(&self.sectors, tracker, offset)? {
+                    Some((target, Some(target_index), value)) => description
+                        .push_str(&format!(", to {target:?} field {target_index} = {value}")),
+                    Some((target, None, value)) => {
+                        description.push_str(&format!(", to the end of {target:?} = {value}"))
+                    }
+                    None => {}
+                }
+
+                description.push('\n');
+                offset += size;
+            }
+        }
+
+        Ok(description)
+    }
+
+    /// Builds like [`SerialBuilder::build`], returning where each sector landed
+    #[cfg(feature = "tokio")]
+    pub async fn build_with_report(
+        &self,
+        buffer: &mut (impl AsyncWrite + Unpin),
+    ) -> anyhow::Result<SerialReport<S>> {
+        let tracker = self.track::<TokioFiles>().await?;
+        let report = SerialReport::new(&self.sectors, &tracker)?;
+        self.build_tracked::<TokioFiles>(&mut TokioWriter(buffer), &tracker)
+            .await?;
+
+        Ok(report)
+    }
+
+    /// Builds like [`SerialBuilder::build`], also writing each sector's offset as a fasmg symbol,
+    /// such as `font_header := 0x000010`.
+    ///
+    /// `namer` must turn every key into a unique, valid identifier.
+    #[cfg(feature = "tokio")]
+    pub async fn build_with_symbols(
+        &self,
+        buffer: &mut (impl AsyncWrite + Unpin),
+        symbol_buffer: &mut (impl AsyncWrite + Unpin),
+        namer: impl Fn(&S) -> String,
+    ) -> anyhow::Result<()> {
+        let tracker = self.track::<TokioFiles>().await?;
+        let symbols = symbols::fasmg(&self.symbols(&tracker, namer)?);
+        self.build_tracked::<TokioFiles>(&mut TokioWriter(buffer), &tracker)
+            .await?;
+
+        symbol_buffer.write_all(symbols.as_bytes()).await?;
+        symbol_buffer.flush().await?;
+
+        Ok(())
+    }
+
+    /// Builds like [`SerialBuilder::build`], also writing each sector's offset as a C define,
+    /// such as `#define FONT_HEADER_OFFSET 0x000010`, wrapped in the include guard `guard`.
+    ///
+    /// Names are uppercased, and any character that can't be in an identifier becomes `_`.
+    #[cfg(feature = "tokio")]
+    pub async fn build_with_c_header(
+        &self,
+        buffer: &mut (impl AsyncWrite + Unpin),
+        header_buffer: &mut (impl AsyncWrite + Unpin),
+        guard: &str,
+        namer: impl Fn(&S) -> String,
+    ) -> anyhow::Result<()> {
+        let tracker = self.track::<TokioFiles>().await?;
+        let symbols = self.symbols(&tracker, |key| {
+            symbols::c_identifier(&format!("{}_offset", namer(key)))
+        })?;
+        let header = symbols::c_header(&symbols::c_identifier(guard), &symbols);
+        self.build_tracked::<TokioFiles>(&mut TokioWriter(buffer), &tracker)
+            .await?;
+
+        header_buffer.write_all(header.as_bytes()).await?;
+        header_buffer.flush().await?;
+
+        Ok(())
+    }
+
+    /// Every sector's name and offset, checking that no two sectors share a name
+    #[cfg(feature = "tokio")]
+    fn symbols(
+        &self,
+        tracker: &SerialTracker<S>,
+        namer: impl Fn(&S) -> String,
+    ) -> anyhow::Result<Vec<(String, usize)>> {
+        let mut names = HashMap::with_capacity(self.sectors.len());
+
+        self.sectors
+            .keys()
+            .map(|key| {
+                let name = namer(key);
+
+                if let Some(existing) = names.insert(name.clone(), key) {
+                    bail!("Duplicate symbol name for sectors {existing:?} and {key:?}: {name}");
+                }
+
+                Ok((name, tracker.offset_from_origin(key)?))
+            })
+            .collect()
+    }
+
+    /// Builds into a vector sized for the whole output up front
+    pub async fn build_to_vec(&self) -> anyhow::Result<Vec<u8>> {
+        let tracker = self.track::<DefaultFiles>().await?;
+        let mut buffer = Vec::with_capacity(tracker.total_size());
+        self.build_tracked::<DefaultFiles>(&mut StdWriter(&mut buffer), &tracker)
+            .await?;
+
+        Ok(buffer)
+    }
+
+    /// Checks every field without writing anything, collecting every problem instead of stopping
+    /// at the first, unlike building
+    pub async fn validate(&self) -> Result<(), Vec<SersegIssue<S>>> {
+        let (tracker, mut issues) = SerialTracker::new_lenient::<DefaultFiles>(
+            &self.sectors,
+            self.max_size,
+            self.endianness,
+        )
+        .await;
+        let mut sink = StdWriter(std::io::sink());
+
+        for (sector_id, sector) in &self.sectors {
+            let Ok(mut offset) = tracker.offset_from_origin(sector_id) else {
+                continue;
+            };
+
+            for (index, field) in tracker.fields(sector_id, sector).iter().enumerate() {
+                // Problems with the size were found when tracking
+                let Ok(size) = field.calculate_size(offset, &tracker) else {
+                    continue;
+                };
+
+                let built = match (sector.check_field(field), field) {
+                    // Only checked, so the file isn't read through
+                    (
+                        Ok(()),
+                        SerialField::External {
+                            path,
+                            offset: start,
+                            ..
+                        },
+                    ) => SerialField::<S>::check_external::<DefaultFiles>(path, *start, size)
+                        .await
+                        .map(|_| ()),
+                    (Ok(()), _) => {
+                        field
+                            .build::<DefaultFiles>(
+                                &mut sink,
+                                &self.sectors,
+                                &tracker,
+                                self.endianness,
+                                offset,
+                            )
+                            .await
+                    }
+                    (Err(error), _) => Err(error),
+                };
+
+                if let Err(error) = built {
+                    issues.push(SersegIssue {
+                        sector: sector_id.clone(),
+                        field: Some(index),
+                        label: sector.field_label(index).map(str::to_owned),
+                        error,
+                    });
+                }
+
+                offset += size;
+            }
+        }
+
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(issues)
+        }
+    }
+
+    /// Builds the sector on its own, as it is once it's decompressed, then compresses it
+    pub(crate) async fn compress_sector<F: Files>(
+        key: &S,
+        sector: &SerialSectorBuilder<S>,
+        compression: Compression,
+        endianness: Endianness,
+    ) -> anyhow::Result<Vec<u8>> {
+        let builder = Self {
+            sectors: IndexMap::from([(
+                key.clone(),
+                // Padded to its fixed size once compressed
+                SerialSectorBuilder {
+                    compression: None,
+                    fixed_size: None,
+                    ..sector.clone()
+                },
+            )]),
+            endianness,
+            max_size: None,
+            trailer: None,
+            strict: false,
+        };
+
+        // Tracked directly, as there's nothing left to compress
+        let (tracker, issues) =
+            SerialTracker::track_auto::<F>(&builder.sectors, None, HashMap::new()).await;
+
+        if let Some(error) = tracker::first_error(issues) {
+            return Err(error);
+        }
+
+        let mut data = Vec::with_capacity(tracker.total_size());
+        builder
+            .build_tracked::<F>(&mut StdWriter(&mut data), &tracker)
+            .await?;
+
+        compression.compress(&data)
+    }
+
+    async fn build_tracked<F: Files>(
+        &self,
+        buffer: &mut impl SectorWrite,
+        tracker: &SerialTracker<S>,
+    ) -> anyhow::Result<()> {
+        let checksums = self.checksums(tracker)?;
+
+        // Checksums can cover data written after them, so everything is written to memory first
+        if checksums.is_empty() {
+            self.write_sectors::<F>(buffer, tracker).await?;
+        } else {
+            let mut output = Vec::with_capacity(tracker.total_size());
+            self.write_sectors::<F>(&mut StdWriter(&mut output), tracker)
+                .await?;
+
+            for checksum in checksums {
+                checksum.patch(&mut output);
+            }
+
+            buffer.write_all(&output).await?;
+        }
+
+        buffer.flush().await?;
+
+        Ok(())
+    }
+
+    /// Finds every checksum field and the range of the output it covers
+    fn checksums(&self, tracker: &SerialTracker<S>) -> anyhow::Result<Vec<ChecksumPatch>> {
+        let mut checksums = Vec::new();
+
+        for (sector_id, sector) in &self.sectors {
+            let mut offset = tracker.offset_from_origin(sector_id)?;
+
+            for field in tracker.fields(sector_id, sector) {
+                if let SerialField::Checksum {
+                    first,
+                    last,
+                    algorithm,
+                } = field
+                {
+                    let context = || {
+                        format!(
+                            "Invalid checksum range in sector {sector_id:?}: {first:?} to {last:?}"
+                        )
+                    };
+                    let start = tracker.offset_from_origin(first).with_context(context)?;
+                    let end = tracker.offset_from_origin(last).with_context(context)?
+                        + tracker.sector_size(last).with_context(context)?;
+
+                    if start > end {
+                        bail!("{}; the last sector is before the first", context());
+                    }
+
+                    if (start..end).contains(&offset) {
+                        bail!("{}; a checksum can't cover itself", context());
+                    }
+
+                    checksums.push(ChecksumPatch {
+                        slot: offset..offset + field.calculate_size(offset, tracker)?,
+                        range: start..end,
+                        algorithm: *algorithm,
+                    });
+                }
+
+                offset += field.calculate_size(offset, tracker)?;
+            }
+        }
+
+        // A checksum covering another is patched after it
+        let mut ordered = Vec::with_capacity(checksums.len());
+
+        while !checksums.is_empty() {
+            let ready = checksums
+                .iter()
+                .position(|checksum| !checksums.iter().any(|other| checksum.covers(&other.slot)))
+                .context("Checksums can't cover each other")?;
+
+            ordered.push(checksums.remove(ready));
+        }
+
+        Ok(ordered)
+    }
+
+    /// With debug logging on, each sector's bytes are logged as a hexdump as they're written,
+    /// along with any padding or fill before it that's written with it.
+    /// Labeled fields are listed after it with where they start and end.
+    /// Checksums are still zero, as they're patched in afterwards.
+    ///
+    /// In strict mode or debug builds, each sector is checked to start and end where it was
+    /// tracked to.
+    async fn write_sectors<F: Files>(
+        &self,
+        buffer: &mut impl SectorWrite,
+        tracker: &SerialTracker<S>,
+    ) -> anyhow::Result<()> {
+        let hexdump = log_enabled!(Level::Debug);
+        let checked = self.strict || cfg!(debug_assertions);
+        let mut buffer = Counted::new(buffer);
+        let mut pending_fill = PendingFill::default();
+
+        for (sector_id, sector) in &self.sectors {
+            let start = tracker.offset_from_origin(sector_id)?;
+            // Written like a fill, so it's dropped if nothing follows
+            pending_fill.push(sector.pad, tracker.sector_padding(sector_id));
+
+            // Pending fills count, as they're written once anything follows them
+            let position = buffer.written + pending_fill.len;
+
+            if checked && position != start {
+                bail!(
+                    "Sector {sector_id:?} starts at {position} in the output, but was tracked to \
+                     start at {start}"
+                );
+            }
+
+            if hexdump {
+                let written = buffer.written;
+                let mut tee = Tee::new(&mut buffer);
+                self.write_sector::<F>(
+                    &mut tee,
+                    sector_id,
+                    sector,
+                    tracker,
+                    start,
+                    &mut pending_fill,
+                )
+                .await?;
+
+                let mut dump = hexdump::hexdump(&format!("{sector_id:?}"), written, &tee.written);
+
+                for index in
+                    (0..sector.fields.len()).filter(|&index| sector.field_label(index).is_some())
+                {
+                    let start = tracker.field_position(sector_id, index, &self.sectors)?;
+                    let end = tracker.field_position(sector_id, index + 1, &self.sectors)?;
+                    dump.push_str(&format!(
+                        "\n{sector_id:?} field {}: {start:06X}..{end:06X}",
+                        field_name(index, sector.field_label(index))
+                    ));
+                }
+
+                debug!(
+                    "Sector {sector_id:?} wrote {} bytes:\n{dump}",
+                    tee.written.len()
+                );
+            } else {
+                self.write_sector::<F>(
+                    &mut buffer,
+                    sector_id,
+                    sector,
+                    tracker,
+                    start,
+                    &mut pending_fill,
+                )
+                .await?;
+            }
+
+            if checked {
+                let written = buffer.written + pending_fill.len - start;
+                let size = tracker.sector_size(sector_id)?;
+
+                if written != size {
+                    bail!(
+                        "Sector {sector_id:?} wrote {written} bytes, but was tracked to take up \
+                         {size} bytes"
+                    );
+                }
+            }
+        }
+
+        // Anything still pending is a trailing fill, which isn't written
+        if checked && buffer.written != tracker.total_size() {
+            bail!(
+                "Wrote {} bytes, but the output was tracked to be {} bytes",
+                buffer.written,
+                tracker.total_size()
+            );
+        }
+
+        Ok(())
+    }
+
+    async fn write_sector<F: Files>(
+        &self,
+        buffer: &mut impl SectorWrite,
+        sector_id: &S,
+        sector: &SerialSectorBuilder<S>,
+        tracker: &SerialTracker<S>,
+        start: usize,
+        pending_fill: &mut PendingFill,
     ) -> anyhow::Result<()> {
-        let tracker = SerialTracker::new(&self.sectors).await?;
+        sector
+            .build::<F>(
+                buffer,
+                sector_id,
+                &self.sectors,
+                tracker,
+                self.endianness,
+                start,
+                pending_fill,
+            )
+            .await
+            .with_context(|| format!("Failed to build sector: {sector_id:#?}"))?;
+        trace!("Built sector: {sector_id:#?}");
+
+        Ok(())
+    }
+}
+
+/// Where to write a checksum and what it covers
+#[derive(Debug)]
+struct ChecksumPatch {
+    /// Where the checksum is written
+    slot: Range<usize>,
+    range: Range<usize>,
+    algorithm: ChecksumAlgorithm,
+}
+
+impl ChecksumPatch {
+    fn covers(&self, slot: &Range<usize>) -> bool {
+        self.range.start < slot.end && slot.start < self.range.end
+    }
+
+    fn patch(&self, output: &mut [u8]) {
+        // A trailing fill isn't written, so the range may extend past the end
+        let end = self.range.end.min(output.len());
+        let value = self
+            .algorithm
+            .compute(&output[self.range.start.min(end)..end]);
+        output[self.slot.start..self.slot.start + value.len()].copy_from_slice(&value);
+    }
+}
+
+/// About how many bytes of a fill are written at once
+const FILL_CHUNK_SIZE: usize = 4096;
+
+/// Fills and padding that haven't been written yet, as they're dropped if nothing follows them
+#[derive(Debug, Default)]
+struct PendingFill {
+    /// Each pad byte and how many times it's repeated
+    runs: Vec<(u8, usize)>,
+    len: usize,
+}
+
+impl PendingFill {
+    fn push(&mut self, pad: u8, count: usize) {
+        if count == 0 {
+            return;
+        }
+
+        match self.runs.last_mut() {
+            Some((last, last_count)) if *last == pad => *last_count += count,
+            _ => self.runs.push((pad, count)),
+        }
+
+        self.len += count;
+    }
+
+    /// Writes the pending bytes a chunk at a time, so a large fill is never held in memory
+    async fn write(&mut self, buffer: &mut impl SectorWrite) -> anyhow::Result<()> {
+        for (pad, count) in self.runs.drain(..) {
+            let chunk = [pad; FILL_CHUNK_SIZE];
+            let mut remaining = count;
+
+            while remaining != 0 {
+                let size = remaining.min(FILL_CHUNK_SIZE);
+                buffer.write_all(&chunk[..size]).await?;
+                remaining -= size;
+            }
+        }
+
+        self.len = 0;
+        Ok(())
+    }
+}
+
+macro_rules! int_field {
+    ($field_name: ident, $unsigned: ident) => {
+        pub fn $unsigned(self, value: impl Into<$unsigned>) -> Self {
+            self.field(SerialField::$field_name(value.into()))
+        }
+
+        /// Writes `default` if there's no value
+        pub fn ${concat(maybe_, $unsigned)}(self, value: Option<$unsigned>, default: $unsigned) -> Self {
+            self.$unsigned(value.unwrap_or(default))
+        }
+    };
+    ($field_name: ident, $unsigned: ident, $signed: ident) => {
+        int_field!($field_name, $unsigned);
+
+        pub fn $signed(self, value: impl Into<$signed>) -> Self {
+            self.field(SerialField::$field_name(value.into() as $unsigned))
+        }
+    };
+}
+
+/// Multi-byte integers use the builder's endianness unless a `_le` or `_be` variant is used
+macro_rules! endian_int_field {
+    ($field_name: ident, $value: ident => $name: ident) => {
+        pub fn $name(self, value: impl Into<$value>) -> Self {
+            self.field(SerialField::$field_name(value.into(), None))
+        }
+
+        pub fn ${concat($name, _le)}(self, value: impl Into<$value>) -> Self {
+            self.field(SerialField::$field_name(value.into(), Some(Endianness::Little)))
+        }
+
+        pub fn ${concat($name, _be)}(self, value: impl Into<$value>) -> Self {
+            self.field(SerialField::$field_name(value.into(), Some(Endianness::Big)))
+        }
+    };
+    ($field_name: ident, $unsigned: ident, $signed: ident) => {
+        pub fn $unsigned(self, value: impl Into<$unsigned>) -> Self {
+            self.field(SerialField::$field_name(value.into(), None))
+        }
+
+        /// Writes `default` if there's no value
+        pub fn ${concat(maybe_, $unsigned)}(self, value: Option<$unsigned>, default: $unsigned) -> Self {
+            self.$unsigned(value.unwrap_or(default))
+        }
+
+        pub fn ${concat($unsigned, _le)}(self, value: impl Into<$unsigned>) -> Self {
+            self.field(SerialField::$field_name(value.into(), Some(Endianness::Little)))
+        }
+
+        pub fn ${concat($unsigned, _be)}(self, value: impl Into<$unsigned>) -> Self {
+            self.field(SerialField::$field_name(value.into(), Some(Endianness::Big)))
+        }
+
+        pub fn $signed(self, value: impl Into<$signed>) -> Self {
+            self.$unsigned(value.into() as $unsigned)
+        }
+
+        pub fn ${concat($signed, _le)}(self, value: impl Into<$signed>) -> Self {
+            self.${concat($unsigned, _le)}(value.into() as $unsigned)
+        }
+
+        pub fn ${concat($signed, _be)}(self, value: impl Into<$signed>) -> Self {
+            self.${concat($unsigned, _be)}(value.into() as $unsigned)
+        }
+    };
+}
+
+/// Floats use the builder's endianness unless a `_le` or `_be` variant is used
+macro_rules! float_field {
+    ($field_name: ident, $float: ident) => {
+        pub fn $float(self, value: $float) -> Self {
+            self.field(SerialField::$field_name(value.to_bits(), None))
+        }
+
+        pub fn ${concat($float, _le)}(self, value: $float) -> Self {
+            self.field(SerialField::$field_name(value.to_bits(), Some(Endianness::Little)))
+        }
+
+        pub fn ${concat($float, _be)}(self, value: $float) -> Self {
+            self.field(SerialField::$field_name(value.to_bits(), Some(Endianness::Big)))
+        }
+    };
+}
+
+macro_rules! sector_size_field {
+    ($name: ident, $bytes: literal) => {
+        /// The size of the sector in bytes
+        pub fn ${concat(sector_size_, $name)}(self, sector: S) -> Self {
+            self.field(SerialField::SectorSize {
+                sector,
+                bytes: $bytes,
+            })
+        }
+    };
+}
 
-        for (sector_id, sector) in &self.sectors {
-            sector.build(buffer, &self.sectors, &tracker).await?;
-            debug!("Built sector: {sector_id:#?}");
+macro_rules! delta_field {
+    ($name: ident, $bytes: literal) => {
+        /// The distance in bytes from field `from_index` of `from` to field `to_index` of `to`
+        pub fn ${concat(delta_, $name)}(
+            self,
+            from: S,
+            from_index: usize,
+            to: S,
+            to_index: usize,
+        ) -> Self {
+            self.field(SerialField::Delta {
+                from,
+                from_index,
+                to,
+                to_index,
+                bytes: $bytes,
+            })
         }
+    };
+}
 
-        buffer.flush().await?;
+macro_rules! span_field {
+    ($name: ident, $bytes: literal) => {
+        /// The distance in bytes from the start of `from` to the start of `to`
+        pub fn ${concat(span_, $name)}(self, from: S, to: S) -> Self {
+            self.field(SerialField::Span {
+                from,
+                to,
+                bytes: $bytes,
+            })
+        }
+    };
+}
 
-        Ok(())
-    }
+macro_rules! string_len_field {
+    ($name: ident, $bytes: literal) => {
+        /// String prefixed by its length, without a terminator
+        pub fn ${concat(string_len_, $name)}(self, value: impl Into<String>) -> Self {
+            self.field(SerialField::LengthPrefixedString {
+                value: value.into(),
+                bytes: $bytes,
+            })
+        }
+    };
 }
 
-macro_rules! int_field {
-    ($field_name: ident, $unsigned: ident) => {
-        pub fn $unsigned(self, value: impl Into<$unsigned>) -> Self {
-            self.field(SerialField::$field_name(value.into()))
+macro_rules! deferred_field {
+    ($name: ident, $bytes: literal) => {
+        /// Computes the value once every sector's position is known.
+        /// The width is fixed, so the value doesn't affect the layout.
+        pub fn ${concat(deferred_, $name)}(
+            self,
+            value: impl Fn(&SerialReport<S>) -> anyhow::Result<u32> + Send + Sync + 'static,
+        ) -> Self {
+            self.field(SerialField::Deferred {
+                value: DeferredValue::new(value),
+                bytes: $bytes,
+            })
         }
     };
-    ($field_name: ident, $unsigned: ident, $signed: ident) => {
-        int_field!($field_name, $unsigned);
+}
 
-        pub fn $signed(self, value: impl Into<$signed>) -> Self {
-            self.field(SerialField::$field_name(value.into() as $unsigned))
+macro_rules! count_field {
+    ($name: ident, $bytes: literal) => {
+        /// The number of sectors with a key that matches, counted once every sector is added
+        pub fn ${concat(count_, $name)}(
+            self,
+            matcher: impl Fn(&S) -> bool + Send + Sync + 'static,
+        ) -> Self {
+            self.field(SerialField::Deferred {
+                value: DeferredValue::new(move |report| {
+                    let count = report
+                        .sectors
+                        .iter()
+                        .filter(|sector| matcher(&sector.key))
+                        .count();
+                    let count = $name::try_from(count).ok().with_context(|| {
+                        format!(
+                            "Sector count exceeds {}-bit limit: {count} > {}",
+                            $name::BITS,
+                            $name::MAX
+                        )
+                    })?;
+
+                    Ok(count.into())
+                }),
+                bytes: $bytes,
+            })
         }
     };
 }
@@ -88,6 +1214,47 @@ macro_rules! null_field {
             self.field(SerialField::${concat(U, $size)}(::std::default::Default::default()))
         }
     };
+    ($size: literal, endian) => {
+        pub fn ${concat(null_, $size)}(self) -> Self {
+            self.field(SerialField::${concat(U, $size)}(
+                ::std::default::Default::default(),
+                None,
+            ))
+        }
+    };
+}
+
+/// Pointers use the builder's endianness unless a `_le` or `_be` variant is used
+macro_rules! endian_dynamic_field {
+    ($name: ident, $bytes: literal) => {
+        pub fn ${concat(dynamic_, $name, _le)}(self, origin: S, sector: S, index: usize) -> Self {
+            self.${concat(dynamic_, $name, _endian)}(origin, sector, index, Endianness::Little)
+        }
+
+        pub fn ${concat(dynamic_, $name, _be)}(self, origin: S, sector: S, index: usize) -> Self {
+            self.${concat(dynamic_, $name, _endian)}(origin, sector, index, Endianness::Big)
+        }
+
+        fn ${concat(dynamic_, $name, _endian)}(
+            self,
+            origin: S,
+            sector: S,
+            index: usize,
+            endianness: Endianness,
+        ) -> Self {
+            self.field(SerialField::Dynamic {
+                origin,
+                sector,
+                index,
+                offset: 0,
+                rounding: ScaleRounding::default(),
+                scale: 1,
+                padded: false,
+                bytes: $bytes,
+                endianness: Some(endianness),
+            })
+        }
+    };
 }
 
 macro_rules! dynamic_field {
@@ -97,9 +1264,64 @@ macro_rules! dynamic_field {
                 origin,
                 sector,
                 index,
+                offset: 0,
+                rounding: ScaleRounding::default(),
+                scale: 1,
+                padded: false,
+                bytes: $bytes,
+                endianness: None,
+            })
+        }
+
+        /// Writes a null pointer if there's no sector
+        pub fn ${concat(dynamic_, $name, _or_null)}(
+            self,
+            origin: S,
+            sector: Option<S>,
+            index: usize,
+        ) -> Self {
+            match sector {
+                Some(sector) => self.${concat(dynamic_, $name)}(origin, sector, index),
+                None => self.field(SerialField::Bytes(vec![0; $bytes])),
+            }
+        }
+
+        /// Points just past the last byte of `sector`
+        pub fn ${concat(dynamic_, $name, _end)}(self, origin: S, sector: S) -> Self {
+            self.field(SerialField::DynamicEnd {
+                origin,
+                sector,
+                bytes: $bytes,
+            })
+        }
+
+        /// Points from where this pointer is written, rather than from an origin sector
+        pub fn ${concat(dynamic_, $name, _self)}(self, sector: S, index: usize) -> Self {
+            self.field(SerialField::DynamicSelf {
+                sector,
+                index,
+                bytes: $bytes,
+            })
+        }
+
+        /// Adds `offset` to the pointer, such as to skip a header
+        pub fn ${concat(dynamic_, $name, _offset)}(
+            self,
+            origin: S,
+            sector: S,
+            index: usize,
+            offset: isize,
+        ) -> Self {
+            self.field(SerialField::Dynamic {
+                origin,
+                sector,
+                index,
+                offset,
                 rounding: ScaleRounding::default(),
                 scale: 1,
+                padded: false,
                 bytes: $bytes,
+                endianness: None,
             })
         }
 
@@ -116,9 +1338,33 @@ macro_rules! dynamic_field {
                 origin,
                 sector,
                 index,
+                offset: 0,
                 rounding,
                 scale,
+                padded: false,
+                bytes: $bytes,
+                endianness: None,
+            })
+        }
+
+        /// Pads the start of `sector` so the pointer is an exact multiple of `scale`
+        pub fn ${concat(dynamic_, $name, _chunk_padded)}(
+            self,
+            origin: S,
+            sector: S,
+            index: usize,
+            scale: usize,
+        ) -> Self {
+            self.field(SerialField::Dynamic {
+                origin,
+                sector,
+                index,
+                offset: 0,
+                rounding: ScaleRounding::default(),
+                scale,
+                padded: true,
                 bytes: $bytes,
+                endianness: None,
             })
         }
     };
@@ -130,52 +1376,498 @@ impl<S: Hash + Eq + Clone + std::fmt::Debug> SerialSectorBuilder<S> {
         self
     }
 
+    /// Adds a field directly, for fields without their own method
+    pub fn append_field(self, field: SerialField<S>) -> Self {
+        self.field(field)
+    }
+
+    /// Adds every field of the other builder after this one's
+    pub fn extend(mut self, other: SerialSectorBuilder<S>) -> Self {
+        let start = self.fields.len();
+        self.labels.extend(
+            other
+                .labels
+                .into_iter()
+                .map(|(index, label)| (start + index, label)),
+        );
+        self.fields.extend(other.fields);
+        self
+    }
+
+    /// Fails to build if any string in the sector has a character that isn't ASCII,
+    /// such as for formats that aren't UTF-8
+    pub fn ascii_only(mut self) -> Self {
+        self.ascii_only = true;
+        self
+    }
+
+    /// Checks what the sector requires of its fields that they can't check themselves
+    pub(crate) fn check_field(&self, field: &SerialField<S>) -> anyhow::Result<()> {
+        if self.ascii_only {
+            field.check_ascii()?;
+        }
+
+        Ok(())
+    }
+
+    /// Names the most recent field in errors and debug hexdumps, instead of its index
+    ///
+    /// # Panics
+    /// If there are no fields yet
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        let index = self
+            .fields
+            .len()
+            .checked_sub(1)
+            .expect("Can't label a field before adding one");
+        self.labels.insert(index, label.into());
+        self
+    }
+
+    /// The field's label, unless the sector is compressed, as it's then built as one field of
+    /// compressed bytes
+    pub(crate) fn field_label(&self, index: usize) -> Option<&str> {
+        self.labels
+            .get(&index)
+            .filter(|_| self.compression.is_none())
+            .map(String::as_str)
+    }
+
+    /// Null terminated string
     pub fn string(self, value: impl Into<String>) -> Self {
-        self.field(SerialField::String(value.into()))
+        self.string_terminated(value, 0)
+    }
+
+    /// Null terminated string, which is skipped entirely if there's no value
+    pub fn maybe_string(self, value: Option<impl Into<String>>) -> Self {
+        match value {
+            Some(value) => self.string(value),
+            None => self,
+        }
+    }
+
+    pub fn string_terminated(self, value: impl Into<String>, terminator: u8) -> Self {
+        self.field(SerialField::String {
+            value: value.into(),
+            terminator: Some(terminator),
+        })
+    }
+
+    /// String without a terminator, for when its length is stored elsewhere
+    pub fn string_raw(self, value: impl Into<String>) -> Self {
+        self.field(SerialField::String {
+            value: value.into(),
+            terminator: None,
+        })
+    }
+
+    /// Pads the string with `pad` up to exactly `len` bytes, without a null terminator
+    pub fn string_fixed(self, value: impl Into<String>, len: usize, pad: u8) -> Self {
+        self.field(SerialField::FixedString {
+            value: value.into(),
+            len,
+            pad,
+        })
     }
 
     pub fn bytes(self, value: impl IntoIterator<Item = u8>) -> Self {
         self.field(SerialField::Bytes(value.into_iter().collect()))
     }
 
+    /// Every value in one field, the same as [`SerialSectorBuilder::bytes`]
+    pub fn u8_array(self, values: impl IntoIterator<Item = u8>) -> Self {
+        self.array(values.into_iter().map(u32::from), 1)
+    }
+
+    /// Every value in one field, each using the builder's endianness
+    pub fn u16_array(self, values: impl IntoIterator<Item = u16>) -> Self {
+        self.array(values.into_iter().map(u32::from), 2)
+    }
+
+    /// Every value in one field, each using the builder's endianness
+    pub fn u24_array(self, values: impl IntoIterator<Item = u24>) -> Self {
+        self.array(
+            values.into_iter().map(|value| {
+                let [low, middle, high] = value.to_le_bytes();
+                u32::from_le_bytes([low, middle, high, 0])
+            }),
+            3,
+        )
+    }
+
+    fn array(self, values: impl Iterator<Item = u32>, bytes: usize) -> Self {
+        self.field(SerialField::Array {
+            values: values.collect(),
+            bytes,
+        })
+    }
+
+    /// Shares the bytes instead of copying them, so large data can be used in several places
+    pub fn bytes_shared(self, value: impl Into<Arc<[u8]>>) -> Self {
+        self.field(SerialField::SharedBytes(value.into()))
+    }
+
+    /// Run-length encodes the bytes when they're added, in the format described by
+    /// [`Compression::Rle`]
+    pub fn bytes_rle(self, value: impl AsRef<[u8]>) -> Self {
+        self.field(SerialField::Bytes(compression::rle(value.as_ref())))
+    }
+
+    /// The byte written `count` times
+    pub fn repeat_u8(self, value: u8, count: usize) -> Self {
+        self.repeat_bytes([value], count)
+    }
+
+    /// The pattern written `count` times
+    pub fn repeat_bytes(self, pattern: impl IntoIterator<Item = u8>, count: usize) -> Self {
+        self.field(SerialField::Repeat {
+            pattern: pattern.into_iter().collect(),
+            count,
+        })
+    }
+
     int_field!(U8, u8, i8);
-    int_field!(U16, u16, i16);
-    int_field!(U24, u24);
-    int_field!(U32, u32, i32);
-    int_field!(U64, u64, i64);
+    endian_int_field!(U16, u16, i16);
+
+    /// Uses the builder's endianness unless [`Self::u24_le`] or [`Self::u24_be`] is used
+    pub fn u24(self, value: impl Into<u24>) -> Self {
+        self.field(SerialField::U24(value.into(), None))
+    }
+
+    /// Writes `default` if there's no value
+    pub fn maybe_u24(self, value: Option<u24>, default: u24) -> Self {
+        self.u24(value.unwrap_or(default))
+    }
+
+    pub fn u24_le(self, value: impl Into<u24>) -> Self {
+        self.field(SerialField::U24(value.into(), Some(Endianness::Little)))
+    }
+
+    pub fn u24_be(self, value: impl Into<u24>) -> Self {
+        self.field(SerialField::U24(value.into(), Some(Endianness::Big)))
+    }
+
+    endian_int_field!(I24, i32 => i24);
+    endian_int_field!(U48, u64 => u48);
+    endian_int_field!(U32, u32, i32);
+    endian_int_field!(U64, u64, i64);
+    endian_int_field!(U128, u128, i128);
+
+    float_field!(F32, f32);
+    float_field!(F64, f64);
+
+    /// Unsigned LEB128, taking 1 to 5 bytes depending on the value
+    pub fn varint_u32(self, value: impl Into<u32>) -> Self {
+        self.field(SerialField::VarUInt(value.into().into()))
+    }
+
+    /// Unsigned LEB128, taking 1 to 10 bytes depending on the value
+    pub fn varint_u64(self, value: impl Into<u64>) -> Self {
+        self.field(SerialField::VarUInt(value.into()))
+    }
+
+    /// Packs flags and small values into a byte
+    pub fn bits_u8(
+        self,
+        order: BitOrder,
+        bits: impl FnOnce(BitfieldBuilder) -> BitfieldBuilder,
+    ) -> anyhow::Result<Self> {
+        let value = bits(BitfieldBuilder::new(order, 8)).finish()?;
+        Ok(self.field(SerialField::U8(value as u8)))
+    }
+
+    /// Packs flags and small values into two bytes, using the builder's endianness
+    pub fn bits_u16(
+        self,
+        order: BitOrder,
+        bits: impl FnOnce(BitfieldBuilder) -> BitfieldBuilder,
+    ) -> anyhow::Result<Self> {
+        let value = bits(BitfieldBuilder::new(order, 16)).finish()?;
+        Ok(self.field(SerialField::U16(value as u16, None)))
+    }
+
+    /// A TI-OS real, rounded to 14 significant digits
+    pub fn ti_float(self, value: f64) -> Self {
+        self.field(SerialField::TiFloat(value.to_bits()))
+    }
+
+    deferred_field!(u8, 1);
+    deferred_field!(u16, 2);
+    deferred_field!(u24, 3);
+    deferred_field!(u32, 4);
+
+    count_field!(u8, 1);
+    count_field!(u16, 2);
+
+    string_len_field!(u8, 1);
+    string_len_field!(u16, 2);
+    string_len_field!(u24, 3);
 
     null_field!(8);
-    null_field!(16);
-    null_field!(24);
-    null_field!(32);
-    null_field!(64);
+    null_field!(16, endian);
+    null_field!(24, endian);
+    null_field!(32, endian);
+    null_field!(48, endian);
+    null_field!(64, endian);
+    null_field!(128, endian);
 
     dynamic_field!(u8, 1);
     dynamic_field!(u16, 2);
     dynamic_field!(u24, 3);
     dynamic_field!(u32, 4);
 
+    endian_dynamic_field!(u16, 2);
+    endian_dynamic_field!(u24, 3);
+    endian_dynamic_field!(u32, 4);
+
+    /// A signed pointer, which can point before its origin
+    pub fn dynamic_i16(self, origin: S, sector: S, index: usize) -> Self {
+        self.dynamic_signed(origin, sector, index, 2)
+    }
+
+    /// A signed pointer, which can point before its origin
+    pub fn dynamic_i24(self, origin: S, sector: S, index: usize) -> Self {
+        self.dynamic_signed(origin, sector, index, 3)
+    }
+
+    fn dynamic_signed(self, origin: S, sector: S, index: usize, bytes: usize) -> Self {
+        self.field(SerialField::DynamicSigned {
+            origin,
+            sector,
+            index,
+            bytes,
+        })
+    }
+
+    /// A pointer in one, two, or three bytes, whichever is the fewest its value fits in.
+    /// The width chosen is in the build report.
+    pub fn dynamic_auto(self, origin: S, sector: S, index: usize) -> Self {
+        self.field(SerialField::DynamicAuto {
+            origin,
+            sector,
+            index,
+        })
+    }
+
+    sector_size_field!(u8, 1);
+    sector_size_field!(u16, 2);
+    sector_size_field!(u24, 3);
+    sector_size_field!(u32, 4);
+
+    span_field!(u8, 1);
+    span_field!(u16, 2);
+    span_field!(u24, 3);
+    span_field!(u32, 4);
+
+    delta_field!(u8, 1);
+    delta_field!(u16, 2);
+    delta_field!(u24, 3);
+
+    /// Pads with zeros up to the next multiple of `boundary` in the output
+    pub fn align(self, boundary: usize) -> Self {
+        self.align_with(boundary, 0)
+    }
+
+    /// Pads with `pad` up to the next multiple of `boundary` in the output
+    pub fn align_with(self, boundary: usize, pad: u8) -> Self {
+        self.field(SerialField::Align { boundary, pad })
+    }
+
+    /// The CRC-32 of every sector from `first` to `last`
+    pub fn crc32(self, first: S, last: S) -> Self {
+        self.checksum(first, last, ChecksumAlgorithm::Crc32)
+    }
+
+    /// The sum of every byte from `first` to `last`, like a TI link file's checksum
+    pub fn checksum_sum16(self, first: S, last: S) -> Self {
+        self.checksum(first, last, ChecksumAlgorithm::Sum16)
+    }
+
+    /// The CRC-16/CCITT of every sector from `first` to `last`
+    pub fn crc16_ccitt(self, first: S, last: S) -> Self {
+        self.checksum(first, last, ChecksumAlgorithm::Crc16Ccitt)
+    }
+
+    pub fn checksum(self, first: S, last: S, algorithm: ChecksumAlgorithm) -> Self {
+        self.field(SerialField::Checksum {
+            first,
+            last,
+            algorithm,
+        })
+    }
+
+    /// Pads with zeros up to `fill` bytes from the start of `origin`
     pub fn fill(self, origin: S, fill: usize) -> Self {
-        self.field(SerialField::Fill { origin, fill })
+        self.fill_with(origin, fill, 0)
+    }
+
+    /// Pads with `pad` up to `fill` bytes from the start of `origin`
+    pub fn fill_with(self, origin: S, fill: usize, pad: u8) -> Self {
+        self.field(SerialField::Fill {
+            origin,
+            fill,
+            pad,
+            at_least: false,
+        })
+    }
+
+    /// Pads up to `fill` bytes from the start of `origin`, but unlike [`Self::fill`], writes
+    /// nothing if it's already past that
+    pub fn fill_at_least(self, origin: S, fill: usize) -> Self {
+        self.field(SerialField::Fill {
+            origin,
+            fill,
+            pad: 0,
+            at_least: true,
+        })
     }
 
     pub fn external(self, path: impl Into<PathBuf>, size: usize) -> Self {
         self.field(SerialField::External {
             path: path.into(),
-            size,
+            offset: None,
+            size: Some(size),
+        })
+    }
+
+    /// Includes the whole file, using its size when the builder is tracked
+    pub fn external_auto(self, path: impl Into<PathBuf>) -> Self {
+        self.field(SerialField::External {
+            path: path.into(),
+            offset: None,
+            size: None,
+        })
+    }
+
+    /// Includes `len` bytes of the file, starting `offset` bytes in
+    pub fn external_range(self, path: impl Into<PathBuf>, offset: u64, len: usize) -> Self {
+        self.field(SerialField::External {
+            path: path.into(),
+            offset: Some(offset),
+            size: Some(len),
         })
     }
 
-    async fn build(
+    #[allow(clippy::too_many_arguments)]
+    async fn build<F: Files>(
         &self,
-        buffer: &mut (impl AsyncWrite + Unpin + AsyncSeek),
+        buffer: &mut impl SectorWrite,
+        sector_id: &S,
         sectors: &IndexMap<S, SerialSectorBuilder<S>>,
         tracker: &SerialTracker<S>,
+        endianness: Endianness,
+        start: usize,
+        pending_fill: &mut PendingFill,
     ) -> anyhow::Result<()> {
-        for field in &self.fields {
-            field.build(buffer, sectors, tracker).await?;
+        let mut offset = start;
+
+        for (index, field) in tracker.fields(sector_id, self).iter().enumerate() {
+            let context = || {
+                format!(
+                    "Failed to build field {}",
+                    field_name(index, self.field_label(index))
+                )
+            };
+            let size = field
+                .calculate_size(offset, tracker)
+                .with_context(context)?;
+
+            if let SerialField::Fill { pad, .. } = field {
+                pending_fill.push(*pad, size);
+            } else {
+                if size != 0 {
+                    pending_fill.write(buffer).await?;
+                }
+
+                self.check_field(field).with_context(context)?;
+                field
+                    .build::<F>(buffer, sectors, tracker, endianness, offset)
+                    .await
+                    .with_context(context)?;
+            }
+
+            offset += size;
+        }
+
+        let padding = self.fixed_padding(sector_id, offset - start)?;
+
+        // A fixed size sector always ends where it was tracked to, even if it ends with a fill
+        if let Some((_, pad)) = self.fixed_size {
+            pending_fill.push(pad, padding);
+            pending_fill.write(buffer).await?;
         }
 
         Ok(())
     }
+
+    /// The padding after the sector's fields that brings it up to its fixed size, if it has one
+    pub(crate) fn fixed_padding(&self, sector_id: &S, content: usize) -> anyhow::Result<usize> {
+        match self.fixed_size {
+            Some((size, _)) => size.checked_sub(content).with_context(|| {
+                format!(
+                    "Sector {sector_id:?} is larger than its fixed size: {content} bytes > \
+                     {size} bytes"
+                )
+            }),
+            None => Ok(0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds with the layout of another builder, as if tracking got a size wrong
+    fn build_mistracked(
+        builder: SerialBuilder<&'static str>,
+        tracked: SerialBuilder<&'static str>,
+    ) -> anyhow::Result<Vec<u8>> {
+        let tracker = backend::block_on(tracked.track::<StdFiles>())?;
+        let mut buffer = Vec::new();
+        backend::block_on(
+            builder
+                .strict()
+                .build_tracked::<StdFiles>(&mut StdWriter(&mut buffer), &tracker),
+        )?;
+
+        Ok(buffer)
+    }
+
+    #[test]
+    fn strict_size() {
+        let error = build_mistracked(
+            SerialBuilder::default()
+                .sector("first", SerialSectorBuilder::default().u16(0u16))
+                .sector("second", SerialSectorBuilder::default().u8(0)),
+            SerialBuilder::default()
+                .sector("first", SerialSectorBuilder::default().u8(0))
+                .sector("second", SerialSectorBuilder::default().u8(0)),
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Sector \"first\" wrote 2 bytes, but was tracked to take up 1 bytes"
+        );
+    }
+
+    #[test]
+    fn strict_start() {
+        let error = build_mistracked(
+            SerialBuilder::default()
+                .sector("first", SerialSectorBuilder::default().u8(0))
+                .sector("second", SerialSectorBuilder::default().u8(0)),
+            SerialBuilder::default()
+                .sector("zeroth", SerialSectorBuilder::default().u8(0))
+                .sector("first", SerialSectorBuilder::default().u8(0))
+                .sector("second", SerialSectorBuilder::default().u8(0)),
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Sector \"first\" starts at 0 in the output, but was tracked to start at 1"
+        );
+    }
 }
@@ -0,0 +1,98 @@
+/// A checksum over a range of the output, written little-endian
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumAlgorithm {
+    /// CRC-32/ISO-HDLC, as used by zip and PNG
+    Crc32,
+    /// The sum of every byte, modulo 65536, as used by TI link files
+    Sum16,
+    /// CRC-16/CCITT-FALSE, with a polynomial of `0x1021` and an initial value of `0xFFFF`
+    Crc16Ccitt,
+}
+
+impl ChecksumAlgorithm {
+    /// The size of the checksum in bytes
+    pub const fn size(self) -> usize {
+        match self {
+            Self::Crc32 => 4,
+            Self::Sum16 | Self::Crc16Ccitt => 2,
+        }
+    }
+
+    /// The little-endian checksum of the data
+    pub fn compute(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Crc32 => crc32(data).to_le_bytes().to_vec(),
+            Self::Sum16 => data
+                .iter()
+                .fold(0u16, |sum, byte| sum.wrapping_add(u16::from(*byte)))
+                .to_le_bytes()
+                .to_vec(),
+            Self::Crc16Ccitt => crc16_ccitt(data).to_le_bytes().to_vec(),
+        }
+    }
+}
+
+/// Reflected, with a polynomial of `0x04C11DB7`
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0;
+
+    for byte in data {
+        crc ^= u32::from(*byte);
+
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+
+    !crc
+}
+
+/// Not reflected
+fn crc16_ccitt(data: &[u8]) -> u16 {
+    let mut crc = 0xFFFF;
+
+    for byte in data {
+        crc ^= u16::from(*byte) << 8;
+
+        for _ in 0..8 {
+            crc = if crc & 0x8000 == 0 {
+                crc << 1
+            } else {
+                (crc << 1) ^ 0x1021
+            };
+        }
+    }
+
+    crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sum16_wraps() {
+        assert_eq!(
+            ChecksumAlgorithm::Sum16.compute(&[0xFF; 0x102]),
+            [0xFE, 0x00]
+        );
+        assert_eq!(ChecksumAlgorithm::Sum16.compute(&[]), [0x00, 0x00]);
+    }
+
+    #[test]
+    fn crc32_check() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(b""), 0);
+    }
+
+    #[test]
+    fn crc16_ccitt_check() {
+        assert_eq!(crc16_ccitt(b"123456789"), 0x29B1);
+        assert_eq!(crc16_ccitt(b""), 0xFFFF);
+        assert_eq!(
+            ChecksumAlgorithm::Crc16Ccitt.compute(b"123456789"),
+            [0xB1, 0x29]
+        );
+    }
+}
@@ -0,0 +1,829 @@
+use anyhow::bail;
+
+/// How a compressed sector's bytes are packed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    /// Einar Saukas' ZX7, as decompressed by the CE toolchain's `zx7_Decompress`
+    Zx7,
+    /// Einar Saukas' ZX0, as decompressed by the CE toolchain's `zx0_Decompress`.
+    /// Usually smaller than ZX7, as matches can be further back and offsets can be repeated.
+    Zx0,
+    /// Run-length encoding, as written by [`rle`]
+    Rle,
+}
+
+impl Compression {
+    pub fn compress(self, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        match self {
+            Self::Zx7 => zx7(data),
+            Self::Zx0 => zx0(data),
+            Self::Rle => Ok(rle(data)),
+        }
+    }
+}
+
+/// The most bytes in a literal run
+const RLE_MAX_LITERAL: usize = 0x80;
+/// Shorter repeats are written as literals, as they'd be no smaller
+const RLE_MIN_REPEAT: usize = 3;
+const RLE_MAX_REPEAT: usize = RLE_MIN_REPEAT + 0x7F;
+
+/// Runs of bytes, each starting with a count byte:
+/// - `0x00..=0x7F`: the next `count + 1` bytes are copied as they are
+/// - `0x80..=0xFF`: the next byte is repeated `count - 0x80 + 3` times
+pub(crate) fn rle(data: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(data.len() + data.len().div_ceil(RLE_MAX_LITERAL));
+    let mut literal_start = 0;
+    let mut index = 0;
+
+    while index < data.len() {
+        let repeat = data[index..]
+            .iter()
+            .take(RLE_MAX_REPEAT)
+            .take_while(|&&byte| byte == data[index])
+            .count();
+
+        if repeat < RLE_MIN_REPEAT {
+            index += 1;
+
+            if index - literal_start == RLE_MAX_LITERAL {
+                rle_literal(&mut output, &data[literal_start..index]);
+                literal_start = index;
+            }
+
+            continue;
+        }
+
+        rle_literal(&mut output, &data[literal_start..index]);
+        output.push((0x80 + repeat - RLE_MIN_REPEAT) as u8);
+        output.push(data[index]);
+
+        index += repeat;
+        literal_start = index;
+    }
+
+    rle_literal(&mut output, &data[literal_start..]);
+    output
+}
+
+fn rle_literal(output: &mut Vec<u8>, literal: &[u8]) {
+    if !literal.is_empty() {
+        output.push((literal.len() - 1) as u8);
+        output.extend_from_slice(literal);
+    }
+}
+
+/// The furthest back a ZX7 match can copy from
+const ZX7_MAX_OFFSET: usize = 2176;
+/// The longest ZX7 match
+const ZX7_MAX_LEN: usize = 65536;
+/// Offsets up to this fit in a single byte
+const ZX7_SHORT_OFFSET: usize = 128;
+/// Longer matches than this are only tried at their full length, to keep long runs linear
+const ZX7_SEARCH_LEN: usize = 256;
+
+/// How the data up to a byte is best encoded
+#[derive(Debug, Default, Clone, Copy)]
+struct Zx7Step {
+    bits: usize,
+    /// Zero for a literal
+    len: usize,
+    offset: usize,
+}
+
+/// Compresses with an optimal parse, apart from very long matches
+fn zx7(data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let Some(&first) = data.first() else {
+        bail!("Can't compress empty data with ZX7");
+    };
+
+    let mut steps = vec![Zx7Step::default(); data.len()];
+    // How many bytes in a row, ending at the current one, match the bytes each offset back
+    let mut runs = vec![0; ZX7_MAX_OFFSET + 1];
+
+    // The first byte is always a literal
+    steps[0].bits = 8;
+
+    for index in 1..data.len() {
+        steps[index] = Zx7Step {
+            bits: steps[index - 1].bits + 9,
+            len: 0,
+            offset: 0,
+        };
+
+        // The longest match and its offset, for short and long offsets
+        let mut short = (0, 0);
+        let mut long = (0, 0);
+
+        for offset in 1..=ZX7_MAX_OFFSET.min(index) {
+            runs[offset] = if data[index] == data[index - offset] {
+                runs[offset] + 1
+            } else {
+                0
+            };
+
+            // The first byte can't be part of a match
+            let run = runs[offset].min(index).min(ZX7_MAX_LEN);
+            let longest = if offset <= ZX7_SHORT_OFFSET {
+                &mut short
+            } else {
+                &mut long
+            };
+
+            if run > longest.0 {
+                *longest = (run, offset);
+            }
+        }
+
+        for (longest, offset) in [short, long] {
+            let lens = (2..=longest.min(ZX7_SEARCH_LEN)).chain(Some(longest));
+
+            for len in lens.filter(|&len| len >= 2) {
+                let bits = steps[index - len].bits + zx7_match_bits(offset, len);
+
+                if bits < steps[index].bits {
+                    steps[index] = Zx7Step { bits, len, offset };
+                }
+            }
+        }
+    }
+
+    // Walks back from the end, then writes forwards
+    let mut path = Vec::new();
+    let mut index = data.len() - 1;
+
+    while index != 0 {
+        path.push(index);
+        index -= steps[index].len.max(1);
+    }
+
+    let mut writer = BitWriter::default();
+    writer.byte(first);
+
+    for &index in path.iter().rev() {
+        let step = steps[index];
+
+        if step.len == 0 {
+            writer.bit(false);
+            writer.byte(data[index]);
+        } else {
+            writer.bit(true);
+            writer.elias_gamma(step.len - 1);
+
+            let offset = step.offset - 1;
+
+            if offset < ZX7_SHORT_OFFSET {
+                writer.byte(offset as u8);
+            } else {
+                let offset = offset - ZX7_SHORT_OFFSET;
+                writer.byte((offset & 0x7F) as u8 | 0x80);
+
+                for bit in (7..=10).rev() {
+                    writer.bit(offset >> bit & 1 != 0);
+                }
+            }
+        }
+    }
+
+    // A match length too long for the format marks the end
+    writer.bit(true);
+
+    for _ in 0..16 {
+        writer.bit(false);
+    }
+
+    writer.bit(true);
+
+    Ok(writer.output)
+}
+
+fn zx7_match_bits(offset: usize, len: usize) -> usize {
+    let offset_bits = if offset > ZX7_SHORT_OFFSET { 12 } else { 8 };
+    1 + offset_bits + elias_gamma_bits(len - 1)
+}
+
+fn elias_gamma_bits(value: usize) -> usize {
+    2 * value.ilog2() as usize + 1
+}
+
+/// The furthest back a ZX0 match can copy from
+const ZX0_MAX_OFFSET: usize = 32640;
+/// The offset repeated before any match sets one
+const ZX0_INITIAL_OFFSET: usize = 1;
+/// The offset's high part that marks the end
+const ZX0_END_MARKER: usize = 256;
+/// The most earlier positions with the same two bytes that are tried for a match
+const ZX0_CHAIN_DEPTH: usize = 256;
+/// Longer matches than this are only tried at their full length, and the bytes they cover
+/// aren't searched for matches of their own, to keep long runs linear
+const ZX0_SEARCH_LEN: usize = 256;
+/// Longer literal runs are only tried from the match they'd cost the least after
+const ZX0_LITERAL_SEARCH: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Zx0Block {
+    Literal,
+    /// Copies from the last offset, only after a literal
+    Repeat,
+    /// Copies from a new offset
+    Match,
+}
+
+/// The cheapest block found that ends at a byte
+#[derive(Debug, Clone, Copy)]
+struct Zx0Step {
+    bits: usize,
+    block: Zx0Block,
+    start: usize,
+    /// Whether the block before it was a literal
+    after_literal: bool,
+    /// The last offset copied from, including this block
+    offset: usize,
+}
+
+impl Zx0Step {
+    fn relax(self, step: &mut Option<Self>) {
+        if step.is_none_or(|step| self.bits < step.bits) {
+            *step = Some(self);
+        }
+    }
+}
+
+/// Compresses with a parse that's optimal for the offsets it tries.
+/// Only the last offset of each step's path is tracked for repeats.
+fn zx0(data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    if data.is_empty() {
+        bail!("Can't compress empty data with ZX0");
+    }
+
+    let len = data.len();
+    // The cheapest steps ending at each byte with a literal, and with a copy
+    let mut literals = vec![None::<Zx0Step>; len + 1];
+    let mut copies = vec![None::<Zx0Step>; len + 1];
+
+    // The first block is always a literal, without the bit saying so
+    for (end, literal) in literals.iter_mut().enumerate().skip(1) {
+        *literal = Some(Zx0Step {
+            bits: elias_gamma_bits(end) + 8 * end,
+            block: Zx0Block::Literal,
+            start: 0,
+            after_literal: false,
+            offset: ZX0_INITIAL_OFFSET,
+        });
+    }
+
+    // The last position starting with each pair of bytes, and the one before each position
+    let mut heads = vec![usize::MAX; 0x10000];
+    let mut chain = vec![usize::MAX; len];
+    let pair = |index: usize| usize::from(data[index]) << 8 | usize::from(data[index + 1]);
+    let insert = |index: usize, heads: &mut [usize], chain: &mut [usize]| {
+        if index + 1 < len {
+            chain[index] = heads[pair(index)];
+            heads[pair(index)] = index;
+        }
+    };
+
+    insert(0, &mut heads, &mut chain);
+
+    // The copy that a literal run costs the least after, not counting the run's length
+    let mut cheapest = None::<(usize, Zx0Step)>;
+    let mut skip_until = 0;
+
+    for start in 1..len {
+        let remaining = len - start;
+
+        if let Some((origin, copy)) = cheapest
+            && start - origin > ZX0_LITERAL_SEARCH
+        {
+            zx0_literal(copy, origin, start).relax(&mut literals[start]);
+        }
+
+        if let Some(copy) = copies[start] {
+            if cheapest
+                .is_none_or(|(origin, cheapest)| copy.bits + 8 * origin < cheapest.bits + 8 * start)
+            {
+                cheapest = Some((start, copy));
+            }
+
+            let ends = start + 1..=start + remaining.min(ZX0_LITERAL_SEARCH);
+
+            for (end, literal) in ends.clone().zip(&mut literals[ends]) {
+                zx0_literal(copy, start, end).relax(literal);
+            }
+        }
+
+        if start >= skip_until {
+            // Long matches are measured in full once, then the bytes they cover are skipped
+            let mut longest = |offset: usize| {
+                let found = match_len(data, start, offset, remaining.min(ZX0_SEARCH_LEN));
+
+                if found < ZX0_SEARCH_LEN {
+                    return found;
+                }
+
+                let found = match_len(data, start, offset, remaining);
+                skip_until = skip_until.max(start + found - ZX0_SEARCH_LEN);
+                found
+            };
+            let lens = |found: usize, shortest: usize| {
+                (shortest..=found.min(ZX0_SEARCH_LEN))
+                    .chain(Some(found).filter(|&found| found > ZX0_SEARCH_LEN))
+            };
+
+            if let Some(literal) = literals[start] {
+                for found in lens(longest(literal.offset), 1) {
+                    Zx0Step {
+                        bits: literal.bits + 1 + elias_gamma_bits(found),
+                        block: Zx0Block::Repeat,
+                        start,
+                        after_literal: true,
+                        offset: literal.offset,
+                    }
+                    .relax(&mut copies[start + found]);
+                }
+            }
+
+            // New offsets can follow either block
+            let previous = match (literals[start], copies[start]) {
+                (Some(literal), Some(copy)) if copy.bits < literal.bits => Some((copy, false)),
+                (Some(literal), _) => Some((literal, true)),
+                (None, copy) => copy.map(|copy| (copy, false)),
+            };
+
+            if let Some((previous, after_literal)) = previous
+                && remaining >= 2
+            {
+                let mut candidate = heads[pair(start)];
+                // Closer offsets cost no more, so further ones only need to be tried for longer matches
+                let mut covered = 1;
+
+                for _ in 0..ZX0_CHAIN_DEPTH {
+                    if candidate == usize::MAX || start - candidate > ZX0_MAX_OFFSET {
+                        break;
+                    }
+
+                    let offset = start - candidate;
+                    let found = longest(offset);
+
+                    for found in lens(found, covered + 1) {
+                        Zx0Step {
+                            bits: previous.bits + zx0_match_bits(offset, found),
+                            block: Zx0Block::Match,
+                            start,
+                            after_literal,
+                            offset,
+                        }
+                        .relax(&mut copies[start + found]);
+                    }
+
+                    if found > ZX0_SEARCH_LEN {
+                        break;
+                    }
+
+                    covered = covered.max(found);
+                    candidate = chain[candidate];
+                }
+            }
+        }
+
+        insert(start, &mut heads, &mut chain);
+    }
+
+    // Walks back from the end, then writes forwards
+    let mut path = Vec::new();
+    let mut end = len;
+    let mut literal = match (literals[len], copies[len]) {
+        (Some(literal), Some(copy)) => literal.bits <= copy.bits,
+        (literal, _) => literal.is_some(),
+    };
+
+    loop {
+        let step = if literal { literals[end] } else { copies[end] }
+            .expect("Every step's start was reached");
+        path.push((step, end));
+
+        if step.start == 0 {
+            break;
+        }
+
+        end = step.start;
+        literal = step.after_literal;
+    }
+
+    let mut writer = BitWriter::default();
+
+    for &(step, end) in path.iter().rev() {
+        let len = end - step.start;
+
+        match step.block {
+            Zx0Block::Literal => {
+                if step.start != 0 {
+                    writer.bit(false);
+                }
+
+                writer.interlaced_elias_gamma(len, false);
+                data[step.start..end]
+                    .iter()
+                    .for_each(|&byte| writer.byte(byte));
+            }
+            Zx0Block::Repeat => {
+                writer.bit(false);
+                writer.interlaced_elias_gamma(len, false);
+            }
+            Zx0Block::Match => {
+                let offset = step.offset - 1;
+
+                writer.bit(true);
+                writer.interlaced_elias_gamma(offset / 128 + 1, true);
+                writer.byte(((127 - offset % 128) << 1) as u8);
+                writer.backtrack = true;
+                writer.interlaced_elias_gamma(len - 1, false);
+            }
+        }
+    }
+
+    writer.bit(true);
+    writer.interlaced_elias_gamma(ZX0_END_MARKER, true);
+
+    Ok(writer.output)
+}
+
+/// A literal run from `start` to `end`, after a copy
+fn zx0_literal(copy: Zx0Step, start: usize, end: usize) -> Zx0Step {
+    Zx0Step {
+        bits: copy.bits + 1 + elias_gamma_bits(end - start) + 8 * (end - start),
+        block: Zx0Block::Literal,
+        start,
+        after_literal: false,
+        offset: copy.offset,
+    }
+}
+
+/// The offset's low byte shares its last bit with the length
+fn zx0_match_bits(offset: usize, len: usize) -> usize {
+    1 + elias_gamma_bits((offset - 1) / 128 + 1) + 7 + elias_gamma_bits(len - 1)
+}
+
+/// How many bytes from `start` match those `offset` back, up to `max`
+fn match_len(data: &[u8], start: usize, offset: usize, max: usize) -> usize {
+    data[start..]
+        .iter()
+        .zip(&data[start - offset..])
+        .take(max)
+        .take_while(|(a, b)| a == b)
+        .count()
+}
+
+/// Bits are packed into bytes reserved in the output as they're needed, most significant first
+#[derive(Debug, Default)]
+struct BitWriter {
+    output: Vec<u8>,
+    /// Where bits are being written to
+    bit_index: usize,
+    bit_mask: u8,
+    /// Whether the next bit goes in the last byte's lowest bit, as ZX0 does after an offset
+    backtrack: bool,
+}
+
+impl BitWriter {
+    fn byte(&mut self, value: u8) {
+        self.output.push(value);
+    }
+
+    fn bit(&mut self, value: bool) {
+        if self.backtrack {
+            self.backtrack = false;
+
+            if value && let Some(last) = self.output.last_mut() {
+                *last |= 1;
+            }
+
+            return;
+        }
+
+        if self.bit_mask == 0 {
+            self.bit_mask = 0x80;
+            self.bit_index = self.output.len();
+            self.output.push(0);
+        }
+
+        if value {
+            self.output[self.bit_index] |= self.bit_mask;
+        }
+
+        self.bit_mask >>= 1;
+    }
+
+    /// A zero for every bit after the first, then every bit
+    fn elias_gamma(&mut self, value: usize) {
+        let bits = value.ilog2();
+
+        for _ in 0..bits {
+            self.bit(false);
+        }
+
+        for bit in (0..=bits).rev() {
+            self.bit(value >> bit & 1 != 0);
+        }
+    }
+
+    /// Each bit after the first follows a zero, then a one ends it.
+    /// ZX0 inverts the bits of offsets.
+    fn interlaced_elias_gamma(&mut self, value: usize, inverted: bool) {
+        for bit in (0..value.ilog2()).rev() {
+            self.bit(false);
+            self.bit((value >> bit & 1 != 0) != inverted);
+        }
+
+        self.bit(true);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Follows the reference decompressors, reading bits and bytes from the same input
+    #[derive(Default)]
+    struct BitReader<'a> {
+        input: &'a [u8],
+        position: usize,
+        bits: u8,
+        mask: u8,
+        backtrack: bool,
+    }
+
+    impl BitReader<'_> {
+        fn byte(&mut self) -> u8 {
+            self.position += 1;
+            self.input[self.position - 1]
+        }
+
+        fn bit(&mut self) -> bool {
+            if self.backtrack {
+                self.backtrack = false;
+                return self.input[self.position - 1] & 1 != 0;
+            }
+
+            self.mask >>= 1;
+
+            if self.mask == 0 {
+                self.mask = 0x80;
+                self.bits = self.byte();
+            }
+
+            self.bits & self.mask != 0
+        }
+
+        /// `None` for the end marker
+        fn elias_gamma(&mut self) -> Option<usize> {
+            let mut zeros = 0;
+
+            while !self.bit() {
+                zeros += 1;
+            }
+
+            if zeros > 15 {
+                return None;
+            }
+
+            Some((0..zeros).fold(1, |value, _| value << 1 | usize::from(self.bit())))
+        }
+
+        fn interlaced_elias_gamma(&mut self, inverted: bool) -> usize {
+            let mut value = 1;
+
+            while !self.bit() {
+                value = value << 1 | usize::from(self.bit() != inverted);
+            }
+
+            value
+        }
+    }
+
+    fn unzx7(input: &[u8]) -> Vec<u8> {
+        let mut reader = BitReader {
+            input,
+            ..Default::default()
+        };
+        let mut output = vec![reader.byte()];
+
+        loop {
+            if !reader.bit() {
+                output.push(reader.byte());
+                continue;
+            }
+
+            let Some(len) = reader.elias_gamma().map(|value| value + 1) else {
+                break;
+            };
+
+            let mut offset = usize::from(reader.byte());
+
+            if offset >= 0x80 {
+                let high = (0..4).fold(0, |high, _| high << 1 | usize::from(reader.bit()));
+                offset = (offset & 0x7F | high << 7) + 0x80;
+            }
+
+            for _ in 0..len {
+                output.push(output[output.len() - offset - 1]);
+            }
+        }
+
+        assert_eq!(reader.position, input.len(), "Trailing compressed bytes");
+        output
+    }
+
+    fn copy(output: &mut Vec<u8>, offset: usize, len: usize) {
+        for _ in 0..len {
+            output.push(output[output.len() - offset]);
+        }
+    }
+
+    fn unzx0(input: &[u8]) -> Vec<u8> {
+        let mut reader = BitReader {
+            input,
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        let mut offset = ZX0_INITIAL_OFFSET;
+
+        loop {
+            for _ in 0..reader.interlaced_elias_gamma(false) {
+                output.push(reader.byte());
+            }
+
+            if !reader.bit() {
+                let len = reader.interlaced_elias_gamma(false);
+                copy(&mut output, offset, len);
+
+                if !reader.bit() {
+                    continue;
+                }
+            }
+
+            loop {
+                let high = reader.interlaced_elias_gamma(true);
+
+                if high == ZX0_END_MARKER {
+                    assert_eq!(reader.position, input.len(), "Trailing compressed bytes");
+                    return output;
+                }
+
+                offset = high * 128 - usize::from(reader.byte() >> 1);
+                reader.backtrack = true;
+
+                let len = reader.interlaced_elias_gamma(false) + 1;
+                copy(&mut output, offset, len);
+
+                if !reader.bit() {
+                    break;
+                }
+            }
+        }
+    }
+
+    fn unrle(mut input: &[u8]) -> Vec<u8> {
+        let mut output = Vec::new();
+
+        while let [count, rest @ ..] = input {
+            let count = usize::from(*count);
+
+            if count < 0x80 {
+                output.extend_from_slice(&rest[..=count]);
+                input = &rest[count + 1..];
+            } else {
+                output.extend(std::iter::repeat_n(rest[0], count - 0x80 + 3));
+                input = &rest[1..];
+            }
+        }
+
+        output
+    }
+
+    fn rle_round_trip(data: &[u8]) -> Vec<u8> {
+        let encoded = Compression::Rle.compress(data).unwrap();
+        assert_eq!(unrle(&encoded), data);
+        encoded
+    }
+
+    #[test]
+    fn rle_runs() {
+        assert_eq!(rle_round_trip(&[]), []);
+        assert_eq!(rle_round_trip(&[7; 3]), [0x80, 7]);
+        assert_eq!(
+            rle_round_trip(b"ab\0\0\0\0cc"),
+            [1, b'a', b'b', 0x81, 0, 1, b'c', b'c']
+        );
+
+        // Split into the longest repeats
+        assert_eq!(rle_round_trip(&[1; 300]), [0xFF, 1, 0xFF, 1, 0xA5, 1]);
+    }
+
+    // Every byte differs from the last, so only the count bytes are added
+    #[test]
+    fn rle_incompressible() {
+        let data = (0..1000_u32).map(|index| index as u8).collect::<Vec<_>>();
+        let encoded = rle_round_trip(&data);
+
+        assert_eq!(
+            encoded.len(),
+            data.len() + data.len().div_ceil(RLE_MAX_LITERAL)
+        );
+    }
+
+    fn round_trip(data: &[u8]) -> Vec<u8> {
+        let compressed = Compression::Zx7.compress(data).unwrap();
+        assert_eq!(unzx7(&compressed), data);
+        compressed
+    }
+
+    #[test]
+    fn zx7_literals() {
+        round_trip(b"a");
+        round_trip(b"abcdefg");
+    }
+
+    #[test]
+    fn zx7_runs() {
+        let compressed = round_trip(&[0; 4000]);
+        assert!(compressed.len() < 16);
+
+        round_trip(b"abcabcabcabcabcabcxyzabcabc");
+    }
+
+    #[test]
+    fn zx7_long_offsets() {
+        // Pseudo-random, so matches only come from the repeated block far back
+        let block = (0..1500_u32)
+            .map(|index| (index.wrapping_mul(2_654_435_761) >> 13) as u8)
+            .collect::<Vec<_>>();
+        let data = [&block[..], b"separator", &block[..]].concat();
+
+        let compressed = round_trip(&data);
+        assert!(compressed.len() < block.len() + 100);
+    }
+
+    #[test]
+    fn zx7_empty() {
+        let error = Compression::Zx7.compress(&[]).unwrap_err();
+        assert_eq!(error.to_string(), "Can't compress empty data with ZX7");
+    }
+
+    fn zx0_round_trip(data: &[u8]) -> Vec<u8> {
+        let compressed = Compression::Zx0.compress(data).unwrap();
+        assert_eq!(unzx0(&compressed), data);
+        compressed
+    }
+
+    #[test]
+    fn zx0_literals() {
+        assert_eq!(zx0_round_trip(b"a"), [0xD5, b'a', 0x55, 0x60]);
+        zx0_round_trip(b"abcdefg");
+    }
+
+    #[test]
+    fn zx0_runs() {
+        let compressed = zx0_round_trip(&[0; 4000]);
+        assert!(compressed.len() < 16);
+
+        zx0_round_trip(b"abcabcabcabcabcabcxyzabcabc");
+        zx0_round_trip(&[[0; 300].as_slice(), b"abc", &[0; 300], b"abd"].concat());
+    }
+
+    // The repeated offset is used between the literals
+    #[test]
+    fn zx0_repeat_offsets() {
+        let data = b"0123456789a0123456789b0123456789c0123456789";
+        let compressed = zx0_round_trip(data);
+
+        assert!(compressed.len() < Compression::Zx7.compress(data).unwrap().len());
+    }
+
+    #[test]
+    fn zx0_long_offsets() {
+        // Too far back for ZX7
+        let block = (0..5000_u32)
+            .map(|index| (index.wrapping_mul(2_654_435_761) >> 13) as u8)
+            .collect::<Vec<_>>();
+        let data = [&block[..], b"separator", &block[..]].concat();
+
+        let compressed = zx0_round_trip(&data);
+        assert!(compressed.len() < block.len() + 100);
+    }
+
+    #[test]
+    fn zx0_incompressible() {
+        let data = (0..1000_u32)
+            .map(|index| (index.wrapping_mul(2_654_435_761) >> 13) as u8)
+            .collect::<Vec<_>>();
+
+        zx0_round_trip(&data);
+    }
+
+    #[test]
+    fn zx0_empty() {
+        let error = Compression::Zx0.compress(&[]).unwrap_err();
+        assert_eq!(error.to_string(), "Can't compress empty data with ZX0");
+    }
+}
@@ -1,18 +1,29 @@
-use std::{hash::Hash, io::SeekFrom, path::PathBuf};
+use std::{
+    hash::Hash,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
-use anyhow::{Context, bail};
+use anyhow::{Context, anyhow, bail};
 use indexmap::IndexMap;
-use tokio::io::{AsyncSeek, AsyncSeekExt, AsyncWrite, AsyncWriteExt};
 use u24::u24;
 
-use crate::{prelude::*, tracker::SerialTracker};
+use crate::{
+    backend::{Files, SectorWrite},
+    prelude::*,
+    ti_float::to_ti_float,
+    tracker::SerialTracker,
+};
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub enum ScaleRounding {
+    /// Fails if the rounded up pointer is past the end of the sector it points into
     Ceiling,
     Nearest,
     #[default]
     Floor,
+    /// Fails unless the pointer is a multiple of the scale
+    Exact,
 }
 
 impl ScaleRounding {
@@ -26,7 +37,7 @@ impl ScaleRounding {
                     (value + 1) / scale
                 }
             }
-            Self::Floor => value / scale,
+            Self::Floor | Self::Exact => value / scale,
         }
     }
 }
@@ -56,6 +67,105 @@ impl Scale for (ScaleRounding, usize) {
     }
 }
 
+/// About how many bytes of a repeated pattern are written at once
+const REPEAT_CHUNK_SIZE: usize = 4096;
+
+/// The smallest value a signed 24-bit field can hold
+pub const I24_MIN: i32 = -(1 << 23);
+/// The largest value a signed 24-bit field can hold
+pub const I24_MAX: i32 = (1 << 23) - 1;
+
+/// The largest value a 48-bit field can hold
+pub const U48_MAX: u64 = (1 << 48) - 1;
+
+/// Computes a field's value once every sector's position is known
+#[derive(Clone)]
+pub struct DeferredValue<S>(Arc<DeferredFn<S>>);
+
+type DeferredFn<S> = dyn Fn(&SerialReport<S>) -> anyhow::Result<u32> + Send + Sync;
+
+impl<S> DeferredValue<S> {
+    pub fn new(
+        value: impl Fn(&SerialReport<S>) -> anyhow::Result<u32> + Send + Sync + 'static,
+    ) -> Self {
+        Self(Arc::new(value))
+    }
+
+    pub fn resolve(&self, report: &SerialReport<S>) -> anyhow::Result<u32> {
+        (self.0)(report)
+    }
+}
+
+impl<S: Hash + Eq + Clone + Send + Sync + 'static> DeferredValue<S> {
+    /// Resolves with a report of only the `keys` sectors, under their original keys
+    pub(crate) fn map_keys<T: Hash + Eq + Send + Sync + 'static>(
+        self,
+        keys: &Arc<[(S, T)]>,
+    ) -> DeferredValue<T> {
+        let keys = Arc::clone(keys);
+
+        DeferredValue::new(move |report: &SerialReport<T>| {
+            let sectors = keys
+                .iter()
+                .filter_map(|(key, mapped)| {
+                    let layout = report.sector(mapped)?;
+
+                    Some(SectorLayout {
+                        key: key.clone(),
+                        start: layout.start,
+                        size: layout.size,
+                        field_count: layout.field_count,
+                    })
+                })
+                .collect();
+            let pointer_widths = report
+                .pointer_widths
+                .iter()
+                .filter_map(|width| {
+                    let (key, _) = keys.iter().find(|(_, mapped)| mapped == &width.sector)?;
+
+                    Some(PointerWidth {
+                        sector: key.clone(),
+                        field: width.field,
+                        bytes: width.bytes,
+                    })
+                })
+                .collect();
+
+            self.resolve(&SerialReport {
+                sectors,
+                total_size: report.total_size,
+                pointer_widths,
+            })
+        })
+    }
+}
+
+// Closures can't be compared, so values are only equal if they're the same closure
+impl<S> PartialEq for DeferredValue<S> {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl<S> Eq for DeferredValue<S> {}
+
+impl<S> std::fmt::Debug for DeferredValue<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad("DeferredValue")
+    }
+}
+
+/// The order multi-byte integers are written in
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Endianness {
+    /// Least significant byte first, as used by the eZ80
+    #[default]
+    Little,
+    /// Most significant byte first, also known as network order
+    Big,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum SerialField<S: Hash + Eq> {
     /// Refences data that isn't know yet
@@ -64,33 +174,446 @@ pub enum SerialField<S: Hash + Eq> {
         sector: S,
         /// Index from begining of first sector
         index: usize,
+        /// Added to the pointer before it's scaled
+        offset: isize,
         scale: usize,
         rounding: ScaleRounding,
+        /// Pads the start of `sector` so the pointer is a multiple of `scale` before it's scaled
+        padded: bool,
+        bytes: usize,
+        endianness: Option<Endianness>,
+    },
+    /// A pointer just past the last byte of a sector
+    DynamicEnd {
+        origin: S,
+        sector: S,
+        bytes: usize,
+    },
+    /// A pointer from the position of the pointer itself
+    DynamicSelf {
+        sector: S,
+        index: usize,
+        bytes: usize,
+    },
+    /// A two's complement pointer, which is negative if the field is before its origin
+    ///
+    /// Checked to fit on build
+    DynamicSigned {
+        origin: S,
+        sector: S,
+        index: usize,
+        bytes: usize,
+    },
+    /// A pointer in as few bytes as its value fits in, up to three, chosen when tracking
+    DynamicAuto {
+        origin: S,
+        sector: S,
+        index: usize,
+    },
+    /// Written in `bytes` bytes once the layout is known
+    ///
+    /// Checked to fit on build
+    Deferred {
+        value: DeferredValue<S>,
         bytes: usize,
     },
     /// File to be loaded on build
     External {
         path: PathBuf,
+        /// Where in the file to start reading; the whole file is included if `None`
+        offset: Option<u64>,
         /// Is checked on build
-        size: usize,
+        ///
+        /// Read from the file's metadata when tracking if `None`
+        size: Option<usize>,
     },
     U8(u8),
-    U16(u16),
-    U24(u24),
-    U32(u32),
-    U64(u64),
-    /// Variable width null terminated string
-    String(String),
+    /// Uses the builder's endianness if `None`
+    U16(u16, Option<Endianness>),
+    U24(u24, Option<Endianness>),
+    /// Two's complement, using the builder's endianness if `None`
+    ///
+    /// Checked to fit in 24 bits on build
+    I24(i32, Option<Endianness>),
+    U32(u32, Option<Endianness>),
+    U64(u64, Option<Endianness>),
+    U128(u128, Option<Endianness>),
+    /// IEEE 754 single precision, stored as bits so NaNs are kept exactly
+    F32(u32, Option<Endianness>),
+    /// IEEE 754 double precision, stored as bits so NaNs are kept exactly
+    F64(u64, Option<Endianness>),
+    /// A TI-OS real, stored as the bits of an `f64`
+    ///
+    /// Checked to be within range on build
+    TiFloat(u64),
+    /// Uses the builder's endianness if `None`
+    ///
+    /// Checked to fit in 48 bits on build
+    U48(u64, Option<Endianness>),
+    /// Unsigned LEB128, seven bits per byte with the lowest first
+    VarUInt(u64),
+    /// Variable width string
+    String {
+        value: String,
+        /// Written after the string if there is one
+        terminator: Option<u8>,
+    },
+    /// Prefixed by its length in `bytes` bytes, without a terminator
+    ///
+    /// Checked to fit in the prefix on build
+    LengthPrefixedString {
+        value: String,
+        bytes: usize,
+    },
+    /// Padded to exactly `len` bytes without a terminator
+    ///
+    /// Checked to fit on build
+    FixedString {
+        value: String,
+        len: usize,
+        pad: u8,
+    },
     Bytes(Vec<u8>),
+    /// Unsigned integers of `bytes` bytes each, using the builder's endianness
+    Array {
+        values: Vec<u32>,
+        bytes: usize,
+    },
+    /// Bytes that can be shared between fields and builders without copying
+    SharedBytes(Arc<[u8]>),
+    /// The pattern written `count` times, without collecting every copy first
+    Repeat {
+        pattern: Vec<u8>,
+        count: usize,
+    },
+    /// The size of a whole sector
+    SectorSize {
+        sector: S,
+        bytes: usize,
+    },
+    /// The distance from the start of one sector to the start of another
+    Span {
+        from: S,
+        to: S,
+        bytes: usize,
+    },
+    /// The distance from one field to another, which can be in different sectors
+    Delta {
+        from: S,
+        from_index: usize,
+        to: S,
+        to_index: usize,
+        bytes: usize,
+    },
+    /// A checksum of every sector from `first` to `last`, patched in once they're written
+    Checksum {
+        first: S,
+        last: S,
+        algorithm: ChecksumAlgorithm,
+    },
+    /// Pads up to the next multiple of `boundary` from the start of the output
+    Align {
+        boundary: usize,
+        pad: u8,
+    },
     /// Fills data up to offset from origin
     /// Errors if past origin
     Fill {
         origin: S,
         fill: usize,
+        pad: u8,
+        /// Writes nothing if already past the fill amount, rather than erroring
+        at_least: bool,
     },
 }
 
+impl<S: Hash + Eq> SerialField<S> {
+    /// Replaces every sector key the field references
+    pub(crate) fn map_keys<T: Hash + Eq>(
+        self,
+        map: &impl Fn(S) -> T,
+        // Closures can't be rewritten, so deferred values are mapped by the caller
+        deferred: &impl Fn(DeferredValue<S>) -> anyhow::Result<DeferredValue<T>>,
+    ) -> anyhow::Result<SerialField<T>> {
+        Ok(match self {
+            Self::Deferred { value, bytes } => SerialField::Deferred {
+                value: deferred(value)?,
+                bytes,
+            },
+            Self::Dynamic {
+                origin,
+                sector,
+                index,
+                offset,
+                scale,
+                rounding,
+                padded,
+                bytes,
+                endianness,
+            } => SerialField::Dynamic {
+                origin: map(origin),
+                sector: map(sector),
+                index,
+                offset,
+                scale,
+                rounding,
+                padded,
+                bytes,
+                endianness,
+            },
+            Self::DynamicEnd {
+                origin,
+                sector,
+                bytes,
+            } => SerialField::DynamicEnd {
+                origin: map(origin),
+                sector: map(sector),
+                bytes,
+            },
+            Self::DynamicSelf {
+                sector,
+                index,
+                bytes,
+            } => SerialField::DynamicSelf {
+                sector: map(sector),
+                index,
+                bytes,
+            },
+            Self::DynamicSigned {
+                origin,
+                sector,
+                index,
+                bytes,
+            } => SerialField::DynamicSigned {
+                origin: map(origin),
+                sector: map(sector),
+                index,
+                bytes,
+            },
+            Self::DynamicAuto {
+                origin,
+                sector,
+                index,
+            } => SerialField::DynamicAuto {
+                origin: map(origin),
+                sector: map(sector),
+                index,
+            },
+            Self::External { path, offset, size } => SerialField::External { path, offset, size },
+            Self::U8(value) => SerialField::U8(value),
+            Self::U16(value, endianness) => SerialField::U16(value, endianness),
+            Self::U24(value, endianness) => SerialField::U24(value, endianness),
+            Self::I24(value, endianness) => SerialField::I24(value, endianness),
+            Self::U32(value, endianness) => SerialField::U32(value, endianness),
+            Self::U64(value, endianness) => SerialField::U64(value, endianness),
+            Self::U128(value, endianness) => SerialField::U128(value, endianness),
+            Self::F32(value, endianness) => SerialField::F32(value, endianness),
+            Self::F64(value, endianness) => SerialField::F64(value, endianness),
+            Self::TiFloat(value) => SerialField::TiFloat(value),
+            Self::U48(value, endianness) => SerialField::U48(value, endianness),
+            Self::VarUInt(value) => SerialField::VarUInt(value),
+            Self::String { value, terminator } => SerialField::String { value, terminator },
+            Self::LengthPrefixedString { value, bytes } => {
+                SerialField::LengthPrefixedString { value, bytes }
+            }
+            Self::FixedString { value, len, pad } => SerialField::FixedString { value, len, pad },
+            Self::Bytes(value) => SerialField::Bytes(value),
+            Self::Array { values, bytes } => SerialField::Array { values, bytes },
+            Self::SharedBytes(value) => SerialField::SharedBytes(value),
+            Self::Repeat { pattern, count } => SerialField::Repeat { pattern, count },
+            Self::SectorSize { sector, bytes } => SerialField::SectorSize {
+                sector: map(sector),
+                bytes,
+            },
+            Self::Span { from, to, bytes } => SerialField::Span {
+                from: map(from),
+                to: map(to),
+                bytes,
+            },
+            Self::Delta {
+                from,
+                from_index,
+                to,
+                to_index,
+                bytes,
+            } => SerialField::Delta {
+                from: map(from),
+                from_index,
+                to: map(to),
+                to_index,
+                bytes,
+            },
+            Self::Checksum {
+                first,
+                last,
+                algorithm,
+            } => SerialField::Checksum {
+                first: map(first),
+                last: map(last),
+                algorithm,
+            },
+            Self::Align { boundary, pad } => SerialField::Align { boundary, pad },
+            Self::Fill {
+                origin,
+                fill,
+                pad,
+                at_least,
+            } => SerialField::Fill {
+                origin: map(origin),
+                fill,
+                pad,
+                at_least,
+            },
+        })
+    }
+}
+
 impl<S: Hash + Eq + Clone + std::fmt::Debug> SerialField<S> {
+    /// The field with an automatic pointer's width chosen, or the field itself if it isn't one
+    pub(crate) fn with_pointer_width(&self, bytes: usize) -> Self {
+        match self {
+            Self::DynamicAuto {
+                origin,
+                sector,
+                index,
+            } => Self::Dynamic {
+                origin: origin.clone(),
+                sector: sector.clone(),
+                index: *index,
+                offset: 0,
+                scale: 1,
+                rounding: ScaleRounding::default(),
+                padded: false,
+                bytes,
+                endianness: None,
+            },
+            field => field.clone(),
+        }
+    }
+
+    /// The name of the field's variant, for describing layouts
+    pub(crate) const fn kind(&self) -> &'static str {
+        match self {
+            Self::Dynamic { .. } => "Dynamic",
+            Self::DynamicEnd { .. } => "DynamicEnd",
+            Self::DynamicSelf { .. } => "DynamicSelf",
+            Self::DynamicSigned { .. } => "DynamicSigned",
+            Self::DynamicAuto { .. } => "DynamicAuto",
+            Self::Deferred { .. } => "Deferred",
+            Self::External { .. } => "External",
+            Self::U8(..) => "U8",
+            Self::U16(..) => "U16",
+            Self::U24(..) => "U24",
+            Self::I24(..) => "I24",
+            Self::U32(..) => "U32",
+            Self::U64(..) => "U64",
+            Self::U128(..) => "U128",
+            Self::F32(..) => "F32",
+            Self::F64(..) => "F64",
+            Self::TiFloat(..) => "TiFloat",
+            Self::U48(..) => "U48",
+            Self::VarUInt(..) => "VarUInt",
+            Self::String { .. } => "String",
+            Self::LengthPrefixedString { .. } => "LengthPrefixedString",
+            Self::FixedString { .. } => "FixedString",
+            Self::Bytes(..) => "Bytes",
+            Self::Array { .. } => "Array",
+            Self::SharedBytes(..) => "SharedBytes",
+            Self::Repeat { .. } => "Repeat",
+            Self::SectorSize { .. } => "SectorSize",
+            Self::Span { .. } => "Span",
+            Self::Delta { .. } => "Delta",
+            Self::Checksum { .. } => "Checksum",
+            Self::Align { .. } => "Align",
+            Self::Fill { .. } => "Fill",
+        }
+    }
+
+    /// Fails if the field is a string with a character that isn't ASCII
+    pub(crate) fn check_ascii(&self) -> anyhow::Result<()> {
+        let (Self::String { value, .. }
+        | Self::LengthPrefixedString { value, .. }
+        | Self::FixedString { value, .. }) = self
+        else {
+            return Ok(());
+        };
+
+        match value
+            .char_indices()
+            .find(|(_, character)| !character.is_ascii())
+        {
+            Some((index, character)) => bail!(
+                "String contains non-ASCII character {character:?} at index {index}: {value:?}"
+            ),
+            None => Ok(()),
+        }
+    }
+
+    /// The sector and field a pointer points to and the value written for it,
+    /// or `None` if the field isn't a pointer.
+    /// Pointers to the end of a sector have no field.
+    /// `offset` is where the pointer starts in the output.
+    pub(crate) fn resolve_pointer(
+        &self,
+        sectors: &IndexMap<S, SerialSectorBuilder<S>>,
+        tracker: &SerialTracker<S>,
+        offset: usize,
+    ) -> anyhow::Result<Option<(&S, Option<usize>, i128)>> {
+        let (sector, index, value) = match self {
+            Self::Dynamic {
+                origin,
+                sector,
+                index,
+                offset,
+                scale,
+                rounding,
+                ..
+            } => {
+                let field_offset =
+                    tracker.offset_field_from_sector(origin, sector, *index, sectors)?;
+                let pointer = field_offset.checked_add_signed(*offset).with_context(|| {
+                    format!(
+                        "Dynamic pointer from {origin:?} to {sector:?} is negative: \
+                         {field_offset} + {offset} < 0"
+                    )
+                })?;
+
+                (
+                    sector,
+                    Some(*index),
+                    rounding.apply(pointer, *scale) as i128,
+                )
+            }
+            Self::DynamicEnd { origin, sector, .. } => {
+                let end = tracker.sector_end(sector)?;
+                let origin_offset = tracker.offset_from_origin(origin)?;
+                (sector, None, end as i128 - origin_offset as i128)
+            }
+            Self::DynamicSelf { sector, index, .. } => {
+                let position = tracker.field_position(sector, *index, sectors)?;
+                (sector, Some(*index), position as i128 - offset as i128)
+            }
+            Self::DynamicSigned {
+                origin,
+                sector,
+                index,
+                ..
+            } => {
+                let position = tracker.field_position(sector, *index, sectors)?;
+                let origin_offset = tracker.offset_from_origin(origin)?;
+                (
+                    sector,
+                    Some(*index),
+                    position as i128 - origin_offset as i128,
+                )
+            }
+            Self::DynamicAuto { .. } => bail!("Automatic pointer width wasn't chosen"),
+            _ => return Ok(None),
+        };
+
+        Ok(Some((sector, index, value)))
+    }
+
     pub(crate) fn calculate_size(
         &self,
         offset: usize,
@@ -98,159 +621,578 @@ impl<S: Hash + Eq + Clone + std::fmt::Debug> SerialField<S> {
     ) -> anyhow::Result<usize> {
         match self {
             // Add one for null terminator
-            Self::String(value) => Ok(value.len() + 1),
+            Self::String { value, terminator } => {
+                Ok(value.len() + usize::from(terminator.is_some()))
+            }
+            Self::LengthPrefixedString { value, bytes } => Ok(bytes + value.len()),
+            Self::FixedString { len, .. } => Ok(*len),
             Self::Dynamic {
                 sector: _,
                 index: _,
                 origin: _,
+                offset: _,
                 scale: _,
                 rounding: _,
+                padded: _,
                 bytes,
+                endianness: _,
             } => Ok(*bytes),
-            Self::U24(_) => Ok(3),
+            Self::DynamicEnd { bytes, .. }
+            | Self::DynamicSelf { bytes, .. }
+            | Self::DynamicSigned { bytes, .. } => Ok(*bytes),
+            Self::DynamicAuto { .. } => bail!("Automatic pointer width wasn't chosen"),
+            Self::U24(..) | Self::I24(..) => Ok(3),
             Self::U8(_) => Ok(1),
-            Self::U16(_) => Ok(2),
-            Self::U32(_) => Ok(4),
-            Self::U64(_) => Ok(8),
+            Self::U16(..) => Ok(2),
+            Self::U32(..) | Self::F32(..) => Ok(4),
+            Self::U64(..) | Self::F64(..) => Ok(8),
+            Self::U128(..) => Ok(16),
+            Self::U48(..) => Ok(6),
+            Self::TiFloat(_) => Ok(9),
+            Self::VarUInt(value) => Ok(leb128(*value).len()),
             Self::Bytes(value) => Ok(value.len()),
-            Self::External { path: _, size } => Ok(*size),
-            Self::Fill { origin, fill } => {
+            Self::Array { values, bytes } => Ok(values.len() * bytes),
+            Self::SharedBytes(value) => Ok(value.len()),
+            Self::Repeat { pattern, count } => pattern
+                .len()
+                .checked_mul(*count)
+                .context("Failed to serialize; repeated bytes overflow usize"),
+            Self::SectorSize { sector: _, bytes } => Ok(*bytes),
+            Self::Deferred { value: _, bytes } => Ok(*bytes),
+            Self::Span {
+                from: _,
+                to: _,
+                bytes,
+            } => Ok(*bytes),
+            Self::Delta { bytes, .. } => Ok(*bytes),
+            Self::External { path, size, .. } => match size {
+                Some(size) => Ok(*size),
+                None => tracker.external_size(path),
+            },
+            Self::Checksum {
+                first: _,
+                last: _,
+                algorithm,
+            } => Ok(algorithm.size()),
+            Self::Align { boundary, pad: _ } => Self::align_size(offset, *boundary),
+            Self::Fill {
+                origin,
+                fill,
+                pad: _,
+                at_least,
+            } => {
                 let origin_position = tracker.offset_from_origin(origin)?;
-                Self::fill_size(offset, origin_position, *fill)
+                Self::fill_size(offset, origin_position, *fill, *at_least)
             }
         }
     }
 
-    pub(crate) async fn build(
+    pub(crate) async fn build<F: Files>(
         &self,
-        buffer: &mut (impl AsyncWrite + Unpin + AsyncSeek),
+        buffer: &mut impl SectorWrite,
         sectors: &IndexMap<S, SerialSectorBuilder<S>>,
         tracker: &SerialTracker<S>,
+        default_endianness: Endianness,
+        // Where the field starts in the output
+        offset: usize,
     ) -> anyhow::Result<()> {
         match self {
-            Self::String(value) => {
+            Self::String { value, terminator } => {
+                if let Some(terminator) = terminator
+                    && let Some(index) = value.bytes().position(|byte| byte == *terminator)
+                {
+                    bail!(
+                        "String contains its terminator {terminator:#04X} at index {index}, \
+                         which would end it early: {value:?}"
+                    );
+                }
+
+                buffer.write_all(value.as_bytes()).await?;
+
+                if let Some(terminator) = terminator {
+                    buffer.write_all(&[*terminator]).await?;
+                }
+            }
+            Self::VarUInt(value) => buffer.write_all(&leb128(*value)).await?,
+            Self::LengthPrefixedString { value, bytes } => {
+                write_sized(
+                    buffer,
+                    "String length",
+                    value.len(),
+                    *bytes,
+                    default_endianness,
+                )
+                .await?;
+                buffer.write_all(value.as_bytes()).await?;
+            }
+            Self::FixedString { value, len, pad } => {
+                let Some(padding) = len.checked_sub(value.len()) else {
+                    bail!(
+                        "String is longer than its fixed length: {value:?} is {} bytes > {len} bytes",
+                        value.len()
+                    );
+                };
+
                 buffer.write_all(value.as_bytes()).await?;
-                buffer.write_u8(0).await?;
+                buffer.write_all(&vec![*pad; padding]).await?;
             }
             Self::Bytes(value) => buffer.write_all(value).await?,
+            Self::Array { values, bytes } => {
+                let mut output = Vec::with_capacity(values.len() * bytes);
+
+                for value in values {
+                    let value = &value.to_le_bytes()[..*bytes];
+
+                    match default_endianness {
+                        Endianness::Little => output.extend_from_slice(value),
+                        Endianness::Big => output.extend(value.iter().rev()),
+                    }
+                }
+
+                buffer.write_all(&output).await?;
+            }
+            Self::SharedBytes(value) => buffer.write_all(value).await?,
+            Self::Repeat { pattern, count } => {
+                // Whole patterns, up to the chunk size
+                let per_chunk = (REPEAT_CHUNK_SIZE / pattern.len().max(1)).max(1);
+                let chunk = pattern.repeat(per_chunk.min(*count));
+                let mut remaining = *count;
+
+                while remaining != 0 {
+                    let patterns = per_chunk.min(remaining);
+                    buffer.write_all(&chunk[..patterns * pattern.len()]).await?;
+                    remaining -= patterns;
+                }
+            }
             Self::Dynamic {
                 sector,
                 index,
                 origin,
+                offset,
                 scale,
                 rounding,
+                padded: _,
                 bytes,
+                endianness,
             } => {
-                let pointer =
-                    tracker.offset_field_from_sector(origin, sector, *index, sectors, tracker)?;
-
-                // Not always what the user wants
-                // TODO: Add scale aligned check
-                //if !pointer.is_multiple_of(*scale) {
-                //    bail!(
-                //        "Dynamic pointer isn't aligned to scale: {} % {} != 0, off by {}",
-                //        pointer,
-                //        scale,
-                //        pointer % scale
-                //    );
-                //}
-
-                macro_rules! match_bytes {
-                    (
-                        $bytes: ident,
-                        $rounding: ident,
-                        $pointer: ident,
-                        $scale: ident,
-                        [$((
-                            $type: ty,
-                            $byte_count: literal,
-                            $try_from: ident,
-                            |$p: ident| $writer: expr$(,)?
-                        )),+$(,)?]$(,)?
-                    ) => {
-                        match $bytes {
-                            $($byte_count => {
-                                let $p =
-                                    <$type>::$try_from($rounding.apply($pointer, *$scale) as u32).with_context(|| {
-                                        format!(
-                                            "Pointer exceeds {}-bit limit: {} bytes > {} bytes",
-                                            <$type>::BITS,
-                                            pointer,
-                                            <$type>::MAX
-                                        )
-                                    })?;
-                                $writer.await?;
-                            })+,
-                            _ => {
-                                ::anyhow::bail!(
-                                    "Unsupported dynamic pointer; length {} is unsupported",
-                                    $bytes
-                                )
-                            }
-                        }
-                    };
+                let field_offset =
+                    tracker.offset_field_from_sector(origin, sector, *index, sectors)?;
+                let pointer = field_offset.checked_add_signed(*offset).with_context(|| {
+                    format!(
+                        "Dynamic pointer from {origin:?} to {sector:?} is negative: \
+                         {field_offset} + {offset} < 0"
+                    )
+                })?;
+
+                if *rounding == ScaleRounding::Exact && !pointer.is_multiple_of(*scale) {
+                    bail!(
+                        "Dynamic pointer from {origin:?} to {sector:?} isn't a multiple of its \
+                         scale with {rounding:?} rounding: {pointer} % {scale} = {}",
+                        pointer % scale
+                    );
+                }
+
+                let scaled = rounding.apply(pointer, *scale);
+
+                if *rounding == ScaleRounding::Ceiling {
+                    // Rounding up moves where the pointer points forwards
+                    let address = field_offset + (scaled * scale - pointer);
+                    let end = tracker.sector_end(sector)? - tracker.offset_from_origin(origin)?;
+
+                    if address > end {
+                        bail!(
+                            "Dynamic pointer from {origin:?} to {sector:?} is past the end of \
+                             its sector with {rounding:?} rounding: {address} > {end}"
+                        );
+                    }
+                }
+
+                let endianness = endianness.unwrap_or(default_endianness);
+                write_sized(buffer, "Pointer", scaled, *bytes, endianness).await?;
+            }
+            Self::DynamicEnd {
+                origin,
+                sector,
+                bytes,
+            } => {
+                let end = tracker.sector_end(sector)?;
+                let origin_offset = tracker.offset_from_origin(origin)?;
+                let pointer = end.checked_sub(origin_offset).with_context(|| {
+                    format!(
+                        "Pointer from {origin:?} to the end of {sector:?} is negative: \
+                         {end} < {origin_offset}"
+                    )
+                })?;
+
+                write_sized(buffer, "Pointer", pointer, *bytes, default_endianness).await?;
+            }
+            Self::DynamicSelf {
+                sector,
+                index,
+                bytes,
+            } => {
+                let position = tracker.field_position(sector, *index, sectors)?;
+                let pointer = position.checked_sub(offset).with_context(|| {
+                    format!(
+                        "Self-relative pointer to {sector:?} is before the pointer: \
+                         {position} < {offset}"
+                    )
+                })?;
+
+                write_sized(buffer, "Pointer", pointer, *bytes, default_endianness).await?;
+            }
+            Self::DynamicSigned {
+                origin,
+                sector,
+                index,
+                bytes,
+            } => {
+                let origin_offset = tracker.offset_from_origin(origin)?;
+                let position = tracker.field_position(sector, *index, sectors)?;
+                let pointer = position as i128 - origin_offset as i128;
+
+                let bits = bytes * 8;
+                let (min, max) = match bytes {
+                    1..=8 => (-1i128 << (bits - 1), (1i128 << (bits - 1)) - 1),
+                    _ => bail!("Unsupported signed pointer length: {bytes} bytes"),
+                };
+
+                if !(min..=max).contains(&pointer) {
+                    bail!(
+                        "Signed pointer from {origin:?} to {sector:?} exceeds signed {bits}-bit \
+                         range: {pointer} is not within {min}..={max}"
+                    );
                 }
 
-                match_bytes!(
-                    bytes,
-                    rounding,
-                    pointer,
-                    scale,
-                    [
-                        (u8, 1, try_from, |p| buffer.write_u8(p)),
-                        (u16, 2, try_from, |p| buffer.write_u16_le(p)),
-                        (u24, 3, checked_from_u32, |p| buffer
-                            .write_all(&p.to_le_bytes())),
-                        (u32, 4, try_from, |p| buffer.write_u32_le(p)),
-                    ],
-                );
+                let mut value = pointer.to_le_bytes()[..*bytes].to_vec();
+
+                if default_endianness == Endianness::Big {
+                    value.reverse();
+                }
+
+                buffer.write_all(&value).await?;
             }
+            Self::DynamicAuto { .. } => bail!("Automatic pointer width wasn't chosen"),
             Self::U8(value) => {
-                buffer.write_u8(*value).await?;
+                buffer.write_all(&[*value]).await?;
+            }
+            Self::U16(value, endianness) => match endianness.unwrap_or(default_endianness) {
+                Endianness::Little => buffer.write_all(&value.to_le_bytes()).await?,
+                Endianness::Big => buffer.write_all(&value.to_be_bytes()).await?,
+            },
+            Self::U24(value, endianness) => {
+                let mut bytes = value.to_le_bytes();
+
+                if endianness.unwrap_or(default_endianness) == Endianness::Big {
+                    bytes.reverse();
+                }
+
+                buffer.write_all(&bytes).await?;
+            }
+            Self::U32(value, endianness) | Self::F32(value, endianness) => {
+                match endianness.unwrap_or(default_endianness) {
+                    Endianness::Little => buffer.write_all(&value.to_le_bytes()).await?,
+                    Endianness::Big => buffer.write_all(&value.to_be_bytes()).await?,
+                }
+            }
+            Self::U64(value, endianness) | Self::F64(value, endianness) => {
+                match endianness.unwrap_or(default_endianness) {
+                    Endianness::Little => buffer.write_all(&value.to_le_bytes()).await?,
+                    Endianness::Big => buffer.write_all(&value.to_be_bytes()).await?,
+                }
+            }
+            Self::U128(value, endianness) => match endianness.unwrap_or(default_endianness) {
+                Endianness::Little => buffer.write_all(&value.to_le_bytes()).await?,
+                Endianness::Big => buffer.write_all(&value.to_be_bytes()).await?,
+            },
+            Self::I24(value, endianness) => {
+                if !(I24_MIN..=I24_MAX).contains(value) {
+                    bail!(
+                        "Value exceeds signed 24-bit range: {value} is not within {I24_MIN}..={I24_MAX}"
+                    );
+                }
+
+                let mut bytes = value.to_le_bytes()[..3].to_vec();
+
+                if endianness.unwrap_or(default_endianness) == Endianness::Big {
+                    bytes.reverse();
+                }
+
+                buffer.write_all(&bytes).await?;
+            }
+            Self::TiFloat(value) => {
+                buffer
+                    .write_all(&to_ti_float(f64::from_bits(*value))?)
+                    .await?;
+            }
+            Self::U48(value, endianness) => {
+                if *value > U48_MAX {
+                    bail!("Value exceeds 48-bit limit: {value} > {U48_MAX}");
+                }
+
+                let mut bytes = value.to_le_bytes()[..6].to_vec();
+
+                if endianness.unwrap_or(default_endianness) == Endianness::Big {
+                    bytes.reverse();
+                }
+
+                buffer.write_all(&bytes).await?;
             }
-            Self::U16(value) => {
-                buffer.write_u16_le(*value).await?;
+            Self::SectorSize { sector, bytes } => {
+                let size = tracker.sector_size(sector)?;
+                write_sized(buffer, "Sector size", size, *bytes, default_endianness)
+                    .await
+                    .with_context(|| format!("Failed to write size of sector: {sector:#?}"))?;
             }
-            Self::U24(value) => {
-                buffer.write_all(&value.to_le_bytes()).await?;
+            Self::Span { from, to, bytes } => {
+                let from_offset = tracker.offset_from_origin(from)?;
+                let to_offset = tracker.offset_from_origin(to)?;
+                let span = to_offset.checked_sub(from_offset).with_context(|| {
+                    format!(
+                        "Span end is before its start: {to:?} at {to_offset} < {from:?} at {from_offset}"
+                    )
+                })?;
+                write_sized(buffer, "Span", span, *bytes, default_endianness).await?;
             }
-            Self::U32(value) => {
-                buffer.write_u32_le(*value).await?;
+            Self::Delta {
+                from,
+                from_index,
+                to,
+                to_index,
+                bytes,
+            } => {
+                // Both are measured from whichever sector comes first
+                let origin =
+                    if tracker.offset_from_origin(from)? <= tracker.offset_from_origin(to)? {
+                        from
+                    } else {
+                        to
+                    };
+                let from_offset =
+                    tracker.offset_field_from_sector(origin, from, *from_index, sectors)?;
+                let to_offset = tracker.offset_field_from_sector(origin, to, *to_index, sectors)?;
+                let delta = to_offset.checked_sub(from_offset).with_context(|| {
+                    format!(
+                        "Delta is negative: {to:?} field {to_index} at {to_offset} < \
+                         {from:?} field {from_index} at {from_offset}, from the start of {origin:?}"
+                    )
+                })?;
+
+                write_sized(buffer, "Delta", delta, *bytes, default_endianness).await?;
             }
-            Self::U64(value) => {
-                buffer.write_u64_le(*value).await?;
+            Self::Deferred { value, bytes } => {
+                let value = value
+                    .resolve(tracker.report()?)
+                    .context("Failed to compute deferred value")?;
+                write_sized(
+                    buffer,
+                    "Deferred value",
+                    usize::try_from(value)?,
+                    *bytes,
+                    default_endianness,
+                )
+                .await?;
             }
-            Self::Fill { origin, fill } => {
-                let offset = buffer.stream_position().await? as usize;
-                let origin_position = tracker.offset_from_origin(origin)?;
-                let fill_amount = Self::fill_size(offset, origin_position, *fill)?;
-                buffer.seek(SeekFrom::Current(fill_amount as i64)).await?;
+            // Patched once the whole output is written
+            Self::Checksum { algorithm, .. } => {
+                buffer.write_all(&vec![0; algorithm.size()]).await?;
             }
-            Self::External { path, size } => {
-                let data = tokio::fs::read(path).await?;
-                let read = buffer.write(&data).await?;
+            Self::Align { boundary, pad } => {
+                let size = Self::align_size(offset, *boundary)?;
+                buffer.write_all(&vec![*pad; size]).await?;
+            }
+            // Written by the sector once more data follows
+            Self::Fill { .. } => (),
+            Self::External {
+                path,
+                offset: Some(start),
+                size: _,
+            } => {
+                let size = self.calculate_size(offset, tracker)?;
+                Self::check_external::<F>(path, Some(*start), size).await?;
+                F::copy(path, *start, size as u64, buffer).await?;
+            }
+            Self::External {
+                path,
+                offset: None,
+                size: _,
+            } => {
+                let size = self.calculate_size(offset, tracker)?;
+                // Checked before copying, so nothing is written for the wrong file
+                let length = Self::check_external::<F>(path, None, size).await?;
+
+                // Copied in chunks, so large files aren't held in memory
+                let copied = F::copy(path, 0, length, buffer).await?;
+
+                // The file may have changed since it was checked
+                if copied != length {
+                    return Err(Self::incorrect_external_size(path, size, copied));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks the external file has the bytes the field is built from, without reading them.
+    /// Returns the file's length.
+    pub(crate) async fn check_external<F: Files>(
+        path: &Path,
+        start: Option<u64>,
+        size: usize,
+    ) -> anyhow::Result<u64> {
+        let length = F::len(path)
+            .await
+            .with_context(|| format!("Failed to open external file: {path:?}"))?;
+
+        match start {
+            Some(start) => {
+                let end = start.checked_add(size as u64).with_context(|| {
+                    format!(
+                        "External file range overflows: {start} + {size} bytes\n\
+                         Path: {path:?}"
+                    )
+                })?;
+
+                if length < start {
+                    bail!(
+                        "External file is shorter than its range's offset:\n\
+                         Offset: {start} bytes, Found: {length} bytes\n\
+                         Path: {path:?}"
+                    );
+                }
 
-                if read != *size {
+                if length < end {
                     bail!(
-                        "External file has incorrect file size:\n\
-                         Expected: {size} bytes, Found: {read} bytes\n\
+                        "External file range extends past the end of the file:\n\
+                         Range: {start}..{end}, Found: {length} bytes\n\
                          Path: {path:?}"
                     );
                 }
             }
+            None if length != size as u64 => {
+                return Err(Self::incorrect_external_size(path, size, length));
+            }
+            None => (),
         }
 
-        Ok(())
+        Ok(length)
+    }
+
+    fn incorrect_external_size(path: &Path, size: usize, found: u64) -> anyhow::Error {
+        anyhow!(
+            "External file has incorrect file size:\n\
+             Expected: {size} bytes, Found: {found} bytes\n\
+             Path: {path:?}"
+        )
+    }
+
+    fn align_size(offset: usize, boundary: usize) -> anyhow::Result<usize> {
+        if boundary == 0 {
+            bail!("Failed to serialize; alignment boundary can't be zero");
+        }
+
+        Ok((boundary - offset % boundary) % boundary)
     }
 
-    fn fill_size(offset: usize, origin_position: usize, fill: usize) -> anyhow::Result<usize> {
+    fn fill_size(
+        offset: usize,
+        origin_position: usize,
+        fill: usize,
+        at_least: bool,
+    ) -> anyhow::Result<usize> {
         let fill_start = offset.checked_sub(origin_position).with_context(|| format!("Failed to serialize; current position is before fill origin: {offset} < {origin_position}"))?;
+
+        if at_least {
+            return Ok(fill.saturating_sub(fill_start));
+        }
+
         fill.checked_sub(fill_start).with_context(|| {
             format!("Failed to serialize; fill start is past fill amount: {fill_start} > {fill}")
         })
     }
 }
 
+/// Seven bits per byte, lowest first, with the top bit set on every byte but the last
+fn leb128(mut value: u64) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(10);
+
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+
+        if value == 0 {
+            bytes.push(byte);
+            return bytes;
+        }
+
+        bytes.push(byte | 0x80);
+    }
+}
+
+/// Writes the value in `bytes` bytes, erroring if it doesn't fit.
+/// Each width is checked separately, so the value is never truncated.
+async fn write_sized(
+    buffer: &mut impl SectorWrite,
+    name: &str,
+    value: usize,
+    bytes: usize,
+    endianness: Endianness,
+) -> anyhow::Result<()> {
+    macro_rules! match_bytes {
+        [$((
+            $type: ty,
+            $byte_count: literal,
+            $convert: expr,
+            |$p: ident| $to_le_bytes: expr$(,)?
+        )),+$(,)?] => {
+            match bytes {
+                $($byte_count => {
+                    let $p = $convert(value).with_context(|| {
+                        format!(
+                            "{name} exceeds {}-bit limit: {value} > {}",
+                            <$type>::BITS,
+                            <$type>::MAX
+                        )
+                    })?;
+                    let mut bytes = $to_le_bytes;
+
+                    if endianness == Endianness::Big {
+                        bytes.reverse();
+                    }
+
+                    buffer.write_all(&bytes).await?;
+                })+,
+                _ => bail!("Unsupported {name} length: {bytes} bytes"),
+            }
+        };
+    }
+
+    match_bytes![
+        (u8, 1, |value| u8::try_from(value).ok(), |p| p.to_le_bytes()),
+        (
+            u16,
+            2,
+            |value| u16::try_from(value).ok(),
+            |p| p.to_le_bytes()
+        ),
+        (
+            u24,
+            3,
+            |value| u32::try_from(value).ok().and_then(u24::checked_from_u32),
+            |p| p.to_le_bytes()
+        ),
+        (
+            u32,
+            4,
+            |value| u32::try_from(value).ok(),
+            |p| p.to_le_bytes()
+        ),
+    ];
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -297,6 +1239,13 @@ mod tests {
         assert_eq!(rounded, 6);
     }
 
+    #[test]
+    fn scale_rounding_exact() {
+        let rounded = ScaleRounding::Exact.apply(26, 13);
+
+        assert_eq!(rounded, 2);
+    }
+
     #[test]
     fn scale_rounding_nearest_0() {
         let rounded = ScaleRounding::Nearest.apply(11, 3);
@@ -0,0 +1,73 @@
+use std::io;
+
+use crate::backend::SectorWrite;
+
+/// Bytes shown on each line of a hexdump
+const BYTES_PER_LINE: usize = 16;
+
+/// Writes through to the inner writer, keeping a copy of everything written
+pub(crate) struct Tee<'a, W> {
+    inner: &'a mut W,
+    pub(crate) written: Vec<u8>,
+}
+
+impl<'a, W> Tee<'a, W> {
+    pub(crate) fn new(inner: &'a mut W) -> Self {
+        Self {
+            inner,
+            written: Vec::new(),
+        }
+    }
+}
+
+impl<W: SectorWrite> SectorWrite for Tee<'_, W> {
+    async fn write_all(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.inner.write_all(bytes).await?;
+        self.written.extend_from_slice(bytes);
+
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush().await
+    }
+}
+
+/// Sixteen bytes per line, each line starting with the label and the offset of its first byte
+pub(crate) fn hexdump(label: &str, offset: usize, bytes: &[u8]) -> String {
+    bytes
+        .chunks(BYTES_PER_LINE)
+        .enumerate()
+        .map(|(line, chunk)| {
+            let hex = chunk
+                .iter()
+                .map(|byte| format!("{byte:02X}"))
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            format!("{label} {:06X}: {hex}", offset + line * BYTES_PER_LINE)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines() {
+        let bytes = (0..20).collect::<Vec<u8>>();
+
+        assert_eq!(
+            hexdump("First", 0x10, &bytes),
+            "First 000010: 00 01 02 03 04 05 06 07 08 09 0A 0B 0C 0D 0E 0F\n\
+             First 000020: 10 11 12 13"
+        );
+    }
+
+    #[test]
+    fn empty() {
+        assert_eq!(hexdump("First", 0, &[]), "");
+    }
+}
@@ -0,0 +1,37 @@
+use std::fmt::{Debug, Display};
+
+/// A problem with a builder found by [`SerialBuilder::validate`]
+///
+/// [`SerialBuilder::validate`]: crate::builder::SerialBuilder::validate
+#[derive(Debug)]
+pub struct SersegIssue<S> {
+    pub sector: S,
+    /// The position of the field in its sector, or `None` if the problem is with the sector itself
+    pub field: Option<usize>,
+    /// The field's label, if it has one
+    pub label: Option<String>,
+    pub error: anyhow::Error,
+}
+
+impl<S: Debug> Display for SersegIssue<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.field {
+            Some(field) => write!(
+                f,
+                "Sector {:?}, field {}: {:#}",
+                self.sector,
+                field_name(field, self.label.as_deref()),
+                self.error
+            ),
+            None => write!(f, "Sector {:?}: {:#}", self.sector, self.error),
+        }
+    }
+}
+
+/// A field's label in quotes, or its index if it isn't labeled
+pub(crate) fn field_name(index: usize, label: Option<&str>) -> String {
+    match label {
+        Some(label) => format!("{label:?}"),
+        None => index.to_string(),
+    }
+}
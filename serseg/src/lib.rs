@@ -1,105 +1,3117 @@
 #![feature(macro_metavar_expr_concat)]
 
+// Lets the derived code refer to `::serseg` from within this crate
+extern crate self as serseg;
+
+pub(crate) mod backend;
+pub mod bitfield;
 pub mod builder;
+pub mod checksum;
+pub mod compression;
 pub mod field;
+pub(crate) mod hexdump;
+pub mod issue;
 pub mod prelude;
+pub mod reader;
+pub mod report;
+#[cfg(feature = "tokio")]
+pub(crate) mod symbols;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub(crate) mod ti_float;
 pub(crate) mod tracker;
 
-#[cfg(test)]
-mod tests {
-    use std::io::Cursor;
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "tokio")]
+    use tokio::io::AsyncReadExt;
+    use u24::u24;
+
+    use crate::prelude::*;
+
+    type Builder = SerialBuilder<ExampleSectorKey>;
+    type SectorBuilder = SerialSectorBuilder<ExampleSectorKey>;
+
+    #[derive(Debug, Clone, Hash, PartialEq, Eq)]
+    enum ExampleSectorKey {
+        First,
+        Second,
+        Third,
+    }
+
+    fn insertion_builder() -> Builder {
+        Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default().dynamic_u8(
+                    ExampleSectorKey::First,
+                    ExampleSectorKey::Third,
+                    0,
+                ),
+            )
+            .sector(ExampleSectorKey::Third, SectorBuilder::default().u8(7))
+    }
+
+    #[tokio::test]
+    async fn sector_insertion() {
+        let second = SectorBuilder::default().u8(5).u8(6);
+        let expected = [3, 5, 6, 7];
+
+        let after = insertion_builder()
+            .sector_after(
+                &ExampleSectorKey::First,
+                ExampleSectorKey::Second,
+                second.clone(),
+            )
+            .unwrap();
+        assert_eq!(after.build_to_vec().await.unwrap(), expected);
+
+        let before = insertion_builder()
+            .sector_before(&ExampleSectorKey::Third, ExampleSectorKey::Second, second)
+            .unwrap();
+        assert_eq!(before.build_to_vec().await.unwrap(), expected);
+    }
+
+    #[tokio::test]
+    async fn sector_trailer() {
+        let buffer = Builder::default()
+            .sector(ExampleSectorKey::First, SectorBuilder::default().u8(1))
+            .trailer(
+                ExampleSectorKey::Third,
+                SectorBuilder::default()
+                    .checksum_sum16(ExampleSectorKey::First, ExampleSectorKey::Second),
+            )
+            .unwrap()
+            .sector(ExampleSectorKey::Second, SectorBuilder::default().u8(2))
+            .build_to_vec()
+            .await
+            .unwrap();
+
+        assert_eq!(buffer, [1, 2, 3, 0]);
+    }
+
+    #[test]
+    fn sector_trailer_errors() {
+        let builder = Builder::default()
+            .trailer(ExampleSectorKey::First, SectorBuilder::default())
+            .unwrap();
+
+        let error = builder
+            .clone()
+            .trailer(ExampleSectorKey::Second, SectorBuilder::default())
+            .unwrap_err();
+        assert_eq!(error.to_string(), "Trailer is already set: First");
+
+        let error = builder
+            .clone()
+            .sector_after(
+                &ExampleSectorKey::First,
+                ExampleSectorKey::Second,
+                SectorBuilder::default(),
+            )
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Can't insert sector after the trailer: Second"
+        );
+
+        let error = builder
+            .sector_default(ExampleSectorKey::Second)
+            .sector_before(
+                &ExampleSectorKey::Second,
+                ExampleSectorKey::First,
+                SectorBuilder::default(),
+            )
+            .unwrap_err();
+        assert_eq!(error.to_string(), "Can't move the trailer: First");
+    }
+
+    #[test]
+    fn sector_insertion_missing_anchor() {
+        let error = Builder::default()
+            .sector_after(
+                &ExampleSectorKey::First,
+                ExampleSectorKey::Second,
+                SectorBuilder::default(),
+            )
+            .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Failed to find sector to insert next to: First"
+        );
+    }
+
+    // Dynamic fields reference sectors, so they're unaffected by their new position
+    #[tokio::test]
+    async fn sector_extend() {
+        let widths = SectorBuilder::default().u8(1).dynamic_u8(
+            ExampleSectorKey::First,
+            ExampleSectorKey::Second,
+            0,
+        );
+        let buffer = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default()
+                    .u8(0)
+                    .extend(widths.clone())
+                    .extend(widths)
+                    .append_field(SerialField::U8(2)),
+            )
+            .sector(ExampleSectorKey::Second, SectorBuilder::default().u8(3))
+            .build_to_vec()
+            .await
+            .unwrap();
+
+        assert_eq!(buffer, [0, 1, 6, 1, 6, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn sector_maybe() {
+        let buffer = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default()
+                    .maybe_u8(Some(1), 0xFF)
+                    .maybe_u8(None, 0xFF)
+                    .maybe_u16(None, 0x1234)
+                    .maybe_string(Some("A"))
+                    .maybe_string(None::<&str>)
+                    .dynamic_u24_or_null(ExampleSectorKey::First, Some(ExampleSectorKey::Second), 0)
+                    .dynamic_u24_or_null(ExampleSectorKey::First, None, 0),
+            )
+            .sector(ExampleSectorKey::Second, SectorBuilder::default().u8(2))
+            .build_to_vec()
+            .await
+            .unwrap();
+
+        assert_eq!(buffer, [1, 0xFF, 0x34, 0x12, b'A', 0, 12, 0, 0, 0, 0, 0, 2]);
+    }
+
+    // Replacing the sector would silently drop the first one
+    #[test]
+    fn try_sector_duplicate() {
+        let error = Builder::default()
+            .try_sector(ExampleSectorKey::First, SectorBuilder::default().u8(1))
+            .unwrap()
+            .try_sector(ExampleSectorKey::First, SectorBuilder::default().u8(2))
+            .unwrap_err();
+
+        assert_eq!(error.to_string(), "Duplicate sector: First");
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "Duplicate sector: First")]
+    fn sector_duplicate() {
+        let _ = Builder::default()
+            .sector_default(ExampleSectorKey::First)
+            .sector_default(ExampleSectorKey::First);
+    }
+
+    #[tokio::test]
+    async fn sectors_from_iter() {
+        let mut builder = Builder::default().sectors_from_iter([
+            (ExampleSectorKey::First, SectorBuilder::default().u8(1)),
+            (ExampleSectorKey::Second, SectorBuilder::default().u8(2)),
+        ]);
+        builder.add_sector(ExampleSectorKey::Third, SectorBuilder::default().u8(3));
+
+        assert_eq!(builder.build_to_vec().await.unwrap(), [1, 2, 3]);
+    }
+
+    #[derive(SerialSector)]
+    struct ExampleHeader {
+        version: u8,
+        #[serseg(skip)]
+        _name: String,
+        count: u16,
+        #[serseg(be)]
+        magic: u32,
+        start: u24,
+        adjust: i8,
+    }
+
+    #[derive(SerialSector)]
+    struct ExampleTuple(u8, #[serseg(le)] u16);
+
+    #[tokio::test]
+    async fn derive_sector() {
+        let header = ExampleHeader {
+            version: 1,
+            _name: "Skipped".to_string(),
+            count: 0x0203,
+            magic: 0x04050607,
+            start: u24::from_le_bytes([8, 9, 10]),
+            adjust: -1,
+        };
+
+        assert_eq!(
+            build_sector(header.into_sector()).await,
+            [1, 3, 2, 4, 5, 6, 7, 8, 9, 10, 0xFF]
+        );
+
+        // Unmarked fields use the builder's endianness
+        let buffer = Builder::big_endian()
+            .sector(
+                ExampleSectorKey::First,
+                ExampleTuple(1, 0x0203).into_sector(),
+            )
+            .build_to_vec()
+            .await
+            .unwrap();
+
+        assert_eq!(buffer, [1, 3, 2]);
+    }
+
+    #[tokio::test]
+    async fn sector_varint() {
+        let buffer = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default().dynamic_u16(
+                    ExampleSectorKey::First,
+                    ExampleSectorKey::Third,
+                    0,
+                ),
+            )
+            .sector(
+                ExampleSectorKey::Second,
+                SectorBuilder::default()
+                    .varint_u32(0u32)
+                    .varint_u32(127u32)
+                    .varint_u32(128u32)
+                    .varint_u32(624_485u32)
+                    .varint_u32(u32::MAX),
+            )
+            .sector(ExampleSectorKey::Third, SectorBuilder::default().u8(0xAA))
+            .build_to_vec()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            buffer,
+            [
+                14, 0, // Pointer past the varints
+                0x00, 0x7F, 0x80, 0x01, 0xE5, 0x8E, 0x26, 0xFF, 0xFF, 0xFF, 0xFF, 0x0F, 0xAA,
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn sector_bits() {
+        let sector = SectorBuilder::default()
+            .bits_u8(BitOrder::MsbFirst, |bits| bits.flag(true).value(2, 0b01))
+            .unwrap()
+            .bits_u16(BitOrder::LsbFirst, |bits| bits.value(4, 0xF).value(8, 0x12))
+            .unwrap();
+
+        assert_eq!(build_sector(sector).await, [0b1010_0000, 0x2F, 0x01]);
+        assert!(
+            SectorBuilder::default()
+                .bits_u8(BitOrder::LsbFirst, |bits| bits.value(9, 0))
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn sector_sized() {
+        let buffer = Builder::default()
+            .sector_sized(
+                ExampleSectorKey::First,
+                SectorBuilder::default()
+                    .u8(1)
+                    .dynamic_u8_end(ExampleSectorKey::First, ExampleSectorKey::First),
+                4,
+                0xFF,
+            )
+            .sector(ExampleSectorKey::Second, SectorBuilder::default().u8(2))
+            // Padded even at the end of the output, after a fill that'd otherwise be dropped
+            .sector_sized(
+                ExampleSectorKey::Third,
+                SectorBuilder::default().fill(ExampleSectorKey::First, 6),
+                2,
+                0xEE,
+            )
+            .build_to_vec()
+            .await
+            .unwrap();
+
+        assert_eq!(buffer, [1, 4, 0xFF, 0xFF, 2, 0, 0xEE]);
+    }
+
+    #[tokio::test]
+    async fn sector_sized_fill_end() {
+        // Filled exactly to its fixed size, so there's no padding after the fill
+        let builder = Builder::default()
+            .sector(ExampleSectorKey::First, SectorBuilder::default().u8(1))
+            .sector_sized(
+                ExampleSectorKey::Second,
+                SectorBuilder::default()
+                    .u8(2)
+                    .fill(ExampleSectorKey::First, 4),
+                3,
+                0xEE,
+            );
+
+        assert_eq!(builder.build_to_vec().await.unwrap(), [1, 2, 0, 0]);
+        assert_eq!(builder.total_size().await.unwrap(), 4);
+    }
+
+    #[tokio::test]
+    async fn sector_sized_too_large() {
+        let error = Builder::default()
+            .sector_sized(
+                ExampleSectorKey::First,
+                SectorBuilder::default().string("Test"),
+                4,
+                0,
+            )
+            .build_to_vec()
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            error.root_cause().to_string(),
+            "Sector First is larger than its fixed size: 5 bytes > 4 bytes"
+        );
+    }
+
+    #[tokio::test]
+    async fn sector_string() {
+        let expected = b"This is a test\x00";
+        let buffer = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default().string("This is a test"),
+            )
+            .build_to_vec()
+            .await
+            .unwrap();
+
+        assert_eq!(buffer, expected);
+    }
+
+    #[tokio::test]
+    async fn sector_string_terminators() {
+        let buffer = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default()
+                    .string_terminated("AB", 0xFF)
+                    .string_raw("CD"),
+            )
+            .sector(
+                ExampleSectorKey::Second,
+                SectorBuilder::default().dynamic_u8(
+                    ExampleSectorKey::First,
+                    ExampleSectorKey::Second,
+                    0,
+                ),
+            )
+            .build_to_vec()
+            .await
+            .unwrap();
+
+        assert_eq!(buffer, [b'A', b'B', 0xFF, b'C', b'D', 5]);
+    }
+
+    #[tokio::test]
+    async fn sector_string_interior_terminator() {
+        let error = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default().string("AB\0CD"),
+            )
+            .build_to_vec()
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            error.root_cause().to_string(),
+            "String contains its terminator 0x00 at index 2, which would end it early: \
+             \"AB\\0CD\""
+        );
+
+        // Without a terminator, nothing is ended early
+        assert_eq!(
+            build_sector(SectorBuilder::default().string_raw("A\0")).await,
+            b"A\0"
+        );
+    }
+
+    #[tokio::test]
+    async fn sector_string_ascii_only() {
+        let builder = |sector: SectorBuilder| {
+            Builder::default().sector(ExampleSectorKey::First, sector.string("Caf\u{e9}"))
+        };
+
+        assert_eq!(
+            builder(SectorBuilder::default())
+                .build_to_vec()
+                .await
+                .unwrap(),
+            b"Caf\xC3\xA9\0"
+        );
+
+        let builder = builder(SectorBuilder::default().u8(0).ascii_only());
+        let error = builder.build_to_vec().await.unwrap_err();
+
+        assert_eq!(
+            format!("{error:#}"),
+            "Failed to build sector: First: Failed to build field 1: String contains non-ASCII \
+             character '\u{e9}' at index 3: \"Caf\u{e9}\""
+        );
+
+        let issues = builder.validate().await.unwrap_err();
+
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].field, Some(1));
+    }
+
+    #[tokio::test]
+    async fn sector_string_len() {
+        let buffer = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default()
+                    .string_len_u8("AB")
+                    .string_len_u16("C")
+                    .string_len_u24(""),
+            )
+            .sector(
+                ExampleSectorKey::Second,
+                SectorBuilder::default().dynamic_u16(
+                    ExampleSectorKey::First,
+                    ExampleSectorKey::Second,
+                    0,
+                ),
+            )
+            .build_to_vec()
+            .await
+            .unwrap();
+
+        assert_eq!(buffer, [2, b'A', b'B', 1, 0, b'C', 0, 0, 0, 9, 0]);
+    }
+
+    #[tokio::test]
+    async fn sector_string_len_too_long() {
+        let error = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default().string_len_u8("A".repeat(256)),
+            )
+            .build_to_vec()
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            error.root_cause().to_string(),
+            "String length exceeds 8-bit limit: 256 > 255"
+        );
+    }
+
+    #[tokio::test]
+    async fn sector_string_fixed() {
+        assert_eq!(
+            build_sector(SectorBuilder::default().string_fixed("PROGRAM1", 8, 0)).await,
+            b"PROGRAM1"
+        );
+        assert_eq!(
+            build_sector(SectorBuilder::default().string_fixed("ABC", 8, b' ')).await,
+            b"ABC     "
+        );
+    }
+
+    #[tokio::test]
+    async fn sector_string_fixed_too_long() {
+        let error = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default().string_fixed("TOOLONGNAME", 8, 0),
+            )
+            .build_to_vec()
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            error.root_cause().to_string(),
+            "String is longer than its fixed length: \"TOOLONGNAME\" is 11 bytes > 8 bytes"
+        );
+    }
+
+    #[tokio::test]
+    async fn sector_arrays() {
+        let sector = || {
+            SectorBuilder::default()
+                .u8_array([1, 2])
+                .u16_array([0x0102, 0x0304])
+                .u24_array([u24::checked_from_u32(0x010203).unwrap()])
+        };
+
+        assert_eq!(
+            build_sector(sector()).await,
+            [1, 2, 0x02, 0x01, 0x04, 0x03, 0x03, 0x02, 0x01]
+        );
+        assert_eq!(
+            Builder::big_endian()
+                .sector(ExampleSectorKey::First, sector())
+                .build_to_vec()
+                .await
+                .unwrap(),
+            [1, 2, 0x01, 0x02, 0x03, 0x04, 0x01, 0x02, 0x03]
+        );
+    }
+
+    #[tokio::test]
+    async fn sector_u24() {
+        let expected = [0x12, 0x34, 0x56];
+        let buffer = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default().u24(u24::from_le_bytes([0x12, 0x34, 0x56])),
+            )
+            .build_to_vec()
+            .await
+            .unwrap();
+
+        assert_eq!(buffer, expected);
+    }
+
+    async fn build_sector(sector: SectorBuilder) -> Vec<u8> {
+        Builder::default()
+            .sector(ExampleSectorKey::First, sector)
+            .build_to_vec()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn sector_u16_endianness() {
+        let sector = SectorBuilder::default()
+            .u16(0x1234u16)
+            .u16_le(0x1234u16)
+            .u16_be(0x1234u16)
+            .i16_be(-2i16);
+
+        assert_eq!(
+            build_sector(sector).await,
+            [0x34, 0x12, 0x34, 0x12, 0x12, 0x34, 0xFF, 0xFE]
+        );
+    }
+
+    #[tokio::test]
+    async fn sector_u32_endianness() {
+        let sector = SectorBuilder::default()
+            .u32(0x12345678u32)
+            .u32_be(0x12345678u32)
+            .i32_le(-2i32);
+
+        assert_eq!(
+            build_sector(sector).await,
+            [
+                0x78, 0x56, 0x34, 0x12, 0x12, 0x34, 0x56, 0x78, 0xFE, 0xFF, 0xFF, 0xFF
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn sector_u64_endianness() {
+        let sector = SectorBuilder::default()
+            .u64(0x0102030405060708u64)
+            .u64_be(0x0102030405060708u64)
+            .i64_be(-2i64);
+
+        assert_eq!(
+            build_sector(sector).await,
+            [
+                0x08, 0x07, 0x06, 0x05, 0x04, 0x03, 0x02, 0x01, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06,
+                0x07, 0x08, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFE
+            ]
+        );
+    }
+
+    // The top bit is only a bit, not a sign
+    #[tokio::test]
+    async fn sector_u128_endianness() {
+        let value = 0x8000_0000_0000_0000_0000_0000_0000_0001u128;
+        let sector = SectorBuilder::default()
+            .u128(value)
+            .u128_be(value)
+            .i128_be(-2i128)
+            .null_128();
+
+        let mut expected = vec![0x01];
+        expected.extend([0x00; 14]);
+        expected.extend([0x80, 0x80]);
+        expected.extend([0x00; 14]);
+        expected.push(0x01);
+        expected.extend([0xFF; 15]);
+        expected.push(0xFE);
+        expected.extend([0x00; 16]);
+
+        assert_eq!(build_sector(sector).await, expected);
+    }
+
+    #[tokio::test]
+    async fn sector_i24() {
+        let sector = SectorBuilder::default()
+            .i24(-2)
+            .i24(-(1 << 23))
+            .i24((1 << 23) - 1)
+            .i24(0x123456);
+
+        assert_eq!(
+            build_sector(sector).await,
+            [
+                0xFE, 0xFF, 0xFF, 0x00, 0x00, 0x80, 0xFF, 0xFF, 0x7F, 0x56, 0x34, 0x12
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn sector_i24_overflow() {
+        for value in [1 << 23, -(1 << 23) - 1] {
+            let error = Builder::default()
+                .sector(ExampleSectorKey::First, SectorBuilder::default().i24(value))
+                .build_to_vec()
+                .await
+                .unwrap_err();
+
+            assert_eq!(
+                error.root_cause().to_string(),
+                format!(
+                    "Value exceeds signed 24-bit range: {value} is not within -8388608..=8388607"
+                )
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn sector_u48() {
+        let sector = SectorBuilder::default()
+            .u48(0x0000_1234_5678_9ABCu64)
+            .null_48();
+
+        assert_eq!(
+            build_sector(sector).await,
+            [0xBC, 0x9A, 0x78, 0x56, 0x34, 0x12, 0, 0, 0, 0, 0, 0]
+        );
+    }
+
+    #[tokio::test]
+    async fn sector_u48_overflow() {
+        let error = Builder::default()
+            .sector(
+                ExampleSectorKey::Second,
+                SectorBuilder::default().u48(1u64 << 48),
+            )
+            .build_to_vec()
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            format!("{error:#}"),
+            "Failed to build sector: Second: Failed to build field 0: \
+             Value exceeds 48-bit limit: 281474976710656 > 281474976710655"
+        );
+    }
+
+    #[tokio::test]
+    async fn sector_f32() {
+        let nan = f32::from_bits(0x7FC0_0001);
+        let sector = SectorBuilder::default().f32(1.5).f32_be(-0.0).f32(nan);
+        let data = build_sector(sector).await;
+
+        assert_eq!(
+            data,
+            [
+                0x00, 0x00, 0xC0, 0x3F, 0x80, 0x00, 0x00, 0x00, 0x01, 0x00, 0xC0, 0x7F
+            ]
+        );
+        assert_eq!(
+            f32::from_le_bytes(data[8..].try_into().unwrap()).to_bits(),
+            nan.to_bits()
+        );
+    }
+
+    #[tokio::test]
+    async fn sector_f64() {
+        let nan = f64::from_bits(0xFFF8_0000_0000_0001);
+        let sector = SectorBuilder::default().f64(-0.0).f64_be(nan);
+
+        assert_eq!(
+            build_sector(sector).await,
+            [
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x80, 0xFF, 0xF8, 0x00, 0x00, 0x00, 0x00,
+                0x00, 0x01
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn sector_ti_float() {
+        let sector = SectorBuilder::default().u8(0x00).ti_float(-0.5);
+
+        assert_eq!(
+            build_sector(sector).await,
+            [0x00, 0x80, 0x7F, 0x50, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]
+        );
+    }
+
+    // Explicit variants override the builder's endianness
+    #[tokio::test]
+    async fn builder_big_endian() {
+        let buffer = Builder::big_endian()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default()
+                    .u16(0x1234u16)
+                    .i32(-2i32)
+                    .u16_le(0x1234u16),
+            )
+            .build_to_vec()
+            .await
+            .unwrap();
+
+        assert_eq!(buffer, [0x12, 0x34, 0xFF, 0xFF, 0xFF, 0xFE, 0x34, 0x12]);
+    }
+
+    #[tokio::test]
+    async fn builder_big_endian_24_and_48_bit() {
+        let buffer = Builder::big_endian()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default()
+                    .i24(-2)
+                    .u48(0x1234_5678_9ABCu64)
+                    .i24_le(-2)
+                    .u48_le(0x1234_5678_9ABCu64),
+            )
+            .build_to_vec()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            buffer,
+            [
+                0xFF, 0xFF, 0xFE, 0x12, 0x34, 0x56, 0x78, 0x9A, 0xBC, 0xFE, 0xFF, 0xFF, 0xBC, 0x9A,
+                0x78, 0x56, 0x34, 0x12
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn sector_u24_endianness() {
+        let value = u24::checked_from_u32(0x123456).unwrap();
+        let sectors = |builder: Builder| async move {
+            builder
+                .sector(
+                    ExampleSectorKey::First,
+                    SectorBuilder::default()
+                        .u24(value)
+                        .u24_le(value)
+                        .u24_be(value)
+                        .dynamic_u24(ExampleSectorKey::First, ExampleSectorKey::Second, 0)
+                        .dynamic_u24_le(ExampleSectorKey::First, ExampleSectorKey::Second, 0)
+                        .dynamic_u24_be(ExampleSectorKey::First, ExampleSectorKey::Second, 0),
+                )
+                .sector_default(ExampleSectorKey::Second)
+                .build_to_vec()
+                .await
+        };
+
+        assert_eq!(
+            sectors(Builder::little_endian()).await.unwrap(),
+            [
+                0x56, 0x34, 0x12, 0x56, 0x34, 0x12, 0x12, 0x34, 0x56, 18, 0, 0, 18, 0, 0, 0, 0, 18
+            ]
+        );
+        assert_eq!(
+            sectors(Builder::big_endian()).await.unwrap(),
+            [
+                0x12, 0x34, 0x56, 0x56, 0x34, 0x12, 0x12, 0x34, 0x56, 0, 0, 18, 18, 0, 0, 0, 0, 18
+            ]
+        );
+    }
+
+    fn delta_builder(from_index: usize, to_index: usize) -> Builder {
+        Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default()
+                    .delta_u16(
+                        ExampleSectorKey::Second,
+                        from_index,
+                        ExampleSectorKey::Third,
+                        to_index,
+                    )
+                    .delta_u24(ExampleSectorKey::Third, 1, ExampleSectorKey::Third, 2),
+            )
+            .sector(
+                ExampleSectorKey::Second,
+                SectorBuilder::default().string("a").string("bcd"),
+            )
+            .sector(
+                ExampleSectorKey::Third,
+                SectorBuilder::default()
+                    .string("efghi")
+                    .string("jk")
+                    .u8(0xFF),
+            )
+    }
+
+    #[tokio::test]
+    async fn sector_delta() {
+        let buffer = delta_builder(1, 1).build_to_vec().await.unwrap();
+
+        // From "bcd" to "jk" is 4 + 6 bytes, and "jk" itself is 3
+        assert_eq!(buffer[..5], [10, 0, 3, 0, 0]);
+    }
+
+    #[tokio::test]
+    async fn sector_delta_negative() {
+        let error = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default().delta_u8(
+                    ExampleSectorKey::Second,
+                    0,
+                    ExampleSectorKey::First,
+                    0,
+                ),
+            )
+            .sector(ExampleSectorKey::Second, SectorBuilder::default().u8(0))
+            .build_to_vec()
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            error.root_cause().to_string(),
+            "Delta is negative: First field 0 at 0 < Second field 0 at 1, from the start of First"
+        );
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn sector_dynamic_auto() {
+        let mut buffer = Vec::new();
+        let report = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default()
+                    .dynamic_auto(ExampleSectorKey::First, ExampleSectorKey::Second, 0)
+                    .dynamic_auto(ExampleSectorKey::First, ExampleSectorKey::Second, 1)
+                    .dynamic_auto(ExampleSectorKey::First, ExampleSectorKey::Third, 0),
+            )
+            .sector(
+                ExampleSectorKey::Second,
+                SectorBuilder::default()
+                    .repeat_u8(0, 0x100)
+                    .repeat_u8(0, 0xFFFF),
+            )
+            .sector(ExampleSectorKey::Third, SectorBuilder::default().u8(0xFF))
+            .build_with_report(&mut buffer)
+            .await
+            .unwrap();
+
+        assert_eq!(buffer[..6], [0x06, 0x06, 0x01, 0x05, 0x01, 0x01]);
+        assert_eq!(buffer.len(), 6 + 0x100 + 0xFFFF + 1);
+        assert_eq!(
+            report.pointer_widths,
+            [1, 2, 3].map(|bytes| PointerWidth {
+                sector: ExampleSectorKey::First,
+                field: bytes - 1,
+                bytes,
+            })
+        );
+    }
+
+    // Shrinking to two bytes brings the pointer under 0x100, so it shrinks again
+    #[tokio::test]
+    async fn sector_dynamic_auto_shrinks_twice() {
+        let buffer = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default()
+                    .dynamic_auto(ExampleSectorKey::First, ExampleSectorKey::Second, 0)
+                    .repeat_u8(0, 0xFD),
+            )
+            .sector(ExampleSectorKey::Second, SectorBuilder::default().u8(0xFF))
+            .build_to_vec()
+            .await
+            .unwrap();
+
+        assert_eq!(buffer[0], 0xFE);
+        assert_eq!(buffer.len(), 0xFF);
+    }
+
+    #[tokio::test]
+    async fn sector_bytes_shared() {
+        let data = std::sync::Arc::<[u8]>::from([1, 2, 3]);
+        let builder = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default().bytes_shared(data.clone()),
+            )
+            .sector(
+                ExampleSectorKey::Second,
+                SectorBuilder::default().bytes_shared(data.clone()),
+            );
+        let copy = builder.clone();
+
+        // Cloning the builder doesn't copy the data
+        assert_eq!(std::sync::Arc::strong_count(&data), 5);
+        assert_eq!(builder.build_to_vec().await.unwrap(), [1, 2, 3, 1, 2, 3]);
+        assert_eq!(copy.total_size().await.unwrap(), 6);
+    }
+
+    #[tokio::test]
+    async fn sector_repeat() {
+        let sector = SectorBuilder::default()
+            .repeat_u8(0xFF, 3)
+            .repeat_bytes([1, 2], 2)
+            .repeat_u8(0, 0);
+
+        assert_eq!(build_sector(sector).await, [0xFF, 0xFF, 0xFF, 1, 2, 1, 2]);
+    }
+
+    // Spans several chunks, ending partway through one
+    #[tokio::test]
+    async fn sector_repeat_large() {
+        let sector = SectorBuilder::default().repeat_bytes([1, 2, 3], 5000);
+        let buffer = build_sector(sector).await;
+
+        assert_eq!(buffer.len(), 15000);
+        assert!(buffer.chunks(3).all(|pattern| pattern == [1, 2, 3]));
+    }
+
+    #[tokio::test]
+    async fn sector_dynamic() {
+        let expected = b"\xFF\x06\x00\x00\x13\x00\x00first string\x00second string\x00";
+        let buffer = Builder::default()
+            .sector(ExampleSectorKey::First, SectorBuilder::default().u8(0xFF))
+            .sector(
+                ExampleSectorKey::Second,
+                SectorBuilder::default()
+                    .dynamic_u24(ExampleSectorKey::Second, ExampleSectorKey::Third, 0)
+                    .dynamic_u24(ExampleSectorKey::Second, ExampleSectorKey::Third, 1),
+            )
+            .sector(
+                ExampleSectorKey::Third,
+                SectorBuilder::default()
+                    .string("first string")
+                    .string("second string"),
+            )
+            .build_to_vec()
+            .await
+            .unwrap();
+
+        assert_eq!(buffer, expected);
+    }
+
+    // Every pointer's offset is looked up from the tracker, rather than re-sizing the sector
+    #[tokio::test]
+    async fn sector_dynamic_many() {
+        const COUNT: usize = 4096;
+
+        let pointers = (0..COUNT).fold(SectorBuilder::default(), |sector, index| {
+            sector.dynamic_u24(ExampleSectorKey::First, ExampleSectorKey::Second, index)
+        });
+        let values = (0..COUNT).fold(SectorBuilder::default(), |sector, index| {
+            sector.u16(index as u16)
+        });
+
+        let buffer = Builder::default()
+            .sector(ExampleSectorKey::First, pointers)
+            .sector(ExampleSectorKey::Second, values)
+            .build_to_vec()
+            .await
+            .unwrap();
+
+        assert_eq!(buffer.len(), COUNT * 5);
+
+        for (index, pointer) in buffer[..COUNT * 3].chunks(3).enumerate() {
+            let pointer = u32::from_le_bytes([pointer[0], pointer[1], pointer[2], 0]) as usize;
+
+            assert_eq!(pointer, COUNT * 3 + index * 2);
+            assert_eq!(buffer[pointer..pointer + 2], (index as u16).to_le_bytes());
+        }
+    }
+
+    fn index_builder(third: SectorBuilder, index: usize) -> Builder {
+        Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default().dynamic_u8(
+                    ExampleSectorKey::First,
+                    ExampleSectorKey::Third,
+                    index,
+                ),
+            )
+            .sector(ExampleSectorKey::Second, SectorBuilder::default().u8(0xAA))
+            .sector(ExampleSectorKey::Third, third)
+    }
+
+    #[tokio::test]
+    async fn sector_dynamic_index_empty() {
+        let buffer = index_builder(SectorBuilder::default(), 0)
+            .build_to_vec()
+            .await
+            .unwrap();
+
+        assert_eq!(buffer, b"\x02\xAA");
+    }
+
+    #[tokio::test]
+    async fn sector_dynamic_index_last() {
+        let buffer = index_builder(SectorBuilder::default().u8(1).u16(2u16), 1)
+            .build_to_vec()
+            .await
+            .unwrap();
+
+        assert_eq!(buffer, b"\x03\xAA\x01\x02\x00");
+    }
+
+    #[tokio::test]
+    async fn sector_dynamic_index_end() {
+        let buffer = index_builder(SectorBuilder::default().u8(1).u16(2u16), 2)
+            .build_to_vec()
+            .await
+            .unwrap();
+
+        assert_eq!(buffer, b"\x05\xAA\x01\x02\x00");
+    }
+
+    #[tokio::test]
+    async fn sector_dynamic_index_out_of_range() {
+        let error = index_builder(SectorBuilder::default().u8(1).u16(2u16), 3)
+            .build_to_vec()
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            error.root_cause().to_string(),
+            "Can't index into sector; not enough fields. Sector: Third, Length: 2, Index: 3"
+        );
+
+        let error = index_builder(SectorBuilder::default(), 1)
+            .build_to_vec()
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            error.root_cause().to_string(),
+            "Can't index into sector; not enough fields. Sector: Third, Length: 0, Index: 1"
+        );
+    }
+
+    type Reader = SerialReader<ExampleSectorKey>;
+    type SchemaBuilder = SerialSchemaBuilder<ExampleSectorKey>;
+
+    fn round_trip_reader() -> Reader {
+        Reader::default()
+            .sector(
+                ExampleSectorKey::First,
+                SchemaBuilder::default()
+                    .u8()
+                    .u16_be()
+                    .dynamic_u24(ExampleSectorKey::First, ExampleSectorKey::Third, 1)
+                    .dynamic_u16(ExampleSectorKey::Second, ExampleSectorKey::Third, 0),
+            )
+            .sector(
+                ExampleSectorKey::Second,
+                SchemaBuilder::default().u24().u32(),
+            )
+            .sector(
+                ExampleSectorKey::Third,
+                SchemaBuilder::default().string().string().bytes(2),
+            )
+    }
+
+    #[tokio::test]
+    async fn reader_round_trip() {
+        let buffer = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default()
+                    .u8(0xAA)
+                    .u16_be(0x1234u16)
+                    .dynamic_u24(ExampleSectorKey::First, ExampleSectorKey::Third, 1)
+                    .dynamic_u16(ExampleSectorKey::Second, ExampleSectorKey::Third, 0),
+            )
+            .sector(
+                ExampleSectorKey::Second,
+                SectorBuilder::default()
+                    .u24(u24::checked_from_u32(0x123456).unwrap())
+                    .u32(0x789ABCDEu32),
+            )
+            .sector(
+                ExampleSectorKey::Third,
+                SectorBuilder::default()
+                    .string("first")
+                    .string("second")
+                    .bytes(vec![1, 2]),
+            )
+            .build_to_vec()
+            .await
+            .unwrap();
+
+        let values = round_trip_reader().read(&buffer).unwrap();
+
+        assert_eq!(
+            values[&ExampleSectorKey::First],
+            [
+                SerialValue::U8(0xAA),
+                SerialValue::U16(0x1234),
+                SerialValue::Pointer(21),
+                SerialValue::Pointer(7),
+            ]
+        );
+        assert_eq!(
+            values[&ExampleSectorKey::Second],
+            [SerialValue::U24(0x123456), SerialValue::U32(0x789ABCDE)]
+        );
+        assert_eq!(
+            values[&ExampleSectorKey::Third],
+            [
+                SerialValue::String("first".to_owned()),
+                SerialValue::String("second".to_owned()),
+                SerialValue::Bytes(vec![1, 2]),
+            ]
+        );
+    }
+
+    #[test]
+    fn reader_pointer_mismatch() {
+        let error = Reader::default()
+            .sector(
+                ExampleSectorKey::First,
+                SchemaBuilder::default().dynamic_u8(
+                    ExampleSectorKey::First,
+                    ExampleSectorKey::Second,
+                    1,
+                ),
+            )
+            .sector(ExampleSectorKey::Second, SchemaBuilder::default().u8().u8())
+            .read(&[1, 0xAA, 0xBB])
+            .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Pointer in sector First, field 0 doesn't point to its field:\n\
+             Expected: 2, Found: 1"
+        );
+    }
+
+    #[test]
+    fn reader_length_mismatch() {
+        let reader =
+            Reader::default().sector(ExampleSectorKey::First, SchemaBuilder::default().u8().u16());
+
+        let error = reader.read(&[1, 2]).unwrap_err();
+        assert_eq!(error.to_string(), "Failed to read sector First, field 1");
+        assert_eq!(
+            error.root_cause().to_string(),
+            "Data ended early: 2 bytes at 1 > 2 bytes"
+        );
+
+        let error = reader.read(&[1, 2, 3, 4]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Data is longer than its schema: 4 bytes > 3 bytes"
+        );
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn sector_aligned() {
+        let report = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default().u8(1).u8(2).dynamic_u8(
+                    ExampleSectorKey::First,
+                    ExampleSectorKey::Second,
+                    1,
+                ),
+            )
+            .sector_aligned(
+                ExampleSectorKey::Second,
+                SectorBuilder::default().u8(0xAA).u8(0xBB),
+                4,
+                0xEE,
+            )
+            .sector_aligned(
+                ExampleSectorKey::Third,
+                SectorBuilder::default().dynamic_u8(
+                    ExampleSectorKey::First,
+                    ExampleSectorKey::Second,
+                    0,
+                ),
+                2,
+                0xEE,
+            );
+
+        let mut buffer = Vec::new();
+        let report = report.build_with_report(&mut buffer).await.unwrap();
+
+        assert_eq!(buffer, [1, 2, 5, 0xEE, 0xAA, 0xBB, 4]);
+        assert_eq!(report.sector(&ExampleSectorKey::Second).unwrap().start, 4);
+    }
+
+    #[tokio::test]
+    async fn sector_aligned_padded_pointer() {
+        // The pointer needs a multiple of 3 and the alignment an even start, so it starts at 6
+        let buffer = Builder::default()
+            .sector(ExampleSectorKey::First, SectorBuilder::default().u8(0xFF))
+            .sector(
+                ExampleSectorKey::Second,
+                SectorBuilder::default().dynamic_u8_chunk_padded(
+                    ExampleSectorKey::First,
+                    ExampleSectorKey::Third,
+                    0,
+                    3,
+                ),
+            )
+            .sector_aligned(
+                ExampleSectorKey::Third,
+                SectorBuilder::default().u8(0xAA).u8(0xBB),
+                2,
+                0xEE,
+            )
+            .build_to_vec()
+            .await
+            .unwrap();
+
+        assert_eq!(buffer, [0xFF, 2, 0xEE, 0xEE, 0xEE, 0xEE, 0xAA, 0xBB]);
+    }
+
+    #[tokio::test]
+    async fn sector_bytes_rle() {
+        let buffer = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default()
+                    .bytes_rle([1, 2, 0, 0, 0, 0])
+                    .dynamic_u8(ExampleSectorKey::First, ExampleSectorKey::First, 2),
+            )
+            .build_to_vec()
+            .await
+            .unwrap();
+
+        assert_eq!(buffer, [1, 1, 2, 0x81, 0, 6]);
+    }
+
+    fn compressed_builder(index: usize) -> Builder {
+        Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default()
+                    .dynamic_u8(ExampleSectorKey::First, ExampleSectorKey::Second, index)
+                    .sector_size_u8(ExampleSectorKey::Second),
+            )
+            .sector_compressed(
+                ExampleSectorKey::Second,
+                SectorBuilder::default()
+                    .dynamic_u8(ExampleSectorKey::Second, ExampleSectorKey::Second, 2)
+                    .repeat_u8(0xAA, 64)
+                    .u8(0xBB),
+                Compression::Zx7,
+            )
+            .sector(ExampleSectorKey::Third, SectorBuilder::default().u8(0xCC))
+    }
+
+    #[tokio::test]
+    async fn sector_compressed() {
+        let buffer = compressed_builder(0).build_to_vec().await.unwrap();
+
+        // Pointers inside are laid out as if it's decompressed
+        let uncompressed = [&[65][..], &[0xAA; 64], &[0xBB]].concat();
+        let compressed = Compression::Zx7.compress(&uncompressed).unwrap();
+
+        assert_eq!(
+            buffer,
+            [&[2, compressed.len() as u8][..], &compressed, &[0xCC]].concat()
+        );
+    }
+
+    #[tokio::test]
+    async fn sector_compressed_pointer() {
+        let error = compressed_builder(1).build_to_vec().await.unwrap_err();
+
+        assert_eq!(
+            error.root_cause().to_string(),
+            "Can't point into compressed sector Second; \
+             its fields are only where they were laid out once it's decompressed"
+        );
+
+        let error = Builder::default()
+            .sector_compressed(
+                ExampleSectorKey::First,
+                SectorBuilder::default().dynamic_u8(
+                    ExampleSectorKey::First,
+                    ExampleSectorKey::Second,
+                    0,
+                ),
+                Compression::Zx7,
+            )
+            .sector_default(ExampleSectorKey::Second)
+            .build_to_vec()
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            format!("{error:#}"),
+            "Failed to compress sector First: Failed to build sector: First: \
+             Failed to build field 0: Sector does not exist: Second"
+        );
+    }
+
+    fn padded_builder(second_scale: usize) -> Builder {
+        Builder::default()
+            .sector(ExampleSectorKey::First, SectorBuilder::default().u8(0xAA))
+            .sector(
+                ExampleSectorKey::Second,
+                SectorBuilder::default()
+                    .dynamic_u8_chunk_padded(ExampleSectorKey::First, ExampleSectorKey::Third, 0, 2)
+                    .dynamic_u8_chunk_padded(
+                        ExampleSectorKey::First,
+                        ExampleSectorKey::Third,
+                        1,
+                        second_scale,
+                    ),
+            )
+            .sector(
+                ExampleSectorKey::Third,
+                SectorBuilder::default().u8(1).u8(2).u8(3),
+            )
+    }
+
+    // The first pointer wants the sector on an even offset and the second wants the offset
+    // after its first field to be a multiple of 3, so the sector is padded from 3 to 8
+    #[tokio::test]
+    async fn sector_dynamic_chunk_padded() {
+        let builder = padded_builder(3);
+
+        assert_eq!(builder.total_size().await.unwrap(), 11);
+        assert_eq!(
+            builder.build_to_vec().await.unwrap(),
+            [0xAA, 4, 3, 0, 0, 0, 0, 0, 1, 2, 3]
+        );
+    }
+
+    #[tokio::test]
+    async fn sector_dynamic_chunk_padded_conflict() {
+        let error = padded_builder(2).build_to_vec().await.unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Can't pad Third so every padded pointer into it is aligned to its scale"
+        );
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn sector_dynamic_c_header() {
+        let mut header = Vec::new();
+
+        Builder::default()
+            .sector(ExampleSectorKey::First, SectorBuilder::default().u8(0xFF))
+            .sector(
+                ExampleSectorKey::Second,
+                SectorBuilder::default()
+                    .dynamic_u24(ExampleSectorKey::Second, ExampleSectorKey::Third, 0)
+                    .dynamic_u24(ExampleSectorKey::Second, ExampleSectorKey::Third, 1),
+            )
+            .sector(
+                ExampleSectorKey::Third,
+                SectorBuilder::default()
+                    .string("first string")
+                    .string("second string"),
+            )
+            .build_with_c_header(&mut Vec::new(), &mut header, "example.h", |key| {
+                format!("example {key:?}")
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(
+            String::from_utf8(header).unwrap(),
+            "#ifndef EXAMPLE_H\n\
+             #define EXAMPLE_H\n\
+             \n\
+             #define EXAMPLE_FIRST_OFFSET 0x000000\n\
+             #define EXAMPLE_SECOND_OFFSET 0x000001\n\
+             #define EXAMPLE_THIRD_OFFSET 0x000007\n\
+             \n\
+             #endif\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn sector_dynamic_chunk() {
+        let expected = b"\xFF\x03\x00\x00\x0A\x00\x00first string\x00second string\x00";
+        let buffer = Builder::default()
+            .sector(ExampleSectorKey::First, SectorBuilder::default().u8(0xFF))
+            .sector(
+                ExampleSectorKey::Second,
+                SectorBuilder::default()
+                    .dynamic_u24_chunk(ExampleSectorKey::Second, ExampleSectorKey::Third, 0, 2)
+                    .dynamic_u24_chunk(
+                        ExampleSectorKey::Second,
+                        ExampleSectorKey::Third,
+                        1,
+                        (ScaleRounding::Nearest, 2),
+                    ),
+            )
+            .sector(
+                ExampleSectorKey::Third,
+                SectorBuilder::default()
+                    .string("first string")
+                    .string("second string"),
+            )
+            .build_to_vec()
+            .await
+            .unwrap();
+
+        assert_eq!(buffer, expected);
+    }
+
+    #[tokio::test]
+    async fn sector_dynamic_chunk_exact() {
+        let builder = |scale| {
+            Builder::default()
+                .sector(
+                    ExampleSectorKey::First,
+                    SectorBuilder::default().dynamic_u8_chunk(
+                        ExampleSectorKey::First,
+                        ExampleSectorKey::Second,
+                        0,
+                        (ScaleRounding::Exact, scale),
+                    ),
+                )
+                .sector(ExampleSectorKey::Second, SectorBuilder::default().u8(0xFF))
+        };
+
+        assert_eq!(builder(1).build_to_vec().await.unwrap(), [0x01, 0xFF]);
+
+        let error = builder(2).build_to_vec().await.unwrap_err();
+
+        assert_eq!(
+            error.root_cause().to_string(),
+            "Dynamic pointer from First to Second isn't a multiple of its scale with Exact \
+             rounding: 1 % 2 = 1"
+        );
+    }
+
+    #[tokio::test]
+    async fn sector_dynamic_chunk_ceiling() {
+        let builder = |len| {
+            Builder::default()
+                .sector(
+                    ExampleSectorKey::First,
+                    SectorBuilder::default().dynamic_u8_chunk(
+                        ExampleSectorKey::First,
+                        ExampleSectorKey::Second,
+                        0,
+                        (ScaleRounding::Ceiling, 4),
+                    ),
+                )
+                .sector(
+                    ExampleSectorKey::Second,
+                    SectorBuilder::default().repeat_u8(0xFF, len),
+                )
+        };
+
+        // Rounded up to 4, which is still in the sector
+        assert_eq!(builder(3).build_to_vec().await.unwrap()[0], 1);
+
+        let error = builder(2).build_to_vec().await.unwrap_err();
+
+        assert_eq!(
+            error.root_cause().to_string(),
+            "Dynamic pointer from First to Second is past the end of its sector with Ceiling \
+             rounding: 4 > 3"
+        );
+    }
+
+    #[tokio::test]
+    async fn sector_dynamic_signed() {
+        let builder = || {
+            Builder::default()
+                .sector(
+                    ExampleSectorKey::First,
+                    SectorBuilder::default().u16(0xBEEFu16),
+                )
+                .sector(
+                    ExampleSectorKey::Second,
+                    SectorBuilder::default()
+                        .dynamic_i16(ExampleSectorKey::Second, ExampleSectorKey::First, 0)
+                        .dynamic_i24(ExampleSectorKey::Second, ExampleSectorKey::Third, 0),
+                )
+                .sector(ExampleSectorKey::Third, SectorBuilder::default().u8(0xFF))
+        };
+
+        // Back to the header, then forward past both pointers
+        assert_eq!(
+            builder().build_to_vec().await.unwrap(),
+            [0xEF, 0xBE, 0xFE, 0xFF, 0x05, 0x00, 0x00, 0xFF]
+        );
+
+        let big_endian = Builder::big_endian()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default().u16(0xBEEFu16),
+            )
+            .sector(
+                ExampleSectorKey::Second,
+                SectorBuilder::default().dynamic_i24(
+                    ExampleSectorKey::Second,
+                    ExampleSectorKey::First,
+                    0,
+                ),
+            );
+
+        assert_eq!(
+            big_endian.build_to_vec().await.unwrap(),
+            [0xBE, 0xEF, 0xFF, 0xFF, 0xFE]
+        );
+    }
+
+    #[tokio::test]
+    async fn sector_dynamic_signed_range() {
+        let backward = |len| {
+            Builder::default()
+                .sector(
+                    ExampleSectorKey::First,
+                    SectorBuilder::default().repeat_u8(0, len),
+                )
+                .sector(
+                    ExampleSectorKey::Second,
+                    SectorBuilder::default().dynamic_i16(
+                        ExampleSectorKey::Second,
+                        ExampleSectorKey::First,
+                        0,
+                    ),
+                )
+        };
+        let forward = |len| {
+            Builder::default()
+                .sector(
+                    ExampleSectorKey::First,
+                    SectorBuilder::default()
+                        .dynamic_i16(ExampleSectorKey::First, ExampleSectorKey::Second, 0)
+                        .repeat_u8(0, len),
+                )
+                .sector(ExampleSectorKey::Second, SectorBuilder::default().u8(0xFF))
+        };
+
+        assert_eq!(
+            backward(0x8000).build_to_vec().await.unwrap()[0x8000..],
+            [0x00, 0x80]
+        );
+        assert_eq!(
+            forward(0x7FFD).build_to_vec().await.unwrap()[..2],
+            [0xFF, 0x7F]
+        );
+
+        let error = backward(0x8001).build_to_vec().await.unwrap_err();
+
+        assert_eq!(
+            error.root_cause().to_string(),
+            "Signed pointer from Second to First exceeds signed 16-bit range: -32769 is not \
+             within -32768..=32767"
+        );
+
+        let error = forward(0x7FFE).build_to_vec().await.unwrap_err();
+
+        assert_eq!(
+            error.root_cause().to_string(),
+            "Signed pointer from First to Second exceeds signed 16-bit range: 32768 is not \
+             within -32768..=32767"
+        );
+    }
+
+    #[tokio::test]
+    async fn sector_dynamic_end() {
+        let buffer = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default()
+                    .dynamic_u24_end(ExampleSectorKey::First, ExampleSectorKey::Second)
+                    .dynamic_u8_end(ExampleSectorKey::Second, ExampleSectorKey::Second),
+            )
+            .sector(
+                ExampleSectorKey::Second,
+                SectorBuilder::default().string("Test"),
+            )
+            .sector_default(ExampleSectorKey::Third)
+            .build_to_vec()
+            .await
+            .unwrap();
+
+        assert_eq!(buffer, [9, 0, 0, 5, b'T', b'e', b's', b't', 0]);
+
+        let error = Builder::default()
+            .sector(ExampleSectorKey::First, SectorBuilder::default().u8(0))
+            .sector(ExampleSectorKey::Second, SectorBuilder::default().u8(0))
+            .sector(
+                ExampleSectorKey::Third,
+                SectorBuilder::default()
+                    .dynamic_u8_end(ExampleSectorKey::Third, ExampleSectorKey::First),
+            )
+            .build_to_vec()
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            error.root_cause().to_string(),
+            "Pointer from Third to the end of First is negative: 1 < 2"
+        );
+    }
+
+    #[tokio::test]
+    async fn sector_dynamic_self() {
+        let builder = Builder::default()
+            .sector(ExampleSectorKey::First, SectorBuilder::default().u8(0xAA))
+            .sector(
+                ExampleSectorKey::Second,
+                SectorBuilder::default()
+                    .dynamic_u16_self(ExampleSectorKey::Third, 0)
+                    .u8(0xBB)
+                    .dynamic_u24_self(ExampleSectorKey::Third, 1),
+            )
+            .sector(
+                ExampleSectorKey::Third,
+                SectorBuilder::default().u8(0xCC).u8(0xDD),
+            );
+
+        assert_eq!(
+            builder.build_to_vec().await.unwrap(),
+            [0xAA, 0x06, 0x00, 0xBB, 0x04, 0x00, 0x00, 0xCC, 0xDD]
+        );
+    }
+
+    #[tokio::test]
+    async fn sector_dynamic_self_backwards() {
+        let builder = Builder::default()
+            .sector(ExampleSectorKey::First, SectorBuilder::default().u8(0xAA))
+            .sector(
+                ExampleSectorKey::Second,
+                SectorBuilder::default().dynamic_u16_self(ExampleSectorKey::First, 0),
+            );
+
+        let error = builder.build_to_vec().await.unwrap_err();
+
+        assert_eq!(
+            error.root_cause().to_string(),
+            "Self-relative pointer to First is before the pointer: 0 < 1"
+        );
+    }
+
+    thread_local! {
+        /// Debug logs from this thread, if they're being captured
+        static CAPTURED_LOGS: std::cell::RefCell<Option<Vec<String>>> =
+            const { std::cell::RefCell::new(None) };
+    }
+
+    /// Only enabled on threads capturing logs, so tests running alongside keep logging off
+    struct CaptureLogger;
+
+    impl log::Log for CaptureLogger {
+        fn enabled(&self, metadata: &log::Metadata) -> bool {
+            metadata.level() <= log::Level::Debug
+                && CAPTURED_LOGS.with_borrow(|logs| logs.is_some())
+        }
+
+        fn log(&self, record: &log::Record) {
+            if self.enabled(record.metadata()) {
+                CAPTURED_LOGS.with_borrow_mut(|logs| {
+                    logs.get_or_insert_default().push(record.args().to_string())
+                });
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    #[tokio::test]
+    async fn labeled_field_errors() {
+        let builder = Builder::default().sector(
+            ExampleSectorKey::First,
+            SectorBuilder::default().u8(0).extend(
+                SectorBuilder::default()
+                    .u8(0)
+                    .dynamic_u8(ExampleSectorKey::First, ExampleSectorKey::Third, 0)
+                    .label("missing pointer"),
+            ),
+        );
+
+        let issues = builder.validate().await.unwrap_err();
+
+        assert_eq!(
+            issues.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            ["Sector First, field \"missing pointer\": Sector does not exist: Third"]
+        );
+
+        let error = builder.build_to_vec().await.unwrap_err();
+
+        assert_eq!(
+            format!("{error:#}"),
+            "Failed to build sector: First: Failed to build field \"missing pointer\": Sector \
+             does not exist: Third"
+        );
+    }
+
+    #[tokio::test]
+    async fn build_debug_hexdump() {
+        static LOGGER: std::sync::Once = std::sync::Once::new();
+
+        LOGGER.call_once(|| {
+            log::set_logger(&CaptureLogger).unwrap();
+            log::set_max_level(log::LevelFilter::Debug);
+        });
+
+        let builder = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default()
+                    .dynamic_u8(ExampleSectorKey::First, ExampleSectorKey::Second, 0)
+                    .label("pointer")
+                    .repeat_u8(0xAA, 16),
+            )
+            .sector_aligned(
+                ExampleSectorKey::Second,
+                SectorBuilder::default().u16(0x1234u16),
+                4,
+                0xFF,
+            );
+        let plain = builder.build_to_vec().await.unwrap();
+
+        CAPTURED_LOGS.set(Some(Vec::new()));
+        let logged = builder.build_to_vec().await.unwrap();
+        let logs = CAPTURED_LOGS.take().unwrap();
+
+        assert_eq!(logged, plain);
+        assert!(
+            logs.contains(
+                &"Sector First wrote 17 bytes:\n\
+              First 000000: 14 AA AA AA AA AA AA AA AA AA AA AA AA AA AA AA\n\
+              First 000010: AA\n\
+              First field \"pointer\": 000000..000001"
+                    .to_owned()
+            )
+        );
+        // The padding before the sector is written with it
+        assert!(
+            logs.contains(
+                &"Sector Second wrote 5 bytes:\nSecond 000011: FF FF FF 34 12".to_owned()
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn sector_dynamic_u32() {
+        let expected = b"\x0A\x00\x00\x00\x05\x00\x05\x00\x00\x00\xFF";
+        let buffer = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default()
+                    .dynamic_u32(ExampleSectorKey::First, ExampleSectorKey::Second, 0)
+                    .dynamic_u16_chunk(ExampleSectorKey::First, ExampleSectorKey::Second, 0, 2)
+                    .dynamic_u32_chunk(ExampleSectorKey::First, ExampleSectorKey::Second, 0, 2),
+            )
+            .sector(ExampleSectorKey::Second, SectorBuilder::default().u8(0xFF))
+            .build_to_vec()
+            .await
+            .unwrap();
+
+        assert_eq!(buffer, expected);
+    }
+
+    #[tokio::test]
+    async fn sector_dynamic_offset() {
+        let expected = b"\x09\x00\x00\x05\x00\x00\xFF";
+        let buffer = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default()
+                    .dynamic_u24_offset(ExampleSectorKey::First, ExampleSectorKey::Second, 0, 3)
+                    .dynamic_u24_offset(ExampleSectorKey::First, ExampleSectorKey::Second, 0, -1),
+            )
+            .sector(ExampleSectorKey::Second, SectorBuilder::default().u8(0xFF))
+            .build_to_vec()
+            .await
+            .unwrap();
+
+        assert_eq!(buffer, expected);
+    }
+
+    #[tokio::test]
+    async fn sector_dynamic_offset_negative() {
+        let error = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default().dynamic_u8_offset(
+                    ExampleSectorKey::First,
+                    ExampleSectorKey::Second,
+                    0,
+                    -2,
+                ),
+            )
+            .sector_default(ExampleSectorKey::Second)
+            .build_to_vec()
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            error.root_cause().to_string(),
+            "Dynamic pointer from First to Second is negative: 1 + -2 < 0"
+        );
+    }
+
+    // Fits in a u24, but not the u16 that was asked for
+    #[tokio::test]
+    async fn sector_dynamic_u16_overflow() {
+        let error = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default().dynamic_u16(
+                    ExampleSectorKey::First,
+                    ExampleSectorKey::Third,
+                    0,
+                ),
+            )
+            .sector(
+                ExampleSectorKey::Second,
+                SectorBuilder::default().bytes(vec![0; 0x10000]),
+            )
+            .sector_default(ExampleSectorKey::Third)
+            .build_to_vec()
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            error.root_cause().to_string(),
+            "Pointer exceeds 16-bit limit: 65538 > 65535"
+        );
+    }
+
+    #[tokio::test]
+    async fn sector_size() {
+        let expected = b"\x0C\x00\x0C\x00\x00Description\x00";
+        let buffer = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default()
+                    .sector_size_u16(ExampleSectorKey::Second)
+                    .sector_size_u24(ExampleSectorKey::Second),
+            )
+            .sector(
+                ExampleSectorKey::Second,
+                SectorBuilder::default().string("Description"),
+            )
+            .build_to_vec()
+            .await
+            .unwrap();
+
+        assert_eq!(buffer, expected);
+    }
+
+    #[tokio::test]
+    async fn sector_size_overflow() {
+        let error = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default().sector_size_u8(ExampleSectorKey::Second),
+            )
+            .sector(
+                ExampleSectorKey::Second,
+                SectorBuilder::default().bytes(vec![0; 0x100]),
+            )
+            .build_to_vec()
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            error.root_cause().to_string(),
+            "Sector size exceeds 8-bit limit: 256 > 255"
+        );
+    }
+
+    #[tokio::test]
+    async fn sector_span() {
+        let expected = b"\x05\x00\x09\x00\x00\xFF\xFF\xFF\xFF\xFF";
+        let buffer = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default()
+                    .span_u16(ExampleSectorKey::First, ExampleSectorKey::Second)
+                    .span_u24(ExampleSectorKey::First, ExampleSectorKey::Third),
+            )
+            .sector(
+                ExampleSectorKey::Second,
+                SectorBuilder::default().bytes([0xFF; 4]),
+            )
+            .sector(ExampleSectorKey::Third, SectorBuilder::default().u8(0xFF))
+            .build_to_vec()
+            .await
+            .unwrap();
+
+        assert_eq!(buffer, expected);
+    }
+
+    #[tokio::test]
+    async fn sector_span_backwards() {
+        let error = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default().span_u8(ExampleSectorKey::Second, ExampleSectorKey::First),
+            )
+            .sector_default(ExampleSectorKey::Second)
+            .build_to_vec()
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            error.root_cause().to_string(),
+            "Span end is before its start: First at 0 < Second at 1"
+        );
+    }
+
+    // Pointers past an aligned field must agree with where it's written
+    #[tokio::test]
+    async fn sector_align() {
+        let expected = b"\x10\xFFab\x00\xFF\xFF\xFF\x08\x08\x00\x00cd\x00\x00\xEE";
+        let buffer = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default()
+                    .dynamic_u8(ExampleSectorKey::First, ExampleSectorKey::Second, 4)
+                    .u8(0xFF)
+                    .string("ab")
+                    .align_with(8, 0xFF),
+            )
+            .sector(
+                ExampleSectorKey::Second,
+                SectorBuilder::default()
+                    .u8(0x08)
+                    .dynamic_u24(ExampleSectorKey::Second, ExampleSectorKey::Second, 4)
+                    .string("cd")
+                    .align(2)
+                    .u8(0xEE),
+            )
+            .build_to_vec()
+            .await
+            .unwrap();
+
+        assert_eq!(buffer, expected);
+    }
+
+    #[tokio::test]
+    async fn sector_align_zero() {
+        let result = Builder::default()
+            .sector(ExampleSectorKey::First, SectorBuilder::default().align(0))
+            .build_to_vec()
+            .await;
+
+        assert!(result.is_err());
+    }
+
+    // The checksum covers sectors written after it
+    #[tokio::test]
+    async fn sector_crc32() {
+        let buffer = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default().crc32(ExampleSectorKey::Second, ExampleSectorKey::Third),
+            )
+            .sector(
+                ExampleSectorKey::Second,
+                SectorBuilder::default().bytes(*b"1234"),
+            )
+            .sector(
+                ExampleSectorKey::Third,
+                SectorBuilder::default().bytes(*b"56789"),
+            )
+            .build_to_vec()
+            .await
+            .unwrap();
+
+        assert_eq!(buffer[..4], 0xCBF4_3926u32.to_le_bytes());
+        assert_eq!(buffer[4..], *b"123456789");
+    }
+
+    // Like a TI link file, with the checksum after the data it covers
+    #[tokio::test]
+    async fn sector_checksum_sum16() {
+        let buffer = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default().bytes([0xFF; 0x102]),
+            )
+            .sector(ExampleSectorKey::Second, SectorBuilder::default().u8(0x02))
+            .sector(
+                ExampleSectorKey::Third,
+                SectorBuilder::default()
+                    .checksum_sum16(ExampleSectorKey::First, ExampleSectorKey::Second),
+            )
+            .build_to_vec()
+            .await
+            .unwrap();
+
+        // 0x102 * 0xFF + 2 = 0x10100, which wraps to 0x0100
+        assert_eq!(buffer[0x103..], [0x00, 0x01]);
+    }
+
+    #[tokio::test]
+    async fn sector_checksum_nested() {
+        let buffer = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default()
+                    .u8(1)
+                    .checksum_sum16(ExampleSectorKey::Second, ExampleSectorKey::Second),
+            )
+            .sector(ExampleSectorKey::Second, SectorBuilder::default().u8(2))
+            .sector(
+                ExampleSectorKey::Third,
+                SectorBuilder::default()
+                    .checksum_sum16(ExampleSectorKey::First, ExampleSectorKey::Second),
+            )
+            .build_to_vec()
+            .await
+            .unwrap();
+
+        // The outer checksum covers the inner one once it's patched
+        assert_eq!(buffer, [1, 2, 0, 2, 5, 0]);
+    }
+
+    #[tokio::test]
+    async fn sector_checksum_cycle() {
+        let error = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default()
+                    .checksum_sum16(ExampleSectorKey::Second, ExampleSectorKey::Second),
+            )
+            .sector(
+                ExampleSectorKey::Second,
+                SectorBuilder::default()
+                    .checksum_sum16(ExampleSectorKey::First, ExampleSectorKey::First),
+            )
+            .build_to_vec()
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.to_string(), "Checksums can't cover each other");
+    }
+
+    #[tokio::test]
+    async fn sector_crc16_ccitt() {
+        let buffer = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default().bytes(*b"123456789"),
+            )
+            .sector(
+                ExampleSectorKey::Second,
+                SectorBuilder::default()
+                    .crc16_ccitt(ExampleSectorKey::First, ExampleSectorKey::First),
+            )
+            .build_to_vec()
+            .await
+            .unwrap();
+
+        assert_eq!(buffer[9..], [0xB1, 0x29]);
+    }
+
+    #[tokio::test]
+    async fn sector_crc32_missing() {
+        let error = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default().crc32(ExampleSectorKey::Second, ExampleSectorKey::Third),
+            )
+            .sector_default(ExampleSectorKey::Second)
+            .build_to_vec()
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Invalid checksum range in sector First: Second to Third"
+        );
+    }
+
+    #[tokio::test]
+    async fn sector_crc32_covers_itself() {
+        let result = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default().crc32(ExampleSectorKey::First, ExampleSectorKey::First),
+            )
+            .build_to_vec()
+            .await;
+
+        assert!(result.is_err());
+    }
+
+    #[derive(Debug, Clone, Hash, PartialEq, Eq)]
+    enum InnerSectorKey {
+        Header,
+        Data,
+    }
+
+    #[derive(Debug, Clone, Hash, PartialEq, Eq)]
+    enum OuterSectorKey {
+        Header,
+        Inner(usize, InnerSectorKey),
+    }
+
+    fn inner_builder(value: u8) -> SerialBuilder<InnerSectorKey> {
+        SerialBuilder::default()
+            .sector(
+                InnerSectorKey::Header,
+                SerialSectorBuilder::default().dynamic_u8(
+                    InnerSectorKey::Header,
+                    InnerSectorKey::Data,
+                    0,
+                ),
+            )
+            .sector(
+                InnerSectorKey::Data,
+                SerialSectorBuilder::default().u8(value),
+            )
+    }
+
+    #[tokio::test]
+    async fn sector_nested() {
+        let buffer = SerialBuilder::default()
+            .sector(
+                OuterSectorKey::Header,
+                SerialSectorBuilder::default().dynamic_u8(
+                    OuterSectorKey::Header,
+                    OuterSectorKey::Inner(1, InnerSectorKey::Header),
+                    0,
+                ),
+            )
+            .sector_nested(|key| OuterSectorKey::Inner(0, key), inner_builder(0xAA))
+            .unwrap()
+            .sector_nested(|key| OuterSectorKey::Inner(1, key), inner_builder(0xBB))
+            .unwrap()
+            .build_to_vec()
+            .await
+            .unwrap();
+
+        assert_eq!(buffer, [0x03, 0x01, 0xAA, 0x01, 0xBB]);
+    }
+
+    #[test]
+    fn sector_nested_errors() {
+        let trailer = |builder: SerialBuilder<InnerSectorKey>| {
+            builder
+                .trailer(InnerSectorKey::Data, SerialSectorBuilder::default())
+                .unwrap()
+        };
+        let outer = SerialBuilder::default()
+            .trailer(OuterSectorKey::Header, SerialSectorBuilder::default())
+            .unwrap();
+
+        let error = outer
+            .sector_nested(
+                |key| OuterSectorKey::Inner(0, key),
+                trailer(SerialBuilder::default()),
+            )
+            .unwrap_err();
+        assert_eq!(error.to_string(), "Trailer is already set: Header");
+
+        let error = SerialBuilder::default()
+            .sector_nested(|_| OuterSectorKey::Header, inner_builder(0xAA))
+            .unwrap_err();
+        assert_eq!(error.to_string(), "Duplicate sector: Header");
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn build_report() {
+        let mut buffer = Vec::new();
+
+        let report = Builder::default()
+            .sector(ExampleSectorKey::First, SectorBuilder::default().u8(0xFF))
+            .sector(
+                ExampleSectorKey::Second,
+                SectorBuilder::default().string("ab").u16(0u16),
+            )
+            .sector_default(ExampleSectorKey::Third)
+            .build_with_report(&mut buffer)
+            .await
+            .unwrap();
+
+        assert_eq!(report.total_size, buffer.len());
+        assert_eq!(
+            report.sectors,
+            [
+                SectorLayout {
+                    key: ExampleSectorKey::First,
+                    start: 0,
+                    size: 1,
+                    field_count: 1,
+                },
+                SectorLayout {
+                    key: ExampleSectorKey::Second,
+                    start: 1,
+                    size: 5,
+                    field_count: 2,
+                },
+                SectorLayout {
+                    key: ExampleSectorKey::Third,
+                    start: 6,
+                    size: 0,
+                    field_count: 0,
+                },
+            ]
+        );
+        assert_eq!(report.sector(&ExampleSectorKey::Second).unwrap().start, 1);
+    }
+
+    #[test]
+    fn stats() {
+        let stats = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default()
+                    .dynamic_u24(ExampleSectorKey::First, ExampleSectorKey::Second, 0)
+                    .dynamic_auto(ExampleSectorKey::First, ExampleSectorKey::Third, 0)
+                    .u8(0),
+            )
+            .sector_aligned(
+                ExampleSectorKey::Second,
+                SectorBuilder::default()
+                    .external(EXTERNAL_FIXTURE, 8)
+                    .external_range(EXTERNAL_FIXTURE, 2, 3),
+                16,
+                0,
+            )
+            .sector_default(ExampleSectorKey::Third)
+            .with_max_size(1)
+            .stats()
+            .unwrap();
+
+        assert_eq!(
+            stats,
+            [
+                (
+                    ExampleSectorKey::First,
+                    SectorStats {
+                        size: 5,
+                        field_count: 3,
+                        pointers: 2,
+                        externals: 0,
+                    },
+                ),
+                (
+                    ExampleSectorKey::Second,
+                    SectorStats {
+                        size: 11,
+                        field_count: 2,
+                        pointers: 0,
+                        externals: 2,
+                    },
+                ),
+                (ExampleSectorKey::Third, SectorStats::default()),
+            ]
+        );
+    }
+
+    #[test]
+    fn describe() {
+        let description = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default()
+                    .dynamic_u24(ExampleSectorKey::First, ExampleSectorKey::Second, 0)
+                    .label("second")
+                    .dynamic_auto(ExampleSectorKey::First, ExampleSectorKey::Third, 0)
+                    .u8(0),
+            )
+            .sector_aligned(
+                ExampleSectorKey::Second,
+                SectorBuilder::default().external_range(EXTERNAL_FIXTURE, 2, 3),
+                16,
+                0,
+            )
+            .sector(
+                ExampleSectorKey::Third,
+                SectorBuilder::default().dynamic_i16(
+                    ExampleSectorKey::Third,
+                    ExampleSectorKey::First,
+                    0,
+                ),
+            )
+            .describe()
+            .unwrap();
+
+        // The automatic pointer is described with the width it was given
+        assert_eq!(
+            description,
+            "Sector First at 000000, 5 bytes\n\
+             \x20 Field \"second\" at 000000: Dynamic, 3 bytes, to Second field 0 = 16\n\
+             \x20 Field 1 at 000003: Dynamic, 1 bytes, to Third field 0 = 19\n\
+             \x20 Field 2 at 000004: U8, 1 bytes\n\
+             Sector Second at 000010, 3 bytes, after 11 bytes of padding\n\
+             \x20 Field 0 at 000010: External, 3 bytes\n\
+             Sector Third at 000013, 2 bytes\n\
+             \x20 Field 0 at 000013: DynamicSigned, 2 bytes, to First field 0 = -19\n"
+        );
+    }
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn describe_async() {
+        let builder = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default().dynamic_u24(
+                    ExampleSectorKey::First,
+                    ExampleSectorKey::Second,
+                    0,
+                ),
+            )
+            .sector(
+                ExampleSectorKey::Second,
+                SectorBuilder::default().external_auto(EXTERNAL_FIXTURE),
+            );
+
+        assert_eq!(
+            builder.describe_async().await.unwrap(),
+            builder.describe().unwrap()
+        );
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn build_with_symbols() {
+        let mut buffer = Vec::new();
+        let mut symbols = Vec::new();
+
+        Builder::default()
+            .sector(ExampleSectorKey::First, SectorBuilder::default().u16(1u16))
+            .sector_default(ExampleSectorKey::Second)
+            .sector(ExampleSectorKey::Third, SectorBuilder::default().u8(2))
+            .build_with_symbols(&mut buffer, &mut symbols, |key| {
+                format!("sector_{key:?}").to_lowercase()
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(buffer, [1, 0, 2]);
+        assert_eq!(
+            String::from_utf8(symbols).unwrap(),
+            "sector_first := 0x000000\n\
+             sector_second := 0x000002\n\
+             sector_third := 0x000002\n"
+        );
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn build_with_symbols_duplicate() {
+        let mut buffer = Vec::new();
+        let error = Builder::default()
+            .sector_default(ExampleSectorKey::First)
+            .sector_default(ExampleSectorKey::Second)
+            .build_with_symbols(&mut buffer, &mut Vec::new(), |_| "sector".to_string())
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Duplicate symbol name for sectors First and Second: sector"
+        );
+        // Nothing is built once the symbols are invalid
+        assert!(buffer.is_empty());
+    }
+
+    #[tokio::test]
+    async fn validate() {
+        let builder = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default()
+                    .u8(0)
+                    .dynamic_u8(ExampleSectorKey::First, ExampleSectorKey::Second, 5)
+                    .align(0)
+                    .dynamic_u8(ExampleSectorKey::First, ExampleSectorKey::Second, 1),
+            )
+            .sector(
+                ExampleSectorKey::Second,
+                SectorBuilder::default()
+                    .repeat_u8(0, 300)
+                    .dynamic_u8(ExampleSectorKey::First, ExampleSectorKey::Third, 0)
+                    .fill(ExampleSectorKey::First, 1),
+            );
+
+        let issues = builder
+            .validate()
+            .await
+            .unwrap_err()
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            issues,
+            [
+                "Sector First, field 2: Failed to serialize; alignment boundary can't be zero",
+                "Sector Second, field 2: Failed to serialize; fill start is past fill amount: 304 > 1",
+                "Sector First, field 1: Can't index into sector; not enough fields. \
+                 Sector: Second, Length: 3, Index: 5",
+                "Sector First, field 3: Pointer exceeds 8-bit limit: 303 > 255",
+                "Sector Second, field 1: Sector does not exist: Third",
+            ]
+        );
+        assert!(
+            Builder::default()
+                .sector(ExampleSectorKey::First, SectorBuilder::default().u8(0))
+                .validate()
+                .await
+                .is_ok()
+        );
+    }
+
+    // External files are checked without being read
+    #[tokio::test]
+    async fn validate_external() {
+        let issues = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default()
+                    .external(EXTERNAL_FIXTURE, 8)
+                    .external(EXTERNAL_FIXTURE, 4)
+                    .external_range(EXTERNAL_FIXTURE, 6, 4),
+            )
+            .validate()
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            issues.iter().map(|issue| issue.field).collect::<Vec<_>>(),
+            [Some(1), Some(2)]
+        );
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn max_size() {
+        let builder = Builder::default()
+            .with_max_size(4)
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default().u24(u24::MIN),
+            )
+            .sector(ExampleSectorKey::Second, SectorBuilder::default().u16(0u16))
+            .sector(ExampleSectorKey::Third, SectorBuilder::default().u8(0));
+        let mut buffer = Vec::new();
+        let error = builder.build(&mut buffer).await.unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Output is over the maximum size: 6 bytes > 4 bytes\n\
+             Sector Second is the first past the limit"
+        );
+        assert!(buffer.is_empty());
+        assert_eq!(builder.total_size().await.unwrap(), 6);
+
+        let exact = Builder::default().with_max_size(3).sector(
+            ExampleSectorKey::First,
+            SectorBuilder::default().u24(u24::MIN),
+        );
+        assert_eq!(exact.build_to_vec().await.unwrap(), [0; 3]);
+    }
+
+    // Trailing fills aren't written, so they don't count towards the limit
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn max_size_trailing_fill() {
+        let builder = Builder::default()
+            .with_max_size(4)
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default().u24(u24::MIN),
+            )
+            .sector(
+                ExampleSectorKey::Second,
+                SectorBuilder::default().fill(ExampleSectorKey::First, 16),
+            );
+
+        assert_eq!(builder.build_to_vec().await.unwrap(), [0; 3]);
+
+        let error = builder
+            .sector(ExampleSectorKey::Third, SectorBuilder::default().u8(0))
+            .build_to_vec()
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Output is over the maximum size: 17 bytes > 4 bytes\n\
+             Sector Third is the first past the limit"
+        );
+    }
+
+    #[tokio::test]
+    async fn sector_deferred() {
+        let buffer = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default()
+                    // The number of 4 byte pages the output takes up
+                    .deferred_u8(|report| Ok(report.total_size.div_ceil(4) as u32))
+                    .deferred_u24(|report| {
+                        let second = report.sector(&ExampleSectorKey::Second).unwrap();
+                        Ok(second.size as u32)
+                    }),
+            )
+            .sector(
+                ExampleSectorKey::Second,
+                SectorBuilder::default().string("Test"),
+            )
+            .build_to_vec()
+            .await
+            .unwrap();
+
+        assert_eq!(buffer, [3, 5, 0, 0, b'T', b'e', b's', b't', 0]);
+    }
+
+    // Nested deferred values see the inner builder's keys
+    #[tokio::test]
+    async fn sector_deferred_nested() {
+        let inner = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default().deferred_u8(|report| {
+                    Ok(report.sector(&ExampleSectorKey::Second).unwrap().start as u32)
+                }),
+            )
+            .sector(ExampleSectorKey::Second, SectorBuilder::default().u8(7));
+        let buffer = SerialBuilder::<(u8, ExampleSectorKey)>::default()
+            .sector(
+                (0, ExampleSectorKey::First),
+                SerialSectorBuilder::default().u16(0u16),
+            )
+            .sector_nested_deferred(|key| (1, key), inner)
+            .unwrap()
+            .build_to_vec()
+            .await
+            .unwrap();
+
+        assert_eq!(buffer, [0, 0, 3, 7]);
+    }
 
-    use u24::u24;
+    #[test]
+    fn sector_deferred_nested_unmapped() {
+        let inner = Builder::default().sector(
+            ExampleSectorKey::First,
+            SectorBuilder::default().deferred_u8(|_| Ok(0)),
+        );
+        let error = SerialBuilder::<(u8, ExampleSectorKey)>::default()
+            .sector_nested(|key| (1, key), inner)
+            .unwrap_err();
 
-    use crate::prelude::*;
+        assert_eq!(
+            error.root_cause().to_string(),
+            "Deferred fields can only be nested with `sector_nested_deferred`"
+        );
+    }
 
-    type Builder = SerialBuilder<ExampleSectorKey>;
-    type SectorBuilder = SerialSectorBuilder<ExampleSectorKey>;
+    // Keys only need to live as long as the builders
+    #[tokio::test]
+    async fn sector_nested_borrowed_keys() {
+        let names = [String::from("header"), String::from("data")];
+        let [header, data] = [names[0].as_str(), names[1].as_str()];
+        let inner = SerialBuilder::default()
+            .sector(
+                header,
+                SerialSectorBuilder::default().dynamic_u8(header, data, 0),
+            )
+            .sector(data, SerialSectorBuilder::default().u8(7));
 
-    #[derive(Debug, Clone, Hash, PartialEq, Eq)]
-    enum ExampleSectorKey {
-        First,
-        Second,
-        Third,
+        let buffer = SerialBuilder::<(u8, &str)>::default()
+            .sector((0, "start"), SerialSectorBuilder::default().u8(1))
+            .sector_nested(|key| (1, key), inner)
+            .unwrap()
+            .build_to_vec()
+            .await
+            .unwrap();
+
+        assert_eq!(buffer, [1, 1, 7]);
     }
 
     #[tokio::test]
-    async fn sector_string() {
-        let expected = b"This is a test\x00";
-        let mut buffer = Cursor::new(Vec::with_capacity(expected.len()));
+    async fn sector_deferred_overflow() {
+        let error = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default().deferred_u8(|_| Ok(256)),
+            )
+            .build_to_vec()
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            error.root_cause().to_string(),
+            "Deferred value exceeds 8-bit limit: 256 > 255"
+        );
+    }
+
+    #[tokio::test]
+    async fn sector_count() {
+        let buffer = SerialBuilder::<(u8, u16)>::default()
+            .sector(
+                (0, 0),
+                SerialSectorBuilder::default()
+                    .count_u8(|&(kind, _)| kind == 1)
+                    .count_u16(|_| true),
+            )
+            .sector((1, 0), SerialSectorBuilder::default())
+            .sector((2, 0), SerialSectorBuilder::default())
+            .sector((1, 1), SerialSectorBuilder::default())
+            .build_to_vec()
+            .await
+            .unwrap();
+
+        assert_eq!(buffer, [2, 4, 0]);
+    }
+
+    #[tokio::test]
+    async fn sector_count_overflow() {
+        let error = SerialBuilder::<u16>::default()
+            .sector(0, SerialSectorBuilder::default().count_u8(|_| true))
+            .sectors_from_iter((1..256).map(|key| (key, SerialSectorBuilder::default())))
+            .build_to_vec()
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            error.root_cause().to_string(),
+            "Sector count exceeds 8-bit limit: 256 > 255"
+        );
+    }
+
+    #[tokio::test]
+    async fn total_size() {
+        let builder = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default()
+                    .string("Test")
+                    .u24(u24::from_le_bytes([0; 3])),
+            )
+            .sector(
+                ExampleSectorKey::Second,
+                SectorBuilder::default()
+                    .fill(ExampleSectorKey::First, 12)
+                    .external("missing.bin", 4),
+            );
+
+        assert_eq!(builder.total_size().await.unwrap(), 16);
+
+        // External files are only read when building
+        assert!(builder.build_to_vec().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn total_size_fill_overflow() {
+        let builder = Builder::default()
+            .sector_default(ExampleSectorKey::First)
+            .sector(
+                ExampleSectorKey::Second,
+                SectorBuilder::default()
+                    .string("Test")
+                    .fill(ExampleSectorKey::First, 2),
+            );
+
+        assert!(builder.total_size().await.is_err());
+    }
+
+    /// A temporary file unique to this process, so concurrent test runs don't share it
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("serseg_{name}_{}.bin", std::process::id()))
+    }
+
+    #[tokio::test]
+    async fn sector_external_auto() {
+        let path = temp_path("sector_external_auto");
+        tokio::fs::write(&path, [1, 2, 3]).await.unwrap();
+
+        let builder = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default().external_auto(&path),
+            )
+            .sector(ExampleSectorKey::Second, SectorBuilder::default().u8(4))
+            .sector(
+                ExampleSectorKey::Third,
+                SectorBuilder::default().dynamic_u8(
+                    ExampleSectorKey::First,
+                    ExampleSectorKey::Second,
+                    0,
+                ),
+            );
+
+        assert_eq!(builder.total_size().await.unwrap(), 5);
+        assert_eq!(builder.build_to_vec().await.unwrap(), [1, 2, 3, 4, 3]);
+        tokio::fs::remove_file(&path).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn sector_external_auto_missing() {
+        let builder = Builder::default().sector(
+            ExampleSectorKey::First,
+            SectorBuilder::default().external_auto("missing.bin"),
+        );
+
+        assert!(builder.total_size().await.is_err());
+    }
+
+    // Holds the bytes 0 to 7
+    const EXTERNAL_FIXTURE: &str =
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/external.bin");
+
+    fn external_range(offset: u64, len: usize) -> Builder {
+        Builder::default().sector(
+            ExampleSectorKey::First,
+            SectorBuilder::default().external_range(EXTERNAL_FIXTURE, offset, len),
+        )
+    }
+
+    #[tokio::test]
+    async fn sector_external_range() {
+        assert_eq!(
+            external_range(2, 3).build_to_vec().await.unwrap(),
+            [2, 3, 4]
+        );
+        assert_eq!(external_range(6, 2).build_to_vec().await.unwrap(), [6, 7]);
+        assert!(
+            external_range(8, 0)
+                .build_to_vec()
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    /// Accepts at most one byte per write
+    #[cfg(feature = "tokio")]
+    #[derive(Default)]
+    struct ShortWriter(Vec<u8>);
+
+    #[cfg(feature = "tokio")]
+    impl tokio::io::AsyncWrite for ShortWriter {
+        fn poll_write(
+            mut self: std::pin::Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+            buf: &[u8],
+        ) -> std::task::Poll<std::io::Result<usize>> {
+            let written = buf.len().min(1);
+            self.0.extend_from_slice(&buf[..written]);
+            std::task::Poll::Ready(Ok(written))
+        }
+
+        fn poll_flush(
+            self: std::pin::Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<std::io::Result<()>> {
+            std::task::Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(
+            self: std::pin::Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<std::io::Result<()>> {
+            std::task::Poll::Ready(Ok(()))
+        }
+    }
+
+    // Short writes aren't mistaken for a file of the wrong size
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn sector_external_short_writes() {
+        let mut writer = ShortWriter::default();
 
         Builder::default()
             .sector(
                 ExampleSectorKey::First,
-                SectorBuilder::default().string("This is a test"),
+                SectorBuilder::default().external(EXTERNAL_FIXTURE, 8),
             )
-            .build(&mut buffer)
+            .build(&mut writer)
             .await
             .unwrap();
 
-        assert_eq!(buffer.into_inner(), expected);
+        assert_eq!(writer.0, [0, 1, 2, 3, 4, 5, 6, 7]);
     }
 
     #[tokio::test]
-    async fn sector_u24() {
-        let expected = [0x12, 0x34, 0x56];
-        let mut buffer = Cursor::new(Vec::with_capacity(expected.len()));
+    async fn sector_external_large() {
+        let path = temp_path("sector_external_large");
+        let data = (0..1 << 20)
+            .map(|i: u32| (i % 251) as u8)
+            .collect::<Vec<_>>();
+        tokio::fs::write(&path, &data).await.unwrap();
 
-        Builder::default()
+        let buffer = Builder::default()
             .sector(
                 ExampleSectorKey::First,
-                SectorBuilder::default().u24(u24::from_le_bytes([0x12, 0x34, 0x56])),
+                SectorBuilder::default()
+                    .u8(0xAA)
+                    .external(&path, data.len())
+                    .u8(0xBB),
             )
-            .build(&mut buffer)
+            .build_to_vec()
             .await
             .unwrap();
 
-        assert_eq!(buffer.into_inner(), expected);
+        assert_eq!(buffer.len(), data.len() + 2);
+        assert_eq!(buffer[1..=data.len()], data);
+        assert_eq!(buffer[data.len() + 1], 0xBB);
+        tokio::fs::remove_file(&path).await.unwrap();
     }
 
+    // Errors rather than wrapping to a small offset
     #[tokio::test]
-    async fn sector_dynamic() {
-        let expected = b"\xFF\x06\x00\x00\x13\x00\x00first string\x00second string\x00";
-        let mut buffer = Cursor::new(Vec::with_capacity(expected.len()));
+    async fn sector_external_overflow() {
+        let error = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default()
+                    .u8(0)
+                    .external(EXTERNAL_FIXTURE, usize::MAX),
+            )
+            .sector(ExampleSectorKey::Second, SectorBuilder::default().u8(0))
+            .build_to_vec()
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            format!("{error:#}"),
+            format!(
+                "Failed to track sector First, field 1: Output is too large; offset 1 + {} bytes \
+                 overflows",
+                usize::MAX
+            )
+        );
+    }
+
+    // Not a tokio test, so nothing can rely on a runtime
+    #[test]
+    fn build_std() {
+        let mut buffer = Vec::new();
 
         Builder::default()
-            .sector(ExampleSectorKey::First, SectorBuilder::default().u8(0xFF))
             .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default()
+                    .u8(1)
+                    .external_range(EXTERNAL_FIXTURE, 2, 2),
+            )
+            .sector_aligned(
                 ExampleSectorKey::Second,
                 SectorBuilder::default()
-                    .dynamic_u24(ExampleSectorKey::Second, ExampleSectorKey::Third, 0)
-                    .dynamic_u24(ExampleSectorKey::Second, ExampleSectorKey::Third, 1),
+                    .checksum_sum16(ExampleSectorKey::First, ExampleSectorKey::First),
+                4,
+                0xFF,
+            )
+            .build_std(&mut buffer)
+            .unwrap();
+
+        assert_eq!(buffer, [1, 2, 3, 0xFF, 6, 0]);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn build_twice() {
+        let builder = Builder::default().sector(
+            ExampleSectorKey::First,
+            SectorBuilder::default()
+                .u8(1)
+                .external_range(EXTERNAL_FIXTURE, 2, 2),
+        );
+        let mut buffer = Vec::new();
+
+        builder.build(&mut buffer).await.unwrap();
+
+        assert_eq!(buffer, [1, 2, 3]);
+        assert_eq!(builder.build_to_vec().await.unwrap(), buffer);
+    }
+
+    #[test]
+    fn build_std_missing_external() {
+        let error = Builder::default()
+            .sector(
+                ExampleSectorKey::First,
+                SectorBuilder::default().external_auto("missing.bin"),
             )
+            .build_std(&mut Vec::new())
+            .unwrap_err();
+
+        // The OS's own message follows
+        assert!(format!("{error:#}").starts_with(
+            "Failed to track sector First, field 0: Failed to read size of external file: \
+             \"missing.bin\": "
+        ));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn sector_external_incorrect_size() {
+        let mut writer = ShortWriter::default();
+        let error = Builder::default()
             .sector(
-                ExampleSectorKey::Third,
+                ExampleSectorKey::First,
+                SectorBuilder::default().external(EXTERNAL_FIXTURE, 9),
+            )
+            .build(&mut writer)
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            error.root_cause().to_string(),
+            format!(
+                "External file has incorrect file size:\n\
+                 Expected: 9 bytes, Found: 8 bytes\n\
+                 Path: {EXTERNAL_FIXTURE:?}"
+            )
+        );
+        // Nothing is written once the size is wrong
+        assert!(writer.0.is_empty());
+    }
+
+    #[tokio::test]
+    async fn sector_external_range_past_offset() {
+        let error = external_range(9, 0).build_to_vec().await.unwrap_err();
+
+        assert_eq!(
+            error.root_cause().to_string(),
+            format!(
+                "External file is shorter than its range's offset:\n\
+                 Offset: 9 bytes, Found: 8 bytes\n\
+                 Path: {EXTERNAL_FIXTURE:?}"
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn sector_external_range_past_end() {
+        let error = external_range(6, 3).build_to_vec().await.unwrap_err();
+
+        assert_eq!(
+            error.root_cause().to_string(),
+            format!(
+                "External file range extends past the end of the file:\n\
+                 Range: 6..9, Found: 8 bytes\n\
+                 Path: {EXTERNAL_FIXTURE:?}"
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn sector_external_range_overflow() {
+        let error = external_range(u64::MAX, 2)
+            .build_to_vec()
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            error.root_cause().to_string(),
+            format!(
+                "External file range overflows: {} + 2 bytes\n\
+                 Path: {EXTERNAL_FIXTURE:?}",
+                u64::MAX
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn sector_fill() {
+        let expected = [
+            b'T', b'e', b's', b't', 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xFF,
+        ];
+        let buffer = Builder::default()
+            .sector_default(ExampleSectorKey::First)
+            .sector(
+                ExampleSectorKey::Second,
                 SectorBuilder::default()
-                    .string("first string")
-                    .string("second string"),
+                    .string("Test")
+                    .fill(ExampleSectorKey::First, 16)
+                    .u8(0xFF),
+            )
+            .build_to_vec()
+            .await
+            .unwrap();
+
+        assert_eq!(buffer, expected);
+    }
+
+    #[tokio::test]
+    async fn sector_fill_with() {
+        let expected = [
+            b'T', b'e', b's', b't', 0, 0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00, 0x01,
+        ];
+        let buffer = Builder::default()
+            .sector_default(ExampleSectorKey::First)
+            .sector(
+                ExampleSectorKey::Second,
+                SectorBuilder::default()
+                    .string("Test")
+                    .fill_with(ExampleSectorKey::First, 8, 0xFF)
+                    .fill(ExampleSectorKey::First, 12)
+                    .u8(0x01),
+            )
+            .build_to_vec()
+            .await
+            .unwrap();
+
+        assert_eq!(buffer, expected);
+    }
+
+    // A `Vec` can't seek, so padding must be written
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn sector_fill_unseekable() {
+        let mut buffer = Vec::new();
+
+        Builder::default()
+            .sector_default(ExampleSectorKey::First)
+            .sector(
+                ExampleSectorKey::Second,
+                SectorBuilder::default()
+                    .string("Test")
+                    .fill(ExampleSectorKey::First, 8)
+                    .align(4)
+                    .u8(0xFF),
             )
             .build(&mut buffer)
             .await
             .unwrap();
 
-        assert_eq!(buffer.into_inner(), expected);
+        assert_eq!(buffer, b"Test\x00\x00\x00\x00\xFF");
     }
 
+    // Builds through a stream, like a pipe, which can only be written to
+    #[cfg(feature = "tokio")]
     #[tokio::test]
-    async fn sector_dynamic_chunk() {
-        let expected = b"\xFF\x03\x00\x00\x0A\x00\x00first string\x00second string\x00";
-        let mut buffer = Cursor::new(Vec::with_capacity(expected.len()));
+    async fn sector_dynamic_stream() {
+        let (mut writer, mut reader) = tokio::io::duplex(256);
 
         Builder::default()
             .sector(ExampleSectorKey::First, SectorBuilder::default().u8(0xFF))
             .sector(
                 ExampleSectorKey::Second,
                 SectorBuilder::default()
-                    .dynamic_u24_chunk(ExampleSectorKey::Second, ExampleSectorKey::Third, 0, 2)
-                    .dynamic_u24_chunk(
-                        ExampleSectorKey::Second,
-                        ExampleSectorKey::Third,
-                        1,
-                        (ScaleRounding::Nearest, 2),
-                    ),
+                    .dynamic_u24(ExampleSectorKey::Second, ExampleSectorKey::Third, 0)
+                    .fill(ExampleSectorKey::First, 9)
+                    .dynamic_u24(ExampleSectorKey::Second, ExampleSectorKey::Third, 1),
             )
             .sector(
                 ExampleSectorKey::Third,
@@ -107,61 +3119,77 @@ mod tests {
                     .string("first string")
                     .string("second string"),
             )
-            .build(&mut buffer)
+            .build(&mut writer)
             .await
             .unwrap();
+        drop(writer);
+
+        let mut buffer = Vec::new();
+        reader.read_to_end(&mut buffer).await.unwrap();
 
-        assert_eq!(buffer.into_inner(), expected);
+        assert_eq!(
+            buffer,
+            b"\xFF\x0B\x00\x00\x00\x00\x00\x00\x00\x18\x00\x00first string\x00second string\x00"
+        );
     }
 
     #[tokio::test]
-    async fn sector_fill() {
-        let expected = [
-            b'T', b'e', b's', b't', 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xFF,
-        ];
-        let mut buffer = Cursor::new(Vec::with_capacity(expected.len()));
-
-        Builder::default()
+    async fn sector_fill_end() {
+        let expected = b"Test\x00";
+        let buffer = Builder::default()
             .sector_default(ExampleSectorKey::First)
             .sector(
                 ExampleSectorKey::Second,
                 SectorBuilder::default()
                     .string("Test")
-                    .fill(ExampleSectorKey::First, 16)
-                    .u8(0xFF),
+                    .fill(ExampleSectorKey::First, 16),
             )
-            .build(&mut buffer)
+            .build_to_vec()
             .await
             .unwrap();
 
-        assert_eq!(buffer.into_inner(), expected);
+        assert_eq!(buffer, expected);
     }
 
+    #[cfg(feature = "tokio")]
     #[tokio::test]
-    async fn sector_fill_end() {
-        let expected = b"Test\x00";
-        let mut buffer = Cursor::new(Vec::with_capacity(expected.len()));
-
-        Builder::default()
+    async fn sector_fill_end_size() {
+        let builder = Builder::default()
             .sector_default(ExampleSectorKey::First)
             .sector(
                 ExampleSectorKey::Second,
                 SectorBuilder::default()
-                    .string("Test")
+                    .u8(2)
                     .fill(ExampleSectorKey::First, 16),
+            );
+
+        let mut buffer = Vec::new();
+        let report = builder.build_with_report(&mut buffer).await.unwrap();
+        assert_eq!(buffer, [2]);
+        assert_eq!(builder.total_size().await.unwrap(), buffer.len());
+        assert_eq!(report.total_size, buffer.len());
+    }
+
+    #[tokio::test]
+    async fn sector_fill_end_large() {
+        let buffer = Builder::default()
+            .sector_default(ExampleSectorKey::First)
+            .sector(
+                ExampleSectorKey::Second,
+                SectorBuilder::default()
+                    .u8(2)
+                    .fill(ExampleSectorKey::First, usize::MAX),
             )
-            .build(&mut buffer)
+            .build_to_vec()
             .await
             .unwrap();
 
-        assert_eq!(buffer.into_inner(), expected);
+        assert_eq!(buffer, [2]);
     }
 
     #[tokio::test]
     async fn sector_fill_overflow() {
-        let mut buffer = Cursor::new(Vec::new());
-
-        let result = Builder::default()
+        let error = Builder::default()
             .sector_default(ExampleSectorKey::First)
             .sector(
                 ExampleSectorKey::Second,
@@ -169,9 +3197,34 @@ mod tests {
                     .string("Test")
                     .fill(ExampleSectorKey::First, 2),
             )
-            .build(&mut buffer)
-            .await;
+            .build_to_vec()
+            .await
+            .unwrap_err();
 
-        assert!(result.is_err());
+        assert_eq!(
+            format!("{error:#}"),
+            "Failed to track sector Second, field 1: \
+             Failed to serialize; fill start is past fill amount: 5 > 2"
+        );
+    }
+
+    #[tokio::test]
+    async fn sector_fill_at_least() {
+        let build = |fill| async move {
+            Builder::default()
+                .sector_default(ExampleSectorKey::First)
+                .sector(
+                    ExampleSectorKey::Second,
+                    SectorBuilder::default()
+                        .string("Test")
+                        .fill_at_least(ExampleSectorKey::First, fill)
+                        .u8(0xFF),
+                )
+                .build_to_vec()
+                .await
+        };
+
+        assert_eq!(build(8).await.unwrap(), b"Test\x00\x00\x00\x00\xFF");
+        assert_eq!(build(2).await.unwrap(), b"Test\x00\xFF");
     }
 }
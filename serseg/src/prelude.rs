@@ -1,4 +1,11 @@
 pub use crate::{
+    bitfield::{BitOrder, BitfieldBuilder},
     builder::{SerialBuilder, SerialSectorBuilder},
-    field::ScaleRounding,
+    checksum::ChecksumAlgorithm,
+    compression::Compression,
+    field::{DeferredValue, Endianness, ScaleRounding, SerialField},
+    issue::SersegIssue,
+    reader::{SchemaField, SerialReader, SerialSchemaBuilder, SerialValue},
+    report::{PointerWidth, SectorLayout, SectorStats, SerialReport},
 };
+pub use serseg_derive::SerialSector;
@@ -0,0 +1,366 @@
+use std::hash::Hash;
+
+use anyhow::{Context, bail};
+use indexmap::IndexMap;
+
+use crate::field::Endianness;
+
+/// A field to read, like a [`SerialField`](crate::field::SerialField) without its value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemaField<S> {
+    U8,
+    U16(Option<Endianness>),
+    U24(Option<Endianness>),
+    U32(Option<Endianness>),
+    /// Up to a null terminator, which isn't included in the value
+    String,
+    Bytes(usize),
+    /// Checked to point to the field it claims to, so it can't be offset or scaled
+    Dynamic {
+        origin: S,
+        sector: S,
+        /// Index from begining of first sector
+        index: usize,
+        bytes: usize,
+        endianness: Option<Endianness>,
+    },
+}
+
+/// A value read from a field
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SerialValue {
+    U8(u8),
+    U16(u16),
+    /// Widened, as there's no native 24-bit type
+    U24(u32),
+    U32(u32),
+    String(String),
+    Bytes(Vec<u8>),
+    /// The offset from the pointer's origin
+    Pointer(usize),
+}
+
+/// Reads sectors back out of built data, following a schema for each of them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SerialReader<S: Hash + Eq + Clone + std::fmt::Debug> {
+    sectors: IndexMap<S, SerialSchemaBuilder<S>>,
+    /// Used by integer fields without an explicit endianness
+    endianness: Endianness,
+}
+
+// Default macro requires S to implement default
+// We don't want that
+impl<S: Hash + Eq + Clone + std::fmt::Debug> Default for SerialReader<S> {
+    fn default() -> Self {
+        Self {
+            sectors: IndexMap::default(),
+            endianness: Endianness::default(),
+        }
+    }
+}
+
+/// The fields of a sector to read, in order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SerialSchemaBuilder<S> {
+    fields: Vec<SchemaField<S>>,
+}
+
+// Default macro requires S to implement default
+// We don't want that
+impl<S> Default for SerialSchemaBuilder<S> {
+    fn default() -> Self {
+        Self {
+            fields: Vec::default(),
+        }
+    }
+}
+
+/// A pointer that was read, to check once every sector is
+struct ReadPointer<'a, S> {
+    sector: &'a S,
+    field: usize,
+    origin: &'a S,
+    target: &'a S,
+    index: usize,
+    value: usize,
+}
+
+impl<S: Hash + Eq + Clone + std::fmt::Debug> SerialReader<S> {
+    /// Integer fields default to little-endian, the same as [`SerialReader::default`]
+    pub fn little_endian() -> Self {
+        Self::default()
+    }
+
+    /// Integer fields default to big-endian
+    pub fn big_endian() -> Self {
+        Self {
+            endianness: Endianness::Big,
+            ..Self::default()
+        }
+    }
+
+    /// Appends the sector, which must be in the same order as it was built
+    pub fn sector(mut self, key: S, schema: SerialSchemaBuilder<S>) -> Self {
+        debug_assert!(
+            !self.sectors.contains_key(&key),
+            "Duplicate sector: {key:?}"
+        );
+        self.sectors.insert(key, schema);
+        self
+    }
+
+    /// Reads every sector's values, in order.
+    ///
+    /// Fails if the data doesn't match the schema exactly, including any bytes left over or
+    /// pointers that don't land on their field.
+    pub fn read(&self, data: &[u8]) -> anyhow::Result<IndexMap<S, Vec<SerialValue>>> {
+        let mut values = IndexMap::with_capacity(self.sectors.len());
+        let mut sector_offsets = IndexMap::with_capacity(self.sectors.len());
+        // Where each field of a sector starts, followed by where the sector ends
+        let mut field_offsets = IndexMap::with_capacity(self.sectors.len());
+        let mut pointers = Vec::new();
+        let mut position = 0;
+
+        for (sector_id, schema) in &self.sectors {
+            let mut sector_values = Vec::with_capacity(schema.fields.len());
+            let mut offsets = Vec::with_capacity(schema.fields.len() + 1);
+            sector_offsets.insert(sector_id, position);
+
+            for (index, field) in schema.fields.iter().enumerate() {
+                offsets.push(position);
+
+                let value = self
+                    .read_field(field, data, &mut position)
+                    .with_context(|| {
+                        format!("Failed to read sector {sector_id:?}, field {index}")
+                    })?;
+
+                if let (
+                    SchemaField::Dynamic {
+                        origin,
+                        sector,
+                        index: target_index,
+                        ..
+                    },
+                    SerialValue::Pointer(pointer),
+                ) = (field, &value)
+                {
+                    pointers.push(ReadPointer {
+                        sector: sector_id,
+                        field: index,
+                        origin,
+                        target: sector,
+                        index: *target_index,
+                        value: *pointer,
+                    });
+                }
+
+                sector_values.push(value);
+            }
+
+            offsets.push(position);
+            field_offsets.insert(sector_id, offsets);
+            values.insert(sector_id.clone(), sector_values);
+        }
+
+        if position != data.len() {
+            bail!(
+                "Data is longer than its schema: {} bytes > {position} bytes",
+                data.len()
+            );
+        }
+
+        for pointer in pointers {
+            let origin_offset = sector_offsets
+                .get(pointer.origin)
+                .with_context(|| format!("Sector does not exist: {:#?}", pointer.origin))?;
+            let field_offset = field_offsets
+                .get(pointer.target)
+                .with_context(|| format!("Sector does not exist: {:#?}", pointer.target))?
+                .get(pointer.index)
+                .with_context(|| {
+                    format!(
+                        "Can't index into sector; not enough fields. Sector: {:#?}, Index: {}",
+                        pointer.target, pointer.index
+                    )
+                })?;
+
+            if origin_offset + pointer.value != *field_offset {
+                bail!(
+                    "Pointer in sector {:?}, field {} doesn't point to its field:\n\
+                     Expected: {}, Found: {}",
+                    pointer.sector,
+                    pointer.field,
+                    field_offset.saturating_sub(*origin_offset),
+                    pointer.value
+                );
+            }
+        }
+
+        Ok(values)
+    }
+
+    fn read_field(
+        &self,
+        field: &SchemaField<S>,
+        data: &[u8],
+        position: &mut usize,
+    ) -> anyhow::Result<SerialValue> {
+        let value = match field {
+            SchemaField::U8 => SerialValue::U8(take(data, position, 1)?[0]),
+            SchemaField::U16(endianness) => {
+                let bytes = take(data, position, 2)?.try_into()?;
+
+                SerialValue::U16(match endianness.unwrap_or(self.endianness) {
+                    Endianness::Little => u16::from_le_bytes(bytes),
+                    Endianness::Big => u16::from_be_bytes(bytes),
+                })
+            }
+            SchemaField::U24(endianness) => {
+                SerialValue::U24(self.read_sized(take(data, position, 3)?, *endianness) as u32)
+            }
+            SchemaField::U32(endianness) => {
+                let bytes = take(data, position, 4)?.try_into()?;
+
+                SerialValue::U32(match endianness.unwrap_or(self.endianness) {
+                    Endianness::Little => u32::from_le_bytes(bytes),
+                    Endianness::Big => u32::from_be_bytes(bytes),
+                })
+            }
+            SchemaField::String => {
+                let rest = data.get(*position..).unwrap_or_default();
+                let len = rest
+                    .iter()
+                    .position(|&byte| byte == 0)
+                    .with_context(|| format!("String at {position} has no null terminator"))?;
+                let value = String::from_utf8(take(data, position, len)?.to_vec())?;
+
+                // Skips the terminator
+                *position += 1;
+
+                SerialValue::String(value)
+            }
+            SchemaField::Bytes(len) => SerialValue::Bytes(take(data, position, *len)?.to_vec()),
+            SchemaField::Dynamic {
+                bytes, endianness, ..
+            } => match bytes {
+                1..=4 => SerialValue::Pointer(
+                    self.read_sized(take(data, position, *bytes)?, *endianness) as usize,
+                ),
+                _ => bail!("Unsupported Pointer length: {bytes} bytes"),
+            },
+        };
+
+        Ok(value)
+    }
+
+    fn read_sized(&self, bytes: &[u8], endianness: Option<Endianness>) -> u64 {
+        match endianness.unwrap_or(self.endianness) {
+            Endianness::Little => read_be(bytes.iter().rev()),
+            Endianness::Big => read_be(bytes),
+        }
+    }
+}
+
+/// The next `len` bytes, moving past them
+fn take<'a>(data: &'a [u8], position: &mut usize, len: usize) -> anyhow::Result<&'a [u8]> {
+    let bytes = position
+        .checked_add(len)
+        .and_then(|end| data.get(*position..end))
+        .with_context(|| {
+            format!(
+                "Data ended early: {len} bytes at {position} > {} bytes",
+                data.len()
+            )
+        })?;
+
+    *position += len;
+    Ok(bytes)
+}
+
+fn read_be<'a>(bytes: impl IntoIterator<Item = &'a u8>) -> u64 {
+    bytes
+        .into_iter()
+        .fold(0, |value, &byte| value << 8 | u64::from(byte))
+}
+
+macro_rules! endian_schema_field {
+    ($name: ident, $variant: ident) => {
+        pub fn $name(self) -> Self {
+            self.field(SchemaField::$variant(None))
+        }
+
+        pub fn ${concat($name, _le)}(self) -> Self {
+            self.field(SchemaField::$variant(Some(Endianness::Little)))
+        }
+
+        pub fn ${concat($name, _be)}(self) -> Self {
+            self.field(SchemaField::$variant(Some(Endianness::Big)))
+        }
+    };
+}
+
+macro_rules! dynamic_schema_field {
+    ($name: ident, $bytes: literal) => {
+        pub fn ${concat(dynamic_, $name)}(self, origin: S, sector: S, index: usize) -> Self {
+            self.field(SchemaField::Dynamic {
+                origin,
+                sector,
+                index,
+                bytes: $bytes,
+                endianness: None,
+            })
+        }
+
+        pub fn ${concat(dynamic_, $name, _le)}(self, origin: S, sector: S, index: usize) -> Self {
+            self.field(SchemaField::Dynamic {
+                origin,
+                sector,
+                index,
+                bytes: $bytes,
+                endianness: Some(Endianness::Little),
+            })
+        }
+
+        pub fn ${concat(dynamic_, $name, _be)}(self, origin: S, sector: S, index: usize) -> Self {
+            self.field(SchemaField::Dynamic {
+                origin,
+                sector,
+                index,
+                bytes: $bytes,
+                endianness: Some(Endianness::Big),
+            })
+        }
+    };
+}
+
+impl<S> SerialSchemaBuilder<S> {
+    pub fn field(mut self, field: SchemaField<S>) -> Self {
+        self.fields.push(field);
+        self
+    }
+
+    pub fn u8(self) -> Self {
+        self.field(SchemaField::U8)
+    }
+
+    endian_schema_field!(u16, U16);
+
+    endian_schema_field!(u24, U24);
+
+    endian_schema_field!(u32, U32);
+
+    /// A null-terminated string
+    pub fn string(self) -> Self {
+        self.field(SchemaField::String)
+    }
+
+    pub fn bytes(self, len: usize) -> Self {
+        self.field(SchemaField::Bytes(len))
+    }
+
+    dynamic_schema_field!(u8, 1);
+    dynamic_schema_field!(u16, 2);
+    dynamic_schema_field!(u24, 3);
+    dynamic_schema_field!(u32, 4);
+}
@@ -0,0 +1,87 @@
+use std::hash::Hash;
+
+use indexmap::IndexMap;
+
+use crate::{builder::SerialSectorBuilder, tracker::SerialTracker};
+
+/// Where a sector landed in the output
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SectorLayout<S> {
+    pub key: S,
+    /// Offset from the start of the output
+    pub start: usize,
+    /// Size in bytes
+    pub size: usize,
+    pub field_count: usize,
+}
+
+/// What a sector is made of, such as to find the largest sectors
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SectorStats {
+    /// Size in bytes, without any padding before the sector
+    pub size: usize,
+    pub field_count: usize,
+    /// Pointers to fields, including signed and automatic-width ones
+    pub pointers: usize,
+    /// Fields read from external files
+    pub externals: usize,
+}
+
+/// The width chosen for an automatic-width pointer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PointerWidth<S> {
+    /// The sector the pointer is in
+    pub sector: S,
+    pub field: usize,
+    /// Size in bytes
+    pub bytes: usize,
+}
+
+/// The layout of a built output, with its sectors in output order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SerialReport<S> {
+    pub sectors: Vec<SectorLayout<S>>,
+    pub total_size: usize,
+    /// Every automatic-width pointer outside of compressed sectors, in output order
+    pub pointer_widths: Vec<PointerWidth<S>>,
+}
+
+impl<S: Hash + Eq> SerialReport<S> {
+    pub fn sector(&self, key: &S) -> Option<&SectorLayout<S>> {
+        self.sectors.iter().find(|sector| &sector.key == key)
+    }
+}
+
+impl<S: Hash + Eq + Clone + std::fmt::Debug> SerialReport<S> {
+    pub(crate) fn new(
+        sectors: &IndexMap<S, SerialSectorBuilder<S>>,
+        tracker: &SerialTracker<S>,
+    ) -> anyhow::Result<Self> {
+        let sectors = sectors
+            .iter()
+            .map(|(key, sector)| {
+                Ok(SectorLayout {
+                    key: key.clone(),
+                    start: tracker.offset_from_origin(key)?,
+                    size: tracker.sector_size(key)?,
+                    field_count: sector.fields.len(),
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        let pointer_widths = tracker
+            .pointer_widths()
+            .map(|((sector, field), bytes)| PointerWidth {
+                sector: sector.clone(),
+                field: *field,
+                bytes: *bytes,
+            })
+            .collect();
+
+        Ok(Self {
+            sectors,
+            total_size: tracker.total_size(),
+            pointer_widths,
+        })
+    }
+}
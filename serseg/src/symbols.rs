@@ -0,0 +1,51 @@
+/// One `name := offset` line per symbol, in fasmg syntax
+pub(crate) fn fasmg(symbols: &[(String, usize)]) -> String {
+    symbols
+        .iter()
+        .map(|(name, offset)| format!("{name} := 0x{offset:06X}\n"))
+        .collect()
+}
+
+/// One `#define` per symbol, wrapped in an include guard
+pub(crate) fn c_header(guard: &str, symbols: &[(String, usize)]) -> String {
+    let defines = symbols
+        .iter()
+        .map(|(name, offset)| format!("#define {name} 0x{offset:06X}\n"))
+        .collect::<String>();
+
+    format!("#ifndef {guard}\n#define {guard}\n\n{defines}\n#endif\n")
+}
+
+/// Uppercases the name and replaces anything that can't be in a C identifier with `_`.
+/// Names starting with a digit are prefixed with `_`.
+pub(crate) fn c_identifier(name: &str) -> String {
+    let identifier = name
+        .chars()
+        .map(|character| {
+            if character.is_ascii_alphanumeric() {
+                character.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+
+    if identifier.is_empty() || identifier.starts_with(|character: char| character.is_ascii_digit())
+    {
+        format!("_{identifier}")
+    } else {
+        identifier
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn c_identifiers() {
+        assert_eq!(c_identifier("font 0.header"), "FONT_0_HEADER");
+        assert_eq!(c_identifier("0glyph"), "_0GLYPH");
+        assert_eq!(c_identifier(""), "_");
+    }
+}
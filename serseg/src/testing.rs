@@ -0,0 +1,142 @@
+//! Helpers for testing what builders write
+
+/// Bytes shown on each line of a diff
+const BYTES_PER_LINE: usize = 16;
+/// Lines shown before and after the first mismatch
+const CONTEXT_LINES: usize = 2;
+
+/// Builds the builder and checks it wrote exactly the expected bytes.
+/// On a mismatch, panics with both outputs side by side around the first differing offset.
+///
+/// Must be used in an async context.
+#[macro_export]
+macro_rules! assert_builds_to {
+    ($builder: expr, $expected: expr $(,)?) => {
+        $crate::testing::assert_bytes_eq(
+            &$crate::prelude::SerialBuilder::build_to_vec(&$builder)
+                .await
+                .unwrap_or_else(|error| panic!("Failed to build: {error:#}")),
+            $expected,
+        )
+    };
+}
+
+/// Panics with a side-by-side diff if the bytes differ, like [`assert_builds_to!`]
+#[track_caller]
+pub fn assert_bytes_eq(built: &[u8], expected: impl AsRef<[u8]>) {
+    if let Some(diff) = diff(built, expected.as_ref()) {
+        panic!("{diff}");
+    }
+}
+
+/// A description of where the bytes first differ, or `None` if they're the same
+pub fn diff(built: &[u8], expected: &[u8]) -> Option<String> {
+    let mismatch = built
+        .iter()
+        .zip(expected)
+        .position(|(built, expected)| built != expected)
+        .or_else(|| (built.len() != expected.len()).then(|| built.len().min(expected.len())))?;
+
+    let mut message = format!(
+        "Built bytes differ from expected at offset {mismatch} ({mismatch:#08X})\n\
+         Built: {} bytes, Expected: {} bytes\n\n\
+         {:<6}  {:<width$}  Expected\n",
+        built.len(),
+        expected.len(),
+        "Offset",
+        "Built",
+        width = BYTES_PER_LINE * 3,
+    );
+
+    let lines = built.len().max(expected.len()).div_ceil(BYTES_PER_LINE);
+    let first = (mismatch / BYTES_PER_LINE).saturating_sub(CONTEXT_LINES);
+    let last = (mismatch / BYTES_PER_LINE + CONTEXT_LINES).min(lines - 1);
+
+    for line in first..=last {
+        let start = line * BYTES_PER_LINE;
+
+        let row = format!(
+            "{start:06X}  {}  {}",
+            diff_line(built, expected, start),
+            diff_line(expected, built, start)
+        );
+
+        message.push_str(row.trim_end());
+        message.push('\n');
+    }
+
+    Some(message)
+}
+
+/// A line of bytes, with each one that differs from the other bytes marked by a `*`.
+/// Bytes past the end are `--`.
+fn diff_line(bytes: &[u8], other: &[u8], start: usize) -> String {
+    (start..start + BYTES_PER_LINE)
+        .map(|offset| match bytes.get(offset) {
+            Some(byte) if other.get(offset) == Some(byte) => format!("{byte:02X} "),
+            Some(byte) => format!("{byte:02X}*"),
+            None => "-- ".to_owned(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn same() {
+        assert_eq!(diff(b"abc", b"abc"), None);
+        assert_bytes_eq(b"abc", b"abc");
+    }
+
+    #[test]
+    fn mismatch() {
+        let expected = (0..40).collect::<Vec<u8>>();
+        let mut built = expected.clone();
+        built[33] = 0xFF;
+
+        assert_eq!(
+            diff(&built, &expected).unwrap(),
+            "Built bytes differ from expected at offset 33 (0x000021)\n\
+             Built: 40 bytes, Expected: 40 bytes\n\n\
+             Offset  Built                                             Expected\n\
+             000000  00 01 02 03 04 05 06 07 08 09 0A 0B 0C 0D 0E 0F   \
+             00 01 02 03 04 05 06 07 08 09 0A 0B 0C 0D 0E 0F\n\
+             000010  10 11 12 13 14 15 16 17 18 19 1A 1B 1C 1D 1E 1F   \
+             10 11 12 13 14 15 16 17 18 19 1A 1B 1C 1D 1E 1F\n\
+             000020  20 FF*22 23 24 25 26 27 -- -- -- -- -- -- -- --   \
+             20 21*22 23 24 25 26 27 -- -- -- -- -- -- -- --\n"
+        );
+    }
+
+    #[test]
+    fn shorter() {
+        let diff = diff(b"ab", b"abc").unwrap();
+
+        assert!(diff.starts_with(
+            "Built bytes differ from expected at offset 2 (0x000002)\n\
+             Built: 2 bytes, Expected: 3 bytes\n"
+        ));
+        assert!(diff.contains("000000  61 62 -- "));
+        assert!(diff.contains("  61 62 63*-- "));
+    }
+
+    #[tokio::test]
+    async fn builds_to() {
+        let builder = || {
+            SerialBuilder::default()
+                .sector("first", SerialSectorBuilder::default().u8(1).u16(0x0302u16))
+        };
+
+        assert_builds_to!(builder(), [1, 2, 3]);
+        assert_builds_to!(builder(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Built bytes differ from expected at offset 0")]
+    fn panics() {
+        assert_bytes_eq(&[1], [2]);
+    }
+}
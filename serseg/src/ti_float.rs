@@ -0,0 +1,115 @@
+use anyhow::{Context, bail};
+
+/// The number of BCD digits in the mantissa
+const DIGITS: usize = 14;
+
+/// The smallest and largest power of ten a TI float's exponent can be
+const EXPONENT_RANGE: std::ops::RangeInclusive<i32> = -99..=99;
+
+/// The exponent byte's bias
+const EXPONENT_BIAS: i32 = 0x80;
+
+/// Set in the first byte of negative floats
+const NEGATIVE_FLAG: u8 = 0x80;
+
+/// Converts the value to a TI-OS real: a flag byte, a biased power of ten exponent,
+/// then 14 mantissa digits as BCD. Rounds to 14 significant digits.
+pub(crate) fn to_ti_float(value: f64) -> anyhow::Result<[u8; 9]> {
+    if !value.is_finite() {
+        bail!("TI float must be finite: {value}");
+    }
+
+    let mut bytes = [0; 9];
+    bytes[1] = EXPONENT_BIAS as u8;
+
+    if value == 0.0 {
+        return Ok(bytes);
+    }
+
+    // Formatting rounds the mantissa, carrying into the exponent when needed
+    let formatted = format!("{:.*e}", DIGITS - 1, value.abs());
+    let (mantissa, exponent) = formatted
+        .split_once('e')
+        .context("Formatted float is missing its exponent")?;
+    let exponent = exponent.parse::<i32>()?;
+
+    if !EXPONENT_RANGE.contains(&exponent) {
+        bail!("TI float is out of range: {value} isn't within 1e-99 to 1e100 in magnitude");
+    }
+
+    let digits = mantissa
+        .bytes()
+        .filter(u8::is_ascii_digit)
+        .map(|digit| digit - b'0')
+        .collect::<Vec<_>>();
+
+    if value.is_sign_negative() {
+        bytes[0] = NEGATIVE_FLAG;
+    }
+
+    bytes[1] = (EXPONENT_BIAS + exponent) as u8;
+
+    for (byte, pair) in bytes[2..].iter_mut().zip(digits.chunks(2)) {
+        *byte = pair[0] << 4 | pair[1];
+    }
+
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ti_float_one() {
+        assert_eq!(
+            to_ti_float(1.0).unwrap(),
+            [0x00, 0x80, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]
+        );
+    }
+
+    #[test]
+    fn ti_float_negative_half() {
+        assert_eq!(
+            to_ti_float(-0.5).unwrap(),
+            [0x80, 0x7F, 0x50, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]
+        );
+    }
+
+    // Not meant to be exactly pi
+    #[test]
+    #[expect(clippy::approx_constant)]
+    fn ti_float_pi() {
+        assert_eq!(
+            to_ti_float(3.14159).unwrap(),
+            [0x00, 0x80, 0x31, 0x41, 0x59, 0x00, 0x00, 0x00, 0x00]
+        );
+    }
+
+    #[test]
+    fn ti_float_zero() {
+        assert_eq!(
+            to_ti_float(0.0).unwrap(),
+            [0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]
+        );
+    }
+
+    // Rounding to 14 digits carries into the exponent
+    #[test]
+    fn ti_float_rounding() {
+        assert_eq!(
+            to_ti_float(9.999_999_999_999_999).unwrap(),
+            [0x00, 0x81, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]
+        );
+    }
+
+    #[test]
+    fn ti_float_range() {
+        assert_eq!(to_ti_float(1e99).unwrap()[1], 0x80 + 99);
+        assert_eq!(to_ti_float(1e-99).unwrap()[1], 0x80 - 99);
+        assert!(to_ti_float(1e100).is_err());
+        assert!(to_ti_float(1e-100).is_err());
+        assert!(to_ti_float(f64::NAN).is_err());
+        assert!(to_ti_float(f64::INFINITY).is_err());
+    }
+}
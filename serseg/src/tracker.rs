@@ -1,24 +1,50 @@
-use std::{collections::HashMap, hash::Hash};
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+    path::{Path, PathBuf},
+};
 
-use anyhow::{Context, bail};
+use anyhow::{Context, anyhow, bail};
 use indexmap::IndexMap;
 use log::debug;
 
-use crate::prelude::*;
+use crate::{backend::Files, issue::field_name, prelude::*};
+
+/// A padded pointer's origin, index, offset, and scale
+type PaddedPointer<'a, S> = (&'a S, usize, isize, usize);
+
+/// The widest an automatic-width pointer can be, which every one starts at
+const MAX_AUTO_POINTER_WIDTH: usize = 3;
 
 #[derive(Debug, Clone)]
 pub struct SerialTracker<S: Hash + Eq> {
     sector_offsets: HashMap<S, usize>,
+    sector_sizes: HashMap<S, usize>,
+    /// Where each field of a sector starts, followed by where the sector ends
+    field_offsets: HashMap<S, Vec<usize>>,
+    /// Written before the sector, so it and padded pointers into it are aligned
+    sector_padding: HashMap<S, usize>,
+    external_sizes: HashMap<PathBuf, usize>,
+    /// Stands in for the fields of compressed sectors, and of sectors with automatic-width
+    /// pointers once their widths are chosen
+    replaced: HashMap<S, Vec<SerialField<S>>>,
+    /// The width of every automatic-width pointer, by its sector and field
+    pointer_widths: IndexMap<(S, usize), usize>,
+    /// Where the last written byte ends
+    end: usize,
+    /// Made once tracking is done, if any field is deferred, for them all to share
+    report: Option<SerialReport<S>>,
 }
 
 impl<S: Hash + Eq + Clone + std::fmt::Debug> SerialTracker<S> {
+    /// The offset of a field from the start of `from_sector`.
+    /// An index equal to the number of fields is the end of the sector.
     pub fn offset_field_from_sector(
         &self,
         from_sector: &S,
         to_sector: &S,
         to_index: usize,
         sectors: &IndexMap<S, SerialSectorBuilder<S>>,
-        tracker: &SerialTracker<S>,
     ) -> anyhow::Result<usize> {
         let from_offset = self
             .sector_offsets
@@ -30,16 +56,35 @@ impl<S: Hash + Eq + Clone + std::fmt::Debug> SerialTracker<S> {
             .get(to_sector)
             .cloned()
             .with_context(|| format!("Sector does not exist: {to_sector:#?}"))?;
-        let mut offset = to_offset.checked_sub(from_offset).with_context(|| {
-            format!("From sector was ahead of to sector: {from_offset} > {to_offset}")
-        })?;
+        if from_offset > to_offset {
+            bail!("From sector was ahead of to sector: {from_offset} > {to_offset}");
+        }
+
+        Ok(self.field_position(to_sector, to_index, sectors)? - from_offset)
+    }
 
-        let fields = &sectors
+    /// The offset of a field from the start of the output.
+    /// An index equal to the number of fields is the end of the sector.
+    pub fn field_position(
+        &self,
+        to_sector: &S,
+        to_index: usize,
+        sectors: &IndexMap<S, SerialSectorBuilder<S>>,
+    ) -> anyhow::Result<usize> {
+        let sector = sectors
             .get(to_sector)
-            .with_context(|| format!("Sector does not exist: {to_sector:#?}"))?
-            .fields;
+            .with_context(|| format!("Sector does not exist: {to_sector:#?}"))?;
 
-        if fields.len() <= to_index && to_index != 0 {
+        if sector.compression.is_some() && to_index != 0 {
+            bail!(
+                "Can't point into compressed sector {to_sector:?}; \
+                 its fields are only where they were laid out once it's decompressed"
+            );
+        }
+
+        let fields = self.fields(to_sector, sector);
+
+        if to_index > fields.len() {
             bail!(
                 "Can't index into sector; not enough fields. Sector: {:#?}, Length: {}, Index: {}",
                 to_sector,
@@ -48,42 +93,565 @@ impl<S: Hash + Eq + Clone + std::fmt::Debug> SerialTracker<S> {
             );
         }
 
-        // Adds the sizes of all fields up to the index
-        for (field, _) in fields.iter().zip(0..to_index) {
-            offset += field.calculate_size(offset, tracker)?;
+        self.field_offsets
+            .get(to_sector)
+            .and_then(|offsets| offsets.get(to_index))
+            .cloned()
+            .with_context(|| format!("Field offsets weren't tracked: {to_sector:#?}"))
+    }
+
+    /// Caches all sector starting and ending offsets, and the sizes of external files without
+    /// declared sizes
+    ///
+    /// Fails if the output would be over `max_size` bytes.
+    ///
+    /// Compressed sectors are built and compressed first, as their size isn't known until then.
+    /// Automatic-width pointers start three bytes wide and are shrunk to fit their values, tracking
+    /// again until none of them shrink.
+    pub async fn new<F: Files>(
+        sectors: &IndexMap<S, SerialSectorBuilder<S>>,
+        max_size: Option<usize>,
+        endianness: Endianness,
+    ) -> anyhow::Result<Self> {
+        let (tracker, issues) = Self::new_lenient::<F>(sectors, max_size, endianness).await;
+
+        match first_error(issues) {
+            Some(error) => Err(error),
+            None => Ok(tracker),
+        }
+    }
+
+    /// Tracks like [`SerialTracker::new`], but continues past problems, collecting them all.
+    /// Fields with problems are treated as empty.
+    pub async fn new_lenient<F: Files>(
+        sectors: &IndexMap<S, SerialSectorBuilder<S>>,
+        max_size: Option<usize>,
+        endianness: Endianness,
+    ) -> (Self, Vec<SersegIssue<S>>) {
+        let mut issues = Vec::new();
+        let mut replaced = HashMap::new();
+
+        for (sector_id, sector) in sectors {
+            let Some(compression) = sector.compression else {
+                continue;
+            };
+
+            let data = match SerialBuilder::compress_sector::<F>(
+                sector_id,
+                sector,
+                compression,
+                endianness,
+            )
+            .await
+            {
+                Ok(data) => data,
+                Err(error) => {
+                    issues.push(SersegIssue {
+                        sector: sector_id.clone(),
+                        field: None,
+                        label: None,
+                        error: error.context(format!("Failed to compress sector {sector_id:?}")),
+                    });
+                    Vec::new()
+                }
+            };
+
+            replaced.insert(
+                sector_id.clone(),
+                vec![SerialField::SharedBytes(data.into())],
+            );
+        }
+
+        let (tracker, mut track_issues) = Self::track_auto::<F>(sectors, max_size, replaced).await;
+        issues.append(&mut track_issues);
+
+        (tracker, issues)
+    }
+
+    /// Tracks the sectors, choosing the width of every automatic-width pointer.
+    ///
+    /// Widths only ever shrink, so this stops after at most two passes per pointer, plus one.
+    /// A pointer whose value grows past its width once others shrink is reported when building.
+    pub(crate) async fn track_auto<F: Files>(
+        sectors: &IndexMap<S, SerialSectorBuilder<S>>,
+        max_size: Option<usize>,
+        replaced: HashMap<S, Vec<SerialField<S>>>,
+    ) -> (Self, Vec<SersegIssue<S>>) {
+        // Compressed sectors choose their own widths
+        let mut widths = IndexMap::new();
+
+        for (sector_id, sector) in sectors {
+            if sector.compression.is_some() {
+                continue;
+            }
+
+            for (index, field) in sector.fields.iter().enumerate() {
+                if let SerialField::DynamicAuto { .. } = field {
+                    widths.insert((sector_id.clone(), index), MAX_AUTO_POINTER_WIDTH);
+                }
+            }
         }
 
-        Ok(offset)
+        let has_deferred = sectors
+            .values()
+            .filter(|sector| sector.compression.is_none())
+            .flat_map(|sector| &sector.fields)
+            .any(|field| matches!(field, SerialField::Deferred { .. }));
+
+        let auto_sectors = widths
+            .keys()
+            .map(|(sector_id, _)| sector_id.clone())
+            .collect::<HashSet<_>>();
+
+        loop {
+            let mut current = replaced.clone();
+
+            for (sector_id, sector) in sectors
+                .iter()
+                .filter(|(sector_id, _)| auto_sectors.contains(sector_id))
+            {
+                let fields = sector
+                    .fields
+                    .iter()
+                    .enumerate()
+                    .map(
+                        |(index, field)| match widths.get(&(sector_id.clone(), index)) {
+                            Some(&bytes) => field.with_pointer_width(bytes),
+                            None => field.clone(),
+                        },
+                    )
+                    .collect();
+
+                current.insert(sector_id.clone(), fields);
+            }
+
+            let (mut tracker, issues) = Self::track::<F>(sectors, max_size, current).await;
+            let mut shrunk = false;
+
+            for ((sector_id, index), width) in &mut widths {
+                let Some(SerialField::DynamicAuto {
+                    origin,
+                    sector,
+                    index: to_index,
+                }) = sectors.get(sector_id).and_then(|s| s.fields.get(*index))
+                else {
+                    continue;
+                };
+
+                // Problems with the pointer are reported when building
+                let Ok(pointer) =
+                    tracker.offset_field_from_sector(origin, sector, *to_index, sectors)
+                else {
+                    continue;
+                };
+
+                let needed = match pointer {
+                    0..=0xFF => 1,
+                    0x100..=0xFFFF => 2,
+                    _ => MAX_AUTO_POINTER_WIDTH,
+                };
+
+                if needed < *width {
+                    *width = needed;
+                    shrunk = true;
+                }
+            }
+
+            if !shrunk {
+                debug!("Chose {} automatic pointer widths", widths.len());
+
+                tracker.pointer_widths = widths;
+
+                // Problems with the layout were already found when tracking
+                if has_deferred {
+                    tracker.report = SerialReport::new(sectors, &tracker).ok();
+                }
+
+                return (tracker, issues);
+            }
+        }
     }
 
-    /// Caches all sector starting and ending offsets
-    pub async fn new(sectors: &IndexMap<S, SerialSectorBuilder<S>>) -> anyhow::Result<Self> {
+    /// Tracks the sectors, with compressed sectors already compressed and automatic pointer
+    /// widths already chosen
+    pub(crate) async fn track<F: Files>(
+        sectors: &IndexMap<S, SerialSectorBuilder<S>>,
+        max_size: Option<usize>,
+        replaced: HashMap<S, Vec<SerialField<S>>>,
+    ) -> (Self, Vec<SersegIssue<S>>) {
+        let mut issues = Vec::new();
         let mut tracker = Self {
             sector_offsets: HashMap::with_capacity(sectors.len()),
+            sector_sizes: HashMap::with_capacity(sectors.len()),
+            field_offsets: HashMap::with_capacity(sectors.len()),
+            sector_padding: HashMap::new(),
+            external_sizes: HashMap::new(),
+            replaced,
+            pointer_widths: IndexMap::new(),
+            end: 0,
+            report: None,
         };
 
+        // Compressed sectors track their own external files
+        for (sector_id, sector) in sectors {
+            if sector.compression.is_some() {
+                continue;
+            }
+
+            for (index, field) in sector.fields.iter().enumerate() {
+                if let SerialField::External {
+                    path, size: None, ..
+                } = field
+                    && !tracker.external_sizes.contains_key(path)
+                {
+                    let size = match F::len(path).await {
+                        Ok(len) => len as usize,
+                        Err(error) => {
+                            issues.push(SersegIssue {
+                                sector: sector_id.clone(),
+                                field: Some(index),
+                                label: sector.field_label(index).map(str::to_owned),
+                                error: anyhow::Error::new(error).context(format!(
+                                    "Failed to read size of external file: {path:?}"
+                                )),
+                            });
+                            0
+                        }
+                    };
+
+                    tracker.external_sizes.insert(path.clone(), size);
+                }
+            }
+        }
+
+        let mut padded_pointers = HashMap::<_, Vec<PaddedPointer<S>>>::new();
+
+        for field in sectors
+            .values()
+            .filter(|sector| sector.compression.is_none())
+            .flat_map(|sector| &sector.fields)
+        {
+            if let SerialField::Dynamic {
+                origin,
+                sector,
+                index,
+                offset,
+                scale,
+                padded: true,
+                ..
+            } = field
+            {
+                padded_pointers
+                    .entry(sector)
+                    .or_default()
+                    .push((origin, *index, *offset, *scale));
+            }
+        }
+
         let mut offset = 0;
+        // Where the last written byte ends, as trailing fills and padding aren't written
+        let mut written_end = 0;
+        // The first sector to end past the maximum size
+        let mut oversized = None;
 
         for (sector_id, sector) in sectors {
+            let pointers = padded_pointers
+                .get(sector_id)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+
+            let alignment = if sector.alignment == 0 {
+                issues.push(SersegIssue {
+                    sector: sector_id.clone(),
+                    field: None,
+                    label: None,
+                    error: anyhow!("Sector alignment can't be zero"),
+                });
+                1
+            } else {
+                sector.alignment
+            };
+            let mut padding = (alignment - offset % alignment) % alignment;
+
+            let pointer_padding = offset
+                .checked_add(padding)
+                .with_context(|| overflow(offset, padding))
+                .and_then(|start| tracker.padding(sectors, sector_id, start, alignment, pointers));
+
+            match pointer_padding {
+                Ok(pointer_padding) => padding += pointer_padding,
+                Err(error) => issues.push(SersegIssue {
+                    sector: sector_id.clone(),
+                    field: None,
+                    label: None,
+                    error,
+                }),
+            }
+
+            if padding != 0 {
+                match offset.checked_add(padding) {
+                    Some(start) => offset = start,
+                    None => issues.push(SersegIssue {
+                        sector: sector_id.clone(),
+                        field: None,
+                        label: None,
+                        error: anyhow!(overflow(offset, padding)),
+                    }),
+                }
+
+                tracker.sector_padding.insert(sector_id.clone(), padding);
+            }
+
             let start = offset;
+            let fields = tracker.fields(sector_id, sector);
+            let mut field_offsets = Vec::with_capacity(fields.len() + 1);
+
+            for (index, field) in fields.iter().enumerate() {
+                field_offsets.push(offset);
+
+                let end = field.calculate_size(offset, &tracker).and_then(|size| {
+                    offset
+                        .checked_add(size)
+                        .with_context(|| overflow(offset, size))
+                });
 
-            for field in &sector.fields {
-                offset += field.calculate_size(offset, &tracker)?;
+                match end {
+                    Ok(end) => {
+                        if end != offset && !matches!(field, SerialField::Fill { .. }) {
+                            written_end = end;
+                        }
+
+                        offset = end;
+                    }
+                    Err(error) => issues.push(SersegIssue {
+                        sector: sector_id.clone(),
+                        field: Some(index),
+                        label: sector.field_label(index).map(str::to_owned),
+                        error,
+                    }),
+                }
+            }
+
+            field_offsets.push(offset);
+            tracker
+                .field_offsets
+                .insert(sector_id.clone(), field_offsets);
+
+            let end = sector
+                .fixed_padding(sector_id, offset - start)
+                .and_then(|padding| {
+                    offset
+                        .checked_add(padding)
+                        .with_context(|| overflow(offset, padding))
+                });
+
+            match end {
+                Ok(end) => {
+                    // A fixed size sector is written in full, along with any fill before it
+                    if sector.fixed_size.is_some() {
+                        written_end = end;
+                    }
+
+                    offset = end;
+                }
+                Err(error) => issues.push(SersegIssue {
+                    sector: sector_id.clone(),
+                    field: None,
+                    label: None,
+                    error,
+                }),
             }
 
             let old_value = tracker.sector_offsets.insert(sector_id.clone(), start);
+            tracker
+                .sector_sizes
+                .insert(sector_id.clone(), offset - start);
+
+            // Trailing fills aren't written, so only the written bytes count
+            if oversized.is_none() && max_size.is_some_and(|max_size| written_end > max_size) {
+                oversized = Some(sector_id);
+            }
 
             if let Some(start) = old_value {
-                bail!(
-                    "Sector offsets was already populated; key: {:#?}, start: {start}",
-                    sector_id
-                );
+                issues.push(SersegIssue {
+                    sector: sector_id.clone(),
+                    field: None,
+                    label: None,
+                    error: anyhow!(
+                        "Sector offsets was already populated; key: {:#?}, start: {start}",
+                        sector_id
+                    ),
+                });
             }
         }
 
+        tracker.end = written_end;
+
+        if let (Some(sector_id), Some(max_size)) = (oversized, max_size) {
+            issues.push(SersegIssue {
+                sector: sector_id.clone(),
+                field: None,
+                label: None,
+                error: anyhow!(
+                    "Output is over the maximum size: {written_end} bytes > {max_size} bytes\n\
+                     Sector {sector_id:?} is the first past the limit"
+                ),
+            });
+        }
+
         debug!("Tracked all sectors");
 
-        Ok(tracker)
+        (tracker, issues)
+    }
+
+    /// The least padding before the sector that aligns every padded pointer into it, in
+    /// multiples of `alignment` so the sector stays aligned.
+    /// Their origins must be earlier sectors or the sector itself, as later ones aren't tracked yet.
+    fn padding(
+        &self,
+        sectors: &IndexMap<S, SerialSectorBuilder<S>>,
+        sector_id: &S,
+        start: usize,
+        alignment: usize,
+        pointers: &[PaddedPointer<S>],
+    ) -> anyhow::Result<usize> {
+        if pointers.is_empty() {
+            return Ok(0);
+        }
+
+        let sector = sectors
+            .get(sector_id)
+            .with_context(|| format!("Sector does not exist: {sector_id:#?}"))?;
+        let fields = self.fields(sector_id, sector);
+
+        // Every combination of remainders repeats after the least common multiple
+        let mut limit = 1_usize;
+
+        for &(_, _, _, scale) in pointers {
+            if scale == 0 {
+                bail!("Padded pointer into {sector_id:?} can't have a scale of zero");
+            }
+
+            limit = (limit / gcd(limit, scale))
+                .checked_mul(scale)
+                .with_context(|| {
+                    format!("Padded pointer scales into {sector_id:?} are too large")
+                })?;
+        }
+
+        // Only fields up to the furthest index affect alignment
+        let furthest = pointers
+            .iter()
+            .map(|&(_, index, _, _)| index)
+            .max()
+            .unwrap_or_default();
+
+        for padding in (0..limit).map(|step| step * alignment) {
+            let sector_start = start
+                .checked_add(padding)
+                .with_context(|| overflow(start, padding))?;
+            let mut aligned = true;
+
+            // Where each field starts with this padding
+            let mut positions = Vec::with_capacity(furthest + 1);
+            let mut position = sector_start;
+
+            for (index, field) in fields.iter().take(furthest).enumerate() {
+                positions.push(position);
+
+                let size = field.calculate_size(position, self)?;
+                position = position.checked_add(size).with_context(|| {
+                    format!(
+                        "Failed to pad field {}: {}",
+                        field_name(index, sector.field_label(index)),
+                        overflow(position, size)
+                    )
+                })?;
+            }
+
+            positions.push(position);
+
+            for &(origin, index, offset, scale) in pointers {
+                let origin_offset = if origin == sector_id {
+                    sector_start
+                } else {
+                    self.offset_from_origin(origin).with_context(|| {
+                        format!(
+                            "Padded pointer from {origin:?} to {sector_id:?} must come from an \
+                             earlier sector or the same one"
+                        )
+                    })?
+                };
+
+                let position = positions[index.min(positions.len() - 1)];
+
+                // Negative pointers are reported when building
+                aligned &= (position - origin_offset)
+                    .checked_add_signed(offset)
+                    .is_none_or(|pointer| pointer.is_multiple_of(scale));
+            }
+
+            if aligned {
+                return Ok(padding);
+            }
+        }
+
+        bail!("Can't pad {sector_id:?} so every padded pointer into it is aligned to its scale")
+    }
+
+    /// The fields written for the sector, which is a single field of compressed data if it's
+    /// compressed, with the widths of any automatic-width pointers chosen
+    pub fn fields<'a>(
+        &'a self,
+        sector_id: &S,
+        sector: &'a SerialSectorBuilder<S>,
+    ) -> &'a [SerialField<S>] {
+        match self.replaced.get(sector_id) {
+            Some(fields) => fields,
+            None => &sector.fields,
+        }
+    }
+
+    /// The width chosen for every automatic-width pointer, by its sector and field
+    pub fn pointer_widths(&self) -> impl Iterator<Item = (&(S, usize), &usize)> {
+        self.pointer_widths.iter()
+    }
+
+    /// Padding written before the sector
+    pub fn sector_padding(&self, sector: &S) -> usize {
+        self.sector_padding.get(sector).copied().unwrap_or_default()
+    }
+
+    pub fn external_size(&self, path: &Path) -> anyhow::Result<usize> {
+        self.external_sizes
+            .get(path)
+            .with_context(|| format!("External file size wasn't read: {path:?}"))
+            .cloned()
+    }
+
+    pub fn sector_size(&self, sector: &S) -> anyhow::Result<usize> {
+        self.sector_sizes
+            .get(sector)
+            .with_context(|| format!("Sector does not exist: {sector:#?}"))
+            .cloned()
+    }
+
+    /// The offset just past the last byte of the sector
+    pub fn sector_end(&self, sector: &S) -> anyhow::Result<usize> {
+        Ok(self.offset_from_origin(sector)? + self.sector_size(sector)?)
+    }
+
+    /// The layout given to deferred fields
+    pub(crate) fn report(&self) -> anyhow::Result<&SerialReport<S>> {
+        self.report
+            .as_ref()
+            .context("The layout wasn't reported for deferred fields")
+    }
+
+    /// The size of the written output, including padding between sectors.
+    /// Fills and padding at the end aren't written, so they aren't counted.
+    pub fn total_size(&self) -> usize {
+        self.end
     }
 
     pub fn offset_from_origin(&self, origin_sector: &S) -> anyhow::Result<usize> {
@@ -95,3 +663,29 @@ impl<S: Hash + Eq + Clone + std::fmt::Debug> SerialTracker<S> {
             .cloned()
     }
 }
+
+/// The first issue as an error, noting the field it's in
+pub(crate) fn first_error<S: std::fmt::Debug>(
+    issues: Vec<SersegIssue<S>>,
+) -> Option<anyhow::Error> {
+    issues.into_iter().next().map(|issue| match issue {
+        SersegIssue {
+            sector,
+            field: Some(field),
+            label,
+            error,
+        } => error.context(format!(
+            "Failed to track sector {sector:?}, field {}",
+            field_name(field, label.as_deref())
+        )),
+        SersegIssue { error, .. } => error,
+    })
+}
+
+fn overflow(offset: usize, size: usize) -> String {
+    format!("Output is too large; offset {offset} + {size} bytes overflows")
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
@@ -0,0 +1,115 @@
+use std::num::NonZeroUsize;
+
+use anyhow::anyhow;
+use log::info;
+use serde::Serialize;
+
+use crate::{
+    diagnostics::Diagnostics,
+    error::{ErrorKind, ResultExt},
+};
+
+/// How full a budget can be, in percent, before warning about it
+pub const DEFAULT_WARN_PERCENT: u8 = 90;
+
+/// How much of its byte budget an asset uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct BudgetUsage {
+    /// The most bytes the asset may use
+    pub budget: usize,
+    pub used: usize,
+    /// Rounded down
+    pub percent: usize,
+}
+
+impl BudgetUsage {
+    /// Logs the usage, warning when it reaches `warn_percent` and failing when over budget
+    pub fn check(
+        name: &str,
+        used: usize,
+        budget: NonZeroUsize,
+        warn_percent: u8,
+        diagnostics: &mut Diagnostics,
+    ) -> anyhow::Result<Self> {
+        let budget = budget.get();
+        let percent = used * 100 / budget;
+
+        if used > budget {
+            return Err(anyhow!(
+                "{name} is over budget: {used} bytes > {budget} bytes ({percent}%)"
+            ))
+            .with_kind(ErrorKind::SizeLimit);
+        }
+
+        info!("{name} uses {used} of {budget} budgeted bytes ({percent}%)");
+
+        if percent >= warn_percent.into() {
+            diagnostics.warn(format!(
+                "{name} is close to its budget: {used} bytes is {percent}% of {budget} bytes"
+            ));
+        }
+
+        Ok(Self {
+            budget,
+            used,
+            percent,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(used: usize, diagnostics: &mut Diagnostics) -> anyhow::Result<BudgetUsage> {
+        let budget = NonZeroUsize::new(1000).unwrap();
+        BudgetUsage::check("Asset", used, budget, DEFAULT_WARN_PERCENT, diagnostics)
+    }
+
+    #[test]
+    fn under_budget() {
+        let mut diagnostics = Diagnostics::default();
+
+        assert_eq!(
+            check(250, &mut diagnostics).unwrap(),
+            BudgetUsage {
+                budget: 1000,
+                used: 250,
+                percent: 25,
+            }
+        );
+        assert!(diagnostics.warnings().is_empty());
+    }
+
+    #[test]
+    fn near_budget() {
+        let mut diagnostics = Diagnostics::default();
+
+        assert_eq!(check(1000, &mut diagnostics).unwrap().percent, 100);
+        assert_eq!(
+            diagnostics.warnings(),
+            ["Asset is close to its budget: 1000 bytes is 100% of 1000 bytes"]
+        );
+    }
+
+    // Near the budget only fails with `--strict`
+    #[test]
+    fn near_budget_strict() {
+        let mut diagnostics = Diagnostics::new(true);
+
+        assert!(check(900, &mut diagnostics).is_ok());
+        assert!(diagnostics.check_strict().is_err());
+    }
+
+    #[test]
+    fn over_budget() {
+        let mut diagnostics = Diagnostics::default();
+        let error = check(1001, &mut diagnostics).unwrap_err();
+
+        assert_eq!(ErrorKind::of(&error), ErrorKind::SizeLimit);
+        assert_eq!(
+            error.to_string(),
+            "Asset is over budget: 1001 bytes > 1000 bytes (100%)"
+        );
+    }
+}
@@ -0,0 +1,200 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    definition::is_stdin,
+    diagnostics::Diagnostics,
+    error::{ErrorKind, ResultExt},
+    inputs::Inputs,
+    path::PathBufExt,
+};
+
+/// The content hashes of a successful build, used to skip rebuilding when nothing changed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cache {
+    /// Builds from other versions may have different output
+    version: String,
+    /// The options that change the output
+    options: String,
+    inputs: BTreeMap<PathBuf, String>,
+    outputs: BTreeMap<PathBuf, String>,
+    /// Emitted again when the build is skipped so `--strict` still sees them
+    warnings: Vec<String>,
+}
+
+/// The lowercase hex SHA-256 of the data
+fn hash(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .fold(String::new(), |mut output, byte| {
+            let _ = write!(output, "{byte:02x}");
+            output
+        })
+}
+
+pub async fn hash_file(path: &Path) -> anyhow::Result<String> {
+    let data = tokio::fs::read(path)
+        .await
+        .with_context(|| format!("Failed to read file to hash it: {path:?}"))?;
+
+    Ok(hash(&data))
+}
+
+async fn hash_files<'a>(
+    paths: impl IntoIterator<Item = &'a Path>,
+) -> anyhow::Result<BTreeMap<PathBuf, String>> {
+    let mut hashes = BTreeMap::new();
+
+    for path in paths {
+        hashes.insert(path.to_path_buf(), hash_file(path).await?);
+    }
+
+    Ok(hashes)
+}
+
+/// Whether every file still has the hash it was built with
+async fn is_unchanged(hashes: &BTreeMap<PathBuf, String>) -> bool {
+    for (path, expected) in hashes {
+        match hash_file(path).await {
+            Ok(hash) if &hash == expected => (),
+            Ok(_) => {
+                debug!("Changed since the last build: {path:?}");
+                return false;
+            }
+            Err(error) => {
+                debug!("{error:#}");
+                return false;
+            }
+        }
+    }
+
+    true
+}
+
+/// Where the cache is kept when `--cache` isn't given
+fn default_path(output: &Path) -> PathBuf {
+    output.to_path_buf().append_str(".cache")
+}
+
+/// Where to keep the build's cache, if it can be cached.
+/// Definitions from stdin aren't files, so changes to them can't be detected.
+pub fn path(definition: &Path, cache: Option<&Path>, output: &Path) -> Option<PathBuf> {
+    if is_stdin(definition) {
+        return None;
+    }
+
+    Some(cache.map_or_else(|| default_path(output), Path::to_path_buf))
+}
+
+impl Cache {
+    pub async fn new(
+        options: String,
+        inputs: &Inputs,
+        outputs: &[PathBuf],
+        diagnostics: &Diagnostics,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            options,
+            inputs: hash_files(inputs.iter()).await?,
+            outputs: hash_files(outputs.iter().map(PathBuf::as_path)).await?,
+            warnings: diagnostics.warnings().to_vec(),
+        })
+    }
+
+    /// Reads the cache, treating a missing or unreadable cache as a miss
+    async fn load(path: &Path) -> Option<Self> {
+        let raw = tokio::fs::read_to_string(path).await.ok()?;
+
+        match serde_json::from_str(&raw) {
+            Ok(cache) => Some(cache),
+            Err(error) => {
+                debug!("Ignoring invalid cache at {path:?}: {error}");
+                None
+            }
+        }
+    }
+
+    pub async fn write(&self, path: &Path) -> anyhow::Result<()> {
+        let json = serde_json::to_string_pretty(self).context("Failed to serialize cache")?;
+
+        tokio::fs::write(path, json + "\n")
+            .await
+            .with_context(|| format!("Failed to write cache: {path:?}"))
+            .with_kind(ErrorKind::Io)
+    }
+}
+
+/// The outputs of the last build, if it had the same options, read the same inputs, and its
+/// outputs are intact. If so, its inputs and warnings are recorded as if it was built again.
+pub async fn is_up_to_date(
+    path: &Path,
+    options: &str,
+    diagnostics: &mut Diagnostics,
+    inputs: &mut Inputs,
+) -> Option<Vec<PathBuf>> {
+    let cache = Cache::load(path).await?;
+
+    if cache.version != env!("CARGO_PKG_VERSION") || cache.options != options {
+        debug!("Build options changed since the last build");
+        return None;
+    }
+
+    if !is_unchanged(&cache.inputs).await || !is_unchanged(&cache.outputs).await {
+        return None;
+    }
+
+    for input in cache.inputs.keys() {
+        inputs.insert(input);
+    }
+
+    // Warnings from reading the definition were already emitted before checking the cache
+    for warning in cache.warnings {
+        if !diagnostics.warnings().contains(&warning) {
+            diagnostics.warn(warning);
+        }
+    }
+
+    Some(cache.outputs.into_keys().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hash_example() {
+        assert_eq!(
+            hash(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn cache_path() {
+        let output = Path::new("out/pack.bin");
+
+        assert_eq!(
+            path(Path::new("pack.toml"), None, output).unwrap(),
+            Path::new("out/pack.bin.cache")
+        );
+        assert_eq!(
+            path(
+                Path::new("pack.toml"),
+                Some(Path::new("build.cache")),
+                output
+            )
+            .unwrap(),
+            Path::new("build.cache")
+        );
+        assert_eq!(path(Path::new("-"), None, output), None);
+    }
+}
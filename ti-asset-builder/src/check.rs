@@ -0,0 +1,202 @@
+use std::path::Path;
+
+use anyhow::{Context, anyhow, bail};
+use log::{error, info};
+
+use crate::{
+    cli::CliCheckCommand,
+    diagnostics::Diagnostics,
+    error::{ErrorKind, with_common_kind},
+    font,
+    search::SearchPaths,
+    sprite,
+};
+
+/// Every problem found while loading and validating definitions.
+/// Allows reporting all problems at once instead of stopping at the first.
+#[derive(Debug, Default)]
+pub struct Problems(Vec<anyhow::Error>);
+
+impl Problems {
+    pub fn push(&mut self, problem: anyhow::Error) {
+        self.0.push(problem);
+    }
+
+    /// Records the error if there is one, otherwise returns the value
+    pub fn check<T>(&mut self, result: anyhow::Result<T>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(problem) => {
+                self.push(problem);
+                None
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Combines every problem into a single error
+    pub fn into_result(mut self) -> anyhow::Result<()> {
+        match self.0.len() {
+            0 => Ok(()),
+            1 => Err(self.0.remove(0)),
+            length => {
+                let problems = self
+                    .0
+                    .iter()
+                    .map(|problem| format!("- {problem:#}"))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                Err(with_common_kind(
+                    anyhow!("Found {length} problems:\n{problems}"),
+                    self.0.iter().map(ErrorKind::of),
+                ))
+            }
+        }
+    }
+}
+
+impl IntoIterator for Problems {
+    type Item = anyhow::Error;
+    type IntoIter = std::vec::IntoIter<anyhow::Error>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+/// The kinds of definitions that can be checked
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DefinitionKind {
+    FontPack,
+    Sprite,
+}
+
+impl DefinitionKind {
+    /// Detects the kind of definition from its root tables
+    fn detect(raw: &str) -> anyhow::Result<Self> {
+        let table = toml::from_str::<toml::Table>(raw).context("Failed to parse definition")?;
+
+        if table.contains_key("pack") {
+            Ok(Self::FontPack)
+        } else if table.contains_key("sprite") || table.contains_key("sprites") {
+            Ok(Self::Sprite)
+        } else {
+            bail!("Unknown definition; expected a `[pack]`, `[sprite]`, or `[[sprites]]`")
+        }
+    }
+}
+
+async fn check_definition(
+    path: &Path,
+    strict: bool,
+    allow_any_size: bool,
+    search: &SearchPaths,
+) -> Problems {
+    let mut problems = Problems::default();
+    let mut diagnostics = Diagnostics::new(strict);
+
+    let raw = tokio::fs::read_to_string(path)
+        .await
+        .with_context(|| format!("Failed to read definition at {path:?}"));
+    let Some(kind) = problems.check(raw.and_then(|raw| DefinitionKind::detect(&raw))) else {
+        return problems;
+    };
+
+    match kind {
+        DefinitionKind::FontPack => {
+            font::check(path, search, &mut problems, &mut diagnostics).await
+        }
+        DefinitionKind::Sprite => {
+            sprite::check(path, allow_any_size, &mut problems, &mut diagnostics).await
+        }
+    }
+
+    problems.check(diagnostics.check_strict());
+
+    problems
+}
+
+pub async fn check(command: CliCheckCommand, strict: bool) -> anyhow::Result<()> {
+    let mut problem_count = 0;
+    let mut kinds = Vec::new();
+    let search = SearchPaths::from_env(&command.asset_paths);
+
+    for definition in &command.definitions {
+        let problems = check_definition(definition, strict, command.allow_any_size, &search).await;
+
+        if problems.is_empty() {
+            info!("No problems found: {definition:?}");
+        }
+
+        problem_count += problems.len();
+
+        for problem in problems {
+            error!("{definition:?}: {problem:#}");
+            kinds.push(ErrorKind::of(&problem));
+        }
+    }
+
+    if problem_count != 0 {
+        return Err(with_common_kind(
+            anyhow!(
+                "Found {problem_count} problems across {} definitions",
+                command.definitions.len()
+            ),
+            kinds,
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_font_pack() {
+        let kind = DefinitionKind::detect("[pack]\nfonts = []").unwrap();
+
+        assert_eq!(kind, DefinitionKind::FontPack);
+    }
+
+    #[test]
+    fn detect_sprite() {
+        let kind = DefinitionKind::detect("[sprite]\nsource = \"a\"").unwrap();
+
+        assert_eq!(kind, DefinitionKind::Sprite);
+    }
+
+    #[test]
+    fn detect_sprite_group() {
+        let kind = DefinitionKind::detect("[[sprites]]\nname = \"a\"").unwrap();
+
+        assert_eq!(kind, DefinitionKind::Sprite);
+    }
+
+    #[test]
+    fn detect_unknown() {
+        assert!(DefinitionKind::detect("[font]\nheight = 8").is_err());
+    }
+
+    #[test]
+    fn problems_into_result() {
+        let mut problems = Problems::default();
+        assert!(Problems::default().into_result().is_ok());
+
+        problems.push(anyhow!("first"));
+        problems.push(anyhow!("second"));
+
+        assert_eq!(
+            problems.into_result().unwrap_err().to_string(),
+            "Found 2 problems:\n- first\n- second"
+        );
+    }
+}
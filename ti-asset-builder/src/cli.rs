@@ -1,49 +1,355 @@
-use std::path::PathBuf;
+use std::{num::NonZeroUsize, ops::RangeInclusive, path::PathBuf};
 
-use anyhow::Context;
-use clap::{Args, Parser, Subcommand};
+use clap::{ArgAction, Args, Command, CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use log::LevelFilter;
 
-use crate::output::OutputType;
+use crate::{budget::DEFAULT_WARN_PERCENT, error::EXIT_CODES_HELP, output::OutputType};
 
 #[derive(Debug, Args, Clone)]
 pub struct CliFontPackCommand {
-    /// The fontpack defintion file
+    /// The fontpack defintion file, or `-` to read it from stdin
     #[clap(short, long)]
     pub definition: PathBuf,
-    /// The folder to output final asset
+    /// The file to output the final asset to
+    ///
+    /// If this is a directory, the asset is output in it, named after the definition.
     #[clap(short, long)]
     pub output: PathBuf,
-    #[clap(short = 't', long)]
+    /// The format of the final asset
+    #[arg(short = 'f', long = "format", value_enum, default_value = "binary")]
     pub output_type: OutputType,
+    /// Rebuild whenever the definition or any file it uses changes
+    #[clap(long)]
+    pub watch: bool,
+    /// Write a Make compatible dependency file listing every file the build read
+    #[clap(long)]
+    pub depfile: Option<PathBuf>,
+    /// Write a JSON report of the inputs, outputs, and warnings of the build for CI
+    #[clap(long)]
+    pub report_json: Option<PathBuf>,
+    /// Where to record the inputs and outputs of the build to skip it when nothing changed
+    ///
+    /// Defaults to beside the output, with `.cache` appended.
+    /// The build always runs when writing a JSON report.
+    #[clap(long)]
+    pub cache: Option<PathBuf>,
+    /// Build even if nothing changed since the last build
+    #[clap(long)]
+    pub force: bool,
+    /// The most bytes the asset may use, overriding the definition's `budget`
+    #[clap(long)]
+    pub budget: Option<NonZeroUsize>,
+    /// How full the budget can be, in percent, before warning about it
+    #[arg(
+        long,
+        default_value_t = DEFAULT_WARN_PERCENT,
+        value_parser = clap::value_parser!(u8).range(1..=100)
+    )]
+    pub budget_warn: u8,
+    /// The directory relative paths in the definition start from
+    ///
+    /// Defaults to the definition's directory. Required when reading from stdin.
+    #[clap(long)]
+    pub base_dir: Option<PathBuf>,
+    /// The most glyphs to load at once; defaults to the number of CPUs
+    #[clap(short, long)]
+    pub jobs: Option<NonZeroUsize>,
+    /// The name of the asset, which the variable name and symbol prefix are made from
+    ///
+    /// Defaults to the pack's family name, or the definition's file name if it has none.
+    #[clap(long)]
+    pub name: Option<String>,
+    /// The name of the variable on the calculator; at most 8 letters and digits
+    #[clap(long)]
+    pub var_name: Option<String>,
+    /// The start of every C and assembly symbol
+    #[clap(long)]
+    pub symbol_prefix: Option<String>,
+    /// A directory to search for fonts and glyphs that aren't relative to their definition
+    ///
+    /// Searched in order, before any in `TI_ASSET_PATH`.
+    #[arg(long = "asset-path", value_name = "DIR")]
+    pub asset_paths: Vec<PathBuf>,
+}
+
+#[derive(Debug, Args, Clone)]
+pub struct CliFontPackInspectCommand {
+    /// The built font pack binary
+    pub file: PathBuf,
+    /// Draw a glyph's bitmap from every font, either as a number or a single character
+    #[clap(long, value_parser = crate::font::parse_glyph_index)]
+    pub glyph: Option<u8>,
+}
+
+#[derive(Debug, Args, Clone)]
+pub struct CliFontPackDiffCommand {
+    /// The original font pack binary
+    pub old: PathBuf,
+    /// The changed font pack binary
+    pub new: PathBuf,
+    /// Exit with a nonzero code if there are any differences
+    #[clap(long)]
+    pub exit_code: bool,
+}
+
+#[derive(Debug, Subcommand, Clone)]
+pub enum CliFontPackAction {
+    /// Print the structure of a built font pack
+    Inspect(CliFontPackInspectCommand),
+    /// Print what changed between two built font packs
+    Diff(CliFontPackDiffCommand),
+}
+
+#[derive(Debug, Args, Clone)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+pub struct CliFontPackArgs {
+    #[command(subcommand)]
+    pub action: Option<CliFontPackAction>,
+    #[command(flatten)]
+    pub build: Option<CliFontPackCommand>,
 }
 
 #[derive(Debug, Args, Clone)]
 pub struct CliSpriteCommand {
-    /// The sprite definition file
+    /// The sprite definition file, or `-` to read it from stdin
     pub definition: PathBuf,
-    /// The folder to output final asset
+    /// The file to output the final asset to
+    ///
+    /// If this is a directory, the asset is output in it, named after the definition.
+    /// For definitions with many sprites, this is always the directory each sprite is output to.
+    pub output: PathBuf,
+    /// Continue building the other sprites in a definition after one fails
+    #[clap(long)]
+    pub keep_going: bool,
+    /// Allow backgrounds that aren't the size of the screen
+    #[clap(long)]
+    pub allow_any_size: bool,
+    /// The format of the final asset
+    #[arg(short = 'f', long = "format", value_enum, default_value = "binary")]
+    pub output_type: OutputType,
+    /// Rebuild whenever the definition or any file it uses changes
+    #[clap(long)]
+    pub watch: bool,
+    /// Write a Make compatible dependency file listing every file the build read
+    #[clap(long)]
+    pub depfile: Option<PathBuf>,
+    /// Write a JSON report of the inputs, outputs, and warnings of the build for CI
+    #[clap(long)]
+    pub report_json: Option<PathBuf>,
+    /// Where to record the inputs and outputs of the build to skip it when nothing changed
+    ///
+    /// Defaults to beside the output, with `.cache` appended.
+    /// The build always runs when writing a JSON report.
+    #[clap(long)]
+    pub cache: Option<PathBuf>,
+    /// Build even if nothing changed since the last build
+    #[clap(long)]
+    pub force: bool,
+    /// The most bytes the asset may use, overriding the definition's `budget`
+    #[clap(long)]
+    pub budget: Option<NonZeroUsize>,
+    /// How full the budget can be, in percent, before warning about it
+    #[arg(
+        long,
+        default_value_t = DEFAULT_WARN_PERCENT,
+        value_parser = clap::value_parser!(u8).range(1..=100)
+    )]
+    pub budget_warn: u8,
+    /// The directory relative paths in the definition start from
+    ///
+    /// Defaults to the definition's directory. Required when reading from stdin.
+    #[clap(long)]
+    pub base_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Args, Clone)]
+pub struct CliCheckCommand {
+    /// The font pack and sprite definition files
+    #[clap(required = true)]
+    pub definitions: Vec<PathBuf>,
+    /// Allow backgrounds that aren't the size of the screen
+    #[clap(long)]
+    pub allow_any_size: bool,
+    /// A directory to search for fonts and glyphs that aren't relative to their definition
+    ///
+    /// Searched in order, before any in `TI_ASSET_PATH`.
+    #[arg(long = "asset-path", value_name = "DIR")]
+    pub asset_paths: Vec<PathBuf>,
+}
+
+#[derive(Debug, Args, Clone)]
+pub struct CliBuildCommand {
+    /// The project file listing every asset to build
+    pub project: PathBuf,
+    /// The most assets to build at once; defaults to the number of CPUs
+    #[clap(short, long)]
+    pub jobs: Option<NonZeroUsize>,
+    /// Stop building the other assets after one fails
+    #[clap(long)]
+    pub fail_fast: bool,
+    /// A directory to search for fonts and glyphs that aren't relative to their definition
+    ///
+    /// Searched in order, before any in `TI_ASSET_PATH`.
+    #[arg(long = "asset-path", value_name = "DIR")]
+    pub asset_paths: Vec<PathBuf>,
+}
+
+#[derive(Debug, Args, Clone)]
+pub struct CliConvertCommand {
+    /// The built binary asset
+    pub input: PathBuf,
+    /// The file to output the converted asset to
     pub output: PathBuf,
+    /// The format to convert to
+    #[arg(short = 'f', long = "format", value_enum)]
+    pub output_type: OutputType,
+    /// The symbol name of the data; defaults to the output's file name
+    #[clap(long)]
+    pub name: Option<String>,
+    /// The name of the variable on the calculator; defaults to the symbol name's letters and digits
+    #[clap(long)]
+    pub var_name: Option<String>,
+}
+
+#[derive(Debug, Args, Clone)]
+pub struct CliCompletionsCommand {
+    /// The shell to generate completions for
+    #[arg(value_enum)]
+    pub shell: Shell,
+}
+
+/// How placeholder images are drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Placeholder {
+    /// Nothing is set
+    Blank,
+    /// Only the outline is set
+    Box,
+}
+
+#[derive(Debug, Args, Clone)]
+pub struct CliInitFontPackCommand {
+    /// The directory to create `pack.toml`, `fonts/main.toml`, and `glyphs/` in
+    pub directory: PathBuf,
+    /// Add a placeholder glyph for each in the range, like `a..z` or `32..126`
+    #[clap(long, value_parser = crate::init::parse_glyph_range)]
+    pub glyphs: Option<RangeInclusive<u8>>,
+    /// How placeholder glyphs are drawn
+    #[arg(long, value_enum, default_value = "box")]
+    pub placeholder: Placeholder,
+    /// Overwrite existing files
+    #[clap(long)]
+    pub force: bool,
+}
+
+#[derive(Debug, Args, Clone)]
+pub struct CliInitSpriteCommand {
+    /// The directory to create `sprite.toml` in
+    pub directory: PathBuf,
+    /// Add a placeholder `sprite.png`
+    #[arg(long, value_enum)]
+    pub placeholder: Option<Placeholder>,
+    /// Overwrite existing files
+    #[clap(long)]
+    pub force: bool,
+}
+
+#[derive(Debug, Subcommand, Clone)]
+#[command(rename_all = "lower")]
+pub enum CliInitCommand {
+    /// Create a font pack with one font
+    FontPack(CliInitFontPackCommand),
+    /// Create a sprite
+    Sprite(CliInitSpriteCommand),
 }
 
 #[derive(Debug, Subcommand, Clone)]
 #[command(rename_all = "lower")]
 pub enum CliSubcommand {
     /// Build a fontpack definition file
-    FontPack(CliFontPackCommand),
+    FontPack(CliFontPackArgs),
     /// Build a sprite definition file
     Sprite(CliSpriteCommand),
+    /// Build every asset listed in a project file
+    Build(CliBuildCommand),
+    /// Validate definition files without building them
+    Check(CliCheckCommand),
+    /// Convert a built binary asset into C or assembly
+    Convert(CliConvertCommand),
+    /// Print a shell completion script
+    Completions(CliCompletionsCommand),
+    /// Create a new definition from a template
+    #[command(subcommand)]
+    Init(CliInitCommand),
 }
 
 #[derive(Debug, Parser, Clone)]
-#[command(version, about, long_about = None)]
-struct CliArgs {
+#[command(version, about, long_about = None, after_help = EXIT_CODES_HELP)]
+pub struct CliArgs {
+    /// Log more details; use twice for every detail
+    #[arg(short, long, global = true, action = ArgAction::Count, conflicts_with = "quiet")]
+    pub verbose: u8,
+    /// Only log warnings and errors
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+    /// Fail the build if there are any warnings
+    #[arg(long, global = true)]
+    pub strict: bool,
     #[clap(subcommand)]
     pub subcommand: CliSubcommand,
 }
 
+impl CliArgs {
+    /// The log level chosen by the verbosity flags
+    pub fn log_level(&self) -> LevelFilter {
+        match (self.quiet, self.verbose) {
+            (true, _) => LevelFilter::Warn,
+            (false, 0) => LevelFilter::Info,
+            (false, 1) => LevelFilter::Debug,
+            (false, _) => LevelFilter::Trace,
+        }
+    }
+}
+
+/// The most tasks to run at once, defaulting to the number of CPUs
+pub fn job_count(jobs: Option<NonZeroUsize>) -> usize {
+    match jobs {
+        Some(jobs) => jobs.get(),
+        None => std::thread::available_parallelism().map_or(1, |jobs| jobs.get()),
+    }
+}
+
+/// The structure of every cli argument and subcommand
+pub fn command() -> Command {
+    CliArgs::command()
+}
+
 /// Parses the cli arguments
-pub fn init_cli() -> anyhow::Result<CliSubcommand> {
-    let args = CliArgs::try_parse().context("Failed to parse CLI arguments")?;
+pub fn init_cli() -> Result<CliArgs, clap::Error> {
+    CliArgs::try_parse()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log_level(args: &[&str]) -> LevelFilter {
+        CliArgs::try_parse_from(
+            ["ti-asset-builder", "check", "pack.toml"]
+                .iter()
+                .chain(args),
+        )
+        .unwrap()
+        .log_level()
+    }
 
-    Ok(args.subcommand)
+    #[test]
+    fn verbosity() {
+        assert_eq!(log_level(&[]), LevelFilter::Info);
+        assert_eq!(log_level(&["-v"]), LevelFilter::Debug);
+        assert_eq!(log_level(&["-vv"]), LevelFilter::Trace);
+        assert_eq!(log_level(&["-q"]), LevelFilter::Warn);
+        assert!(CliArgs::try_parse_from(["ti-asset-builder", "-q", "-v", "check", "a"]).is_err());
+    }
 }
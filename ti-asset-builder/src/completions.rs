@@ -0,0 +1,45 @@
+use std::io::Write;
+
+use anyhow::Context;
+use clap_complete::Shell;
+
+use crate::{
+    cli,
+    error::{ErrorKind, ResultExt},
+};
+
+/// Writes the completion script of the shell
+pub fn generate(shell: Shell, output: &mut impl Write) {
+    let mut command = cli::command();
+    let name = command.get_name().to_string();
+    clap_complete::generate(shell, &mut command, name, output);
+}
+
+/// Prints the completion script of the shell to stdout
+pub fn print(shell: Shell) -> anyhow::Result<()> {
+    let mut script = Vec::new();
+    generate(shell, &mut script);
+
+    std::io::stdout()
+        .write_all(&script)
+        .context("Failed to print completion script")
+        .with_kind(ErrorKind::Io)
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::ValueEnum;
+
+    use super::*;
+
+    #[test]
+    fn generate_every_shell() {
+        for shell in Shell::value_variants() {
+            let mut output = Vec::new();
+            generate(*shell, &mut output);
+            let script = String::from_utf8(output).unwrap();
+
+            assert!(script.contains("fontpack"), "Missing fontpack for {shell}");
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use std::path::Path;
+
+use anyhow::{Context, bail};
+use log::info;
+
+use crate::{
+    cli::CliConvertCommand,
+    error::{ErrorKind, ResultExt},
+    font::ParsedFontPack,
+    output::{
+        AssetNames, OutputType,
+        source::{self, Label},
+    },
+};
+
+/// Gets the symbol name from the option or the output's file name
+fn get_name(name: Option<String>, output: &Path) -> anyhow::Result<String> {
+    let name = match name {
+        Some(name) => name,
+        None => {
+            let stem = output
+                .file_stem()
+                .with_context(|| format!("Output has no file name: {output:?}"))?;
+            source::to_identifier(&stem.to_string_lossy())
+        }
+    };
+
+    source::validate_identifier(&name)?;
+    Ok(name)
+}
+
+/// Labels the structure of known assets.
+/// Unknown assets are left as a plain array of bytes.
+fn get_labels(data: &[u8]) -> anyhow::Result<Vec<Label>> {
+    if ParsedFontPack::is_font_pack(data) {
+        let pack = ParsedFontPack::parse(data).context("Failed to parse font pack")?;
+        return Ok(pack.labels());
+    }
+
+    info!("Unknown asset type, converting as plain bytes");
+    Ok(Vec::new())
+}
+
+pub async fn convert(command: CliConvertCommand) -> anyhow::Result<()> {
+    let data = tokio::fs::read(&command.input)
+        .await
+        .with_context(|| format!("Failed to read asset at {:?}", command.input))?;
+    let name = get_name(command.name, &command.output)?;
+    let names = AssetNames::resolve(&name, command.var_name, Some(name.clone()))?;
+    let labels = get_labels(&data)?;
+
+    let converted = match command.output_type {
+        OutputType::Assembly => source::to_asm(&names, &data, &labels),
+        OutputType::C => source::to_c(&names, &data, &labels),
+        OutputType::Binary => {
+            bail!("Asset is already binary; use `--format c` or `--format assembly`")
+        }
+    };
+
+    tokio::fs::write(&command.output, converted)
+        .await
+        .with_context(|| format!("Failed to write converted asset to {:?}", command.output))
+        .with_kind(ErrorKind::Io)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn name_from_output() {
+        assert_eq!(
+            get_name(None, Path::new("out/my-font.h")).unwrap(),
+            "my_font"
+        );
+        assert_eq!(
+            get_name(Some("font".to_string()), Path::new("out/my-font.h")).unwrap(),
+            "font"
+        );
+        assert!(get_name(Some("my-font".to_string()), Path::new("a.h")).is_err());
+    }
+
+    #[test]
+    fn unknown_labels() {
+        assert_eq!(get_labels(&[1, 2, 3]).unwrap(), []);
+    }
+}
@@ -0,0 +1,117 @@
+use std::{
+    ffi::OsStr,
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use tokio::io::AsyncReadExt;
+
+use crate::path::PathBufExt;
+
+/// The definition path that reads from stdin instead
+pub const STDIN_PATH: &str = "-";
+
+/// Where a definition is read from and the directory its relative paths start from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DefinitionSource {
+    /// The canon definition file, or `None` for stdin
+    file: Option<PathBuf>,
+    anchor: PathBuf,
+}
+
+impl DefinitionSource {
+    /// The base directory overrides the definition file's directory and is required for stdin
+    pub fn new(path: &Path, base_dir: Option<&Path>) -> anyhow::Result<Self> {
+        let base_dir = base_dir
+            .map(|base_dir| {
+                base_dir
+                    .canonicalize()
+                    .map(PathBufExt::strip_verbatim)
+                    .with_context(|| format!("Failed to get canon base directory: {base_dir:?}"))
+            })
+            .transpose()?;
+
+        if is_stdin(path) {
+            let anchor = base_dir.context(
+                "Reading a definition from stdin requires `--base-dir`\n\
+                 Relative paths in the definition are resolved from it",
+            )?;
+
+            return Ok(Self { file: None, anchor });
+        }
+
+        let file = path
+            .canonicalize()
+            .map(PathBufExt::strip_verbatim)
+            .with_context(|| format!("Failed to get canon definition path: {path:?}"))?;
+        let anchor = match base_dir {
+            Some(base_dir) => base_dir,
+            None => file
+                .parent()
+                .with_context(|| format!("Definition has no parent directory: {file:?}"))?
+                .to_path_buf(),
+        };
+
+        Ok(Self {
+            file: Some(file),
+            anchor,
+        })
+    }
+
+    /// The directory relative paths in the definition start from
+    pub fn anchor(&self) -> &Path {
+        &self.anchor
+    }
+
+    /// The definition's file name without its extension, if it's a file
+    pub fn stem(&self) -> Option<&OsStr> {
+        self.file.as_deref().and_then(Path::file_stem)
+    }
+
+    pub async fn read(&self) -> anyhow::Result<String> {
+        let Some(file) = &self.file else {
+            let mut raw = String::new();
+            tokio::io::stdin()
+                .read_to_string(&mut raw)
+                .await
+                .context("Failed to read definition from stdin")?;
+
+            return Ok(raw);
+        };
+
+        tokio::fs::read_to_string(file)
+            .await
+            .with_context(|| format!("Failed to read definition at {file:?}"))
+    }
+}
+
+impl Display for DefinitionSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.file {
+            Some(file) => write!(f, "{file:?}"),
+            None => write!(f, "stdin"),
+        }
+    }
+}
+
+/// Whether the definition path means stdin
+pub fn is_stdin(path: &Path) -> bool {
+    path == Path::new(STDIN_PATH)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stdin_requires_base_dir() {
+        assert!(DefinitionSource::new(Path::new("-"), None).is_err());
+
+        let source = DefinitionSource::new(Path::new("-"), Some(Path::new("."))).unwrap();
+
+        assert_eq!(source.anchor(), Path::new(".").canonicalize().unwrap());
+        assert_eq!(source.stem(), None);
+        assert_eq!(source.to_string(), "stdin");
+    }
+}
@@ -0,0 +1,100 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+
+use crate::{
+    error::{ErrorKind, ResultExt},
+    inputs::Inputs,
+};
+
+/// Escapes characters with special meaning to Make, like gcc.
+/// Backslashes are only escaped before a space or `#`, so Windows paths are kept as they are.
+fn escape(path: &Path) -> String {
+    let path = path.to_string_lossy().chars().collect::<Vec<_>>();
+    let mut output = String::new();
+
+    for (index, &char) in path.iter().enumerate() {
+        match char {
+            ' ' | '#' => output.push('\\'),
+            '\\' if path[index..]
+                .iter()
+                .find(|&&char| char != '\\')
+                .is_some_and(|&char| char == ' ' || char == '#') =>
+            {
+                output.push('\\')
+            }
+            '$' => output.push('$'),
+            _ => (),
+        }
+
+        output.push(char);
+    }
+
+    output
+}
+
+/// Formats a gcc style dependency file with every output depending on every input
+pub fn format(outputs: &[PathBuf], inputs: &Inputs) -> String {
+    let mut depfile = outputs
+        .iter()
+        .map(|output| escape(output))
+        .collect::<Vec<_>>()
+        .join(" ");
+    depfile.push(':');
+
+    for input in inputs.iter() {
+        depfile.push_str(" \\\n  ");
+        depfile.push_str(&escape(input));
+    }
+
+    depfile.push('\n');
+    depfile
+}
+
+pub async fn write(path: &Path, outputs: &[PathBuf], inputs: &Inputs) -> anyhow::Result<()> {
+    tokio::fs::write(path, format(outputs, inputs))
+        .await
+        .with_context(|| format!("Failed to write dependency file: {path:?}"))
+        .with_kind(ErrorKind::Io)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_escaped() {
+        let mut inputs = Inputs::default();
+        inputs.insert(Path::new("/fonts/pack.toml"));
+        inputs.insert(Path::new("/fonts/my glyphs/$#.png"));
+
+        assert_eq!(
+            format(&[PathBuf::from("out/fonts.bin")], &inputs),
+            "out/fonts.bin: \\\n  /fonts/my\\ glyphs/$$\\#.png \\\n  /fonts/pack.toml\n"
+        );
+    }
+
+    // Like gcc, only the backslashes before a space or `#` are escaped
+    #[test]
+    fn escape_backslashes() {
+        assert_eq!(
+            escape(Path::new(r"C:\fonts\pack.toml")),
+            r"C:\fonts\pack.toml"
+        );
+        assert_eq!(escape(Path::new(r"a\ b\\#c")), r"a\\\ b\\\\\#c");
+    }
+
+    #[test]
+    fn format_outputs() {
+        let mut inputs = Inputs::default();
+        inputs.insert(Path::new("/sprites.toml"));
+
+        assert_eq!(
+            format(
+                &[PathBuf::from("out/a.bin"), PathBuf::from("out/b.bin")],
+                &inputs
+            ),
+            "out/a.bin out/b.bin: \\\n  /sprites.toml\n"
+        );
+    }
+}
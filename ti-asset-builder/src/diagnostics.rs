@@ -0,0 +1,69 @@
+use anyhow::bail;
+use log::warn;
+
+/// Every warning emitted during a build, so they can be reported after it finishes.
+/// With `--strict`, any warning fails the build.
+#[derive(Debug, Default)]
+pub struct Diagnostics {
+    strict: bool,
+    warnings: Vec<String>,
+}
+
+impl Diagnostics {
+    pub fn new(strict: bool) -> Self {
+        Self {
+            strict,
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Logs the warning and records it
+    pub fn warn(&mut self, message: impl Into<String>) {
+        let message = message.into();
+        warn!("{message}");
+        self.warnings.push(message);
+    }
+
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// Fails with every warning so far if strict
+    pub fn check_strict(&self) -> anyhow::Result<()> {
+        if !self.strict || self.warnings.is_empty() {
+            return Ok(());
+        }
+
+        let warnings = self
+            .warnings
+            .iter()
+            .map(|warning| format!("- {warning}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        bail!("Warnings are errors with `--strict`:\n{warnings}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_strict() {
+        let mut lenient = Diagnostics::new(false);
+        let mut strict = Diagnostics::new(true);
+        assert!(strict.check_strict().is_ok());
+
+        for diagnostics in [&mut lenient, &mut strict] {
+            diagnostics.warn("first");
+            diagnostics.warn("second");
+        }
+
+        assert!(lenient.check_strict().is_ok());
+        assert_eq!(
+            strict.check_strict().unwrap_err().to_string(),
+            "Warnings are errors with `--strict`:\n- first\n- second"
+        );
+    }
+}
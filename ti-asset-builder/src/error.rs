@@ -0,0 +1,166 @@
+use std::{fmt::Display, io};
+
+/// Explains the exit codes in `--help`
+pub const EXIT_CODES_HELP: &str = "Exit codes:
+  0  Success
+  1  Invalid command line usage
+  2  Invalid definition or failed validation
+  3  Missing input file
+  4  Failed to read or write a file
+  5  Asset is too large
+  6  Assets differ, with `fontpack diff --exit-code`";
+
+/// The category of a failure, which decides the exit code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    Usage,
+    Definition,
+    MissingInput,
+    Io,
+    SizeLimit,
+    /// Not a failure, but reported like one for scripts
+    Different,
+}
+
+impl ErrorKind {
+    pub fn exit_code(self) -> u8 {
+        match self {
+            Self::Usage => 1,
+            Self::Definition => 2,
+            Self::MissingInput => 3,
+            Self::Io => 4,
+            Self::SizeLimit => 5,
+            Self::Different => 6,
+        }
+    }
+
+    /// Finds the category of the error from the outermost tagged cause.
+    /// Untagged I/O errors are missing inputs if the file wasn't found.
+    /// Anything else is a problem with the definition.
+    pub fn of(error: &anyhow::Error) -> Self {
+        if let Some(tagged) = error
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<Tagged>())
+        {
+            return tagged.kind;
+        }
+
+        match error
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<io::Error>())
+        {
+            Some(error) if error.kind() == io::ErrorKind::NotFound => Self::MissingInput,
+            Some(_) => Self::Io,
+            None => Self::Definition,
+        }
+    }
+
+    /// The category shared by every error, if there is one
+    pub fn common(kinds: impl IntoIterator<Item = Self>) -> Option<Self> {
+        let mut kinds = kinds.into_iter();
+        let first = kinds.next()?;
+
+        kinds.all(|kind| kind == first).then_some(first)
+    }
+}
+
+/// An error tagged with its category.
+/// Displays as the error it wraps so messages are unchanged.
+#[derive(Debug)]
+struct Tagged {
+    kind: ErrorKind,
+    error: anyhow::Error,
+}
+
+impl Display for Tagged {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.error, f)
+    }
+}
+
+impl std::error::Error for Tagged {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error.source()
+    }
+}
+
+/// Tags the error with the category shared by every error that caused it, if there is one
+pub fn with_common_kind(
+    error: anyhow::Error,
+    kinds: impl IntoIterator<Item = ErrorKind>,
+) -> anyhow::Error {
+    match ErrorKind::common(kinds) {
+        Some(kind) => Tagged { kind, error }.into(),
+        None => error,
+    }
+}
+
+pub trait ResultExt<T> {
+    /// Tags the error with its category
+    fn with_kind(self, kind: ErrorKind) -> anyhow::Result<T>;
+}
+
+impl<T, E: Into<anyhow::Error>> ResultExt<T> for Result<T, E> {
+    fn with_kind(self, kind: ErrorKind) -> anyhow::Result<T> {
+        self.map_err(|error| {
+            Tagged {
+                kind,
+                error: error.into(),
+            }
+            .into()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::{Context, anyhow};
+
+    use super::*;
+
+    fn not_found() -> anyhow::Error {
+        anyhow::Error::new(io::Error::from(io::ErrorKind::NotFound)).context("Failed to read")
+    }
+
+    #[test]
+    fn kind_of_untagged() {
+        assert_eq!(ErrorKind::of(&anyhow!("bad")), ErrorKind::Definition);
+        assert_eq!(ErrorKind::of(&not_found()), ErrorKind::MissingInput);
+        assert_eq!(
+            ErrorKind::of(&io::Error::from(io::ErrorKind::PermissionDenied).into()),
+            ErrorKind::Io
+        );
+    }
+
+    // Tags take precedence over the causes they wrap, even under more context
+    #[test]
+    fn kind_of_tagged() {
+        let error = Err::<(), _>(not_found())
+            .with_kind(ErrorKind::Io)
+            .context("Failed to build")
+            .unwrap_err();
+
+        assert_eq!(ErrorKind::of(&error), ErrorKind::Io);
+        assert_eq!(
+            format!("{error:#}"),
+            "Failed to build: Failed to read: entity not found"
+        );
+    }
+
+    #[test]
+    fn common_kind() {
+        let size = Err::<(), _>(anyhow!("big"))
+            .with_kind(ErrorKind::SizeLimit)
+            .unwrap_err();
+
+        assert_eq!(ErrorKind::common([]), None);
+        assert_eq!(
+            ErrorKind::common([&size, &size].map(ErrorKind::of)),
+            Some(ErrorKind::SizeLimit)
+        );
+        assert_eq!(
+            ErrorKind::common([&size, &anyhow!("bad")].map(ErrorKind::of)),
+            None
+        );
+    }
+}
@@ -1,22 +1,42 @@
-mod definition;
+pub mod definition;
+mod diff;
+mod inspect;
 mod output;
+mod parse;
 
 use std::{
     collections::HashMap,
     path::{Path, PathBuf},
+    sync::Arc,
+    time::Instant,
 };
 
-use anyhow::Context;
-use log::warn;
+use anyhow::{Context, anyhow, bail};
+use log::{debug, info};
+use tokio::{sync::Semaphore, task::JoinSet};
+
+pub use diff::diff_files;
+pub use inspect::{inspect, parse_glyph_index};
+pub use parse::ParsedFontPack;
 
 use crate::{
-    cli::CliFontPackCommand,
+    budget::{BudgetUsage, DEFAULT_WARN_PERCENT},
+    cache::{self, Cache},
+    check::Problems,
+    cli::{CliFontPackCommand, job_count},
+    definition::{DefinitionSource, is_stdin},
+    depfile,
+    diagnostics::Diagnostics,
+    error::{ErrorKind, ResultExt},
     font::definition::{
-        FontDefinition, FontDefinitionWrapper, FontGlyph, FontPackDefinition,
-        FontPackDefinitionWrapper,
+        FontDefinition, FontDefinitionWrapper, FontPackDefinition, FontPackDefinitionWrapper,
     },
-    output::OutputType,
+    inputs::Inputs,
+    output::{AssetNames, OutputType},
     path::PathExt,
+    report::{Report, ReportOutput},
+    schema::{self, Schema},
+    search::SearchPaths,
     sprite::{ColorMonochrome, RawImage},
 };
 
@@ -27,31 +47,62 @@ struct FontGlyphs {
     last_glyph: u8,
 }
 
+/// A glyph's width and bitmap, or why it failed to load
+type LoadedGlyph = anyhow::Result<(u8, Vec<u8>)>;
+
 impl FontGlyphs {
-    async fn new(font: &Path, glyphs: &[FontGlyph]) -> anyhow::Result<Self> {
-        let glyph_table = HashMap::with_capacity(glyphs.len());
+    /// Inserts the font's loaded glyphs in definition order, recording any that failed to load
+    fn new(
+        font_path: &Path,
+        font: &FontDefinition,
+        loaded: Vec<LoadedGlyph>,
+        problems: &mut Problems,
+        diagnostics: &mut Diagnostics,
+    ) -> Self {
+        let glyph_table = HashMap::with_capacity(font.glyphs.len());
 
         let mut output = Self {
             glyphs: glyph_table,
             ..Default::default()
         };
 
-        for glyph in glyphs {
-            let path = get_glyph_path(font, &glyph.source)?;
-            let (width, _height, pixels) = RawImage::load(&path).await?.into_monochrome();
-            let width = width.try_into().with_context(|| {
-                format!(
-                    "Glyph width must be within range [{}, {}]. Found width: {}",
-                    u8::MIN,
-                    u8::MAX,
-                    width
-                )
-            })?;
-            let bitmap = Self::pixels_to_bytes(width, pixels);
-            output.insert(glyph.index.into(), width, bitmap);
+        for (glyph, glyph_result) in font.glyphs.iter().zip(loaded) {
+            if let Some((width, bitmap)) = problems.check(glyph_result) {
+                let index = glyph.index.into();
+
+                if output.insert(index, width, bitmap).is_some() {
+                    diagnostics.warn(format!(
+                        "Glyph is already defined: {index}\nPath: {font_path:?}"
+                    ));
+                }
+            }
+        }
+
+        output
+    }
+
+    /// Returns the width and bitmap of the glyph
+    async fn load_glyph(path: &Path, font_height: u8) -> LoadedGlyph {
+        let (width, height, pixels) = RawImage::load(path).await?.into_monochrome();
+        let width = width.try_into().with_context(|| {
+            format!(
+                "Glyph width must be within range [{}, {}]. Found width: {}",
+                u8::MIN,
+                u8::MAX,
+                width
+            )
+        })?;
+
+        if height != font_height as u32 {
+            bail!(
+                "Glyph height must match the font height: {} != {}\n\
+                 Path: {path:?}",
+                height,
+                font_height
+            );
         }
 
-        Ok(output)
+        Ok((width, Self::pixels_to_bytes(width, pixels)))
     }
 
     fn pixels_to_bytes(width: u8, pixels: Vec<ColorMonochrome>) -> Vec<u8> {
@@ -76,14 +127,11 @@ impl FontGlyphs {
             .collect()
     }
 
-    fn insert(&mut self, index: u8, width: u8, bitmap: Vec<u8>) {
+    /// Returns the glyph's previous bitmap and width if it was already defined
+    fn insert(&mut self, index: u8, width: u8, bitmap: Vec<u8>) -> Option<(Vec<u8>, u8)> {
         self.first_glyph = self.first_glyph.min(index);
         self.last_glyph = self.last_glyph.max(index);
-        let old = self.glyphs.insert(index, (bitmap, width));
-
-        if old.is_some() {
-            warn!("Glyph is already defined: {index}");
-        }
+        self.glyphs.insert(index, (bitmap, width))
     }
 
     fn glyph_count(&self) -> u8 {
@@ -102,58 +150,395 @@ impl Default for FontGlyphs {
     }
 }
 
-async fn load_pack_definition(path: &Path) -> anyhow::Result<FontPackDefinition> {
-    let raw = tokio::fs::read_to_string(path)
-        .await
-        .with_context(|| format!("Failed to read font pack definition at {path:?}"))?;
-    let definition = toml::from_str::<FontPackDefinitionWrapper>(&raw)
-        .with_context(|| format!("Failed to parse font pack definition at {path:?}"))?
-        .pack;
+async fn load_pack_definition(
+    source: &DefinitionSource,
+    diagnostics: &mut Diagnostics,
+) -> anyhow::Result<FontPackDefinition> {
+    let raw = source.read().await?;
+    let definition =
+        schema::parse::<FontPackDefinitionWrapper>(Schema::FontPack, &raw, source, diagnostics)
+            .with_context(|| format!("Failed to parse font pack definition from {source}"))?
+            .pack;
 
     Ok(definition)
 }
 
-fn get_font_path(pack: &Path, font: &Path) -> anyhow::Result<PathBuf> {
-    pack.relative_parent_suffix(font, ".toml")
+/// Resolves the font's path from the directory the pack's relative paths start from,
+/// falling back to the search paths
+fn get_font_path(anchor: &Path, font: &Path, search: &SearchPaths) -> anyhow::Result<PathBuf> {
+    search.resolve(anchor.relative_suffix(font, ".toml")?, font, ".toml")
 }
 
-fn get_glyph_path(font: &Path, glyph: &Path) -> anyhow::Result<PathBuf> {
-    font.relative_parent_suffix(glyph, ".png")
+/// Resolves the glyph's path from the font's directory, falling back to the search paths
+fn get_glyph_path(font: &Path, glyph: &Path, search: &SearchPaths) -> anyhow::Result<PathBuf> {
+    search.resolve(font.relative_parent_suffix(glyph, ".png")?, glyph, ".png")
 }
 
-async fn load_font_definition(path: &Path) -> anyhow::Result<FontDefinition> {
+async fn load_font_definition(
+    path: &Path,
+    diagnostics: &mut Diagnostics,
+) -> anyhow::Result<FontDefinition> {
     let raw = tokio::fs::read_to_string(path)
         .await
         .with_context(|| format!("Failed to read font definition at {path:?}"))?;
-    let definition = toml::from_str::<FontDefinitionWrapper>(&raw)
-        .with_context(|| format!("Failed to parse font definition at {path:?}"))?
-        .font;
+    let source = format!("{path:?}");
+    let definition =
+        schema::parse::<FontDefinitionWrapper>(Schema::Font, &raw, &source, diagnostics)
+            .with_context(|| format!("Failed to parse font definition at {path:?}"))?
+            .font;
     Ok(definition)
 }
 
-pub async fn build(command: CliFontPackCommand) -> anyhow::Result<()> {
-    let pack_definition_path = command.definition.canonicalize().with_context(|| {
-        format!(
-            "Failed to get canon font pack definition path: {:?}",
-            command.definition
-        )
-    })?;
-    let pack_definition = load_pack_definition(&pack_definition_path).await?;
+/// The largest an AppVar's data can be in bytes
+const MAX_APPVAR_SIZE: usize = 65505;
+
+/// How full an AppVar can be, in percent, before warning about it
+const NEAR_LIMIT_PERCENT: usize = 90;
+
+/// A font pack with all of its fonts and glyphs loaded
+pub struct FontPack {
+    definition: FontPackDefinition,
+    fonts: Vec<(FontDefinition, FontGlyphs)>,
+}
+
+/// Checks the font's metrics are usable by fontlibc
+fn validate_font(font_path: &Path, font: &FontDefinition, problems: &mut Problems) {
+    if font.fontlibc_version != 0 {
+        problems.push(anyhow!(
+            "Font fontlibc_version must be 0: {}\nPath: {font_path:?}",
+            font.fontlibc_version
+        ));
+    }
+
+    if font.height == 0 {
+        problems.push(anyhow!("Font height must not be 0\nPath: {font_path:?}"));
+    }
+
+    if font.glyphs.is_empty() {
+        problems.push(anyhow!(
+            "Font must have at least one glyph\nPath: {font_path:?}"
+        ));
+    }
+
+    let metrics = [
+        ("cap_height", font.cap_height),
+        ("x_height", font.x_height),
+        ("baseline_height", font.baseline_height),
+    ];
+
+    for (name, value) in metrics {
+        if value > font.height {
+            problems.push(anyhow!(
+                "Font {name} is past the font height: {value} > {}\nPath: {font_path:?}",
+                font.height
+            ));
+        }
+    }
+}
+
+fn validate_size(size: usize, diagnostics: &mut Diagnostics) -> anyhow::Result<()> {
+    if size > MAX_APPVAR_SIZE {
+        return Err(anyhow!(
+            "Font pack is too large to fit in an AppVar: {size} bytes > {MAX_APPVAR_SIZE} bytes"
+        ))
+        .with_kind(ErrorKind::SizeLimit);
+    }
+
+    let percent = size * 100 / MAX_APPVAR_SIZE;
+
+    if percent >= NEAR_LIMIT_PERCENT {
+        diagnostics.warn(format!(
+            "Font pack is close to the largest AppVar size: \
+             {size} bytes is {percent}% of {MAX_APPVAR_SIZE} bytes"
+        ));
+    }
+
+    Ok(())
+}
+
+/// Loads the glyphs of every font, at most `jobs` at a time.
+/// The results are in the same order as the fonts and their glyphs.
+async fn load_glyphs(
+    fonts: &[(PathBuf, FontDefinition)],
+    search: &SearchPaths,
+    jobs: usize,
+    inputs: &mut Inputs,
+) -> anyhow::Result<Vec<Vec<LoadedGlyph>>> {
+    let semaphore = Arc::new(Semaphore::new(jobs));
+    let mut tasks = JoinSet::new();
+    let mut results = fonts
+        .iter()
+        .map(|(_, font)| font.glyphs.iter().map(|_| None).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+
+    for (font_index, (font_path, font)) in fonts.iter().enumerate() {
+        for (glyph_index, glyph) in font.glyphs.iter().enumerate() {
+            let path = match get_glyph_path(font_path, &glyph.source, search) {
+                Ok(path) => path,
+                Err(error) => {
+                    results[font_index][glyph_index] = Some(Err(error));
+                    continue;
+                }
+            };
+            inputs.insert(&path);
+            let semaphore = semaphore.clone();
+            let font_height = font.height;
+
+            tasks.spawn(async move {
+                let result = match semaphore.acquire_owned().await {
+                    Ok(_permit) => FontGlyphs::load_glyph(&path, font_height).await,
+                    Err(error) => Err(error.into()),
+                };
+
+                (font_index, glyph_index, result)
+            });
+        }
+    }
+
+    while let Some(task) = tasks.join_next().await {
+        let (font_index, glyph_index, result) = task.context("Glyph loading task panicked")?;
+        results[font_index][glyph_index] = Some(result);
+    }
+
+    Ok(results
+        .into_iter()
+        .map(|glyphs| {
+            glyphs
+                .into_iter()
+                .map(|result| result.unwrap_or_else(|| Err(anyhow!("Glyph was never loaded"))))
+                .collect()
+        })
+        .collect())
+}
+
+/// Loads and validates the font pack and all of its fonts, recording every problem found
+async fn load(
+    source: &DefinitionSource,
+    search: &SearchPaths,
+    jobs: usize,
+    problems: &mut Problems,
+    diagnostics: &mut Diagnostics,
+    inputs: &mut Inputs,
+) -> Option<FontPack> {
+    let pack_definition = problems.check(load_pack_definition(source, diagnostics).await)?;
+
+    problems.check(output::get_fonts_length(pack_definition.fonts.len()));
 
     let mut fonts = Vec::with_capacity(pack_definition.fonts.len());
 
     for font_path in &pack_definition.fonts {
-        let font_path = get_font_path(&pack_definition_path, font_path)?;
-        let font = load_font_definition(&font_path).await?;
-        let font_glyphs = FontGlyphs::new(&font_path, &font.glyphs).await?;
-        fonts.push((font, font_glyphs));
+        let Some(font_path) = problems.check(get_font_path(source.anchor(), font_path, search))
+        else {
+            continue;
+        };
+        inputs.insert(&font_path);
+        let Some(font) = problems.check(load_font_definition(&font_path, diagnostics).await) else {
+            continue;
+        };
+
+        validate_font(&font_path, &font, problems);
+        fonts.push((font_path, font));
+    }
+
+    let loaded = problems.check(load_glyphs(&fonts, search, jobs, inputs).await)?;
+    let fonts = fonts
+        .into_iter()
+        .zip(loaded)
+        .map(|((font_path, font), loaded)| {
+            let font_glyphs = FontGlyphs::new(&font_path, &font, loaded, problems, diagnostics);
+            (font, font_glyphs)
+        })
+        .collect();
+
+    Some(FontPack {
+        definition: pack_definition,
+        fonts,
+    })
+}
+
+/// Runs every loading and validation stage of a build without writing anything
+pub async fn check(
+    definition: &Path,
+    search: &SearchPaths,
+    problems: &mut Problems,
+    diagnostics: &mut Diagnostics,
+) {
+    let Some(source) = problems.check(DefinitionSource::new(definition, None)) else {
+        return;
+    };
+    let Some(pack) = load(
+        &source,
+        search,
+        job_count(None),
+        problems,
+        diagnostics,
+        &mut Inputs::default(),
+    )
+    .await
+    else {
+        return;
+    };
+
+    if problems.is_empty() {
+        let budget = pack.definition.budget;
+        let data = output::bin::generate(pack.definition, pack.fonts, diagnostics).await;
+
+        if let Some(data) = problems.check(data) {
+            problems.check(validate_size(data.len(), diagnostics));
+
+            if let Some(budget) = budget {
+                problems.check(BudgetUsage::check(
+                    "Font pack",
+                    data.len(),
+                    budget,
+                    DEFAULT_WARN_PERCENT,
+                    diagnostics,
+                ));
+            }
+        }
+    }
+}
+
+/// Names the font pack from the options, falling back to its family name then its file name
+fn resolve_names(
+    command: &CliFontPackCommand,
+    source: &DefinitionSource,
+    definition: &FontPackDefinition,
+) -> anyhow::Result<AssetNames> {
+    let family_name =
+        Some(definition.metadata.family_name.as_str()).filter(|name| !name.is_empty());
+    let stem = source.stem().map(|stem| stem.to_string_lossy());
+    let base = match (&command.name, family_name, &stem) {
+        (Some(name), _, _) => name.as_str(),
+        (None, Some(family_name), _) => family_name,
+        (None, None, Some(stem)) => stem,
+        (None, None, None) => bail!(
+            "Font pack has no family name or file name to name it after\n\
+             Use `--name` to choose one"
+        ),
+    };
+
+    AssetNames::resolve(
+        base,
+        command.var_name.clone(),
+        command.symbol_prefix.clone(),
+    )
+}
+
+/// Builds the font pack, recording every file read into `inputs`
+pub async fn build(
+    command: CliFontPackCommand,
+    diagnostics: &mut Diagnostics,
+    inputs: &mut Inputs,
+) -> anyhow::Result<()> {
+    let started = Instant::now();
+
+    if !is_stdin(&command.definition) {
+        inputs.insert(&command.definition);
+    }
+
+    let source = DefinitionSource::new(&command.definition, command.base_dir.as_deref())?;
+    let output_path =
+        crate::output::resolve_path(&command.output, source.stem(), command.output_type)?;
+    let cache_path = cache::path(&command.definition, command.cache.as_deref(), &output_path);
+    let search = SearchPaths::from_env(&command.asset_paths);
+    let cache_options = format!(
+        "fontpack {:?} {:?} {:?} {:?} {:?} {:?} {:?} {}",
+        command.output_type,
+        command.base_dir,
+        search,
+        command.name,
+        command.var_name,
+        command.symbol_prefix,
+        command.budget,
+        command.budget_warn
+    );
+
+    // The report needs the layout of the outputs, which isn't cached
+    if let Some(cache_path) = &cache_path
+        && !command.force
+        && command.report_json.is_none()
+        && cache::is_up_to_date(cache_path, &cache_options, diagnostics, inputs)
+            .await
+            .is_some()
+    {
+        diagnostics.check_strict()?;
+        println!("{} is up to date", output_path.display());
+
+        if let Some(depfile) = &command.depfile {
+            depfile::write(depfile, std::slice::from_ref(&output_path), inputs).await?;
+        }
+
+        return Ok(());
     }
 
+    let mut problems = Problems::default();
+    let pack = load(
+        &source,
+        &search,
+        job_count(command.jobs),
+        &mut problems,
+        diagnostics,
+        inputs,
+    )
+    .await;
+    problems.into_result()?;
+    let FontPack { definition, fonts } = pack.context("Font pack failed to load")?;
+    let names = resolve_names(&command, &source, &definition)?;
+    debug!(
+        "Variable name: {}, symbol prefix: {}",
+        names.var_name, names.symbol_prefix
+    );
+
+    crate::output::create_parent(&output_path).await?;
+    info!("Writing font pack to: {output_path:?}");
+
+    let budget = command.budget.or(definition.budget);
+
+    let data = output::bin::generate(definition, fonts, diagnostics).await?;
+    validate_size(data.len(), diagnostics)?;
+    let budget_usage = budget
+        .map(|budget| {
+            BudgetUsage::check(
+                "Font pack",
+                data.len(),
+                budget,
+                command.budget_warn,
+                diagnostics,
+            )
+        })
+        .transpose()?;
+    diagnostics.check_strict()?;
+    let labels = ParsedFontPack::parse(&data)
+        .context("Failed to read back the built font pack")?
+        .labels();
+    let outputs = vec![ReportOutput::new(&output_path, &data, &labels)];
+
     match command.output_type {
-        OutputType::Assembly => todo!(),
-        OutputType::Binary => output::bin::build(&command.output, pack_definition, fonts).await,
-        OutputType::C => todo!(),
+        OutputType::Assembly => output::asm::build(&output_path, &names, &data, &labels).await?,
+        OutputType::Binary => output::bin::build(&output_path, data).await?,
+        OutputType::C => output::c::build(&output_path, &names, &data, &labels).await?,
+    }
+
+    if let Some(depfile) = &command.depfile {
+        depfile::write(depfile, std::slice::from_ref(&output_path), inputs).await?;
+    }
+
+    if let Some(report_path) = &command.report_json {
+        Report::new(started, inputs, outputs, diagnostics, budget_usage)
+            .await?
+            .write(report_path)
+            .await?;
+    }
+
+    if let Some(cache_path) = &cache_path {
+        Cache::new(cache_options, inputs, &[output_path], diagnostics)
+            .await?
+            .write(cache_path)
+            .await?;
     }
+
+    Ok(())
 }
 
 #[cfg(test)]
@@ -180,6 +565,30 @@ mod tests {
         assert!(font_glyphs.glyphs.is_empty());
     }
 
+    #[test]
+    fn font_glyphs_duplicate() {
+        let mut font_glyphs = FontGlyphs::default();
+
+        assert_eq!(font_glyphs.insert(b'a', 6, vec![1, 2, 3]), None);
+        assert_eq!(
+            font_glyphs.insert(b'a', 7, vec![4, 5, 6]),
+            Some((vec![1, 2, 3], 6))
+        );
+    }
+
+    #[test]
+    fn validate_size_near_limit() {
+        let mut diagnostics = Diagnostics::default();
+
+        assert!(validate_size(1000, &mut diagnostics).is_ok());
+        assert!(diagnostics.warnings().is_empty());
+
+        assert!(validate_size(MAX_APPVAR_SIZE, &mut diagnostics).is_ok());
+        assert_eq!(diagnostics.warnings().len(), 1);
+
+        assert!(validate_size(MAX_APPVAR_SIZE + 1, &mut diagnostics).is_err());
+    }
+
     #[test]
     fn pixels_to_bytes_6() {
         let bytes = FontGlyphs::pixels_to_bytes(
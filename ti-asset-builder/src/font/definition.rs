@@ -1,27 +1,35 @@
 /// Doc comments adapted from [CE-Toolchain](https://ce-programming.github.io/toolchain/libraries/fontlibc.html)
-use std::path::PathBuf;
+use std::{num::NonZeroUsize, path::PathBuf};
 
 use ascii::AsciiChar;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use serseg::bitfield::BitfieldBuilder;
 
 const DEFAULT_CODE_PAGE: &str = "ASCII";
 
 // TODO: Check if there's a better way to wrap TOML structs
 /// Wraps the definition so there's no root fields
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct FontPackDefinitionWrapper {
     pub pack: FontPackDefinition,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct FontPackDefinition {
     pub metadata: FontPackMetadata,
     /// Relative paths, from the font pack definition, to each font definition without the `.toml`
     /// extension.
     pub fonts: Vec<PathBuf>,
+    /// The most bytes the built font pack may use.
+    pub budget: Option<NonZeroUsize>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+impl FontPackDefinition {
+    /// Every field in the `[pack]` table
+    pub const FIELDS: &[&str] = &["metadata", "fonts", "budget"];
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(default)]
 pub struct FontPackMetadata {
     /// A **short**, human-readable typeface name, such as "Times".
@@ -54,16 +62,17 @@ impl Default for FontPackMetadata {
 
 // TODO: Check if there's a better way to wrap TOML structs
 /// Wraps the definition so there's no root fields
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct FontDefinitionWrapper {
     pub font: FontDefinition,
 }
 
-#[derive(Debug, Clone, Deserialize, Default)]
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
 #[serde(default)]
 pub struct FontDefinition {
     /// Currently, only zero is accepted with fontlibc.
-    pub version: u8,
+    /// Called `version` before schema 2.
+    pub fontlibc_version: u8,
     /// Height in pixels not including space above/below.
     pub height: u8,
     /// Specifies how much to move the cursor left after each glyph.
@@ -90,7 +99,24 @@ pub struct FontDefinition {
     pub glyphs: Vec<FontGlyph>,
 }
 
-#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+impl FontDefinition {
+    /// Every field in the `[font]` table
+    pub const FIELDS: &[&str] = &[
+        "fontlibc_version",
+        "height",
+        "italic_space_adjust",
+        "space_above",
+        "space_below",
+        "weight",
+        "style",
+        "cap_height",
+        "x_height",
+        "baseline_height",
+        "glyphs",
+    ];
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 #[repr(u8)]
 pub enum FontWeight {
@@ -112,7 +138,7 @@ impl From<FontWeight> for u8 {
     }
 }
 
-#[derive(Debug, Clone, Copy, Deserialize, Default, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, Default, PartialEq, Eq)]
 #[serde(default)]
 pub struct FontStyle {
     /// Clear = sans-serif font.
@@ -126,31 +152,17 @@ pub struct FontStyle {
     pub monospaced: bool,
 }
 
-impl From<FontStyle> for u8 {
-    fn from(value: FontStyle) -> Self {
-        let mut output = 0;
-
-        if value.serif {
-            output |= 0b0000_0001;
-        }
-
-        if value.oblique {
-            output |= 0b0000_0010;
-        }
-
-        if value.italic {
-            output |= 0b0000_0100;
-        }
-
-        if value.monospaced {
-            output |= 0b0000_1000;
-        }
-
-        output
+impl FontStyle {
+    /// Packs the style into fontlibc's style byte, serif first
+    pub fn bits(self, bits: BitfieldBuilder) -> BitfieldBuilder {
+        bits.flag(self.serif)
+            .flag(self.oblique)
+            .flag(self.italic)
+            .flag(self.monospaced)
     }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct FontGlyph {
     pub index: GlyphIndex,
     /// A path relative from the font definition to the glyph's PNG without the `.png` extension.
@@ -158,7 +170,7 @@ pub struct FontGlyph {
 }
 
 /// Where a glyph is mapped in the code page.
-#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
 #[serde(untagged)]
 pub enum GlyphIndex {
     Number(u8),
@@ -0,0 +1,335 @@
+use std::{fmt::Display, ops::Range};
+
+use anyhow::{Context, anyhow};
+
+use crate::{
+    cli::CliFontPackDiffCommand,
+    error::{ErrorKind, ResultExt},
+    font::{
+        inspect::{format_glyph_index, format_string},
+        parse::{ParsedFont, ParsedFontPack, ParsedMetadata},
+    },
+};
+
+/// The differences within one part of the font packs
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Section {
+    name: String,
+    changes: Vec<String>,
+}
+
+/// Records the change if the values differ
+fn compare<T: PartialEq + Display>(changes: &mut Vec<String>, name: &str, old: T, new: T) {
+    if old != new {
+        changes.push(format!("{name}: {old} -> {new}"));
+    }
+}
+
+fn diff_metadata(old: Option<&ParsedMetadata>, new: Option<&ParsedMetadata>) -> Vec<String> {
+    let (old, new) = match (old, new) {
+        (None, None) => return Vec::new(),
+        (Some(_), None) => return vec!["removed".to_string()],
+        (None, Some(_)) => return vec!["added".to_string()],
+        (Some(old), Some(new)) => (old, new),
+    };
+
+    old.fields()
+        .into_iter()
+        .zip(new.fields())
+        .filter(|((_, old), (_, new))| old != new)
+        .map(|((name, old), (_, new))| {
+            format!("{name}: {} -> {}", format_string(old), format_string(new))
+        })
+        .collect()
+}
+
+/// Compares every header field, then every glyph in either font's range
+fn diff_font(old: &ParsedFont, new: &ParsedFont) -> Vec<String> {
+    let mut changes = Vec::new();
+
+    compare(&mut changes, "version", old.version, new.version);
+    compare(&mut changes, "height", old.height, new.height);
+    compare(
+        &mut changes,
+        "first glyph",
+        format_glyph_index(old.first_glyph),
+        format_glyph_index(new.first_glyph),
+    );
+    compare(
+        &mut changes,
+        "last glyph",
+        format_glyph_index(old.last_glyph()),
+        format_glyph_index(new.last_glyph()),
+    );
+    compare(
+        &mut changes,
+        "italic space adjust",
+        old.italic_space_adjust,
+        new.italic_space_adjust,
+    );
+    compare(
+        &mut changes,
+        "space above",
+        old.space_above,
+        new.space_above,
+    );
+    compare(
+        &mut changes,
+        "space below",
+        old.space_below,
+        new.space_below,
+    );
+    compare(&mut changes, "weight", old.weight, new.weight);
+    compare(&mut changes, "style", old.style, new.style);
+    compare(&mut changes, "cap height", old.cap_height, new.cap_height);
+    compare(&mut changes, "x height", old.x_height, new.x_height);
+    compare(
+        &mut changes,
+        "baseline height",
+        old.baseline_height,
+        new.baseline_height,
+    );
+
+    let first = old.first_glyph.min(new.first_glyph);
+    let last = old.last_glyph().max(new.last_glyph());
+
+    for index in first..=last {
+        let name = format!("glyph {}", format_glyph_index(index));
+
+        match (old.glyph(index), new.glyph(index)) {
+            (None, None) => (),
+            (Some(_), None) => changes.push(format!("{name}: removed")),
+            (None, Some(_)) => changes.push(format!("{name}: added")),
+            (Some(old), Some(new)) => {
+                compare(&mut changes, &format!("{name} width"), old.width, new.width);
+
+                if old.width == new.width && old.bitmap != new.bitmap {
+                    changes.push(format!("{name}: bitmap differs"));
+                }
+            }
+        }
+    }
+
+    changes
+}
+
+/// Compares the parsed structure of the font packs, grouped by metadata and font
+fn diff_packs(old: &ParsedFontPack, new: &ParsedFontPack) -> Vec<Section> {
+    let mut sections = vec![Section {
+        name: "Metadata".to_string(),
+        changes: diff_metadata(old.metadata.as_ref(), new.metadata.as_ref()),
+    }];
+
+    for index in 0..old.fonts.len().max(new.fonts.len()) {
+        let changes = match (old.fonts.get(index), new.fonts.get(index)) {
+            (Some(old), Some(new)) => diff_font(old, new),
+            (Some(_), None) => vec!["removed".to_string()],
+            (None, Some(_)) => vec!["added".to_string()],
+            (None, None) => unreachable!("The index is within one of the packs"),
+        };
+
+        sections.push(Section {
+            name: format!("Font {index}"),
+            changes,
+        });
+    }
+
+    sections.retain(|section| !section.changes.is_empty());
+    sections
+}
+
+/// The ranges of bytes that differ, including bytes past the end of the shorter data
+fn diff_bytes(old: &[u8], new: &[u8]) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+
+    for offset in 0..old.len().max(new.len()) {
+        if old.get(offset) == new.get(offset) {
+            continue;
+        }
+
+        match ranges.last_mut() {
+            Some(range) if range.end == offset => range.end += 1,
+            _ => ranges.push(offset..offset + 1),
+        }
+    }
+
+    ranges
+}
+
+/// The differing bytes, for when the structure is unknown or identical
+fn byte_section(old: &[u8], new: &[u8], reason: &str) -> Option<Section> {
+    let mut changes = diff_bytes(old, new)
+        .into_iter()
+        .map(|range| format!("{:#08X}..{:#08X} differ", range.start, range.end))
+        .collect::<Vec<_>>();
+
+    if changes.is_empty() {
+        return None;
+    }
+
+    changes.insert(0, reason.to_string());
+    compare(&mut changes, "size", old.len(), new.len());
+
+    Some(Section {
+        name: "Bytes".to_string(),
+        changes,
+    })
+}
+
+/// Every difference between the font packs.
+/// Packs that fail to parse are compared byte by byte instead.
+fn diff(old: &[u8], new: &[u8]) -> Vec<Section> {
+    let (old_pack, new_pack) = match (ParsedFontPack::parse(old), ParsedFontPack::parse(new)) {
+        (Ok(old_pack), Ok(new_pack)) => (old_pack, new_pack),
+        (Err(error), _) | (_, Err(error)) => {
+            let reason = format!("Comparing bytes since parsing failed: {error:#}");
+            return byte_section(old, new, &reason).into_iter().collect();
+        }
+    };
+
+    let sections = diff_packs(&old_pack, &new_pack);
+
+    // Anything not parsed, like padding, may still differ
+    if sections.is_empty() {
+        return byte_section(old, new, "Structure is identical, but the layout differs")
+            .into_iter()
+            .collect();
+    }
+
+    sections
+}
+
+fn format_sections(sections: &[Section]) -> String {
+    sections
+        .iter()
+        .map(|section| {
+            let changes = section
+                .changes
+                .iter()
+                .map(|change| format!("  {change}\n"))
+                .collect::<String>();
+
+            format!("{}:\n{changes}", section.name)
+        })
+        .collect()
+}
+
+pub async fn diff_files(command: CliFontPackDiffCommand) -> anyhow::Result<()> {
+    let read = async |path| {
+        tokio::fs::read(path)
+            .await
+            .with_context(|| format!("Failed to read font pack at {path:?}"))
+    };
+    let old = read(&command.old).await?;
+    let new = read(&command.new).await?;
+
+    let sections = diff(&old, &new);
+
+    if sections.is_empty() {
+        println!("Font packs are identical");
+        return Ok(());
+    }
+
+    print!("{}", format_sections(&sections));
+
+    if command.exit_code {
+        return Err(anyhow!("Font packs differ")).with_kind(ErrorKind::Different);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font::parse::ParsedGlyph;
+
+    fn glyph(width: u8, bitmap: &[u8]) -> Option<ParsedGlyph> {
+        Some(ParsedGlyph {
+            offset: 0,
+            width,
+            bitmap: bitmap.to_vec(),
+        })
+    }
+
+    fn font(glyphs: Vec<Option<ParsedGlyph>>) -> ParsedFont {
+        ParsedFont {
+            offset: 0,
+            version: 0,
+            height: 1,
+            glyph_count: glyphs.len() as u8,
+            first_glyph: b'a',
+            widths_offset: 0,
+            bitmaps_offset: 0,
+            italic_space_adjust: 0,
+            space_above: 0,
+            space_below: 0,
+            weight: 0x80,
+            style: 0,
+            cap_height: 0,
+            x_height: 0,
+            baseline_height: 0,
+            widths: glyphs.iter().flatten().map(|glyph| glyph.width).collect(),
+            glyphs,
+        }
+    }
+
+    #[test]
+    fn diff_glyphs() {
+        let old = font(vec![glyph(3, &[1]), glyph(3, &[2]), None, glyph(1, &[0])]);
+        let mut new = font(vec![glyph(3, &[1]), glyph(3, &[4]), glyph(2, &[0]), None]);
+        new.height = 2;
+
+        assert_eq!(
+            diff_font(&old, &new),
+            [
+                "height: 1 -> 2",
+                "glyph 0x62 ('b'): bitmap differs",
+                "glyph 0x63 ('c'): added",
+                "glyph 0x64 ('d'): removed",
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_pack_sections() {
+        let metadata = ParsedMetadata {
+            description: Some("Old".to_string()),
+            ..Default::default()
+        };
+        let old = ParsedFontPack {
+            metadata: Some(metadata.clone()),
+            fonts: vec![font(vec![glyph(3, &[1])])],
+        };
+        let new = ParsedFontPack {
+            metadata: Some(ParsedMetadata {
+                description: Some("New".to_string()),
+                ..metadata
+            }),
+            fonts: vec![font(vec![glyph(3, &[1])]), font(Vec::new())],
+        };
+
+        assert_eq!(
+            format_sections(&diff_packs(&old, &new)),
+            "Metadata:\n  description: \"Old\" -> \"New\"\nFont 1:\n  added\n"
+        );
+        assert!(diff_packs(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn diff_bytes_ranges() {
+        assert_eq!(diff_bytes(b"abcdef", b"abXYeZ"), [2..4, 5..6]);
+        assert_eq!(diff_bytes(b"ab", b"abcd").pop(), Some(2..4));
+        assert!(diff_bytes(b"ab", b"ab").is_empty());
+    }
+
+    // Data that isn't a font pack is still compared
+    #[test]
+    fn diff_unparseable() {
+        let sections = diff(b"abc", b"abd");
+
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections[0].name, "Bytes");
+        assert_eq!(sections[0].changes[1], "0x000002..0x000003 differ");
+    }
+}
@@ -0,0 +1,202 @@
+use std::fmt::Write;
+
+use anyhow::{Context, bail};
+
+use crate::{
+    cli::CliFontPackInspectCommand,
+    font::parse::{ParsedFont, ParsedFontPack, ParsedGlyph},
+};
+
+/// Parses a glyph index from either a number or a single character
+pub fn parse_glyph_index(raw: &str) -> Result<u8, String> {
+    let mut chars = raw.chars();
+
+    match (chars.next(), chars.next()) {
+        (Some(char), None) if !char.is_ascii_digit() => u8::try_from(char)
+            .map_err(|_| format!("Character {char:?} is outside of the range of a glyph")),
+        _ => parse_number(raw)
+            .ok_or_else(|| format!("Expected a glyph number or a single character: {raw:?}")),
+    }
+}
+
+fn parse_number(raw: &str) -> Option<u8> {
+    match raw.strip_prefix("0x") {
+        Some(hex) => u8::from_str_radix(hex, 16).ok(),
+        None => raw.parse().ok(),
+    }
+}
+
+pub fn format_string(string: Option<&str>) -> String {
+    match string {
+        Some(string) => format!("{string:?}"),
+        None => "(none)".to_string(),
+    }
+}
+
+pub fn format_glyph_index(index: u8) -> String {
+    if index.is_ascii_graphic() {
+        format!("{index:#04X} ({:?})", index as char)
+    } else {
+        format!("{index:#04X}")
+    }
+}
+
+fn format_font(output: &mut String, font_index: usize, font: &ParsedFont) -> std::fmt::Result {
+    let set_glyphs = font.glyphs.iter().flatten().collect::<Vec<_>>();
+    let set_widths = set_glyphs.iter().map(|glyph| glyph.width);
+
+    writeln!(output, "Font {font_index} @ {:#08X}", font.offset)?;
+    writeln!(output, "  version: {}", font.version)?;
+    writeln!(output, "  height: {}", font.height)?;
+    writeln!(
+        output,
+        "  glyphs: {} .. {} ({} total, {} set)",
+        format_glyph_index(font.first_glyph),
+        format_glyph_index(font.last_glyph()),
+        font.total_glyphs(),
+        set_glyphs.len()
+    )?;
+
+    match (set_widths.clone().min(), set_widths.max()) {
+        (Some(min), Some(max)) => writeln!(output, "  widths: {min} .. {max}")?,
+        _ => writeln!(output, "  widths: (none)")?,
+    }
+
+    writeln!(
+        output,
+        "  widths table: {:#08X}",
+        font.offset + font.widths_offset
+    )?;
+    writeln!(
+        output,
+        "  bitmap table: {:#08X}",
+        font.offset + font.bitmaps_offset
+    )?;
+    writeln!(
+        output,
+        "  italic space adjust: {}",
+        font.italic_space_adjust
+    )?;
+    writeln!(output, "  space above: {}", font.space_above)?;
+    writeln!(output, "  space below: {}", font.space_below)?;
+    writeln!(output, "  weight: {:#04X}", font.weight)?;
+    writeln!(output, "  style: {:#010b}", font.style)?;
+    writeln!(output, "  cap height: {}", font.cap_height)?;
+    writeln!(output, "  x height: {}", font.x_height)?;
+    writeln!(output, "  baseline height: {}", font.baseline_height)
+}
+
+/// Formats the structure of the font pack as a readable tree
+pub fn format_pack(pack: &ParsedFontPack) -> String {
+    let mut output = String::new();
+    write_pack(&mut output, pack).expect("Writing to a string can't fail");
+    output
+}
+
+fn write_pack(output: &mut String, pack: &ParsedFontPack) -> std::fmt::Result {
+    match &pack.metadata {
+        Some(metadata) => {
+            writeln!(output, "Metadata @ {:#08X}", metadata.offset)?;
+            writeln!(output, "  size: {}", metadata.size)?;
+
+            for (name, value) in metadata.fields() {
+                writeln!(output, "  {name}: {}", format_string(value))?;
+            }
+        }
+        None => writeln!(output, "Metadata: (none)")?,
+    }
+
+    writeln!(output, "Fonts: {}", pack.fonts.len())?;
+
+    for (font_index, font) in pack.fonts.iter().enumerate() {
+        format_font(output, font_index, font)?;
+    }
+
+    Ok(())
+}
+
+/// Draws the glyph's bitmap with `#` for set pixels and `.` for unset pixels
+pub fn format_glyph(glyph: &ParsedGlyph) -> String {
+    let row_size = glyph.row_size();
+
+    if row_size == 0 {
+        return String::new();
+    }
+
+    glyph
+        .bitmap
+        .chunks(row_size)
+        .map(|row| {
+            (0..glyph.width as usize)
+                .map(|x| {
+                    let byte = row[x / u8::BITS as usize];
+                    let mask = 0b1000_0000 >> (x % u8::BITS as usize);
+
+                    if byte & mask != 0 { '#' } else { '.' }
+                })
+                .chain(['\n'])
+                .collect::<String>()
+        })
+        .collect()
+}
+
+pub async fn inspect(command: CliFontPackInspectCommand) -> anyhow::Result<()> {
+    let data = tokio::fs::read(&command.file)
+        .await
+        .with_context(|| format!("Failed to read font pack at {:?}", command.file))?;
+    let pack = ParsedFontPack::parse(&data)
+        .with_context(|| format!("Failed to parse font pack at {:?}", command.file))?;
+
+    let Some(index) = command.glyph else {
+        print!("{}", format_pack(&pack));
+        return Ok(());
+    };
+
+    let mut found = false;
+
+    for (font_index, font) in pack.fonts.iter().enumerate() {
+        if let Some(glyph) = font.glyph(index) {
+            found = true;
+            println!(
+                "Font {font_index}, glyph {} @ {:#08X} ({}x{})",
+                format_glyph_index(index),
+                glyph.offset,
+                glyph.width,
+                font.height
+            );
+            print!("{}", format_glyph(glyph));
+        }
+    }
+
+    if !found {
+        bail!("Glyph {} isn't set in any font", format_glyph_index(index));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glyph_index() {
+        assert_eq!(parse_glyph_index("a"), Ok(b'a'));
+        assert_eq!(parse_glyph_index("7"), Ok(7));
+        assert_eq!(parse_glyph_index("97"), Ok(b'a'));
+        assert_eq!(parse_glyph_index("0x61"), Ok(b'a'));
+        assert!(parse_glyph_index("256").is_err());
+        assert!(parse_glyph_index("ab").is_err());
+    }
+
+    #[test]
+    fn glyph_ascii_art() {
+        let glyph = ParsedGlyph {
+            offset: 0,
+            width: 3,
+            bitmap: vec![0b1010_0000, 0b0100_0000],
+        };
+
+        assert_eq!(format_glyph(&glyph), "#.#\n.#.\n");
+    }
+}
@@ -0,0 +1,24 @@
+use std::path::Path;
+
+use anyhow::Context;
+
+use crate::{
+    error::{ErrorKind, ResultExt},
+    output::{
+        AssetNames,
+        source::{self, Label},
+    },
+};
+
+/// Writes the built font pack as assembly, named by the asset's names
+pub async fn build(
+    output: &Path,
+    names: &AssetNames,
+    data: &[u8],
+    labels: &[Label],
+) -> anyhow::Result<()> {
+    tokio::fs::write(output, source::to_asm(names, data, labels))
+        .await
+        .with_context(|| format!("Failed to write output font file: {output:?}"))
+        .with_kind(ErrorKind::Io)
+}
@@ -1,108 +1,122 @@
 use std::path::Path;
 
 use anyhow::Context;
-use log::debug;
+use log::{Level, debug, log_enabled, trace, warn};
 use serseg::prelude::*;
 
-use crate::font::{
-    FontGlyphs,
-    definition::{FontDefinition, FontPackDefinition},
-    output::FONT_PACK_HEADER,
+use crate::{
+    diagnostics::Diagnostics,
+    error::{ErrorKind, ResultExt},
+    font::{
+        FontGlyphs,
+        definition::{FontDefinition, FontPackDefinition},
+        output::FONT_PACK_HEADER,
+    },
 };
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 enum SectorId {
     Header,
     Metadata,
-    MetadataEnd,
     MetadataStrings,
-    FontHeader(usize),
-    FontGlyphWidths(usize),
-    FontGlyphBitmaps(usize),
-    FontGlyphBitmap(usize, u8),
+    Font(usize, FontSectorId),
+}
+
+/// The sectors of a single font, nested in the pack
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum FontSectorId {
+    Header,
+    GlyphWidths,
+    GlyphBitmaps,
+    GlyphBitmap(u8),
 }
 
 type SectorBuilder = SerialSectorBuilder<SectorId>;
 type Builder = SerialBuilder<SectorId>;
+type FontSectorBuilder = SerialSectorBuilder<FontSectorId>;
+type FontBuilder = SerialBuilder<FontSectorId>;
 
-fn add_font_sectors(
-    mut builder: Builder,
+fn font_builder(
     font: FontDefinition,
     font_index: usize,
     mut font_glyphs: FontGlyphs,
-) -> anyhow::Result<Builder> {
-    let mut widths_builder = SectorBuilder::default();
-    let mut bitmap_table_builder = SectorBuilder::default();
+    diagnostics: &mut Diagnostics,
+) -> anyhow::Result<FontBuilder> {
     let first_glyph = font_glyphs.first_glyph;
-    let glyph_count = font_glyphs.glyph_count();
-    let mut glyph_bitmaps = Vec::with_capacity(font_glyphs.glyphs.len());
+    let mut builder = FontBuilder::default().sector(
+        FontSectorId::Header,
+        FontSectorBuilder::default()
+            .u8(font.fontlibc_version)
+            .u8(font.height)
+            .u8(font_glyphs.glyph_count())
+            .u8(first_glyph)
+            .dynamic_u24(FontSectorId::Header, FontSectorId::GlyphWidths, 0)
+            .dynamic_u24(FontSectorId::Header, FontSectorId::GlyphBitmaps, 0)
+            .u8(font.italic_space_adjust)
+            .u8(font.space_above)
+            .u8(font.space_below)
+            .u8(font.weight.map(u8::from).unwrap_or_default())
+            .bits_u8(BitOrder::LsbFirst, |bits| font.style.bits(bits))?
+            .u8(font.cap_height)
+            .u8(font.x_height)
+            .u8(font.baseline_height),
+    );
+    let mut widths = Vec::new();
+    let mut bitmap_table_builder = FontSectorBuilder::default();
+    let mut unset_glyphs = Vec::new();
 
     for glyph_index in first_glyph..=font_glyphs.last_glyph {
         if let Some((glyph_bitmap, glyph_width)) = font_glyphs.glyphs.remove(&glyph_index) {
-            widths_builder = widths_builder.u8(glyph_width);
+            widths.push(glyph_width);
             bitmap_table_builder = bitmap_table_builder.dynamic_u16(
-                SectorId::FontHeader(font_index),
-                SectorId::FontGlyphBitmap(font_index, glyph_index),
+                FontSectorId::Header,
+                FontSectorId::GlyphBitmap(glyph_index),
                 0,
             );
-            glyph_bitmaps.push((glyph_bitmap, glyph_index));
+            builder.add_sector(
+                FontSectorId::GlyphBitmap(glyph_index),
+                FontSectorBuilder::default().bytes(glyph_bitmap),
+            );
         } else {
-            debug!("Glyph {glyph_index} of font {font_index} is unset and will be defaulted.");
-            widths_builder = widths_builder.u8(0);
+            unset_glyphs.push(glyph_index.to_string());
+            widths.push(0);
             // TODO: Add default glyphs
             bitmap_table_builder = bitmap_table_builder.null_16();
         }
     }
 
-    builder = builder
-        .sector(
-            SectorId::FontHeader(font_index),
-            SectorBuilder::default()
-                .u8(font.version)
-                .u8(font.height)
-                .u8(glyph_count)
-                .u8(first_glyph)
-                .dynamic_u24(
-                    SectorId::FontHeader(font_index),
-                    SectorId::FontGlyphWidths(font_index),
-                    0,
-                )
-                .dynamic_u24(
-                    SectorId::FontHeader(font_index),
-                    SectorId::FontGlyphBitmaps(font_index),
-                    0,
-                )
-                .u8(font.italic_space_adjust)
-                .u8(font.space_above)
-                .u8(font.space_below)
-                .u8(font.weight.map(u8::from).unwrap_or_default())
-                .u8(font.style)
-                .u8(font.cap_height)
-                .u8(font.x_height)
-                .u8(font.baseline_height),
-        )
-        .sector(SectorId::FontGlyphWidths(font_index), widths_builder)
-        .sector(SectorId::FontGlyphBitmaps(font_index), bitmap_table_builder);
-
-    for (glyph_bitmap, glyph_index) in glyph_bitmaps {
-        builder = builder.sector(
-            SectorId::FontGlyphBitmap(font_index, glyph_index),
-            SectorBuilder::default().bytes(glyph_bitmap),
-        );
+    if !unset_glyphs.is_empty() {
+        diagnostics.warn(format!(
+            "Font {font_index} has unset glyphs that will be defaulted: {}",
+            unset_glyphs.join(", ")
+        ));
     }
 
-    Ok(builder)
+    // The tables are only complete once every glyph is added, but go before the bitmaps
+    builder
+        .sector_after(
+            &FontSectorId::Header,
+            FontSectorId::GlyphWidths,
+            FontSectorBuilder::default().u8_array(widths),
+        )?
+        .sector_after(
+            &FontSectorId::GlyphWidths,
+            FontSectorId::GlyphBitmaps,
+            bitmap_table_builder,
+        )
 }
 
 fn generate_serial_builder(
     pack: FontPackDefinition,
     fonts: Vec<(FontDefinition, FontGlyphs)>,
+    diagnostics: &mut Diagnostics,
 ) -> anyhow::Result<Builder> {
     // Pack metadata
     let mut metadata_builder =
-        SectorBuilder::default().dynamic_u24(SectorId::Metadata, SectorId::MetadataEnd, 0);
+        SectorBuilder::default().span_u24(SectorId::Metadata, SectorId::MetadataStrings);
 
-    let mut metadata_string_builder = SectorBuilder::default();
+    // fontlibc reads these through the calculator's code page
+    let mut metadata_string_builder = SectorBuilder::default().ascii_only();
 
     let mut string_index = 0;
 
@@ -116,22 +130,21 @@ fn generate_serial_builder(
         metadata.code_page,
     ];
 
-    // Add each optional string's pointer and data. If the string is `None`, null will be written.
+    // Add each optional string's pointer and data. If the string is empty, null will be written.
     for text in strings {
-        if text.is_empty() {
-            metadata_builder = metadata_builder.null_24();
-        } else {
-            metadata_builder = metadata_builder.dynamic_u24(
-                SectorId::Header,
-                SectorId::MetadataStrings,
-                string_index,
-            );
-            metadata_string_builder = metadata_string_builder.string(text);
-            string_index += 1;
-        }
+        let text = (!text.is_empty()).then_some(text);
+
+        metadata_builder = metadata_builder.dynamic_u24_or_null(
+            SectorId::Header,
+            text.as_ref().map(|_| SectorId::MetadataStrings),
+            string_index,
+        );
+        string_index += usize::from(text.is_some());
+        metadata_string_builder = metadata_string_builder.maybe_string(text);
     }
 
-    let fonts_length = super::get_fonts_length(fonts.len())?;
+    // Checked here, but written by counting the font sectors, so it can't drift from them
+    super::get_fonts_length(fonts.len())?;
 
     // Pack header
     let mut header_builder = SectorBuilder::default().bytes(*FONT_PACK_HEADER);
@@ -143,11 +156,16 @@ fn generate_serial_builder(
         header_builder.dynamic_u24(SectorId::Header, SectorId::Metadata, 0)
     };
 
-    header_builder = header_builder.u8(fonts_length);
+    header_builder =
+        header_builder.count_u8(|id| matches!(id, SectorId::Font(_, FontSectorId::Header)));
 
     // Points to all the fonts in the pack
     for (i, _) in fonts.iter().enumerate() {
-        header_builder = header_builder.dynamic_u24(SectorId::Header, SectorId::FontHeader(i), 0);
+        header_builder = header_builder.dynamic_u24(
+            SectorId::Header,
+            SectorId::Font(i, FontSectorId::Header),
+            0,
+        );
     }
 
     let mut builder = Builder::default().sector(SectorId::Header, header_builder);
@@ -155,40 +173,58 @@ fn generate_serial_builder(
     if string_index != 0 {
         builder = builder
             .sector(SectorId::Metadata, metadata_builder)
-            .sector_default(SectorId::MetadataEnd)
             .sector(SectorId::MetadataStrings, metadata_string_builder);
     }
 
     // Add each font
     for (font_index, (font, font_glyphs)) in fonts.into_iter().enumerate() {
-        builder = add_font_sectors(builder, font, font_index, font_glyphs)?;
+        builder = builder.sector_nested(
+            |id| SectorId::Font(font_index, id),
+            font_builder(font, font_index, font_glyphs, diagnostics)?,
+        )?;
     }
 
-    debug!("{builder:?}");
-
     Ok(builder)
 }
 
-pub async fn build(
-    output: &Path,
+/// Serializes the font pack
+pub async fn generate(
     pack: FontPackDefinition,
     fonts: Vec<(FontDefinition, FontGlyphs)>,
-) -> anyhow::Result<()> {
-    let file = tokio::fs::File::create(output)
-        .await
-        .with_context(|| format!("Failed to open output font file: {output:?}"))?;
-    let mut buffer = tokio::io::BufWriter::new(file);
-    generate_serial_builder(pack, fonts)?
-        .build(&mut buffer)
-        .await?;
+    diagnostics: &mut Diagnostics,
+) -> anyhow::Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+    let builder = generate_serial_builder(pack, fonts, diagnostics)?;
+
+    // The layout is only a diagnostic, so failing to describe it shouldn't fail the build
+    if log_enabled!(Level::Trace) {
+        match builder.describe_async().await {
+            Ok(layout) => trace!("Font pack layout:\n{layout}"),
+            Err(error) => warn!("Failed to describe the font pack layout: {error:#}"),
+        }
+    }
+
+    let report = builder.build_with_report(&mut buffer).await?;
 
-    Ok(())
+    for sector in report.sectors {
+        debug!(
+            "Font pack sector {:?}: {} bytes at {:#08X}",
+            sector.key, sector.size, sector.start
+        );
+    }
+
+    Ok(buffer)
+}
+
+pub async fn build(output: &Path, data: Vec<u8>) -> anyhow::Result<()> {
+    tokio::fs::write(output, data)
+        .await
+        .with_context(|| format!("Failed to write output font file: {output:?}"))
+        .with_kind(ErrorKind::Io)
 }
 
 #[cfg(test)]
 mod tests {
-    use std::io::Cursor;
-
     use crate::font::definition::{FontPackMetadata, FontStyle, FontWeight};
 
     use super::*;
@@ -203,10 +239,11 @@ mod tests {
                 ..Default::default()
             },
             fonts: vec!["test".into()],
+            budget: None,
         };
 
         let font = FontDefinition {
-            version: 0,
+            fontlibc_version: 0,
             height: 6,
             // This is only used to load `FontGlyphs`
             // We can skip this
@@ -230,12 +267,9 @@ mod tests {
         font_glyphs.insert(b'a', 3, vec![0, 1, 2, 3, 4, 5]);
         font_glyphs.insert(b'c', 8, vec![255, 255, 255, 255, 255, 255]);
 
-        let mut buffer = Cursor::new(Vec::new());
-        generate_serial_builder(pack, vec![(font, font_glyphs)])
-            .unwrap()
-            .build(&mut buffer)
-            .await
-            .unwrap();
+        let mut diagnostics = Diagnostics::default();
+        let builder =
+            generate_serial_builder(pack, vec![(font, font_glyphs)], &mut diagnostics).unwrap();
 
         let expected = [
             b"FONTPACK".iter(),
@@ -303,12 +337,10 @@ mod tests {
         .copied()
         .collect::<Vec<_>>();
 
+        serseg::assert_builds_to!(builder, expected);
         assert_eq!(
-            buffer.get_ref().clone(),
-            expected,
-            "Generated:\n{}\n\nExpected:\n{}",
-            buffer.get_ref().escape_ascii(),
-            expected.escape_ascii()
+            diagnostics.warnings(),
+            ["Font 0 has unset glyphs that will be defaulted: 98"]
         );
     }
 }
@@ -0,0 +1,425 @@
+use anyhow::{Context, bail};
+
+use crate::{font::output::FONT_PACK_HEADER, output::source::Label};
+
+/// The size of a font's header in bytes
+const FONT_HEADER_SIZE: usize = 18;
+/// The number of string pointers in the metadata
+const METADATA_STRINGS: usize = 6;
+
+/// Reads values from a built font pack, erroring with the offset of any out of bounds read
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&self, offset: usize, length: usize) -> anyhow::Result<&'a [u8]> {
+        offset
+            .checked_add(length)
+            .and_then(|end| self.data.get(offset..end))
+            .with_context(|| {
+                format!(
+                    "Unexpected end of file at offset {offset:#08X}: \
+                     needed {length} bytes, file is {} bytes",
+                    self.data.len()
+                )
+            })
+    }
+
+    fn u8(&self, offset: usize) -> anyhow::Result<u8> {
+        Ok(self.bytes(offset, 1)?[0])
+    }
+
+    fn u16(&self, offset: usize) -> anyhow::Result<u16> {
+        let bytes = self.bytes(offset, 2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u24(&self, offset: usize) -> anyhow::Result<usize> {
+        let bytes = self.bytes(offset, 3)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], 0]) as usize)
+    }
+
+    /// Reads a null terminated string
+    fn string(&self, offset: usize) -> anyhow::Result<String> {
+        let remaining = self
+            .bytes(offset, 0)
+            .and_then(|_| self.data.get(offset..).context("String is out of bounds"))?;
+        let length = remaining
+            .iter()
+            .position(|&byte| byte == 0)
+            .with_context(|| {
+                format!("String at offset {offset:#08X} is missing a null terminator")
+            })?;
+
+        Ok(String::from_utf8_lossy(&remaining[..length]).into_owned())
+    }
+}
+
+/// The metadata strings of a font pack
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParsedMetadata {
+    pub offset: usize,
+    /// The declared size of the metadata in bytes
+    pub size: usize,
+    pub family_name: Option<String>,
+    pub author: Option<String>,
+    pub pseudocopyright: Option<String>,
+    pub description: Option<String>,
+    pub version: Option<String>,
+    pub code_page: Option<String>,
+}
+
+impl ParsedMetadata {
+    /// Each field's name and value
+    pub fn fields(&self) -> [(&'static str, Option<&str>); METADATA_STRINGS] {
+        [
+            ("family_name", self.family_name.as_deref()),
+            ("author", self.author.as_deref()),
+            ("pseudocopyright", self.pseudocopyright.as_deref()),
+            ("description", self.description.as_deref()),
+            ("version", self.version.as_deref()),
+            ("code_page", self.code_page.as_deref()),
+        ]
+    }
+}
+
+/// A glyph's bitmap along with where it's located
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedGlyph {
+    /// The absolute offset of the bitmap
+    pub offset: usize,
+    pub width: u8,
+    pub bitmap: Vec<u8>,
+}
+
+impl ParsedGlyph {
+    /// The bytes needed to store a row of the glyph
+    pub fn row_size(&self) -> usize {
+        (self.width as usize).div_ceil(u8::BITS as usize)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedFont {
+    /// The absolute offset of the font's header
+    pub offset: usize,
+    pub version: u8,
+    pub height: u8,
+    /// A count of 0 means 256 glyphs
+    pub glyph_count: u8,
+    pub first_glyph: u8,
+    /// The offset of the widths table relative to the font's header
+    pub widths_offset: usize,
+    /// The offset of the bitmap table relative to the font's header
+    pub bitmaps_offset: usize,
+    pub italic_space_adjust: u8,
+    pub space_above: u8,
+    pub space_below: u8,
+    pub weight: u8,
+    pub style: u8,
+    pub cap_height: u8,
+    pub x_height: u8,
+    pub baseline_height: u8,
+    pub widths: Vec<u8>,
+    /// Every glyph in the font's range; unset glyphs are `None`
+    pub glyphs: Vec<Option<ParsedGlyph>>,
+}
+
+impl ParsedFont {
+    pub fn total_glyphs(&self) -> usize {
+        match self.glyph_count {
+            0 => 256,
+            count => count as usize,
+        }
+    }
+
+    /// The index of the last glyph in the font's range
+    pub fn last_glyph(&self) -> u8 {
+        (self.first_glyph as usize + self.total_glyphs() - 1).min(u8::MAX as usize) as u8
+    }
+
+    /// Gets a glyph by its code page index
+    pub fn glyph(&self, index: u8) -> Option<&ParsedGlyph> {
+        index
+            .checked_sub(self.first_glyph)
+            .and_then(|index| self.glyphs.get(index as usize))
+            .and_then(Option::as_ref)
+    }
+
+    fn parse(reader: &Reader, offset: usize) -> anyhow::Result<Self> {
+        reader
+            .bytes(offset, FONT_HEADER_SIZE)
+            .context("Failed to parse font header")?;
+
+        let mut font = Self {
+            offset,
+            version: reader.u8(offset)?,
+            height: reader.u8(offset + 1)?,
+            glyph_count: reader.u8(offset + 2)?,
+            first_glyph: reader.u8(offset + 3)?,
+            widths_offset: reader.u24(offset + 4)?,
+            bitmaps_offset: reader.u24(offset + 7)?,
+            italic_space_adjust: reader.u8(offset + 10)?,
+            space_above: reader.u8(offset + 11)?,
+            space_below: reader.u8(offset + 12)?,
+            weight: reader.u8(offset + 13)?,
+            style: reader.u8(offset + 14)?,
+            cap_height: reader.u8(offset + 15)?,
+            x_height: reader.u8(offset + 16)?,
+            baseline_height: reader.u8(offset + 17)?,
+            widths: Vec::new(),
+            glyphs: Vec::new(),
+        };
+
+        let total_glyphs = font.total_glyphs();
+        let widths_offset = offset + font.widths_offset;
+        font.widths = reader
+            .bytes(widths_offset, total_glyphs)
+            .context("Failed to parse glyph widths")?
+            .to_vec();
+
+        let bitmaps_offset = offset + font.bitmaps_offset;
+        font.glyphs = Vec::with_capacity(total_glyphs);
+
+        for (glyph, &width) in font.widths.iter().enumerate() {
+            let table_offset = bitmaps_offset + glyph * size_of::<u16>();
+            let bitmap_offset = reader
+                .u16(table_offset)
+                .with_context(|| format!("Failed to parse bitmap table entry for glyph {glyph}"))?
+                as usize;
+
+            if bitmap_offset == 0 {
+                font.glyphs.push(None);
+                continue;
+            }
+
+            let bitmap_offset = offset + bitmap_offset;
+            let row_size = (width as usize).div_ceil(u8::BITS as usize);
+            let bitmap = reader
+                .bytes(bitmap_offset, row_size * font.height as usize)
+                .with_context(|| format!("Failed to parse bitmap of glyph {glyph}"))?
+                .to_vec();
+
+            font.glyphs.push(Some(ParsedGlyph {
+                offset: bitmap_offset,
+                width,
+                bitmap,
+            }));
+        }
+
+        Ok(font)
+    }
+}
+
+/// A built font pack's binary parsed back into its structure
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedFontPack {
+    pub metadata: Option<ParsedMetadata>,
+    pub fonts: Vec<ParsedFont>,
+}
+
+impl ParsedFontPack {
+    /// Whether the data starts with a font pack header
+    pub fn is_font_pack(data: &[u8]) -> bool {
+        data.starts_with(FONT_PACK_HEADER)
+    }
+
+    pub fn parse(data: &[u8]) -> anyhow::Result<Self> {
+        let reader = Reader { data };
+
+        let header = reader
+            .bytes(0, FONT_PACK_HEADER.len())
+            .context("Failed to parse font pack header")?;
+
+        if header != FONT_PACK_HEADER {
+            bail!(
+                "File isn't a font pack; expected header {:?}, found {:?}",
+                FONT_PACK_HEADER.escape_ascii().to_string(),
+                header.escape_ascii().to_string()
+            );
+        }
+
+        let metadata_offset = reader.u24(8).context("Failed to parse metadata pointer")?;
+        let metadata = match metadata_offset {
+            0 => None,
+            offset => Some(
+                Self::parse_metadata(&reader, offset)
+                    .with_context(|| format!("Failed to parse metadata at offset {offset:#08X}"))?,
+            ),
+        };
+
+        let font_count = reader.u8(11).context("Failed to parse font count")?;
+        let mut fonts = Vec::with_capacity(font_count as usize);
+
+        for font_index in 0..font_count as usize {
+            let pointer_offset = 12 + font_index * 3;
+            let font_offset = reader
+                .u24(pointer_offset)
+                .with_context(|| format!("Failed to parse pointer to font {font_index}"))?;
+            let font = ParsedFont::parse(&reader, font_offset).with_context(|| {
+                format!("Failed to parse font {font_index} at offset {font_offset:#08X}")
+            })?;
+            fonts.push(font);
+        }
+
+        Ok(Self { metadata, fonts })
+    }
+
+    /// Labels the start of every part of the font pack
+    pub fn labels(&self) -> Vec<Label> {
+        let mut labels = vec![Label::new(0, "header")];
+
+        if let Some(metadata) = &self.metadata {
+            labels.push(Label::new(metadata.offset, "metadata"));
+
+            if metadata.fields().iter().any(|(_, value)| value.is_some()) {
+                labels.push(Label::new(
+                    metadata.offset + 3 + METADATA_STRINGS * 3,
+                    "metadata_strings",
+                ));
+            }
+        }
+
+        for (index, font) in self.fonts.iter().enumerate() {
+            labels.push(Label::new(font.offset, format!("font_{index}")));
+            labels.push(Label::new(
+                font.offset + font.widths_offset,
+                format!("font_{index}_widths"),
+            ));
+            labels.push(Label::new(
+                font.offset + font.bitmaps_offset,
+                format!("font_{index}_bitmap_table"),
+            ));
+
+            if let Some(bitmaps) = font.glyphs.iter().flatten().map(|glyph| glyph.offset).min() {
+                labels.push(Label::new(bitmaps, format!("font_{index}_bitmaps")));
+            }
+        }
+
+        labels
+    }
+
+    fn parse_metadata(reader: &Reader, offset: usize) -> anyhow::Result<ParsedMetadata> {
+        let size = reader.u24(offset)?;
+        let mut strings: [Option<String>; METADATA_STRINGS] = Default::default();
+
+        for (index, string) in strings.iter_mut().enumerate() {
+            let pointer_offset = offset + 3 + index * 3;
+            let pointer = reader.u24(pointer_offset)?;
+
+            if pointer != 0 {
+                *string = Some(
+                    reader
+                        .string(pointer)
+                        .with_context(|| format!("Failed to parse metadata string {index}"))?,
+                );
+            }
+        }
+
+        let [
+            family_name,
+            author,
+            pseudocopyright,
+            description,
+            version,
+            code_page,
+        ] = strings;
+
+        Ok(ParsedMetadata {
+            offset,
+            size,
+            family_name,
+            author,
+            pseudocopyright,
+            description,
+            version,
+            code_page,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The same pack as the binary output's example
+    const EXAMPLE: &[u8] = b"FONTPACK\x0F\x00\x00\x01\x42\x00\x00\
+        \x15\x00\x00\x24\x00\x00\x00\x00\x00\x00\x00\x00\x30\x00\x00\x00\x00\x00\x3C\x00\x00\
+        Family Name\x00Description\x00ASCII\x00\
+        \x00\x06\x03\x61\x12\x00\x00\x15\x00\x00\x06\x04\x05\x80\x05\x02\x07\x01\
+        \x03\x00\x08\
+        \x1B\x00\x00\x00\x21\x00\
+        \x00\x01\x02\x03\x04\x05\
+        \xFF\xFF\xFF\xFF\xFF\xFF";
+
+    #[test]
+    fn parse_example() {
+        let pack = ParsedFontPack::parse(EXAMPLE).unwrap();
+        let metadata = pack.metadata.unwrap();
+
+        assert_eq!(metadata.offset, 15);
+        assert_eq!(metadata.size, 21);
+        assert_eq!(metadata.family_name.as_deref(), Some("Family Name"));
+        assert_eq!(metadata.author, None);
+        assert_eq!(metadata.description.as_deref(), Some("Description"));
+        assert_eq!(metadata.code_page.as_deref(), Some("ASCII"));
+
+        let font = &pack.fonts[0];
+        assert_eq!(font.offset, 66);
+        assert_eq!(font.height, 6);
+        assert_eq!(font.first_glyph, b'a');
+        assert_eq!(font.last_glyph(), b'c');
+        assert_eq!(font.widths, [3, 0, 8]);
+        assert_eq!(font.glyph(b'b'), None);
+        assert_eq!(
+            font.glyph(b'a'),
+            Some(&ParsedGlyph {
+                offset: 93,
+                width: 3,
+                bitmap: vec![0, 1, 2, 3, 4, 5],
+            })
+        );
+        assert_eq!(font.glyph(b'c').unwrap().bitmap, [255; 6]);
+    }
+
+    #[test]
+    fn labels_example() {
+        let labels = ParsedFontPack::parse(EXAMPLE).unwrap().labels();
+        let offsets = labels
+            .iter()
+            .map(|label| (label.offset, label.name.as_str()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            offsets,
+            [
+                (0, "header"),
+                (15, "metadata"),
+                (36, "metadata_strings"),
+                (66, "font_0"),
+                (84, "font_0_widths"),
+                (87, "font_0_bitmap_table"),
+                (93, "font_0_bitmaps"),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_not_font_pack() {
+        let error = ParsedFontPack::parse(b"NOTAPACK").unwrap_err();
+
+        assert!(error.to_string().starts_with("File isn't a font pack"));
+    }
+
+    // Errors should say where parsing failed
+    #[test]
+    fn parse_truncated() {
+        let error = ParsedFontPack::parse(&EXAMPLE[..100]).unwrap_err();
+        let message = format!("{error:#}");
+
+        assert!(message.contains("Failed to parse font 0 at offset 0x000042"));
+        assert!(message.contains("Failed to parse bitmap of glyph 2"));
+        assert!(message.contains("offset 0x000063"));
+    }
+}
@@ -0,0 +1,374 @@
+use std::{
+    io::Cursor,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, bail};
+use image::{ImageFormat, Rgba, RgbaImage};
+use log::info;
+use serde::Serialize;
+
+use crate::{
+    cli::{CliInitCommand, CliInitFontPackCommand, CliInitSpriteCommand, Placeholder},
+    error::{ErrorKind, ResultExt},
+    font::{
+        self,
+        definition::{
+            FontDefinition, FontDefinitionWrapper, FontGlyph, FontPackDefinition,
+            FontPackDefinitionWrapper, FontPackMetadata, FontWeight, GlyphIndex,
+        },
+    },
+    schema::Schema,
+    sprite::definition::{SpriteDefinition, SpriteDefinitionWrapper},
+};
+
+/// The height of a new font in pixels
+const FONT_HEIGHT: u8 = 8;
+/// The width of placeholder glyphs in pixels
+const GLYPH_WIDTH: u32 = 6;
+/// The width and height of a placeholder sprite in pixels
+const SPRITE_SIZE: u32 = 16;
+
+/// Explains the fields of the templates, by table and field
+const COMMENTS: &[(&str, &str, &str)] = &[
+    (
+        "pack",
+        "fonts",
+        "Paths to each font definition, relative to this file, without `.toml`",
+    ),
+    (
+        "pack.metadata",
+        "family_name",
+        "A short, human-readable typeface name, such as \"Times\"",
+    ),
+    (
+        "pack.metadata",
+        "author",
+        "A short string naming the typeface designer",
+    ),
+    (
+        "pack.metadata",
+        "pseudocopyright",
+        "A short copyright claim",
+    ),
+    (
+        "pack.metadata",
+        "description",
+        "A brief description of the font",
+    ),
+    (
+        "pack.metadata",
+        "version",
+        "Any version string, such as \"1.0.0.0\" or \"1 June 2019\"",
+    ),
+    (
+        "pack.metadata",
+        "code_page",
+        "Suggested values: \"ASCII\", \"TIOS\", \"ISO-8859-1\", \"Windows 1252\", \"Calculator 1252\"",
+    ),
+    (
+        "font",
+        "fontlibc_version",
+        "Only 0 is supported by fontlibc",
+    ),
+    (
+        "font",
+        "height",
+        "Height in pixels, not including space above or below",
+    ),
+    (
+        "font",
+        "weight",
+        "One of: thin, extra_light, light, semilight, normal, medium, semibold, bold, extra_bold, black",
+    ),
+    (
+        "font",
+        "cap_height",
+        "Pixels down from the top of the glyph to the top of capital letters",
+    ),
+    (
+        "font",
+        "x_height",
+        "Pixels down from the top of the glyph to the top of lowercase letters",
+    ),
+    (
+        "font",
+        "baseline_height",
+        "Pixels down from the top of the glyph to the baseline",
+    ),
+    (
+        "sprite",
+        "source",
+        "Path to the sprite's PNG, relative to this file, without `.png`",
+    ),
+    (
+        "sprite",
+        "palette_offset",
+        "The first palette index the sprite's colors are assigned to",
+    ),
+    ("sprite", "mode", "Either sprite or background"),
+];
+
+/// Parses a glyph range, like `a..z` or `32..126`
+pub fn parse_glyph_range(raw: &str) -> Result<RangeInclusive<u8>, String> {
+    let (first, last) = raw
+        .split_once("..")
+        .ok_or_else(|| format!("Expected a range like `a..z` or `32..126`: {raw:?}"))?;
+    let first = font::parse_glyph_index(first)?;
+    let last = font::parse_glyph_index(last)?;
+
+    if first > last {
+        return Err(format!("The range must not be backwards: {raw:?}"));
+    }
+
+    Ok(first..=last)
+}
+
+/// Serializes the definition with its schema version and comments explaining each field.
+/// Serializing the real structs keeps the templates in sync with what's read.
+fn template(schema: Schema, definition: &impl Serialize) -> anyhow::Result<Vec<u8>> {
+    let raw = toml::to_string(definition).context("Failed to serialize template")?;
+    let mut output = format!("schema = {}\n\n", schema.current());
+    let mut table = "";
+
+    for line in raw.lines() {
+        if let Some(header) = line.strip_prefix('[') {
+            table = header.trim_matches(['[', ']']);
+        } else if let Some((key, _)) = line.split_once(" = ")
+            && let Some((_, _, comment)) =
+                COMMENTS.iter().find(|(comment_table, comment_key, _)| {
+                    *comment_table == table && *comment_key == key
+                })
+        {
+            output.push_str(&format!("# {comment}\n"));
+        }
+
+        output.push_str(line);
+        output.push('\n');
+    }
+
+    Ok(output.into_bytes())
+}
+
+/// A PNG with either nothing set or only its outline set
+fn placeholder_image(width: u32, height: u32, placeholder: Placeholder) -> anyhow::Result<Vec<u8>> {
+    let image = RgbaImage::from_fn(width, height, |x, y| {
+        let edge = x == 0 || y == 0 || x == width - 1 || y == height - 1;
+
+        match placeholder {
+            Placeholder::Box if edge => Rgba([0, 0, 0, 255]),
+            Placeholder::Box | Placeholder::Blank => Rgba([0, 0, 0, 0]),
+        }
+    });
+    let mut png = Cursor::new(Vec::new());
+    image
+        .write_to(&mut png, ImageFormat::Png)
+        .context("Failed to encode placeholder image")?;
+
+    Ok(png.into_inner())
+}
+
+/// The files of a new font pack
+fn font_pack_files(
+    directory: &Path,
+    command: &CliInitFontPackCommand,
+) -> anyhow::Result<Vec<(PathBuf, Vec<u8>)>> {
+    let family_name = directory
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let pack = FontPackDefinitionWrapper {
+        pack: FontPackDefinition {
+            metadata: FontPackMetadata {
+                family_name,
+                ..Default::default()
+            },
+            fonts: vec![PathBuf::from("fonts/main")],
+            budget: None,
+        },
+    };
+    let glyphs = command.glyphs.clone().into_iter().flatten();
+    let font = FontDefinitionWrapper {
+        font: FontDefinition {
+            height: FONT_HEIGHT,
+            weight: Some(FontWeight::Normal),
+            cap_height: 1,
+            x_height: 3,
+            baseline_height: 6,
+            glyphs: glyphs
+                .clone()
+                .map(|index| FontGlyph {
+                    index: GlyphIndex::Number(index),
+                    source: PathBuf::from(format!("../glyphs/{index}")),
+                })
+                .collect(),
+            ..Default::default()
+        },
+    };
+
+    let mut files = vec![
+        (
+            directory.join("pack.toml"),
+            template(Schema::FontPack, &pack)?,
+        ),
+        (
+            directory.join("fonts/main.toml"),
+            template(Schema::Font, &font)?,
+        ),
+    ];
+
+    for index in glyphs {
+        files.push((
+            directory.join(format!("glyphs/{index}.png")),
+            placeholder_image(GLYPH_WIDTH, FONT_HEIGHT.into(), command.placeholder)?,
+        ));
+    }
+
+    Ok(files)
+}
+
+/// The files of a new sprite
+fn sprite_files(
+    directory: &Path,
+    command: &CliInitSpriteCommand,
+) -> anyhow::Result<Vec<(PathBuf, Vec<u8>)>> {
+    let sprite = SpriteDefinitionWrapper {
+        sprite: Some(SpriteDefinition {
+            source: PathBuf::from("sprite"),
+            ..Default::default()
+        }),
+        sprites: Vec::new(),
+        shared_palette: false,
+        budget: None,
+    };
+
+    let mut files = vec![(
+        directory.join("sprite.toml"),
+        template(Schema::Sprite, &sprite)?,
+    )];
+
+    if let Some(placeholder) = command.placeholder {
+        files.push((
+            directory.join("sprite.png"),
+            placeholder_image(SPRITE_SIZE, SPRITE_SIZE, placeholder)?,
+        ));
+    }
+
+    Ok(files)
+}
+
+/// Writes every file, refusing to overwrite any unless forced
+async fn write_files(files: Vec<(PathBuf, Vec<u8>)>, force: bool) -> anyhow::Result<()> {
+    let existing = files
+        .iter()
+        .filter(|(path, _)| path.exists())
+        .map(|(path, _)| format!("- {path:?}"))
+        .collect::<Vec<_>>();
+
+    if !force && !existing.is_empty() {
+        bail!(
+            "Refusing to overwrite existing files:\n{}\n\
+             Use `--force` to overwrite them",
+            existing.join("\n")
+        );
+    }
+
+    for (path, data) in files {
+        crate::output::create_parent(&path).await?;
+        info!("Writing {path:?}");
+        tokio::fs::write(&path, data)
+            .await
+            .with_context(|| format!("Failed to write file: {path:?}"))
+            .with_kind(ErrorKind::Io)?;
+    }
+
+    Ok(())
+}
+
+/// Creates a new definition and its files from a template
+pub async fn init(command: CliInitCommand) -> anyhow::Result<()> {
+    match command {
+        CliInitCommand::FontPack(command) => {
+            let files = font_pack_files(&command.directory, &command)?;
+            write_files(files, command.force).await?;
+
+            // Created even without placeholders, as where glyphs are expected
+            let glyphs = command.directory.join("glyphs");
+            tokio::fs::create_dir_all(&glyphs)
+                .await
+                .with_context(|| format!("Failed to create glyph directory: {glyphs:?}"))
+                .with_kind(ErrorKind::Io)
+        }
+        CliInitCommand::Sprite(command) => {
+            let files = sprite_files(&command.directory, &command)?;
+            write_files(files, command.force).await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{diagnostics::Diagnostics, schema};
+
+    fn parse<T: serde::de::DeserializeOwned>(schema: Schema, data: &[u8]) -> T {
+        let mut diagnostics = Diagnostics::default();
+        let raw = std::str::from_utf8(data).unwrap();
+        let definition = schema::parse(schema, raw, &"template", &mut diagnostics).unwrap();
+
+        assert!(diagnostics.warnings().is_empty(), "{raw}");
+        definition
+    }
+
+    #[test]
+    fn glyph_range() {
+        assert_eq!(parse_glyph_range("a..z"), Ok(b'a'..=b'z'));
+        assert_eq!(parse_glyph_range("32..0x7E"), Ok(32..=126));
+        assert!(parse_glyph_range("z..a").is_err());
+        assert!(parse_glyph_range("a-z").is_err());
+    }
+
+    // The templates must read back without any unknown fields
+    #[test]
+    fn font_pack_templates() {
+        let command = CliInitFontPackCommand {
+            directory: PathBuf::from("fonts"),
+            glyphs: Some(b'a'..=b'c'),
+            placeholder: Placeholder::Box,
+            force: false,
+        };
+        let files = font_pack_files(Path::new("fonts"), &command).unwrap();
+
+        let pack = parse::<FontPackDefinitionWrapper>(Schema::FontPack, &files[0].1).pack;
+        assert_eq!(pack.fonts, [Path::new("fonts/main")]);
+        assert_eq!(pack.metadata.family_name, "fonts");
+        assert_eq!(pack.metadata.code_page, "ASCII");
+
+        let font = parse::<FontDefinitionWrapper>(Schema::Font, &files[1].1).font;
+        assert_eq!(font.height, FONT_HEIGHT);
+        assert_eq!(font.glyphs.len(), 3);
+        assert_eq!(font.glyphs[0].source, Path::new("../glyphs/97"));
+
+        assert_eq!(files.len(), 5);
+        assert_eq!(files[2].0, Path::new("fonts/glyphs/97.png"));
+    }
+
+    #[test]
+    fn sprite_template() {
+        let command = CliInitSpriteCommand {
+            directory: PathBuf::from("player"),
+            placeholder: None,
+            force: false,
+        };
+        let files = sprite_files(Path::new("player"), &command).unwrap();
+        let raw = std::str::from_utf8(&files[0].1).unwrap();
+
+        assert_eq!(files.len(), 1);
+        assert!(raw.starts_with("schema = 1\n"));
+        assert!(raw.contains("# Either sprite or background\nmode = \"sprite\"\n"));
+
+        let sprite = parse::<SpriteDefinitionWrapper>(Schema::Sprite, &files[0].1);
+        assert_eq!(sprite.sprite.unwrap().source, Path::new("sprite"));
+    }
+}
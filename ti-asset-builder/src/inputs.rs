@@ -0,0 +1,49 @@
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
+
+/// Every file a build reads, including those that failed to load
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Inputs(BTreeSet<PathBuf>);
+
+impl Inputs {
+    /// Records the file as an input.
+    /// Relative paths are made absolute so they can be compared with watched paths.
+    pub fn insert(&mut self, path: &Path) {
+        let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+        self.0.insert(path.normalize_lexically().unwrap_or(path));
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        self.0.contains(path)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Path> {
+        self.0.iter().map(PathBuf::as_path)
+    }
+
+    /// The directories containing every input
+    pub fn directories(&self) -> BTreeSet<&Path> {
+        self.0.iter().filter_map(|path| path.parent()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn directories() {
+        let mut inputs = Inputs::default();
+        inputs.insert(Path::new("/fonts/pack.toml"));
+        inputs.insert(Path::new("/fonts/glyphs/../regular.toml"));
+        inputs.insert(Path::new("/fonts/glyphs/a.png"));
+
+        assert!(inputs.contains(Path::new("/fonts/regular.toml")));
+        assert_eq!(
+            inputs.directories().into_iter().collect::<Vec<_>>(),
+            [Path::new("/fonts"), Path::new("/fonts/glyphs")]
+        );
+    }
+}
@@ -1,18 +1,108 @@
 #![feature(normalize_lexically)]
 
+mod budget;
+mod cache;
+mod check;
 mod cli;
+mod completions;
+mod convert;
+mod definition;
+mod depfile;
+mod diagnostics;
+mod error;
 mod font;
+mod init;
+mod inputs;
 mod output;
 mod path;
+mod project;
+mod report;
+mod schema;
+mod search;
 mod sprite;
+mod watch;
+
+use std::process::ExitCode;
+
+use diagnostics::Diagnostics;
+use error::ErrorKind;
+use inputs::Inputs;
+
+/// Runs the chosen subcommand
+async fn run(args: cli::CliArgs) -> anyhow::Result<()> {
+    let strict = args.strict;
+
+    match args.subcommand {
+        cli::CliSubcommand::FontPack(args) => match (args.action, args.build) {
+            (Some(cli::CliFontPackAction::Inspect(command)), _) => font::inspect(command).await,
+            (Some(cli::CliFontPackAction::Diff(command)), _) => font::diff_files(command).await,
+            (None, Some(command)) if command.watch => {
+                watch::ensure_watchable(&command.definition)?;
+                watch::watch(async |inputs| {
+                    font::build(command.clone(), &mut Diagnostics::new(strict), inputs).await
+                })
+                .await
+            }
+            (None, Some(command)) => {
+                font::build(
+                    command,
+                    &mut Diagnostics::new(strict),
+                    &mut Inputs::default(),
+                )
+                .await
+            }
+            (None, None) => unreachable!("Clap requires the build arguments without an action"),
+        },
+        cli::CliSubcommand::Sprite(command) if command.watch => {
+            watch::ensure_watchable(&command.definition)?;
+            watch::watch(async |inputs| {
+                sprite::build(command.clone(), &mut Diagnostics::new(strict), inputs).await
+            })
+            .await
+        }
+        cli::CliSubcommand::Sprite(command) => {
+            sprite::build(
+                command,
+                &mut Diagnostics::new(strict),
+                &mut Inputs::default(),
+            )
+            .await
+        }
+        cli::CliSubcommand::Build(command) => project::build(command, strict).await,
+        cli::CliSubcommand::Check(command) => check::check(command, strict).await,
+        cli::CliSubcommand::Convert(command) => convert::convert(command).await,
+        cli::CliSubcommand::Completions(command) => completions::print(command.shell),
+        cli::CliSubcommand::Init(command) => init::init(command).await,
+    }
+}
 
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    env_logger::init();
-    let subcommand = cli::init_cli()?;
+async fn main() -> ExitCode {
+    let args = match cli::init_cli() {
+        Ok(args) => args,
+        // Help and version are also returned as errors
+        Err(error) => {
+            let _ = error.print();
+
+            return if error.use_stderr() {
+                ExitCode::from(ErrorKind::Usage.exit_code())
+            } else {
+                ExitCode::SUCCESS
+            };
+        }
+    };
+
+    // `RUST_LOG` takes precedence over the verbosity flags
+    env_logger::Builder::new()
+        .filter_level(args.log_level())
+        .parse_default_env()
+        .init();
 
-    match subcommand {
-        cli::CliSubcommand::FontPack(command) => font::build(command).await,
-        cli::CliSubcommand::Sprite(command) => sprite::build(command).await,
+    match run(args).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("Error: {error:?}");
+            ExitCode::from(ErrorKind::of(&error).exit_code())
+        }
     }
 }
@@ -1,9 +1,234 @@
-#[derive(Debug, Clone, clap::ValueEnum)]
+pub mod source;
+
+use std::{
+    ffi::OsStr,
+    path::{Component, Path, PathBuf, is_separator},
+};
+
+use anyhow::{Context, bail};
+use serde::Deserialize;
+
+use crate::{
+    error::{ErrorKind, ResultExt},
+    path::PathBufExt,
+};
+
+/// The longest a variable name can be on the calculator
+pub const MAX_VAR_NAME_LENGTH: usize = 8;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
 pub enum OutputType {
     /// A fasmg compatible assembly file.
     Assembly,
     /// The raw binary asset with no header.
+    #[default]
     Binary,
     /// A C header file.
     C,
 }
+
+impl OutputType {
+    /// The file extension of the format without a `.`
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Assembly => "asm",
+            Self::Binary => "bin",
+            Self::C => "h",
+        }
+    }
+}
+
+/// The names an asset is given by each kind of output
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssetNames {
+    /// The name of the variable on the calculator
+    pub var_name: String,
+    /// The start of every C and assembly symbol of the asset
+    pub symbol_prefix: String,
+}
+
+impl AssetNames {
+    /// Derives each name from the base name, unless it's overridden.
+    /// Overrides are used as is, so they must already be valid.
+    pub fn resolve(
+        base: &str,
+        var_name: Option<String>,
+        symbol_prefix: Option<String>,
+    ) -> anyhow::Result<Self> {
+        let var_name = match var_name {
+            Some(var_name) => var_name,
+            None => to_var_name(base).with_context(|| {
+                format!(
+                    "Failed to make a variable name from {base:?}\n\
+                     Use `--var-name` to choose one"
+                )
+            })?,
+        };
+        validate_var_name(&var_name)?;
+
+        let symbol_prefix = symbol_prefix.unwrap_or_else(|| source::to_identifier(base));
+        source::validate_identifier(&symbol_prefix)?;
+
+        Ok(Self {
+            var_name,
+            symbol_prefix,
+        })
+    }
+}
+
+/// Checks the name can be used as a variable on the calculator
+pub fn validate_var_name(name: &str) -> anyhow::Result<()> {
+    let mut chars = name.chars();
+
+    let valid = chars.next().is_some_and(|char| char.is_ascii_alphabetic())
+        && chars.all(|char| char.is_ascii_alphanumeric())
+        && name.len() <= MAX_VAR_NAME_LENGTH;
+
+    if !valid {
+        bail!(
+            "Invalid variable name: {name:?}\n\
+             Names must start with a letter, only contain letters and digits, \
+             and be at most {MAX_VAR_NAME_LENGTH} characters"
+        );
+    }
+
+    Ok(())
+}
+
+/// Checks the name is a single file name, so it can't write outside the directory it's joined to
+pub fn validate_file_name(name: &str) -> anyhow::Result<()> {
+    let mut components = Path::new(name).components();
+    let valid = !name.contains(is_separator)
+        && matches!(components.next(), Some(Component::Normal(file_name)) if file_name == name)
+        && components.next().is_none();
+
+    if !valid {
+        bail!(
+            "Invalid file name: {name:?}\n\
+             Names can't contain path separators or be `.` or `..`"
+        );
+    }
+
+    Ok(())
+}
+
+/// Makes a variable name from any text by removing what isn't allowed and truncating it
+fn to_var_name(raw: &str) -> Option<String> {
+    let name = raw
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .skip_while(char::is_ascii_digit)
+        .take(MAX_VAR_NAME_LENGTH)
+        .collect::<String>();
+
+    (!name.is_empty()).then_some(name)
+}
+
+/// Whether the output is meant to be a directory to put the asset in instead of the asset itself
+fn is_directory(output: &Path) -> bool {
+    output.is_dir() || output.to_string_lossy().ends_with(is_separator)
+}
+
+/// Gets the file to write the asset to.
+/// Directories get a file named after the definition's stem with the format's extension.
+pub fn resolve_path(
+    output: &Path,
+    stem: Option<&OsStr>,
+    output_type: OutputType,
+) -> anyhow::Result<PathBuf> {
+    if !is_directory(output) {
+        return Ok(output.to_path_buf());
+    }
+
+    let stem = stem.with_context(|| {
+        format!(
+            "Output is a directory, but the definition has no file name to name the output after\n\
+             Output: {output:?}"
+        )
+    })?;
+
+    // Appended, so dotted stems like `pack.v2` keep every part
+    Ok(output
+        .join(stem)
+        .append_str(format!(".{}", output_type.extension())))
+}
+
+/// Creates every missing parent directory of the output
+pub async fn create_parent(output: &Path) -> anyhow::Result<()> {
+    match output.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => tokio::fs::create_dir_all(parent)
+            .await
+            .with_context(|| format!("Failed to create output directory: {parent:?}"))
+            .with_kind(ErrorKind::Io),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_derived() {
+        let names = AssetNames::resolve("My Font Pack 2", None, None).unwrap();
+
+        assert_eq!(names.var_name, "MyFontPa");
+        assert_eq!(names.symbol_prefix, "My_Font_Pack_2");
+    }
+
+    // Leading digits can't start either name
+    #[test]
+    fn names_derived_sanitized() {
+        let names = AssetNames::resolve("8x-font", None, None).unwrap();
+
+        assert_eq!(names.var_name, "xfont");
+        assert_eq!(names.symbol_prefix, "_8x_font");
+        assert!(AssetNames::resolve("123", None, None).is_err());
+        assert!(AssetNames::resolve("123", Some("FONT".to_string()), None).is_ok());
+    }
+
+    // Overrides aren't sanitized or truncated
+    #[test]
+    fn names_overridden() {
+        let names = AssetNames::resolve("pack", Some("FONTS".to_string()), Some("fnt".to_string()))
+            .unwrap();
+
+        assert_eq!(names.var_name, "FONTS");
+        assert_eq!(names.symbol_prefix, "fnt");
+        assert!(AssetNames::resolve("pack", Some("TOOLONGNAME".to_string()), None).is_err());
+        assert!(AssetNames::resolve("pack", Some("A_B".to_string()), None).is_err());
+        assert!(AssetNames::resolve("pack", None, Some("my-font".to_string())).is_err());
+    }
+
+    #[test]
+    fn resolve_file() {
+        let path = resolve_path(
+            Path::new("out/fonts.bin"),
+            Some(OsStr::new("pack")),
+            OutputType::Binary,
+        );
+
+        assert_eq!(path.unwrap(), Path::new("out/fonts.bin"));
+    }
+
+    #[test]
+    fn resolve_directory() {
+        let path = resolve_path(Path::new("out/"), Some(OsStr::new("pack")), OutputType::C);
+
+        assert_eq!(path.unwrap(), Path::new("out/pack.h"));
+
+        let path = resolve_path(
+            Path::new("out/"),
+            Some(OsStr::new("pack.v2")),
+            OutputType::Binary,
+        );
+
+        assert_eq!(path.unwrap(), Path::new("out/pack.v2.bin"));
+    }
+
+    #[test]
+    fn resolve_directory_without_stem() {
+        assert!(resolve_path(Path::new("out/"), None, OutputType::C).is_err());
+    }
+}
@@ -0,0 +1,265 @@
+use std::fmt::Write;
+
+use anyhow::bail;
+
+use crate::output::AssetNames;
+
+/// How many bytes are written on each line
+const BYTES_PER_LINE: usize = 16;
+
+/// A named position within the data.
+/// Each label's region runs until the next label.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    pub offset: usize,
+    pub name: String,
+}
+
+impl Label {
+    pub fn new(offset: usize, name: impl Into<String>) -> Self {
+        Self {
+            offset,
+            name: name.into(),
+        }
+    }
+}
+
+/// Checks the name can be used as a symbol in C and assembly
+pub fn validate_identifier(name: &str) -> anyhow::Result<()> {
+    let mut chars = name.chars();
+
+    let valid = chars
+        .next()
+        .is_some_and(|char| char.is_ascii_alphabetic() || char == '_')
+        && chars.all(|char| char.is_ascii_alphanumeric() || char == '_');
+
+    if !valid {
+        bail!(
+            "Invalid symbol name: {name:?}\n\
+             Names must start with a letter or `_` and only contain letters, digits, and `_`"
+        );
+    }
+
+    Ok(())
+}
+
+/// Makes a symbol name from any text, such as a file name
+pub fn to_identifier(raw: &str) -> String {
+    let name = raw
+        .chars()
+        .map(|char| {
+            if char.is_ascii_alphanumeric() {
+                char
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+
+    match name.chars().next() {
+        Some(char) if !char.is_ascii_digit() => name,
+        _ => format!("_{name}"),
+    }
+}
+
+/// The labels within the data in order of offset, keeping the first label at each offset
+pub fn sorted_labels<'a>(data: &[u8], labels: &'a [Label]) -> Vec<&'a Label> {
+    let mut labels = labels
+        .iter()
+        .filter(|label| label.offset < data.len())
+        .collect::<Vec<_>>();
+    labels.sort_by_key(|label| label.offset);
+    labels.dedup_by_key(|label| label.offset);
+    labels
+}
+
+/// Splits the data at each label.
+/// Data before the first label is unnamed.
+fn regions<'a>(data: &'a [u8], labels: &'a [Label]) -> Vec<(Option<&'a str>, &'a [u8])> {
+    let labels = sorted_labels(data, labels);
+    let mut regions = Vec::with_capacity(labels.len() + 1);
+    let first = labels.first().map_or(data.len(), |label| label.offset);
+
+    if first != 0 || data.is_empty() {
+        regions.push((None, &data[..first]));
+    }
+
+    for (index, label) in labels.iter().enumerate() {
+        let end = labels.get(index + 1).map_or(data.len(), |next| next.offset);
+        regions.push((Some(label.name.as_str()), &data[label.offset..end]));
+    }
+
+    regions
+}
+
+/// Formats the data as a C header with a single array named by the symbol prefix.
+/// Labels become comments and offset defines.
+pub fn to_c(names: &AssetNames, data: &[u8], labels: &[Label]) -> String {
+    let mut output = String::new();
+    write_c(&mut output, names, data, labels).expect("Writing to a string can't fail");
+    output
+}
+
+fn write_c(
+    output: &mut String,
+    names: &AssetNames,
+    data: &[u8],
+    labels: &[Label],
+) -> std::fmt::Result {
+    let name = &names.symbol_prefix;
+    let guard = format!("{}_H", name.to_ascii_uppercase());
+    let regions = regions(data, labels);
+
+    writeln!(output, "// Generated by ti-asset-builder")?;
+    writeln!(output, "#ifndef {guard}")?;
+    writeln!(output, "#define {guard}")?;
+    writeln!(output)?;
+    writeln!(output, "#define {name}_var_name \"{}\"", names.var_name)?;
+    writeln!(output, "#define {name}_size {}", data.len())?;
+
+    for label in sorted_labels(data, labels) {
+        writeln!(
+            output,
+            "#define {name}_{}_offset {}",
+            label.name, label.offset
+        )?;
+    }
+
+    writeln!(output)?;
+    writeln!(
+        output,
+        "static const unsigned char {name}[{}] = {{",
+        data.len()
+    )?;
+
+    for (label, bytes) in regions {
+        if let Some(label) = label {
+            writeln!(output, "    // {label}")?;
+        }
+
+        for line in bytes.chunks(BYTES_PER_LINE) {
+            let line = line
+                .iter()
+                .map(|byte| format!("0x{byte:02X},"))
+                .collect::<Vec<_>>()
+                .join(" ");
+            writeln!(output, "    {line}")?;
+        }
+    }
+
+    writeln!(output, "}};")?;
+    writeln!(output)?;
+    writeln!(output, "#endif")
+}
+
+/// Formats the data as fasmg assembly with a label at each region, named by the symbol prefix
+pub fn to_asm(names: &AssetNames, data: &[u8], labels: &[Label]) -> String {
+    let mut output = String::new();
+    write_asm(&mut output, names, data, labels).expect("Writing to a string can't fail");
+    output
+}
+
+fn write_asm(
+    output: &mut String,
+    names: &AssetNames,
+    data: &[u8],
+    labels: &[Label],
+) -> std::fmt::Result {
+    let name = &names.symbol_prefix;
+
+    writeln!(output, "; Generated by ti-asset-builder")?;
+    writeln!(output, "{name}_var_name := \"{}\"", names.var_name)?;
+    writeln!(output, "{name}_size := {}", data.len())?;
+    writeln!(output)?;
+    writeln!(output, "{name}:")?;
+
+    for (label, bytes) in regions(data, labels) {
+        if let Some(label) = label {
+            writeln!(output, "{name}_{label}:")?;
+        }
+
+        for line in bytes.chunks(BYTES_PER_LINE) {
+            let line = line
+                .iter()
+                .map(|byte| format!("${byte:02X}"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            writeln!(output, "\tdb {line}")?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATA: &[u8] = &[
+        0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19,
+    ];
+
+    fn names() -> AssetNames {
+        AssetNames {
+            var_name: "DATA".to_string(),
+            symbol_prefix: "data".to_string(),
+        }
+    }
+
+    fn labels() -> Vec<Label> {
+        vec![Label::new(18, "tail"), Label::new(2, "body")]
+    }
+
+    #[test]
+    fn c_snapshot() {
+        let expected = "\
+// Generated by ti-asset-builder
+#ifndef DATA_H
+#define DATA_H
+
+#define data_var_name \"DATA\"
+#define data_size 20
+#define data_body_offset 2
+#define data_tail_offset 18
+
+static const unsigned char data[20] = {
+    0x00, 0x01,
+    // body
+    0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A, 0x0B, 0x0C, 0x0D, 0x0E, 0x0F, 0x10, 0x11,
+    // tail
+    0x12, 0x13,
+};
+
+#endif
+";
+
+        assert_eq!(to_c(&names(), DATA, &labels()), expected);
+    }
+
+    #[test]
+    fn asm_snapshot() {
+        let expected = "\
+; Generated by ti-asset-builder
+data_var_name := \"DATA\"
+data_size := 20
+
+data:
+\tdb $00, $01
+data_body:
+\tdb $02, $03, $04, $05, $06, $07, $08, $09, $0A, $0B, $0C, $0D, $0E, $0F, $10, $11
+data_tail:
+\tdb $12, $13
+";
+
+        assert_eq!(to_asm(&names(), DATA, &labels()), expected);
+    }
+
+    #[test]
+    fn identifiers() {
+        assert_eq!(to_identifier("my-font.pack"), "my_font_pack");
+        assert_eq!(to_identifier("8x8"), "_8x8");
+        assert!(validate_identifier("font_8x8").is_ok());
+        assert!(validate_identifier("8x8").is_err());
+        assert!(validate_identifier("").is_err());
+    }
+}
@@ -1,22 +1,35 @@
 use std::{
     ffi::OsStr,
-    path::{Path, PathBuf},
+    path::{Component, MAIN_SEPARATOR, Path, PathBuf},
 };
 
-use anyhow::Context;
+use anyhow::{Context, bail};
 
 pub trait PathBufExt {
     /// Appends a string directly to the end of the path
     fn append_str(self, suffix: impl AsRef<OsStr>) -> Self;
+
+    /// Removes the `\\?\` prefix Windows adds to canonicalized paths when it's not needed.
+    /// Other platforms are left as is.
+    fn strip_verbatim(self) -> Self;
 }
 
 pub trait PathExt {
-    /// Makes the relative path relative to the main path's parent with a suffix
+    /// Makes the relative path relative to the main path's parent with a suffix.
+    /// Absolute paths are left as is and the suffix isn't added if it's already there.
     fn relative_parent_suffix(
         &self,
         relative: impl AsRef<Path>,
         suffix: impl AsRef<OsStr>,
     ) -> anyhow::Result<PathBuf>;
+
+    /// Makes the relative path relative to the main path, as a directory, with a suffix.
+    /// Absolute paths are left as is and the suffix isn't added if it's already there.
+    fn relative_suffix(
+        &self,
+        relative: impl AsRef<Path>,
+        suffix: impl AsRef<OsStr>,
+    ) -> anyhow::Result<PathBuf>;
 }
 
 impl PathBufExt for PathBuf {
@@ -24,6 +37,41 @@ impl PathBufExt for PathBuf {
         self.as_mut_os_string().push(suffix);
         self
     }
+
+    fn strip_verbatim(self) -> Self {
+        if !cfg!(windows) {
+            return self;
+        }
+
+        let raw = self.to_string_lossy();
+
+        if let Some(unc) = raw.strip_prefix(r"\\?\UNC\") {
+            PathBuf::from(format!(r"\\{unc}"))
+        } else if let Some(disk) = raw.strip_prefix(r"\\?\")
+            && disk.as_bytes().get(1) == Some(&b':')
+        {
+            PathBuf::from(disk)
+        } else {
+            self
+        }
+    }
+}
+
+/// Uses the platform's separator for both `/` and `\\` so definitions work across platforms
+fn normalize_separators(path: &Path) -> PathBuf {
+    path.to_string_lossy()
+        .chars()
+        .map(|char| match char {
+            '/' | '\\' => MAIN_SEPARATOR,
+            char => char,
+        })
+        .collect::<String>()
+        .into()
+}
+
+/// Whether the path has a drive, but is relative to that drive's current directory, like `C:a`
+fn is_drive_relative(path: &Path) -> bool {
+    matches!(path.components().next(), Some(Component::Prefix(_))) && !path.has_root()
 }
 
 impl PathExt for Path {
@@ -32,12 +80,55 @@ impl PathExt for Path {
         relative: impl AsRef<Path>,
         suffix: impl AsRef<OsStr>,
     ) -> anyhow::Result<PathBuf> {
-        let path = self.join("..").join(relative).append_str(suffix);
+        self.join("..").relative_suffix(relative, suffix)
+    }
+
+    fn relative_suffix(
+        &self,
+        relative: impl AsRef<Path>,
+        suffix: impl AsRef<OsStr>,
+    ) -> anyhow::Result<PathBuf> {
+        let relative = normalize_separators(relative.as_ref());
+        let suffix = suffix.as_ref();
+
+        if is_drive_relative(&relative) {
+            bail!(
+                "Drive relative paths aren't supported: {relative:?}\n\
+                 Use an absolute path like `C:\\path` or a path relative to the definition"
+            );
+        }
+
+        let path = if relative.is_absolute() {
+            relative.to_path_buf()
+        } else {
+            self.join(relative)
+        };
+
+        let path = if has_suffix(&path, suffix) {
+            path
+        } else {
+            path.append_str(suffix)
+        };
+
+        // `..` may leave the main path, but not the start of the path itself
         path.normalize_lexically()
             .with_context(|| format!("Failed to normalize path: {path:?}"))
     }
 }
 
+/// Whether the path already ends with the suffix.
+/// Case is ignored on Windows where file names are case-insensitive.
+fn has_suffix(path: &Path, suffix: &OsStr) -> bool {
+    let path = path.as_os_str().to_string_lossy();
+    let suffix = suffix.to_string_lossy();
+
+    if cfg!(windows) {
+        path.to_lowercase().ends_with(&suffix.to_lowercase())
+    } else {
+        path.ends_with(suffix.as_ref())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -51,4 +142,105 @@ mod tests {
             expected
         );
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn relative_parent_suffix_absolute() {
+        let path = PathBuf::from("/this/is/a/test.toml");
+        let expected = PathBuf::from("/shared/glyphs/a.png");
+        assert_eq!(
+            path.relative_parent_suffix("/shared/glyphs/a", ".png")
+                .unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn relative_parent_suffix_existing_suffix() {
+        let path = PathBuf::from("this/is/a/test.toml");
+        let expected = PathBuf::from("this/is/a/file.png");
+        assert_eq!(
+            path.relative_parent_suffix("file.png", ".png").unwrap(),
+            expected
+        );
+    }
+
+    // Shared assets outside of the definition's directory are allowed
+    #[test]
+    fn relative_parent_suffix_escape_parent() {
+        let path = PathBuf::from("this/is/a/test.toml");
+        let expected = PathBuf::from("this/shared/file.png");
+        assert_eq!(
+            path.relative_parent_suffix("../../shared/file", ".png")
+                .unwrap(),
+            expected
+        );
+    }
+
+    // Going above the start of the path can't be resolved
+    #[test]
+    fn relative_parent_suffix_escape_start() {
+        let path = PathBuf::from("a/test.toml");
+        assert!(path.relative_parent_suffix("../../file", ".png").is_err());
+    }
+
+    // Definitions written on Windows still work elsewhere
+    #[test]
+    fn relative_parent_suffix_backslash() {
+        let path = PathBuf::from("this/is/a/test.toml");
+        let expected = PathBuf::from("this/is/a/glyphs/file.png");
+        assert_eq!(
+            path.relative_parent_suffix(r"glyphs\file", ".png").unwrap(),
+            expected
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn relative_parent_suffix_drive_relative() {
+        let path = PathBuf::from(r"C:\fonts\pack.toml");
+        assert!(path.relative_parent_suffix(r"D:glyphs\a", ".png").is_err());
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn relative_parent_suffix_windows_absolute() {
+        let path = PathBuf::from(r"C:\fonts\pack.toml");
+        let expected = PathBuf::from(r"D:\shared\a.png");
+        assert_eq!(
+            path.relative_parent_suffix("D:/shared/a", ".png").unwrap(),
+            expected
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn strip_verbatim() {
+        assert_eq!(
+            PathBuf::from(r"\\?\C:\fonts\pack.toml").strip_verbatim(),
+            PathBuf::from(r"C:\fonts\pack.toml")
+        );
+        assert_eq!(
+            PathBuf::from(r"\\?\UNC\server\share\pack.toml").strip_verbatim(),
+            PathBuf::from(r"\\server\share\pack.toml")
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn relative_parent_suffix_after_canonicalize() {
+        let path = PathBuf::from(r"\\?\C:\fonts\pack.toml").strip_verbatim();
+        let expected = PathBuf::from(r"C:\fonts\glyphs\a.png");
+        assert_eq!(
+            path.relative_parent_suffix("glyphs/a", ".png").unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn relative_suffix_example() {
+        let path = PathBuf::from("this/is/a");
+        let expected = PathBuf::from("this/is/file.png");
+        assert_eq!(path.relative_suffix("../file", ".png").unwrap(), expected);
+    }
 }
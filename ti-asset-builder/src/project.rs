@@ -0,0 +1,242 @@
+mod definition;
+
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{Context, anyhow};
+use log::error;
+use tokio::{sync::Semaphore, task::JoinSet};
+
+use crate::{
+    budget::DEFAULT_WARN_PERCENT,
+    cli::{CliBuildCommand, CliFontPackCommand, CliSpriteCommand, job_count},
+    diagnostics::Diagnostics,
+    error::{ErrorKind, with_common_kind},
+    font,
+    inputs::Inputs,
+    path::PathBufExt,
+    project::definition::{AssetKind, ProjectAsset, ProjectDefinition},
+    sprite,
+};
+
+impl Display for AssetKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::FontPack => f.pad("fontpack"),
+            Self::Sprite => f.pad("sprite"),
+        }
+    }
+}
+
+async fn load_project_definition(path: &Path) -> anyhow::Result<ProjectDefinition> {
+    let raw = tokio::fs::read_to_string(path)
+        .await
+        .with_context(|| format!("Failed to read project at {path:?}"))?;
+    let definition = toml::from_str::<ProjectDefinition>(&raw)
+        .with_context(|| format!("Failed to parse project at {path:?}"))?;
+
+    Ok(definition)
+}
+
+/// An asset with its paths resolved from the project
+#[derive(Debug, Clone)]
+struct Asset {
+    definition: PathBuf,
+    output: PathBuf,
+    asset: ProjectAsset,
+}
+
+impl Asset {
+    fn new(project_path: &Path, asset: ProjectAsset) -> anyhow::Result<Self> {
+        // The project file's parent is its directory
+        let resolve = |path: &Path| {
+            project_path
+                .join("..")
+                .join(path)
+                .normalize_lexically()
+                .with_context(|| format!("Failed to normalize path: {path:?}"))
+        };
+
+        Ok(Self {
+            definition: resolve(&asset.definition)?,
+            output: resolve(&asset.output)?,
+            asset,
+        })
+    }
+
+    async fn build(self, strict: bool, asset_paths: Vec<PathBuf>) -> anyhow::Result<()> {
+        let mut diagnostics = Diagnostics::new(strict);
+        let mut inputs = Inputs::default();
+
+        match self.asset.kind {
+            AssetKind::FontPack => {
+                let command = CliFontPackCommand {
+                    definition: self.definition,
+                    output: self.output,
+                    output_type: self.asset.format,
+                    watch: false,
+                    depfile: None,
+                    report_json: None,
+                    cache: None,
+                    force: false,
+                    budget: None,
+                    budget_warn: DEFAULT_WARN_PERCENT,
+                    base_dir: None,
+                    jobs: None,
+                    asset_paths,
+                    name: None,
+                    var_name: None,
+                    symbol_prefix: None,
+                };
+
+                font::build(command, &mut diagnostics, &mut inputs).await
+            }
+            AssetKind::Sprite => {
+                let command = CliSpriteCommand {
+                    definition: self.definition,
+                    output: self.output,
+                    keep_going: false,
+                    allow_any_size: false,
+                    output_type: self.asset.format,
+                    watch: false,
+                    depfile: None,
+                    report_json: None,
+                    cache: None,
+                    force: false,
+                    budget: None,
+                    budget_warn: DEFAULT_WARN_PERCENT,
+                    base_dir: None,
+                };
+
+                sprite::build(command, &mut diagnostics, &mut inputs).await
+            }
+        }
+    }
+}
+
+/// Builds every asset in the project, stopping early only with `--fail-fast`
+pub async fn build(command: CliBuildCommand, strict: bool) -> anyhow::Result<()> {
+    let project_path = command
+        .project
+        .canonicalize()
+        .map(PathBufExt::strip_verbatim)
+        .with_context(|| format!("Failed to get canon project path: {:?}", command.project))?;
+    let project = load_project_definition(&project_path).await?;
+    let assets = project
+        .assets
+        .into_iter()
+        .map(|asset| Asset::new(&project_path, asset))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let semaphore = Arc::new(Semaphore::new(job_count(command.jobs)));
+    let mut tasks = JoinSet::new();
+
+    for (index, asset) in assets.iter().cloned().enumerate() {
+        // Waiting for a permit before spawning starts the assets in order.
+        // It's only closed by `--fail-fast`, so the rest are skipped.
+        let Ok(permit) = semaphore.clone().acquire_owned().await else {
+            break;
+        };
+        let semaphore = semaphore.clone();
+        let asset_paths = command.asset_paths.clone();
+        let fail_fast = command.fail_fast;
+
+        tasks.spawn(async move {
+            let result = asset.build(strict, asset_paths).await;
+
+            // Closing before the permit is released stops any more assets from starting
+            if fail_fast && result.is_err() {
+                semaphore.close();
+            }
+
+            drop(permit);
+            (index, result)
+        });
+    }
+
+    // Assets without a result were skipped or cancelled by `--fail-fast`
+    let mut results = assets.iter().map(|_| None).collect::<Vec<_>>();
+
+    while let Some(task) = tasks.join_next().await {
+        let (index, result) = match task {
+            Ok(task) => task,
+            Err(error) if error.is_cancelled() => continue,
+            Err(error) => return Err(error).context("Asset build task panicked"),
+        };
+
+        if result.is_err() && command.fail_fast {
+            tasks.abort_all();
+        }
+
+        results[index] = Some(result);
+    }
+
+    let mut failed = 0;
+    let mut built = 0;
+    let mut kinds = Vec::new();
+
+    for (asset, result) in assets.iter().zip(results) {
+        let status = match result {
+            Some(Ok(())) => {
+                built += 1;
+                "ok"
+            }
+            Some(Err(error)) => {
+                failed += 1;
+                error!("{:?}: {error:#}", asset.definition);
+                kinds.push(ErrorKind::of(&error));
+                "failed"
+            }
+            None => "skipped",
+        };
+
+        println!(
+            "{status:<8}{:<10}{} -> {}",
+            asset.asset.kind,
+            asset.asset.definition.display(),
+            asset.asset.output.display()
+        );
+    }
+
+    println!("Built {built} of {} assets", assets.len());
+
+    if failed != 0 {
+        return Err(with_common_kind(
+            anyhow!("{failed} of {} assets failed to build", assets.len()),
+            kinds,
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::OutputType;
+
+    #[test]
+    fn asset_paths() {
+        let project = toml::from_str::<ProjectDefinition>(
+            "[[assets]]\n\
+             kind = \"fontpack\"\n\
+             definition = \"fonts/pack.toml\"\n\
+             output = \"../out/fonts.bin\"\n\
+             format = \"c\"",
+        )
+        .unwrap();
+        let asset = Asset::new(
+            Path::new("/game/assets/project.toml"),
+            project.assets[0].clone(),
+        )
+        .unwrap();
+
+        assert_eq!(asset.definition, Path::new("/game/assets/fonts/pack.toml"));
+        assert_eq!(asset.output, Path::new("/game/out/fonts.bin"));
+        assert_eq!(asset.asset.kind, AssetKind::FontPack);
+        assert_eq!(asset.asset.format, OutputType::C);
+    }
+}
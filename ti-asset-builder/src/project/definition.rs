@@ -0,0 +1,30 @@
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+use crate::output::OutputType;
+
+/// Every asset built by the `build` subcommand
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProjectDefinition {
+    pub assets: Vec<ProjectAsset>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProjectAsset {
+    pub kind: AssetKind,
+    /// A path relative from the project to the asset's definition.
+    pub definition: PathBuf,
+    /// A path relative from the project to the asset's output.
+    pub output: PathBuf,
+    /// The format of the output.
+    #[serde(default)]
+    pub format: OutputType,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AssetKind {
+    FontPack,
+    Sprite,
+}
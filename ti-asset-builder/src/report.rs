@@ -0,0 +1,215 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use anyhow::Context;
+use serde::Serialize;
+
+use crate::{
+    budget::BudgetUsage,
+    cache::hash_file,
+    diagnostics::Diagnostics,
+    error::{ErrorKind, ResultExt},
+    inputs::Inputs,
+    output::source::{Label, sorted_labels},
+};
+
+/// The version of the report's layout.
+/// Bumped whenever a field is removed, renamed, or changes meaning.
+/// New fields may be added without a bump.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// A machine-readable summary of a build.
+///
+/// ```json
+/// {
+///   "schema_version": 1,
+///   "inputs": [{ "path": "/fonts/pack.toml", "sha256": "..." }],
+///   "outputs": [{
+///     "path": "out/pack.bin",
+///     "size": 102,
+///     "sections": [{ "name": "header", "offset": 0, "size": 15 }]
+///   }],
+///   "warnings": ["Glyph is already defined: 97"],
+///   "budget": { "budget": 20480, "used": 102, "percent": 0 },
+///   "wall_time_ms": 12
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Report {
+    pub schema_version: u32,
+    /// Every file the build read, sorted by path
+    pub inputs: Vec<ReportInput>,
+    pub outputs: Vec<ReportOutput>,
+    pub warnings: Vec<String>,
+    /// How much of its budget the build used, if it has one
+    pub budget: Option<BudgetUsage>,
+    pub wall_time_ms: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ReportInput {
+    pub path: PathBuf,
+    /// The lowercase hex SHA-256 of the file's contents
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ReportOutput {
+    pub path: PathBuf,
+    /// The size of the file in bytes
+    pub size: usize,
+    /// Named regions of the file, in order of offset.
+    /// Empty when the output's layout isn't known.
+    pub sections: Vec<ReportSection>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ReportSection {
+    pub name: String,
+    pub offset: usize,
+    pub size: usize,
+}
+
+impl ReportOutput {
+    pub fn new(path: &Path, data: &[u8], labels: &[Label]) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            size: data.len(),
+            sections: sections(data, labels),
+        }
+    }
+}
+
+/// Sizes each label's region, which runs until the next label
+fn sections(data: &[u8], labels: &[Label]) -> Vec<ReportSection> {
+    let labels = sorted_labels(data, labels);
+
+    labels
+        .iter()
+        .enumerate()
+        .map(|(index, label)| {
+            let end = labels.get(index + 1).map_or(data.len(), |next| next.offset);
+
+            ReportSection {
+                name: label.name.clone(),
+                offset: label.offset,
+                size: end - label.offset,
+            }
+        })
+        .collect()
+}
+
+impl Report {
+    /// Reads every input to hash it
+    pub async fn new(
+        started: Instant,
+        inputs: &Inputs,
+        outputs: Vec<ReportOutput>,
+        diagnostics: &Diagnostics,
+        budget: Option<BudgetUsage>,
+    ) -> anyhow::Result<Self> {
+        let mut report_inputs = Vec::new();
+
+        for path in inputs.iter() {
+            report_inputs.push(ReportInput {
+                path: path.to_path_buf(),
+                sha256: hash_file(path).await?,
+            });
+        }
+
+        Ok(Self {
+            schema_version: SCHEMA_VERSION,
+            inputs: report_inputs,
+            outputs,
+            warnings: diagnostics.warnings().to_vec(),
+            budget,
+            wall_time_ms: started.elapsed().as_millis().try_into().unwrap_or(u64::MAX),
+        })
+    }
+
+    pub async fn write(&self, path: &Path) -> anyhow::Result<()> {
+        let json = serde_json::to_string_pretty(self).context("Failed to serialize report")?;
+
+        tokio::fs::write(path, json + "\n")
+            .await
+            .with_context(|| format!("Failed to write report: {path:?}"))
+            .with_kind(ErrorKind::Io)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sections_example() {
+        let labels = [
+            Label::new(15, "metadata"),
+            Label::new(0, "header"),
+            Label::new(15, "duplicate"),
+            Label::new(200, "past_end"),
+        ];
+
+        assert_eq!(
+            sections(&[0; 40], &labels),
+            [
+                ReportSection {
+                    name: "header".to_string(),
+                    offset: 0,
+                    size: 15,
+                },
+                ReportSection {
+                    name: "metadata".to_string(),
+                    offset: 15,
+                    size: 25,
+                },
+            ]
+        );
+    }
+
+    // Downstream scripts depend on these names, so changing them needs a schema bump
+    #[test]
+    fn field_names() {
+        let report = Report {
+            schema_version: SCHEMA_VERSION,
+            inputs: vec![ReportInput {
+                path: "pack.toml".into(),
+                sha256: "00".to_string(),
+            }],
+            outputs: vec![ReportOutput {
+                path: "pack.bin".into(),
+                size: 3,
+                sections: vec![ReportSection {
+                    name: "header".to_string(),
+                    offset: 0,
+                    size: 3,
+                }],
+            }],
+            warnings: vec!["warning".to_string()],
+            budget: Some(BudgetUsage {
+                budget: 10,
+                used: 3,
+                percent: 30,
+            }),
+            wall_time_ms: 5,
+        };
+
+        assert_eq!(
+            serde_json::to_value(&report).unwrap(),
+            serde_json::json!({
+                "schema_version": 1,
+                "inputs": [{ "path": "pack.toml", "sha256": "00" }],
+                "outputs": [{
+                    "path": "pack.bin",
+                    "size": 3,
+                    "sections": [{ "name": "header", "offset": 0, "size": 3 }],
+                }],
+                "warnings": ["warning"],
+                "budget": { "budget": 10, "used": 3, "percent": 30 },
+                "wall_time_ms": 5,
+            })
+        );
+    }
+}
@@ -0,0 +1,322 @@
+use std::fmt::Display;
+
+use anyhow::{Context, bail};
+use serde::de::DeserializeOwned;
+use toml::Table;
+
+use crate::{
+    diagnostics::Diagnostics,
+    font::definition::{FontDefinition, FontPackDefinition},
+    sprite::definition::{SpriteDefinition, SpriteGroupEntry},
+};
+
+/// The root field holding the version of the definition's schema
+const SCHEMA_FIELD: &str = "schema";
+
+/// The version definitions without a `schema` field are assumed to be
+const UNVERSIONED: u32 = 1;
+
+/// The layout of a kind of definition. Each is versioned separately.
+///
+/// Older definitions are upgraded to the current version before they're read:
+/// - Font 2: `version` was renamed to `fontlibc_version`
+///
+/// Unversioned definitions predate the `schema` field, so they're upgraded silently.
+/// Only definitions that ask for an older version are warned about what changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Schema {
+    FontPack,
+    Font,
+    Sprite,
+}
+
+impl Display for Schema {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::FontPack => f.pad("font pack"),
+            Self::Font => f.pad("font"),
+            Self::Sprite => f.pad("sprite"),
+        }
+    }
+}
+
+impl Schema {
+    /// The newest version this build can read
+    pub fn current(self) -> u32 {
+        match self {
+            Self::FontPack => 1,
+            Self::Font => 2,
+            Self::Sprite => 1,
+        }
+    }
+
+    /// Reads the definition's version, which can't be newer than the current version.
+    /// `None` if it's unversioned.
+    fn version(self, table: &Table) -> anyhow::Result<Option<u32>> {
+        let Some(version) = table.get(SCHEMA_FIELD) else {
+            return Ok(None);
+        };
+        let version = version
+            .as_integer()
+            .and_then(|version| u32::try_from(version).ok())
+            .filter(|&version| version >= UNVERSIONED)
+            .with_context(|| format!("Schema must be a positive integer: {version}"))?;
+
+        if version > self.current() {
+            bail!(
+                "The {self} schema {version} is newer than the latest supported: {}\n\
+                 Please upgrade ti-asset-builder",
+                self.current()
+            );
+        }
+
+        Ok(Some(version))
+    }
+
+    /// Upgrades the definition from its version to the current one, returning whether it changed.
+    /// Warns about each change unless the definition is unversioned.
+    fn migrate(
+        self,
+        table: &mut Table,
+        version: Option<u32>,
+        source: &dyn Display,
+        diagnostics: &mut Diagnostics,
+    ) -> bool {
+        let mut migrated = false;
+        let mut warn = |message: &str| {
+            if version.is_some() {
+                diagnostics.warn(format!("{message}\nPath: {source}"));
+            }
+        };
+
+        if self == Self::Font
+            && version.unwrap_or(UNVERSIONED) < 2
+            && let Some(font) = table.get_mut("font").and_then(|font| font.as_table_mut())
+            && let Some(value) = font.remove("version")
+        {
+            font.insert("fontlibc_version".to_string(), value);
+            warn("Font field `version` was renamed to `fontlibc_version` in schema 2");
+            migrated = true;
+        }
+
+        migrated
+    }
+
+    /// Warns about every field that isn't in the current version, suggesting the closest one
+    fn check_fields(self, table: &Table, source: &dyn Display, diagnostics: &mut Diagnostics) {
+        let mut check = |table: &Table, location: &str, known: &[&[&str]]| {
+            for field in table.keys() {
+                if known.iter().any(|known| known.contains(&field.as_str())) {
+                    continue;
+                }
+
+                let suggestion = closest(field, known.iter().copied().flatten().copied())
+                    .map(|closest| format!("; did you mean `{closest}`?"))
+                    .unwrap_or_default();
+
+                diagnostics.warn(format!(
+                    "Unknown field `{field}` in {location}{suggestion}\nPath: {source}"
+                ));
+            }
+        };
+        let get = |name: &str| table.get(name).and_then(|value| value.as_table());
+
+        match self {
+            Self::FontPack => {
+                check(table, "the root", &[&[SCHEMA_FIELD, "pack"]]);
+
+                if let Some(pack) = get("pack") {
+                    check(pack, "`[pack]`", &[FontPackDefinition::FIELDS]);
+                }
+            }
+            Self::Font => {
+                check(table, "the root", &[&[SCHEMA_FIELD, "font"]]);
+
+                if let Some(font) = get("font") {
+                    check(font, "`[font]`", &[FontDefinition::FIELDS]);
+                }
+            }
+            Self::Sprite => {
+                check(
+                    table,
+                    "the root",
+                    &[&[
+                        SCHEMA_FIELD,
+                        "sprite",
+                        "sprites",
+                        "shared_palette",
+                        "budget",
+                    ]],
+                );
+
+                if let Some(sprite) = get("sprite") {
+                    check(sprite, "`[sprite]`", &[SpriteDefinition::FIELDS]);
+                }
+
+                let entries = table.get("sprites").and_then(|value| value.as_array());
+
+                for entry in entries
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|entry| entry.as_table())
+                {
+                    check(
+                        entry,
+                        "`[[sprites]]`",
+                        &[SpriteGroupEntry::FIELDS, SpriteDefinition::FIELDS],
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// The number of single character insertions, deletions, or substitutions between the strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+
+        for (j, &b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != b);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+/// The most edits a field can be from a known field to suggest it
+const MAX_SUGGESTION_DISTANCE: usize = 2;
+
+/// The known field closest to the unknown one, if it's close enough to be a typo
+fn closest<'a>(field: &str, known: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    known
+        .into_iter()
+        .map(|known| (edit_distance(field, known), known))
+        .filter(|&(distance, known)| distance <= MAX_SUGGESTION_DISTANCE && distance < known.len())
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, known)| known)
+}
+
+/// Parses the definition, upgrading it from an older version of its schema.
+/// Warns about fields that would otherwise be silently ignored.
+pub fn parse<T: DeserializeOwned>(
+    schema: Schema,
+    raw: &str,
+    source: &dyn Display,
+    diagnostics: &mut Diagnostics,
+) -> anyhow::Result<T> {
+    let mut table = toml::from_str::<Table>(raw)?;
+    let version = schema.version(&table)?;
+    let migrated = schema.migrate(&mut table, version, source, diagnostics);
+    schema.check_fields(&table, source, diagnostics);
+
+    // Parsing the original keeps the line numbers in errors
+    if migrated {
+        Ok(table.try_into()?)
+    } else {
+        Ok(toml::from_str(raw)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font::definition::FontDefinitionWrapper;
+
+    fn parse_font(raw: &str) -> (anyhow::Result<FontDefinitionWrapper>, Diagnostics) {
+        let mut diagnostics = Diagnostics::default();
+        let definition = parse(Schema::Font, raw, &"font.toml", &mut diagnostics);
+
+        (definition, diagnostics)
+    }
+
+    #[test]
+    fn schema_newer() {
+        let (definition, _) = parse_font("schema = 3\n[font]\nheight = 8");
+
+        assert_eq!(
+            definition.unwrap_err().to_string(),
+            "The font schema 3 is newer than the latest supported: 2\n\
+             Please upgrade ti-asset-builder"
+        );
+        assert!(parse_font("schema = 0\n[font]\nheight = 8").0.is_err());
+        assert!(parse_font("schema = \"2\"\n[font]\nheight = 8").0.is_err());
+    }
+
+    #[test]
+    fn schema_current() {
+        let (definition, diagnostics) =
+            parse_font("schema = 2\n[font]\nfontlibc_version = 1\nheight = 8");
+
+        assert_eq!(definition.unwrap().font.fontlibc_version, 1);
+        assert!(diagnostics.warnings().is_empty());
+    }
+
+    // Unversioned fonts are schema 1, which still called it `version`
+    #[test]
+    fn migrate_unversioned_font_version() {
+        let (definition, diagnostics) = parse_font("[font]\nversion = 1\nheight = 8");
+
+        assert_eq!(definition.unwrap().font.fontlibc_version, 1);
+        assert!(diagnostics.warnings().is_empty());
+    }
+
+    #[test]
+    fn migrate_font_version() {
+        let (definition, diagnostics) = parse_font("schema = 1\n[font]\nversion = 1\nheight = 8");
+
+        assert_eq!(definition.unwrap().font.fontlibc_version, 1);
+        assert_eq!(
+            diagnostics.warnings(),
+            [
+                "Font field `version` was renamed to `fontlibc_version` in schema 2\n\
+              Path: font.toml"
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_fields() {
+        let (definition, diagnostics) =
+            parse_font("schema = 2\nextra = 1\n[font]\nhieght = 8\nversion = 0");
+
+        assert!(definition.is_ok());
+        assert_eq!(
+            diagnostics.warnings(),
+            [
+                "Unknown field `extra` in the root\nPath: font.toml",
+                "Unknown field `hieght` in `[font]`; did you mean `height`?\nPath: font.toml",
+                "Unknown field `version` in `[font]`\nPath: font.toml",
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_sprite_group_fields() {
+        let mut diagnostics = Diagnostics::default();
+        let raw = "[[sprites]]\nname = \"a\"\nsource = \"a\"\npalete_offset = 1";
+
+        parse::<toml::Table>(Schema::Sprite, raw, &"sprite.toml", &mut diagnostics).unwrap();
+
+        assert_eq!(
+            diagnostics.warnings(),
+            [
+                "Unknown field `palete_offset` in `[[sprites]]`; did you mean `palette_offset`?\n\
+                 Path: sprite.toml"
+            ]
+        );
+    }
+
+    #[test]
+    fn edit_distance_example() {
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("same", "same"), 0);
+    }
+}
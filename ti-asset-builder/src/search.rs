@@ -0,0 +1,161 @@
+use std::{
+    ffi::OsStr,
+    path::{Path, PathBuf},
+};
+
+use anyhow::anyhow;
+
+use crate::{
+    error::{ErrorKind, ResultExt},
+    path::PathExt,
+};
+
+/// Lists more asset paths, separated like `PATH` on the platform
+pub const ASSET_PATH_VAR: &str = "TI_ASSET_PATH";
+
+/// The directories searched for files that aren't relative to their definition, in order
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SearchPaths(Vec<PathBuf>);
+
+impl SearchPaths {
+    /// The `--asset-path` directories are searched before the environment's
+    pub fn new(flags: &[PathBuf], env: Option<&OsStr>) -> Self {
+        let env = env
+            .into_iter()
+            .flat_map(std::env::split_paths)
+            .filter(|path| !path.as_os_str().is_empty());
+
+        Self(flags.iter().cloned().chain(env).collect())
+    }
+
+    /// Uses the `--asset-path` directories and those in `TI_ASSET_PATH`
+    pub fn from_env(flags: &[PathBuf]) -> Self {
+        Self::new(flags, std::env::var_os(ASSET_PATH_VAR).as_deref())
+    }
+
+    /// Finds the first file that exists, starting with the path relative to the definition.
+    /// Absolute paths aren't searched for.
+    pub fn resolve(
+        &self,
+        from_definition: PathBuf,
+        relative: &Path,
+        suffix: &str,
+    ) -> anyhow::Result<PathBuf> {
+        self.resolve_with(from_definition, relative, suffix, Path::is_file)
+    }
+
+    fn resolve_with(
+        &self,
+        from_definition: PathBuf,
+        relative: &Path,
+        suffix: &str,
+        exists: impl Fn(&Path) -> bool,
+    ) -> anyhow::Result<PathBuf> {
+        let mut candidates = vec![from_definition];
+
+        if !relative.is_absolute() {
+            for root in &self.0 {
+                candidates.push(root.relative_suffix(relative, suffix)?);
+            }
+        }
+
+        if let Some(found) = candidates.iter().find(|candidate| exists(candidate)) {
+            return Ok(found.clone());
+        }
+
+        let tried = candidates
+            .iter()
+            .map(|candidate| format!("- {candidate:?}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        Err(anyhow!("Failed to find {relative:?}\nTried:\n{tried}"))
+            .with_kind(ErrorKind::MissingInput)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn search_paths() -> SearchPaths {
+        SearchPaths::new(
+            &[PathBuf::from("/flag")],
+            Some(
+                std::env::join_paths(["/env/first", "/env/second"])
+                    .unwrap()
+                    .as_os_str(),
+            ),
+        )
+    }
+
+    fn resolve(relative: &str, existing: &[&str]) -> anyhow::Result<PathBuf> {
+        let relative = Path::new(relative);
+        let from_definition = Path::new("/fonts").relative_suffix(relative, ".png")?;
+
+        search_paths().resolve_with(from_definition, relative, ".png", |path| {
+            existing.iter().any(|existing| path == Path::new(existing))
+        })
+    }
+
+    #[test]
+    fn search_paths_order() {
+        assert_eq!(
+            search_paths().0,
+            [
+                Path::new("/flag"),
+                Path::new("/env/first"),
+                Path::new("/env/second")
+            ]
+        );
+        assert_eq!(
+            SearchPaths::new(&[], Some(OsStr::new(""))).0,
+            [] as [PathBuf; 0]
+        );
+    }
+
+    #[test]
+    fn resolve_precedence() {
+        let all = [
+            "/fonts/a.png",
+            "/flag/a.png",
+            "/env/first/a.png",
+            "/env/second/a.png",
+        ];
+
+        assert_eq!(resolve("a", &all).unwrap(), Path::new("/fonts/a.png"));
+        assert_eq!(resolve("a", &all[1..]).unwrap(), Path::new("/flag/a.png"));
+        assert_eq!(
+            resolve("a", &all[2..]).unwrap(),
+            Path::new("/env/first/a.png")
+        );
+        assert_eq!(
+            resolve("a", &all[3..]).unwrap(),
+            Path::new("/env/second/a.png")
+        );
+    }
+
+    #[test]
+    fn resolve_missing() {
+        let error = resolve("common/a", &[]).unwrap_err();
+
+        assert_eq!(ErrorKind::of(&error), ErrorKind::MissingInput);
+        assert_eq!(
+            error.to_string(),
+            "Failed to find \"common/a\"\nTried:\n\
+             - \"/fonts/common/a.png\"\n\
+             - \"/flag/common/a.png\"\n\
+             - \"/env/first/common/a.png\"\n\
+             - \"/env/second/common/a.png\""
+        );
+    }
+
+    #[test]
+    fn resolve_absolute() {
+        assert!(resolve("/flag/a", &["/env/first/flag/a.png"]).is_err());
+        assert_eq!(
+            resolve("/flag/a", &["/flag/a.png"]).unwrap(),
+            Path::new("/flag/a.png")
+        );
+    }
+}
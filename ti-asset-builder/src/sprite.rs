@@ -1,9 +1,34 @@
-use std::path::Path;
+pub mod definition;
+mod output;
+mod palette;
 
-use anyhow::Context;
+use std::{
+    collections::HashSet,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use anyhow::{Context, anyhow, bail};
 use image::GenericImageView;
+use log::info;
 
-use crate::cli::CliSpriteCommand;
+use crate::{
+    budget::{BudgetUsage, DEFAULT_WARN_PERCENT},
+    cache::{self, Cache},
+    check::Problems,
+    cli::CliSpriteCommand,
+    definition::{DefinitionSource, is_stdin},
+    depfile,
+    diagnostics::Diagnostics,
+    error::{ErrorKind, ResultExt},
+    inputs::Inputs,
+    output::OutputType,
+    path::{PathBufExt, PathExt},
+    report::{Report, ReportOutput},
+    schema::{self, Schema},
+    sprite::definition::{SpriteDefinition, SpriteDefinitionWrapper, SpriteGroupEntry, SpriteMode},
+};
 
 #[derive(Debug, Clone, Copy)]
 pub struct ColorRGB24 {
@@ -78,6 +103,33 @@ impl From<ColorRGB24> for Color8 {
     }
 }
 
+/// A 16-bit color as used by the graphx palette.
+/// Bits are laid out as `0bRRRRRGGGGGBBBBB`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ColorRGB1555(u16);
+
+impl From<u16> for ColorRGB1555 {
+    fn from(value: u16) -> Self {
+        Self(value)
+    }
+}
+
+impl From<ColorRGB1555> for u16 {
+    fn from(value: ColorRGB1555) -> Self {
+        value.0
+    }
+}
+
+impl From<ColorRGB24> for ColorRGB1555 {
+    fn from(value: ColorRGB24) -> Self {
+        let (red, green, blue) = value.into();
+        let red = (red as u16 >> 3) << 10;
+        let green = (green as u16 >> 3) << 5;
+        let blue = blue as u16 >> 3;
+        Self(red | green | blue)
+    }
+}
+
 pub struct RawImage {
     image: image::DynamicImage,
 }
@@ -88,8 +140,13 @@ impl RawImage {
             .await
             .with_context(|| format!("Failed to read image file at: {path:?}"))?;
 
-        let image = image::load_from_memory_with_format(&file, image::ImageFormat::Png)
-            .with_context(|| format!("Failed to parse PNG: {path:?}"))?;
+        // Decoding is CPU bound, so it's kept off of the async workers
+        let image = tokio::task::spawn_blocking(move || {
+            image::load_from_memory_with_format(&file, image::ImageFormat::Png)
+        })
+        .await
+        .context("PNG decoding task panicked")?
+        .with_context(|| format!("Failed to parse PNG: {path:?}"))?;
 
         Ok(Self { image })
     }
@@ -107,6 +164,23 @@ impl RawImage {
         (width, height, pixels)
     }
 
+    /// Returns the width, height, and pixel data of the image
+    /// Fully transparent pixels are `None`
+    pub fn into_rgba32(self) -> (u32, u32, Vec<Option<ColorRGB24>>) {
+        let (width, height) = self.image.dimensions();
+        let pixels = self
+            .image
+            .into_rgba8()
+            .pixels()
+            .map(|pixel| {
+                let [red, green, blue, alpha] = pixel.0;
+                (alpha != 0).then_some([red, green, blue].into())
+            })
+            .collect();
+
+        (width, height, pixels)
+    }
+
     /// Returns the width, height, and pixel data of the image
     pub fn into_monochrome(self) -> (u32, u32, Vec<ColorMonochrome>) {
         let (width, height) = self.image.dimensions();
@@ -121,6 +195,556 @@ impl RawImage {
     }
 }
 
-pub async fn build(command: CliSpriteCommand) -> anyhow::Result<()> {
-    todo!()
+/// A sprite's size and pixels. Fully transparent pixels are `None`.
+struct SpriteImage {
+    width: u32,
+    height: u32,
+    pixels: Vec<Option<ColorRGB24>>,
+}
+
+async fn load_sprite_definition(
+    source: &DefinitionSource,
+    diagnostics: &mut Diagnostics,
+) -> anyhow::Result<SpriteDefinitionWrapper> {
+    let raw = source.read().await?;
+    let definition =
+        schema::parse::<SpriteDefinitionWrapper>(Schema::Sprite, &raw, source, diagnostics)
+            .with_context(|| format!("Failed to parse sprite definition from {source}"))?;
+
+    Ok(definition)
+}
+
+async fn load_sprite_image(
+    source: &DefinitionSource,
+    definition: &SpriteDefinition,
+    inputs: &mut Inputs,
+) -> anyhow::Result<SpriteImage> {
+    let image_path = source
+        .anchor()
+        .relative_suffix(&definition.source, ".png")?;
+    inputs.insert(&image_path);
+    let image = RawImage::load(&image_path).await?;
+
+    let (width, height, pixels) = if definition.transparent_index.is_some() {
+        image.into_rgba32()
+    } else {
+        let (width, height, pixels) = image.into_rgb24();
+        (width, height, pixels.into_iter().map(Some).collect())
+    };
+
+    Ok(SpriteImage {
+        width,
+        height,
+        pixels,
+    })
+}
+
+/// The size of the calculator's screen in pixels
+const SCREEN_SIZE: (u32, u32) = (320, 240);
+
+/// The serialized files of a sprite definition paired with their output paths
+type SpriteOutputs = Vec<(PathBuf, Vec<u8>)>;
+
+/// Options that apply to every sprite in a definition
+#[derive(Debug, Clone, Copy)]
+struct SpriteOptions {
+    allow_any_size: bool,
+    keep_going: bool,
+}
+
+/// Serializes the sprite's palette indices in the layout of its mode
+async fn generate_sprite(
+    definition: &SpriteDefinition,
+    image: &SpriteImage,
+    data: Vec<u8>,
+    options: SpriteOptions,
+) -> anyhow::Result<Vec<u8>> {
+    match definition.mode {
+        SpriteMode::Sprite => {
+            output::bin::generate_sprite(image.width, image.height, data, definition.compression)
+                .await
+        }
+        SpriteMode::Background => {
+            let strip_height = definition
+                .strip_height
+                .context("Background sprites require a `strip_height`")?;
+
+            if strip_height == 0 {
+                bail!("Background strip height must be greater than 0");
+            }
+
+            if !options.allow_any_size && (image.width, image.height) != SCREEN_SIZE {
+                bail!(
+                    "Background must be the size of the screen: {}x{} != {}x{}\n\
+                     Use `--allow-any-size` to allow other sizes",
+                    image.width,
+                    image.height,
+                    SCREEN_SIZE.0,
+                    SCREEN_SIZE.1
+                );
+            }
+
+            output::bin::generate_background(
+                image.width,
+                image.height,
+                strip_height,
+                data,
+                definition.compression,
+            )
+            .await
+        }
+    }
+}
+
+/// Generates a sprite along with its own palette
+async fn generate_single(
+    source: &DefinitionSource,
+    definition: &SpriteDefinition,
+    output: &Path,
+    options: SpriteOptions,
+    inputs: &mut Inputs,
+) -> anyhow::Result<SpriteOutputs> {
+    let image = load_sprite_image(source, definition, inputs).await?;
+    let (palette, data) = palette::quantize(
+        &image.pixels,
+        definition.palette_offset,
+        definition.transparent_index,
+    )
+    .with_context(|| {
+        format!(
+            "Failed to generate palette for sprite: {:?}",
+            definition.source
+        )
+    })?;
+
+    Ok(vec![
+        (
+            output.to_path_buf(),
+            generate_sprite(definition, &image, data, options).await?,
+        ),
+        (
+            output::get_palette_path(output),
+            output::bin::generate_palette(palette).await?,
+        ),
+    ])
+}
+
+/// Checks every sprite has a unique name and the same palette options if shared
+fn validate_group(entries: &[SpriteGroupEntry], shared_palette: bool) -> anyhow::Result<()> {
+    let mut names = HashSet::with_capacity(entries.len());
+
+    for (index, entry) in entries.iter().enumerate() {
+        if entry.name.is_empty() {
+            bail!("Sprite {index} has an empty name");
+        }
+
+        crate::output::validate_file_name(&entry.name)
+            .with_context(|| format!("Sprite {index} has an invalid name"))?;
+
+        if !names.insert(&entry.name) {
+            bail!("Sprite {index} has a duplicate name: {:?}", entry.name);
+        }
+    }
+
+    if shared_palette && let Some(first) = entries.first() {
+        let first = &first.sprite;
+
+        for (index, entry) in entries.iter().enumerate() {
+            if entry.sprite.palette_offset != first.palette_offset
+                || entry.sprite.transparent_index != first.transparent_index
+            {
+                bail!(
+                    "Sprite {index} ({:?}) has different palette options from the first sprite; \
+                     shared palettes require the same `palette_offset` and `transparent_index`",
+                    entry.name
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Adds which sprite in the group failed to the error
+fn with_entry<T>(
+    result: anyhow::Result<T>,
+    index: usize,
+    entry: &SpriteGroupEntry,
+) -> anyhow::Result<T> {
+    result.with_context(|| format!("Failed to build sprite {index}: {:?}", entry.name))
+}
+
+/// Generates every sprite in the group into the output directory.
+/// Stops at the first failed sprite unless `keep_going` is set.
+async fn generate_group(
+    source: &DefinitionSource,
+    entries: &[SpriteGroupEntry],
+    shared_palette: bool,
+    output: &Path,
+    options: SpriteOptions,
+    problems: &mut Problems,
+    inputs: &mut Inputs,
+) -> SpriteOutputs {
+    let mut outputs = Vec::new();
+
+    if problems
+        .check(validate_group(entries, shared_palette))
+        .is_none()
+    {
+        return outputs;
+    }
+
+    let get_output = |entry: &SpriteGroupEntry| output.join(&entry.name).append_str(".bin");
+
+    if shared_palette {
+        let mut images = Vec::with_capacity(entries.len());
+
+        for (index, entry) in entries.iter().enumerate() {
+            let image = load_sprite_image(source, &entry.sprite, inputs).await;
+
+            match problems.check(with_entry(image, index, entry)) {
+                Some(image) => images.push((index, entry, image)),
+                None if options.keep_going => (),
+                None => return outputs,
+            }
+        }
+
+        // Validated to be the same for every sprite
+        let (palette_offset, transparent_index) = entries
+            .first()
+            .map(|entry| (entry.sprite.palette_offset, entry.sprite.transparent_index))
+            .unwrap_or_default();
+        let pixels = images
+            .iter()
+            .map(|(_, _, image)| image.pixels.as_slice())
+            .collect::<Vec<_>>();
+        let palette = palette::quantize_shared(&pixels, palette_offset, transparent_index)
+            .context("Failed to generate shared palette");
+        let Some((palette, data)) = problems.check(palette) else {
+            return outputs;
+        };
+
+        for ((index, entry, image), data) in images.iter().zip(data) {
+            let sprite = generate_sprite(&entry.sprite, image, data, options).await;
+
+            match problems.check(with_entry(sprite, *index, entry)) {
+                Some(sprite) => outputs.push((get_output(entry), sprite)),
+                None if options.keep_going => (),
+                None => return outputs,
+            }
+        }
+
+        let palette_name = source.stem().context(
+            "Shared palettes are named after the sprite definition, which has no file name",
+        );
+        let Some(palette_name) = problems.check(palette_name) else {
+            return outputs;
+        };
+
+        if let Some(palette) = problems.check(output::bin::generate_palette(palette).await) {
+            outputs.push((
+                output::get_palette_path(&output.join(palette_name)),
+                palette,
+            ));
+        }
+    } else {
+        for (index, entry) in entries.iter().enumerate() {
+            let sprite =
+                generate_single(source, &entry.sprite, &get_output(entry), options, inputs).await;
+
+            match problems.check(with_entry(sprite, index, entry)) {
+                Some(sprite) => outputs.extend(sprite),
+                None if options.keep_going => (),
+                None => return outputs,
+            }
+        }
+    }
+
+    outputs
+}
+
+/// Generates every sprite in the definition.
+/// For groups, the output is a directory.
+async fn generate(
+    source: &DefinitionSource,
+    definition: &SpriteDefinitionWrapper,
+    output: &Path,
+    options: SpriteOptions,
+    problems: &mut Problems,
+    inputs: &mut Inputs,
+) -> SpriteOutputs {
+    match (&definition.sprite, definition.sprites.is_empty()) {
+        (Some(sprite), true) => problems
+            .check(generate_single(source, sprite, output, options, inputs).await)
+            .unwrap_or_default(),
+        (None, false) => {
+            generate_group(
+                source,
+                &definition.sprites,
+                definition.shared_palette,
+                output,
+                options,
+                problems,
+                inputs,
+            )
+            .await
+        }
+        (Some(_), false) => {
+            problems.push(anyhow!(
+                "Sprite definition can't have both `[sprite]` and `[[sprites]]`"
+            ));
+            Vec::new()
+        }
+        (None, true) => {
+            problems.push(anyhow!(
+                "Sprite definition has no `[sprite]` or `[[sprites]]`"
+            ));
+            Vec::new()
+        }
+    }
+}
+
+/// Runs every loading and validation stage of a build without writing anything
+pub async fn check(
+    definition: &Path,
+    allow_any_size: bool,
+    problems: &mut Problems,
+    diagnostics: &mut Diagnostics,
+) {
+    let options = SpriteOptions {
+        allow_any_size,
+        keep_going: true,
+    };
+
+    let Some(source) = problems.check(DefinitionSource::new(definition, None)) else {
+        return;
+    };
+    let Some(definition) = problems.check(load_sprite_definition(&source, diagnostics).await)
+    else {
+        return;
+    };
+
+    let outputs = generate(
+        &source,
+        &definition,
+        Path::new(""),
+        options,
+        problems,
+        &mut Inputs::default(),
+    )
+    .await;
+
+    if let Some(budget) = definition.budget {
+        problems.check(check_budget(
+            &outputs,
+            budget,
+            DEFAULT_WARN_PERCENT,
+            diagnostics,
+        ));
+    }
+}
+
+/// Checks every output of the definition fits in its budget together
+fn check_budget(
+    outputs: &SpriteOutputs,
+    budget: NonZeroUsize,
+    warn_percent: u8,
+    diagnostics: &mut Diagnostics,
+) -> anyhow::Result<BudgetUsage> {
+    let used = outputs.iter().map(|(_, data)| data.len()).sum();
+    BudgetUsage::check("Sprites", used, budget, warn_percent, diagnostics)
+}
+
+/// Builds every sprite in the definition, recording every file read into `inputs`
+pub async fn build(
+    command: CliSpriteCommand,
+    diagnostics: &mut Diagnostics,
+    inputs: &mut Inputs,
+) -> anyhow::Result<()> {
+    let started = Instant::now();
+
+    match command.output_type {
+        OutputType::Binary => (),
+        OutputType::Assembly => bail!("Assembly sprite output is not implemented yet"),
+        OutputType::C => bail!("C sprite output is not implemented yet"),
+    }
+
+    let options = SpriteOptions {
+        allow_any_size: command.allow_any_size,
+        keep_going: command.keep_going,
+    };
+
+    if !is_stdin(&command.definition) {
+        inputs.insert(&command.definition);
+    }
+
+    let source = DefinitionSource::new(&command.definition, command.base_dir.as_deref())?;
+    let definition = load_sprite_definition(&source, diagnostics).await?;
+
+    // Groups always output to a directory
+    let output_path = if definition.sprites.is_empty() {
+        let output_path =
+            crate::output::resolve_path(&command.output, source.stem(), command.output_type)?;
+        crate::output::create_parent(&output_path).await?;
+        output_path
+    } else {
+        tokio::fs::create_dir_all(&command.output)
+            .await
+            .with_context(|| format!("Failed to create output directory: {:?}", command.output))
+            .with_kind(ErrorKind::Io)?;
+        command.output.clone()
+    };
+
+    let cache_path = cache::path(&command.definition, command.cache.as_deref(), &output_path);
+    let cache_options = format!(
+        "sprite {:?} {:?} {} {:?} {}",
+        command.output_type,
+        command.base_dir,
+        command.allow_any_size,
+        command.budget,
+        command.budget_warn
+    );
+
+    // The report needs the layout of the outputs, which isn't cached
+    if let Some(cache_path) = &cache_path
+        && !command.force
+        && command.report_json.is_none()
+        && let Some(output_paths) =
+            cache::is_up_to_date(cache_path, &cache_options, diagnostics, inputs).await
+    {
+        diagnostics.check_strict()?;
+        println!("{} is up to date", output_path.display());
+
+        if let Some(depfile) = &command.depfile {
+            depfile::write(depfile, &output_paths, inputs).await?;
+        }
+
+        return Ok(());
+    }
+
+    let mut problems = Problems::default();
+    let outputs = generate(
+        &source,
+        &definition,
+        &output_path,
+        options,
+        &mut problems,
+        inputs,
+    )
+    .await;
+
+    let budget_usage = command
+        .budget
+        .or(definition.budget)
+        .map(|budget| check_budget(&outputs, budget, command.budget_warn, diagnostics))
+        .transpose()?;
+    diagnostics.check_strict()?;
+    let mut reports = Vec::with_capacity(outputs.len());
+    let mut output_paths = Vec::with_capacity(outputs.len());
+
+    for (path, data) in outputs {
+        info!("Writing sprite output to: {path:?}");
+        reports.push(ReportOutput::new(&path, &data, &[]));
+        tokio::fs::write(&path, data)
+            .await
+            .with_context(|| format!("Failed to write output sprite file: {path:?}"))
+            .with_kind(ErrorKind::Io)?;
+        output_paths.push(path);
+    }
+
+    problems.into_result()?;
+
+    // Groups depend on the files written, not the directory they're in
+    if let Some(depfile) = &command.depfile {
+        depfile::write(depfile, &output_paths, inputs).await?;
+    }
+
+    if let Some(report_path) = &command.report_json {
+        Report::new(started, inputs, reports, diagnostics, budget_usage)
+            .await?
+            .write(report_path)
+            .await?;
+    }
+
+    if let Some(cache_path) = &cache_path {
+        Cache::new(cache_options, inputs, &output_paths, diagnostics)
+            .await?
+            .write(cache_path)
+            .await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn group_entry(name: &str, palette_offset: u8) -> SpriteGroupEntry {
+        SpriteGroupEntry {
+            name: name.to_string(),
+            sprite: SpriteDefinition {
+                source: name.into(),
+                palette_offset,
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn definition_group() {
+        let definition = toml::from_str::<SpriteDefinitionWrapper>(
+            r#"
+            shared_palette = true
+
+            [[sprites]]
+            name = "player"
+            source = "images/player"
+            palette_offset = 16
+
+            [[sprites]]
+            name = "enemy"
+            source = "images/enemy"
+            palette_offset = 16
+            "#,
+        )
+        .unwrap();
+
+        assert!(definition.sprite.is_none());
+        assert!(definition.shared_palette);
+        assert_eq!(definition.sprites.len(), 2);
+        assert_eq!(definition.sprites[1].name, "enemy");
+        assert_eq!(definition.sprites[1].sprite.palette_offset, 16);
+    }
+
+    #[test]
+    fn validate_group_duplicate_name() {
+        let entries = [
+            group_entry("a", 0),
+            group_entry("b", 0),
+            group_entry("a", 0),
+        ];
+
+        assert!(validate_group(&entries, false).is_err());
+    }
+
+    #[test]
+    fn validate_group_path_name() {
+        for name in ["../escape", "nested/sprite", "/root", ".", ".."] {
+            let entries = [group_entry("a", 0), group_entry(name, 0)];
+
+            assert!(
+                validate_group(&entries, false).is_err(),
+                "Accepted {name:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn validate_group_shared_palette() {
+        let entries = [group_entry("a", 0), group_entry("b", 16)];
+
+        assert!(validate_group(&entries, false).is_ok());
+        assert!(validate_group(&entries, true).is_err());
+    }
 }
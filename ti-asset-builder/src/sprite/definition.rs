@@ -0,0 +1,85 @@
+use std::{num::NonZeroUsize, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// Either a single `[sprite]` or a `[[sprites]]` group
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SpriteDefinitionWrapper {
+    pub sprite: Option<SpriteDefinition>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sprites: Vec<SpriteGroupEntry>,
+    /// Whether every sprite in the group shares one palette.
+    /// All sprites must then have the same palette options.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub shared_palette: bool,
+    /// The most bytes every output of the definition may use together.
+    pub budget: Option<NonZeroUsize>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SpriteGroupEntry {
+    /// The name of the sprite's output file without an extension.
+    pub name: String,
+    #[serde(flatten)]
+    pub sprite: SpriteDefinition,
+}
+
+impl SpriteGroupEntry {
+    /// The fields of a `[[sprites]]` entry, besides those of the sprite
+    pub const FIELDS: &[&str] = &["name"];
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct SpriteDefinition {
+    /// A path relative from the sprite definition to the sprite's PNG without the `.png`
+    /// extension.
+    pub source: PathBuf,
+    /// The first palette index the sprite's colors are assigned to.
+    /// Indices below this are left untouched for colors reserved by the program.
+    #[serde(default)]
+    pub palette_offset: u8,
+    /// The palette index fully transparent pixels are mapped to.
+    /// No colors will be assigned to this index.
+    #[serde(default)]
+    pub transparent_index: Option<u8>,
+    /// How the sprite is laid out in the output.
+    #[serde(default)]
+    pub mode: SpriteMode,
+    /// The height of each strip in pixels when in background mode.
+    /// The last strip is shorter if the height doesn't divide evenly.
+    pub strip_height: Option<u8>,
+    /// Compresses the sprite, or each strip of a background, to be decompressed when loaded.
+    pub compression: Option<SpriteCompression>,
+}
+
+impl SpriteDefinition {
+    /// Every field in the `[sprite]` table
+    pub const FIELDS: &[&str] = &[
+        "source",
+        "palette_offset",
+        "transparent_index",
+        "mode",
+        "strip_height",
+        "compression",
+    ];
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SpriteMode {
+    /// A single graphx sprite.
+    #[default]
+    Sprite,
+    /// A full-screen image split into horizontal strips sharing one palette.
+    /// Each strip has its own header and is pointed to by an index at the start of the output.
+    Background,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SpriteCompression {
+    /// ZX7, as decompressed by the CE toolchain's `zx7_Decompress`.
+    Zx7,
+    /// ZX0, as decompressed by the CE toolchain's `zx0_Decompress`.
+    Zx0,
+}
@@ -0,0 +1,8 @@
+use std::path::{Path, PathBuf};
+
+pub mod bin;
+
+/// The palette is written next to the sprite with a `.pal` extension.
+pub fn get_palette_path(output: &Path) -> PathBuf {
+    output.with_extension("pal")
+}
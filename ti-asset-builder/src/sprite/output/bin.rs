@@ -0,0 +1,282 @@
+use anyhow::Context;
+use serseg::prelude::*;
+
+use crate::sprite::{definition::SpriteCompression, palette::SpritePalette};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum SectorId {
+    Sprite,
+    Palette,
+    BackgroundIndex,
+    BackgroundStrip(usize),
+}
+
+type SectorBuilder = SerialSectorBuilder<SectorId>;
+type Builder = SerialBuilder<SectorId>;
+
+/// Adds the sector, compressed if the sprite is
+fn add_sector(
+    builder: Builder,
+    key: SectorId,
+    sector: SectorBuilder,
+    compression: Option<SpriteCompression>,
+) -> Builder {
+    match compression {
+        Some(SpriteCompression::Zx7) => builder.sector_compressed(key, sector, Compression::Zx7),
+        Some(SpriteCompression::Zx0) => builder.sector_compressed(key, sector, Compression::Zx0),
+        None => builder.sector(key, sector),
+    }
+}
+
+/// Generates a graphx sprite; the width and height followed by each pixel's palette index.
+fn generate_sprite_builder(
+    width: u32,
+    height: u32,
+    data: Vec<u8>,
+    compression: Option<SpriteCompression>,
+) -> anyhow::Result<Builder> {
+    let width = u8::try_from(width).with_context(|| {
+        format!(
+            "Sprite width must be within range [{}, {}]. Found width: {}",
+            u8::MIN,
+            u8::MAX,
+            width
+        )
+    })?;
+    let height = u8::try_from(height).with_context(|| {
+        format!(
+            "Sprite height must be within range [{}, {}]. Found height: {}",
+            u8::MIN,
+            u8::MAX,
+            height
+        )
+    })?;
+
+    Ok(add_sector(
+        Builder::default(),
+        SectorId::Sprite,
+        SectorBuilder::default().u8(width).u8(height).bytes(data),
+        compression,
+    ))
+}
+
+/// Generates a background split into strips of `strip_height` rows.
+///
+/// The index starts with the number of strips followed by a pointer to each strip.
+/// graphx sprites can't be wider than 255 pixels, so each strip's header stores the width as a
+/// 16-bit value followed by the strip's height.
+/// Each strip is compressed on its own, so it can be decompressed as it's drawn.
+fn generate_background_builder(
+    width: u32,
+    height: u32,
+    strip_height: u8,
+    data: Vec<u8>,
+    compression: Option<SpriteCompression>,
+) -> anyhow::Result<Builder> {
+    let width = u16::try_from(width).with_context(|| {
+        format!(
+            "Background width must be within range [{}, {}]. Found width: {}",
+            u16::MIN,
+            u16::MAX,
+            width
+        )
+    })?;
+    let strip_count = height.div_ceil(strip_height as u32);
+    let strip_count = u8::try_from(strip_count).with_context(|| {
+        format!(
+            "Background has too many strips: {strip_count} > {}\n\
+             Use a larger strip height",
+            u8::MAX
+        )
+    })?;
+
+    let mut index_builder = SectorBuilder::default().u8(strip_count);
+    let mut strips = Vec::with_capacity(strip_count as usize);
+
+    for (strip_index, strip) in data
+        .chunks(width as usize * strip_height as usize)
+        .enumerate()
+    {
+        index_builder = index_builder.dynamic_u24(
+            SectorId::BackgroundIndex,
+            SectorId::BackgroundStrip(strip_index),
+            0,
+        );
+        let rows = (strip.len() / width as usize) as u8;
+        strips.push((
+            SectorId::BackgroundStrip(strip_index),
+            SectorBuilder::default()
+                .u16(width)
+                .u8(rows)
+                .bytes(strip.iter().copied()),
+        ));
+    }
+
+    Ok(strips.into_iter().fold(
+        Builder::default().sector(SectorId::BackgroundIndex, index_builder),
+        |builder, (key, strip)| add_sector(builder, key, strip, compression),
+    ))
+}
+
+/// Generates the palette; the absolute index of the first entry, the size of the entries in
+/// bytes, then each entry.
+/// These line up with the arguments of `gfx_SetPalette`.
+fn generate_palette_builder(palette: SpritePalette) -> anyhow::Result<Builder> {
+    let size = u16::try_from(palette.size())
+        .with_context(|| format!("Palette is too large: {} bytes", palette.size()))?;
+
+    let palette_builder = palette.entries.into_iter().fold(
+        SectorBuilder::default().u8(palette.offset).u16(size),
+        |builder, color| builder.u16(u16::from(color)),
+    );
+
+    Ok(Builder::default().sector(SectorId::Palette, palette_builder))
+}
+
+/// Serializes the builder
+async fn generate(builder: Builder) -> anyhow::Result<Vec<u8>> {
+    builder.build_to_vec().await
+}
+
+pub async fn generate_sprite(
+    width: u32,
+    height: u32,
+    data: Vec<u8>,
+    compression: Option<SpriteCompression>,
+) -> anyhow::Result<Vec<u8>> {
+    generate(generate_sprite_builder(width, height, data, compression)?).await
+}
+
+pub async fn generate_background(
+    width: u32,
+    height: u32,
+    strip_height: u8,
+    data: Vec<u8>,
+    compression: Option<SpriteCompression>,
+) -> anyhow::Result<Vec<u8>> {
+    generate(generate_background_builder(
+        width,
+        height,
+        strip_height,
+        data,
+        compression,
+    )?)
+    .await
+}
+
+pub async fn generate_palette(palette: SpritePalette) -> anyhow::Result<Vec<u8>> {
+    generate(generate_palette_builder(palette)?).await
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sprite::{ColorRGB24, palette::quantize};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn generate_offset_indices() {
+        let red = ColorRGB24::from([255, 0, 0]);
+        let blue = ColorRGB24::from([0, 0, 255]);
+        let (palette, data) =
+            quantize(&[Some(red), None, Some(blue), None], 16, Some(255)).unwrap();
+
+        let sprite = generate_sprite_builder(2, 2, data, None)
+            .unwrap()
+            .build_to_vec()
+            .await
+            .unwrap();
+
+        // The top left pixel is the first color after the reserved indices
+        assert_eq!(sprite[2], 16);
+        assert_eq!(sprite, [2, 2, 16, 255, 17, 255]);
+
+        let buffer = generate_palette_builder(palette)
+            .unwrap()
+            .build_to_vec()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            buffer,
+            [
+                16, // Offset
+                4, 0, // Size
+                0x00, 0x7C, // Red
+                0x1F, 0x00, // Blue
+            ]
+        );
+    }
+
+    // A downscaled background with a shorter last strip
+    #[tokio::test]
+    async fn generate_background_strips() {
+        let data = (0..12).collect::<Vec<u8>>();
+
+        let buffer = generate_background_builder(4, 3, 2, data, None)
+            .unwrap()
+            .build_to_vec()
+            .await
+            .unwrap();
+
+        let expected = [
+            // Strip count
+            [2].as_slice(),
+            // First strip pointer
+            &[7, 0, 0],
+            // Second strip pointer
+            &[18, 0, 0],
+            // First strip
+            &[4, 0, 2],
+            &[0, 1, 2, 3, 4, 5, 6, 7],
+            // Second strip
+            &[4, 0, 1],
+            &[8, 9, 10, 11],
+        ]
+        .concat();
+
+        assert_eq!(buffer, expected);
+    }
+
+    // The same fixture, with each strip compressed on its own
+    #[tokio::test]
+    async fn generate_background_strips_compressed() {
+        let data = (0..12).collect::<Vec<u8>>();
+
+        let buffer = generate_background_builder(4, 3, 2, data, Some(SpriteCompression::Zx7))
+            .unwrap()
+            .build_to_vec()
+            .await
+            .unwrap();
+
+        let first = Compression::Zx7
+            .compress(&[4, 0, 2, 0, 1, 2, 3, 4, 5, 6, 7])
+            .unwrap();
+        let second = Compression::Zx7.compress(&[4, 0, 1, 8, 9, 10, 11]).unwrap();
+        let second_offset = 7 + first.len() as u8;
+
+        let expected = [
+            // Strip count
+            [2].as_slice(),
+            // First strip pointer
+            &[7, 0, 0],
+            // Second strip pointer
+            &[second_offset, 0, 0],
+            &first,
+            &second,
+        ]
+        .concat();
+
+        assert_eq!(buffer, expected);
+    }
+
+    #[test]
+    fn generate_background_too_many_strips() {
+        assert!(generate_background_builder(1, 256, 1, vec![0; 256], None).is_err());
+    }
+
+    #[test]
+    fn generate_too_wide() {
+        assert!(generate_sprite_builder(256, 1, vec![0; 256], None).is_err());
+    }
+}
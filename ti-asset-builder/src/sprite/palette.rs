@@ -0,0 +1,188 @@
+use std::collections::{HashMap, hash_map::Entry};
+
+use anyhow::bail;
+
+use crate::sprite::{ColorRGB24, ColorRGB1555};
+
+/// The number of entries in the calculator's palette.
+const PALETTE_LENGTH: usize = 256;
+
+/// The palette indices generated for a sprite.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpritePalette {
+    /// The absolute index of the first entry.
+    pub offset: u8,
+    /// Every generated entry starting at `offset`.
+    /// A transparent index within this range is left as black.
+    pub entries: Vec<ColorRGB1555>,
+}
+
+impl SpritePalette {
+    /// The size of the palette entries in bytes.
+    pub fn size(&self) -> usize {
+        self.entries.len() * size_of::<u16>()
+    }
+}
+
+/// Assigns each color a palette index starting at `offset`, skipping the transparent index.
+/// Fully transparent pixels are `None`.
+///
+/// Returns the palette and the absolute index of every pixel.
+pub fn quantize(
+    pixels: &[Option<ColorRGB24>],
+    offset: u8,
+    transparent_index: Option<u8>,
+) -> anyhow::Result<(SpritePalette, Vec<u8>)> {
+    let (palette, mut data) = quantize_shared(&[pixels], offset, transparent_index)?;
+    Ok((palette, data.remove(0)))
+}
+
+/// Same as [`quantize`], but every sprite shares a single palette.
+///
+/// Returns the palette and the absolute index of every pixel of each sprite.
+pub fn quantize_shared(
+    sprites: &[&[Option<ColorRGB24>]],
+    offset: u8,
+    transparent_index: Option<u8>,
+) -> anyhow::Result<(SpritePalette, Vec<Vec<u8>>)> {
+    let mut colors = Vec::new();
+    let mut color_indices = HashMap::new();
+
+    for color in sprites.iter().copied().flatten().flatten() {
+        let color = ColorRGB1555::from(*color);
+
+        if let Entry::Vacant(entry) = color_indices.entry(color) {
+            entry.insert(0);
+            colors.push(color);
+        }
+    }
+
+    // The transparent index only takes up a slot if it's in the sprite's range
+    let reserved = transparent_index.is_some_and(|index| index >= offset) as usize;
+    let budget = PALETTE_LENGTH - offset as usize - reserved;
+
+    if colors.len() > budget {
+        bail!(
+            "Sprite has too many colors: {} colors > {} available palette entries\n\
+             ({PALETTE_LENGTH} entries - {offset} offset - {reserved} reserved)",
+            colors.len(),
+            budget
+        );
+    }
+
+    let mut palette = SpritePalette {
+        offset,
+        entries: Vec::with_capacity(colors.len() + reserved),
+    };
+
+    for color in colors {
+        let mut index = offset as usize + palette.entries.len();
+
+        if transparent_index.is_some_and(|transparent| transparent as usize == index) {
+            palette.entries.push(ColorRGB1555::default());
+            index += 1;
+        }
+
+        palette.entries.push(color);
+        color_indices.insert(color, index as u8);
+    }
+
+    let data = sprites
+        .iter()
+        .map(|pixels| {
+            pixels
+                .iter()
+                .map(|color| match color {
+                    Some(color) => Ok(color_indices[&ColorRGB1555::from(*color)]),
+                    None => transparent_index.ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "Sprite has transparent pixels, but no transparent index is set"
+                        )
+                    }),
+                })
+                .collect()
+        })
+        .collect::<anyhow::Result<_>>()?;
+
+    Ok((palette, data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: ColorRGB24 = ColorRGB24 {
+        red: 255,
+        green: 0,
+        blue: 0,
+    };
+    const GREEN: ColorRGB24 = ColorRGB24 {
+        red: 0,
+        green: 255,
+        blue: 0,
+    };
+
+    #[test]
+    fn quantize_offset() {
+        let (palette, data) = quantize(&[Some(RED), Some(GREEN), Some(RED)], 16, None).unwrap();
+
+        assert_eq!(palette.offset, 16);
+        assert_eq!(palette.entries, [RED.into(), GREEN.into()]);
+        assert_eq!(data, [16, 17, 16]);
+    }
+
+    #[test]
+    fn quantize_transparent() {
+        let (palette, data) = quantize(&[None, Some(GREEN)], 16, Some(255)).unwrap();
+
+        assert_eq!(palette.entries, [GREEN.into()]);
+        assert_eq!(data, [255, 16]);
+    }
+
+    // Colors skip over a transparent index within the sprite's range
+    #[test]
+    fn quantize_transparent_in_range() {
+        let (palette, data) = quantize(&[Some(RED), None, Some(GREEN)], 16, Some(17)).unwrap();
+
+        assert_eq!(
+            palette.entries,
+            [RED.into(), ColorRGB1555::default(), GREEN.into()]
+        );
+        assert_eq!(data, [16, 17, 18]);
+    }
+
+    #[test]
+    fn quantize_transparent_missing() {
+        assert!(quantize(&[None], 0, None).is_err());
+    }
+
+    #[test]
+    fn quantize_shared_indices() {
+        let (palette, data) =
+            quantize_shared(&[&[Some(RED)], &[Some(GREEN), Some(RED)]], 16, None).unwrap();
+
+        assert_eq!(palette.entries, [RED.into(), GREEN.into()]);
+        assert_eq!(data, [vec![16], vec![17, 16]]);
+    }
+
+    #[test]
+    fn quantize_over_budget() {
+        let pixels = (0..=240)
+            .map(|value: u8| {
+                Some(ColorRGB24::from([
+                    (value & 0b1_1111) << 3,
+                    (value >> 5) << 3,
+                    0,
+                ]))
+            })
+            .collect::<Vec<_>>();
+
+        let error = quantize(&pixels, 16, Some(255)).unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Sprite has too many colors: 241 colors > 239 available palette entries\n\
+             (256 entries - 16 offset - 1 reserved)"
+        );
+    }
+}
@@ -0,0 +1,177 @@
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{Context, bail};
+use jiff::Zoned;
+use log::{debug, warn};
+use notify::{
+    Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher,
+    event::{AccessKind, AccessMode},
+};
+use tokio::sync::mpsc::{UnboundedReceiver, unbounded_channel};
+
+use crate::{definition::is_stdin, inputs::Inputs};
+
+/// How long to wait for more changes before rebuilding
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Prints a status line with the time of the build
+fn report(result: anyhow::Result<()>) {
+    let time = Zoned::now().strftime("%H:%M:%S");
+
+    match result {
+        Ok(()) => println!("[{time}] Build succeeded, watching for changes"),
+        Err(error) => eprintln!("[{time}] Build failed, watching for changes\n{error:?}"),
+    }
+}
+
+/// Whether the event changes any of the inputs.
+/// Reading files also creates events, so those are ignored.
+fn is_change(event: &Event, inputs: &Inputs) -> bool {
+    let is_read = match event.kind {
+        EventKind::Access(AccessKind::Close(AccessMode::Write)) => false,
+        EventKind::Access(_) => true,
+        _ => false,
+    };
+
+    !is_read && event.paths.iter().any(|path| inputs.contains(path))
+}
+
+/// Waits for an input to change, then for the changes to settle
+async fn wait_for_change(
+    events: &mut UnboundedReceiver<notify::Result<Event>>,
+    inputs: &Inputs,
+) -> anyhow::Result<()> {
+    loop {
+        let event = events
+            .recv()
+            .await
+            .context("File watcher stopped unexpectedly")?;
+
+        match event {
+            Ok(event) if is_change(&event, inputs) => break,
+            Ok(_) => (),
+            Err(error) => warn!("File watcher error: {error}"),
+        }
+    }
+
+    while let Ok(Some(_)) = tokio::time::timeout(DEBOUNCE, events.recv()).await {}
+
+    Ok(())
+}
+
+/// Watches the directory of every input.
+/// Directories are watched instead of files so replaced and newly created files are seen.
+fn update_watched(
+    watcher: &mut RecommendedWatcher,
+    watched: &mut BTreeSet<PathBuf>,
+    inputs: &Inputs,
+) {
+    let directories = inputs.directories();
+
+    watched.retain(|directory| {
+        if directories.contains(directory.as_path()) {
+            return true;
+        }
+
+        if let Err(error) = watcher.unwatch(directory) {
+            debug!("Failed to stop watching {directory:?}: {error}");
+        }
+
+        false
+    });
+
+    for directory in directories {
+        if watched.contains(directory) {
+            continue;
+        }
+
+        match watcher.watch(directory, RecursiveMode::NonRecursive) {
+            Ok(()) => {
+                watched.insert(directory.to_path_buf());
+            }
+            Err(error) => warn!("Failed to watch {directory:?}: {error}"),
+        }
+    }
+}
+
+async fn watch_loop(
+    mut build: impl AsyncFnMut(&mut Inputs) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let (sender, mut events) = unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event| {
+        // The receiver only closes when watching stops
+        let _ = sender.send(event);
+    })
+    .context("Failed to start file watcher")?;
+    let mut watched = BTreeSet::new();
+
+    loop {
+        // Rederived every build so changes to the definitions are picked up
+        let mut inputs = Inputs::default();
+        report(build(&mut inputs).await);
+        update_watched(&mut watcher, &mut watched, &inputs);
+        wait_for_change(&mut events, &inputs).await?;
+    }
+}
+
+/// Checks the definition can be read again for each rebuild
+pub fn ensure_watchable(definition: &Path) -> anyhow::Result<()> {
+    if is_stdin(definition) {
+        bail!("Can't watch a definition read from stdin");
+    }
+
+    Ok(())
+}
+
+/// Builds, then rebuilds whenever a file read by the build changes until interrupted
+pub async fn watch(
+    build: impl AsyncFnMut(&mut Inputs) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    tokio::select! {
+        result = watch_loop(build) => result,
+        result = tokio::signal::ctrl_c() => result.context("Failed to listen for Ctrl-C"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use notify::event::{CreateKind, ModifyKind};
+
+    use super::*;
+
+    fn inputs() -> Inputs {
+        let mut inputs = Inputs::default();
+        inputs.insert(Path::new("/fonts/a.png"));
+        inputs
+    }
+
+    #[test]
+    fn change_to_input() {
+        let event = Event::new(EventKind::Modify(ModifyKind::Any)).add_path("/fonts/a.png".into());
+
+        assert!(is_change(&event, &inputs()));
+    }
+
+    #[test]
+    fn change_to_other_file() {
+        let event = Event::new(EventKind::Create(CreateKind::File)).add_path("/fonts/b.png".into());
+
+        assert!(!is_change(&event, &inputs()));
+    }
+
+    // Builds read their inputs, which mustn't trigger another build
+    #[test]
+    fn read_input() {
+        let open = Event::new(EventKind::Access(AccessKind::Open(AccessMode::Read)))
+            .add_path("/fonts/a.png".into());
+        let write = Event::new(EventKind::Access(AccessKind::Close(AccessMode::Write)))
+            .add_path("/fonts/a.png".into());
+
+        assert!(!is_change(&open, &inputs()));
+        assert!(is_change(&write, &inputs()));
+    }
+}
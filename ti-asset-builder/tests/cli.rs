@@ -0,0 +1,1198 @@
+use std::path::{Path, PathBuf};
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+
+fn fixture(path: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(path)
+}
+
+fn output_dir(name: &str) -> PathBuf {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    std::fs::create_dir_all(&path).unwrap();
+    path
+}
+
+#[test]
+fn fontpack_format_binary() {
+    let output = output_dir("fontpack_format_binary").join("pack.bin");
+
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("fontpack")
+        .arg("-d")
+        .arg(fixture("font/pack.toml"))
+        .arg("-o")
+        .arg(&output)
+        .assert()
+        .success();
+
+    assert!(std::fs::read(output).unwrap().starts_with(b"FONTPACK"));
+}
+
+#[test]
+fn fontpack_format_c() {
+    let output = output_dir("fontpack_format_c").join("pack.h");
+
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("fontpack")
+        .arg("-d")
+        .arg(fixture("font/pack.toml"))
+        .arg("-o")
+        .arg(&output)
+        .arg("--format")
+        .arg("c")
+        .arg("--var-name")
+        .arg("FIXTURE")
+        .arg("--symbol-prefix")
+        .arg("fixture_font")
+        .assert()
+        .success();
+
+    let header = std::fs::read_to_string(output).unwrap();
+
+    assert!(header.contains("#define fixture_font_var_name \"FIXTURE\""));
+    assert!(header.contains("static const unsigned char fixture_font["));
+    assert!(header.contains("// font_0_bitmaps"));
+}
+
+#[test]
+fn fontpack_format_assembly() {
+    let output = output_dir("fontpack_format_assembly").join("pack.asm");
+
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("fontpack")
+        .arg("-d")
+        .arg(fixture("font/pack.toml"))
+        .arg("-o")
+        .arg(&output)
+        .arg("--format")
+        .arg("assembly")
+        .arg("--symbol-prefix")
+        .arg("fixture_font")
+        .assert()
+        .success();
+
+    let assembly = std::fs::read_to_string(output).unwrap();
+
+    assert!(assembly.contains("fixture_font:\n"));
+    assert!(assembly.contains("fixture_font_header:\n"));
+}
+
+#[test]
+fn fontpack_format_invalid() {
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("fontpack")
+        .arg("-d")
+        .arg(fixture("font/pack.toml"))
+        .arg("-o")
+        .arg("pack.bin")
+        .arg("--format")
+        .arg("png")
+        .assert()
+        .failure();
+}
+
+#[test]
+fn check_valid() {
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("check")
+        .arg(fixture("font/pack.toml"))
+        .arg(fixture("sprite/player.toml"))
+        .assert()
+        .success();
+}
+
+// Like `sprite`, backgrounds that aren't screen sized are only allowed with the flag
+#[test]
+fn check_allow_any_size() {
+    let directory = output_dir("check_allow_any_size");
+    let definition = directory.join("background.toml");
+    std::fs::copy(fixture("sprite/player.png"), directory.join("player.png")).unwrap();
+    std::fs::write(
+        &definition,
+        "[sprite]\nsource = \"player\"\nmode = \"background\"\nstrip_height = 4\n",
+    )
+    .unwrap();
+
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("check")
+        .arg(&definition)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Background must be the size of the screen",
+        ));
+
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("check")
+        .arg(&definition)
+        .arg("--allow-any-size")
+        .assert()
+        .success();
+}
+
+/// Copies the fixture font pack into an empty directory so it can be changed
+fn copy_font_fixture(name: &str) -> PathBuf {
+    let directory = output_dir(name);
+    std::fs::remove_dir_all(&directory).unwrap();
+    std::fs::create_dir_all(directory.join("glyphs")).unwrap();
+
+    for file in ["pack.toml", "regular.toml", "glyphs/a.png", "glyphs/b.png"] {
+        std::fs::copy(fixture("font").join(file), directory.join(file)).unwrap();
+    }
+
+    directory
+}
+
+/// Builds the copied font pack, returning whether it was up to date
+fn build_cached(directory: &Path, extra: &[&str]) -> bool {
+    let output = Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("fontpack")
+        .arg("-d")
+        .arg(directory.join("pack.toml"))
+        .arg("-o")
+        .arg(directory.join("pack.bin"))
+        .args(extra)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    String::from_utf8(output).unwrap().contains("up to date")
+}
+
+#[test]
+fn cache_hit() {
+    let directory = copy_font_fixture("cache_hit");
+
+    assert!(!build_cached(&directory, &[]));
+    assert!(directory.join("pack.bin.cache").exists());
+    assert!(build_cached(&directory, &[]));
+    assert!(!build_cached(&directory, &["--force"]));
+}
+
+#[test]
+fn cache_skipped_report_json() {
+    let directory = copy_font_fixture("cache_skipped_report_json");
+    let report = directory.join("report.json");
+    let report_arg = report.to_str().unwrap();
+
+    assert!(!build_cached(&directory, &[]));
+    assert!(!build_cached(&directory, &["--report-json", report_arg]));
+    assert!(report.exists());
+}
+
+#[test]
+fn cache_miss_input_changed() {
+    let directory = copy_font_fixture("cache_miss_input_changed");
+
+    assert!(!build_cached(&directory, &[]));
+
+    let font = directory.join("regular.toml");
+    let raw = std::fs::read_to_string(&font).unwrap();
+    std::fs::write(&font, raw.replace("x_height = 1", "x_height = 2")).unwrap();
+
+    assert!(!build_cached(&directory, &[]));
+    assert!(build_cached(&directory, &[]));
+}
+
+#[test]
+fn cache_miss_output_deleted() {
+    let directory = copy_font_fixture("cache_miss_output_deleted");
+
+    assert!(!build_cached(&directory, &[]));
+    std::fs::remove_file(directory.join("pack.bin")).unwrap();
+    assert!(!build_cached(&directory, &[]));
+    assert!(directory.join("pack.bin").exists());
+}
+
+/// Writes a font pack that defines the same glyph twice
+fn write_duplicate_glyph_pack(directory: &Path) -> PathBuf {
+    let glyph = fixture("font/glyphs/a.png");
+    std::fs::write(
+        directory.join("pack.toml"),
+        "[pack]\nfonts = [\"font\"]\n\n[pack.metadata]\n",
+    )
+    .unwrap();
+    std::fs::write(
+        directory.join("font.toml"),
+        format!(
+            "[font]\nheight = 4\ncap_height = 0\nx_height = 1\nbaseline_height = 3\n\n\
+             [[font.glyphs]]\nindex = \"a\"\nsource = {glyph:?}\n\n\
+             [[font.glyphs]]\nindex = \"a\"\nsource = {glyph:?}\n"
+        ),
+    )
+    .unwrap();
+    directory.join("pack.toml")
+}
+
+// Glyphs load concurrently, but the output mustn't depend on which finishes first
+#[test]
+fn fontpack_jobs_deterministic() {
+    let directory = output_dir("fontpack_jobs_deterministic");
+    let glyphs = (32..127)
+        .map(|index| {
+            let glyph = fixture(if index % 2 == 0 {
+                "font/glyphs/a.png"
+            } else {
+                "font/glyphs/b.png"
+            });
+            format!("[[font.glyphs]]\nindex = {index}\nsource = {glyph:?}\n")
+        })
+        .collect::<String>();
+    std::fs::write(
+        directory.join("pack.toml"),
+        "[pack]\nfonts = [\"font\"]\n\n[pack.metadata]\n",
+    )
+    .unwrap();
+    std::fs::write(
+        directory.join("font.toml"),
+        format!(
+            "[font]\nheight = 4\ncap_height = 0\nx_height = 1\nbaseline_height = 3\n\n{glyphs}"
+        ),
+    )
+    .unwrap();
+
+    let build = |jobs: &str| {
+        let output = directory.join(format!("pack_{jobs}.bin"));
+
+        Command::cargo_bin("ti-asset-builder")
+            .unwrap()
+            .arg("fontpack")
+            .arg("-d")
+            .arg(directory.join("pack.toml"))
+            .arg("-o")
+            .arg(&output)
+            .arg("--jobs")
+            .arg(jobs)
+            .assert()
+            .success();
+
+        std::fs::read(output).unwrap()
+    };
+
+    assert_eq!(build("1"), build("16"));
+}
+
+#[test]
+fn fontpack_strict() {
+    let directory = output_dir("fontpack_strict");
+    let definition = write_duplicate_glyph_pack(&directory);
+    let output = directory.join("pack.bin");
+    let _ = std::fs::remove_file(&output);
+
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("fontpack")
+        .arg("-d")
+        .arg(&definition)
+        .arg("-o")
+        .arg(&output)
+        .assert()
+        .success();
+
+    let build_strict = || {
+        Command::cargo_bin("ti-asset-builder")
+            .unwrap()
+            .arg("--strict")
+            .arg("fontpack")
+            .arg("-d")
+            .arg(&definition)
+            .arg("-o")
+            .arg(&output)
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("Glyph is already defined: 97"));
+    };
+
+    // The cached build's warnings still count
+    build_strict();
+
+    std::fs::remove_file(&output).unwrap();
+    build_strict();
+    assert!(!output.exists());
+}
+
+#[test]
+fn check_strict() {
+    let directory = output_dir("check_strict");
+    let definition = write_duplicate_glyph_pack(&directory);
+
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("check")
+        .arg("--strict")
+        .arg(&definition)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--strict"));
+}
+
+// Every problem in every file is reported
+#[test]
+fn check_broken() {
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("check")
+        .arg(fixture("font/pack.toml"))
+        .arg(fixture("broken/pack.toml"))
+        .arg(fixture("missing.toml"))
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "cap_height is past the font height",
+        ))
+        .stderr(predicate::str::contains(
+            "Glyph height must match the font height",
+        ))
+        .stderr(predicate::str::contains("missing.png"))
+        .stderr(predicate::str::contains("missing.toml"))
+        .stderr(predicate::str::contains(
+            "Found 5 problems across 3 definitions",
+        ));
+}
+
+#[test]
+fn fontpack_inspect() {
+    let output = output_dir("fontpack_inspect").join("pack.bin");
+
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("fontpack")
+        .arg("-d")
+        .arg(fixture("font/pack.toml"))
+        .arg("-o")
+        .arg(&output)
+        .assert()
+        .success();
+
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("fontpack")
+        .arg("inspect")
+        .arg(&output)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("family_name: \"Fixture\""))
+        .stdout(predicate::str::contains("glyphs: 0x61 ('a') .. 0x62 ('b')"));
+
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("fontpack")
+        .arg("inspect")
+        .arg(&output)
+        .arg("--glyph")
+        .arg("a")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("(3x4)"));
+}
+
+// Truncated files report where parsing stopped
+#[test]
+fn fontpack_inspect_truncated() {
+    let output = output_dir("fontpack_inspect_truncated").join("pack.bin");
+    std::fs::write(&output, b"FONTPACK\x0F\x00").unwrap();
+
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("fontpack")
+        .arg("inspect")
+        .arg(&output)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("offset 0x000008"));
+}
+
+#[test]
+fn fontpack_depfile() {
+    let directory = output_dir("fontpack_depfile");
+    let output = directory.join("pack.bin");
+    let depfile = directory.join("pack.d");
+
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("fontpack")
+        .arg("-d")
+        .arg(fixture("font/pack.toml"))
+        .arg("-o")
+        .arg(&output)
+        .arg("--depfile")
+        .arg(&depfile)
+        .assert()
+        .success();
+
+    let depfile = std::fs::read_to_string(depfile).unwrap();
+
+    assert!(depfile.starts_with(&format!("{}:", output.display())));
+
+    for input in [
+        "font/pack.toml",
+        "font/regular.toml",
+        "font/glyphs/a.png",
+        "font/glyphs/b.png",
+    ] {
+        assert!(
+            depfile.contains(&fixture(input).display().to_string()),
+            "Missing {input} from depfile:\n{depfile}"
+        );
+    }
+}
+
+#[test]
+fn sprite_depfile() {
+    let directory = output_dir("sprite_depfile");
+    let depfile = directory.join("player.d");
+
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("sprite")
+        .arg(fixture("sprite/player.toml"))
+        .arg(directory.join("player.bin"))
+        .arg("--depfile")
+        .arg(&depfile)
+        .assert()
+        .success();
+
+    let depfile = std::fs::read_to_string(depfile).unwrap();
+
+    for input in ["sprite/player.toml", "sprite/player.png"] {
+        assert!(
+            depfile.contains(&fixture(input).display().to_string()),
+            "Missing {input} from depfile:\n{depfile}"
+        );
+    }
+}
+
+/// Writes a sprite group definition beside a copy of the player sprite
+fn write_sprite_group(directory: &Path, definition: &str) -> PathBuf {
+    std::fs::copy(fixture("sprite/player.png"), directory.join("player.png")).unwrap();
+
+    let path = directory.join("group.toml");
+    std::fs::write(&path, definition).unwrap();
+    path
+}
+
+#[test]
+fn sprite_group_shared_palette() {
+    let directory = output_dir("sprite_group_shared_palette");
+    let output = directory.join("out");
+    let definition = write_sprite_group(
+        &directory,
+        "shared_palette = true\n\n\
+         [[sprites]]\nname = \"first\"\nsource = \"player\"\n\n\
+         [[sprites]]\nname = \"second\"\nsource = \"player\"\n",
+    );
+
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("sprite")
+        .arg(&definition)
+        .arg(&output)
+        .assert()
+        .success();
+
+    assert!(output.join("first.bin").exists());
+    assert!(output.join("second.bin").exists());
+    assert!(output.join("group.pal").exists());
+    assert!(!output.join("first.pal").exists());
+}
+
+// Groups list each file written as a target, not the output directory
+#[test]
+fn sprite_group_depfile() {
+    let directory = output_dir("sprite_group_depfile");
+    let output = directory.join("out");
+    let depfile = directory.join("group.d");
+    let definition = write_sprite_group(
+        &directory,
+        "[[sprites]]\nname = \"first\"\nsource = \"player\"\n\n\
+         [[sprites]]\nname = \"second\"\nsource = \"player\"\n",
+    );
+
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("sprite")
+        .arg(&definition)
+        .arg(&output)
+        .arg("--depfile")
+        .arg(&depfile)
+        .assert()
+        .success();
+
+    let depfile = std::fs::read_to_string(depfile).unwrap();
+    let (targets, _) = depfile.split_once(": ").unwrap();
+    let targets = targets.split(' ').collect::<Vec<_>>();
+
+    assert_eq!(
+        targets.len(),
+        4,
+        "Unexpected targets in depfile:\n{depfile}"
+    );
+
+    for file in ["first.bin", "first.pal", "second.bin", "second.pal"] {
+        assert!(
+            targets.contains(&output.join(file).display().to_string().as_str()),
+            "Missing {file} from depfile targets:\n{depfile}"
+        );
+    }
+}
+
+/// Builds a sprite group whose first sprite is missing, returning whether the second was written
+fn build_sprite_group_failure(name: &str, extra: &[&str]) -> bool {
+    let directory = output_dir(name);
+    let output = directory.join("out");
+    let definition = write_sprite_group(
+        &directory,
+        "[[sprites]]\nname = \"missing\"\nsource = \"missing\"\n\n\
+         [[sprites]]\nname = \"player\"\nsource = \"player\"\n",
+    );
+
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("sprite")
+        .arg(&definition)
+        .arg(&output)
+        .args(extra)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Failed to build sprite 0: \"missing\"",
+        ));
+
+    output.join("player.bin").exists()
+}
+
+#[test]
+fn sprite_group_failure() {
+    assert!(!build_sprite_group_failure("sprite_group_failure", &[]));
+}
+
+#[test]
+fn sprite_group_failure_keep_going() {
+    assert!(build_sprite_group_failure(
+        "sprite_group_failure_keep_going",
+        &["--keep-going"]
+    ));
+}
+
+#[test]
+fn fontpack_report_json() {
+    let directory = output_dir("fontpack_report_json");
+    let output = directory.join("pack.bin");
+    let report = directory.join("report.json");
+
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("fontpack")
+        .arg("-d")
+        .arg(fixture("font/pack.toml"))
+        .arg("-o")
+        .arg(&output)
+        .arg("--report-json")
+        .arg(&report)
+        .assert()
+        .success();
+
+    let report =
+        serde_json::from_str::<serde_json::Value>(&std::fs::read_to_string(report).unwrap())
+            .unwrap();
+    let size = std::fs::metadata(&output).unwrap().len();
+
+    assert_eq!(report["schema_version"], 1);
+    assert_eq!(report["inputs"].as_array().unwrap().len(), 4);
+    assert_eq!(report["inputs"][0]["sha256"].as_str().unwrap().len(), 64);
+    assert_eq!(report["outputs"][0]["path"], output.display().to_string());
+    assert_eq!(report["outputs"][0]["size"], size);
+    assert_eq!(report["outputs"][0]["sections"][0]["name"], "header");
+
+    let section_sizes = report["outputs"][0]["sections"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|section| section["size"].as_u64().unwrap())
+        .sum::<u64>();
+
+    assert_eq!(section_sizes, size);
+    assert!(report["wall_time_ms"].is_u64());
+}
+
+/// Writes a project file with a font pack and a sprite, plus any extra assets
+fn write_project(directory: &Path, extra: &str) -> PathBuf {
+    let project = directory.join("project.toml");
+    let font = fixture("font/pack.toml");
+    let sprite = fixture("sprite/player.toml");
+
+    std::fs::write(
+        &project,
+        format!(
+            "[[assets]]\n\
+             kind = \"fontpack\"\n\
+             definition = {font:?}\n\
+             output = \"out/fonts.bin\"\n\
+             \n\
+             [[assets]]\n\
+             kind = \"sprite\"\n\
+             definition = {sprite:?}\n\
+             output = \"out/player.bin\"\n\
+             {extra}"
+        ),
+    )
+    .unwrap();
+    std::fs::create_dir_all(directory.join("out")).unwrap();
+
+    project
+}
+
+#[test]
+fn build_project() {
+    let directory = output_dir("build_project");
+    let project = write_project(&directory, "");
+
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("build")
+        .arg(&project)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Built 2 of 2 assets"));
+
+    assert!(
+        std::fs::read(directory.join("out/fonts.bin"))
+            .unwrap()
+            .starts_with(b"FONTPACK")
+    );
+    assert!(directory.join("out/player.bin").exists());
+    assert!(directory.join("out/player.pal").exists());
+}
+
+// A failed asset doesn't stop the others from building
+#[test]
+fn build_project_failure() {
+    let directory = output_dir("build_project_failure");
+    let broken = fixture("broken/pack.toml");
+    let project = write_project(
+        &directory,
+        &format!(
+            "\n\
+             [[assets]]\n\
+             kind = \"fontpack\"\n\
+             definition = {broken:?}\n\
+             output = \"out/broken.bin\"\n"
+        ),
+    );
+
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("build")
+        .arg(&project)
+        .arg("--jobs")
+        .arg("1")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("Built 2 of 3 assets"))
+        .stdout(predicate::str::contains("failed  fontpack"))
+        .stderr(predicate::str::contains("1 of 3 assets failed to build"));
+
+    assert!(directory.join("out/fonts.bin").exists());
+    assert!(!directory.join("out/broken.bin").exists());
+}
+
+// With one job, nothing after the failed asset is started
+#[test]
+fn build_project_fail_fast() {
+    let directory = output_dir("build_project_fail_fast");
+    let project = directory.join("project.toml");
+    let broken = fixture("broken/pack.toml");
+    let font = fixture("font/pack.toml");
+    let sprite = fixture("sprite/player.toml");
+
+    std::fs::write(
+        &project,
+        format!(
+            "[[assets]]\n\
+             kind = \"fontpack\"\n\
+             definition = {broken:?}\n\
+             output = \"out/broken.bin\"\n\
+             \n\
+             [[assets]]\n\
+             kind = \"fontpack\"\n\
+             definition = {font:?}\n\
+             output = \"out/fonts.bin\"\n\
+             \n\
+             [[assets]]\n\
+             kind = \"sprite\"\n\
+             definition = {sprite:?}\n\
+             output = \"out/player.bin\"\n"
+        ),
+    )
+    .unwrap();
+    std::fs::create_dir_all(directory.join("out")).unwrap();
+
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("build")
+        .arg(&project)
+        .arg("--jobs")
+        .arg("1")
+        .arg("--fail-fast")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("failed  fontpack"))
+        .stdout(predicate::str::contains("skipped fontpack"))
+        .stdout(predicate::str::contains("skipped sprite"))
+        .stdout(predicate::str::contains("Built 0 of 3 assets"))
+        .stderr(predicate::str::contains("1 of 3 assets failed to build"));
+
+    assert!(!directory.join("out/fonts.bin").exists());
+    assert!(!directory.join("out/player.bin").exists());
+}
+
+#[test]
+fn convert_fontpack() {
+    let directory = output_dir("convert_fontpack");
+    let binary = directory.join("pack.bin");
+    let header = directory.join("pack.h");
+
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("fontpack")
+        .arg("-d")
+        .arg(fixture("font/pack.toml"))
+        .arg("-o")
+        .arg(&binary)
+        .assert()
+        .success();
+
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("convert")
+        .arg(&binary)
+        .arg(&header)
+        .arg("-f")
+        .arg("c")
+        .arg("--name")
+        .arg("fixture_font")
+        .assert()
+        .success();
+
+    let header = std::fs::read_to_string(header).unwrap();
+
+    assert!(header.contains("static const unsigned char fixture_font["));
+    assert!(header.contains("#define fixture_font_var_name \"fixturef\""));
+    assert!(header.contains("// font_0_bitmaps"));
+    assert!(header.contains("#define fixture_font_metadata_offset 15"));
+}
+
+#[test]
+fn convert_unknown() {
+    let directory = output_dir("convert_unknown");
+    let binary = directory.join("data.bin");
+    let assembly = directory.join("data.asm");
+    std::fs::write(&binary, [1, 2, 3]).unwrap();
+
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("convert")
+        .arg(&binary)
+        .arg(&assembly)
+        .arg("-f")
+        .arg("assembly")
+        .assert()
+        .success();
+
+    assert_eq!(
+        std::fs::read_to_string(assembly).unwrap(),
+        "; Generated by ti-asset-builder\ndata_var_name := \"data\"\ndata_size := 3\n\ndata:\n\tdb $01, $02, $03\n"
+    );
+}
+
+// Directories get a file named after the definition
+#[test]
+fn fontpack_output_directory() {
+    let directory = output_dir("fontpack_output_directory");
+
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("fontpack")
+        .arg("-d")
+        .arg(fixture("font/pack.toml"))
+        .arg("-o")
+        .arg(&directory)
+        .arg("--force")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("pack.bin"));
+
+    assert!(
+        std::fs::read(directory.join("pack.bin"))
+            .unwrap()
+            .starts_with(b"FONTPACK")
+    );
+}
+
+// Missing parent directories are created
+#[test]
+fn fontpack_output_missing_parent() {
+    let output = output_dir("fontpack_output_missing_parent").join("missing/fonts.bin");
+    let _ = std::fs::remove_dir_all(output.parent().unwrap());
+
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("fontpack")
+        .arg("-d")
+        .arg(fixture("font/pack.toml"))
+        .arg("-o")
+        .arg(&output)
+        .assert()
+        .success();
+
+    assert!(output.exists());
+}
+
+#[test]
+fn sprite_output_directory() {
+    let directory = output_dir("sprite_output_directory");
+
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("sprite")
+        .arg(fixture("sprite/player.toml"))
+        .arg(&directory)
+        .assert()
+        .success();
+
+    assert!(directory.join("player.bin").exists());
+    assert!(directory.join("player.pal").exists());
+}
+
+#[test]
+fn fontpack_stdin() {
+    let output = output_dir("fontpack_stdin").join("pack.bin");
+    let definition = std::fs::read_to_string(fixture("font/pack.toml")).unwrap();
+
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("fontpack")
+        .arg("-d")
+        .arg("-")
+        .arg("-o")
+        .arg(&output)
+        .arg("--base-dir")
+        .arg(fixture("font"))
+        .write_stdin(definition)
+        .assert()
+        .success();
+
+    assert!(std::fs::read(output).unwrap().starts_with(b"FONTPACK"));
+}
+
+#[test]
+fn fontpack_stdin_without_base_dir() {
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("fontpack")
+        .arg("-d")
+        .arg("-")
+        .arg("-o")
+        .arg("pack.bin")
+        .write_stdin("[pack]")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("requires `--base-dir`"));
+}
+
+#[test]
+fn sprite_stdin() {
+    let output = output_dir("sprite_stdin").join("player.bin");
+    let definition = std::fs::read_to_string(fixture("sprite/player.toml")).unwrap();
+
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("sprite")
+        .arg("-")
+        .arg(&output)
+        .arg("--base-dir")
+        .arg(fixture("sprite"))
+        .write_stdin(definition)
+        .assert()
+        .success();
+
+    assert!(output.exists());
+}
+
+#[test]
+fn exit_code_usage() {
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("fontpack")
+        .arg("--unknown")
+        .assert()
+        .code(1);
+
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("--help")
+        .assert()
+        .code(0)
+        .stdout(predicate::str::contains("Exit codes:"));
+}
+
+#[test]
+fn exit_code_definition() {
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("check")
+        .arg(fixture("broken/broken.toml"))
+        .assert()
+        .code(2);
+}
+
+#[test]
+fn exit_code_missing_input() {
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("fontpack")
+        .arg("-d")
+        .arg(fixture("missing.toml"))
+        .arg("-o")
+        .arg(output_dir("exit_code_missing_input").join("pack.bin"))
+        .assert()
+        .code(3);
+}
+
+#[test]
+fn exit_code_io() {
+    // The output's parent is a file, so it can't be created
+    let file = output_dir("exit_code_io").join("file");
+    std::fs::write(&file, "").unwrap();
+
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("fontpack")
+        .arg("-d")
+        .arg(fixture("font/pack.toml"))
+        .arg("-o")
+        .arg(file.join("pack.bin"))
+        .assert()
+        .code(4);
+}
+
+/// Writes a font pack whose glyph is only found in the fixture's parent directory
+fn write_shared_glyph_pack(directory: &Path) -> PathBuf {
+    std::fs::write(
+        directory.join("pack.toml"),
+        "[pack]\nfonts = [\"font\"]\n\n[pack.metadata]\n",
+    )
+    .unwrap();
+    std::fs::write(
+        directory.join("font.toml"),
+        "[font]\nheight = 4\ncap_height = 0\nx_height = 1\nbaseline_height = 3\n\n\
+         [[font.glyphs]]\nindex = \"a\"\nsource = \"font/glyphs/a\"\n",
+    )
+    .unwrap();
+    directory.join("pack.toml")
+}
+
+// Glyphs missing beside their font are searched for in the asset paths
+#[test]
+fn fontpack_asset_path() {
+    let directory = output_dir("fontpack_asset_path");
+    let pack = write_shared_glyph_pack(&directory);
+
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("check")
+        .arg(&pack)
+        .env_remove("TI_ASSET_PATH")
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("Tried:"));
+
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("check")
+        .arg(&pack)
+        .arg("--asset-path")
+        .arg(fixture(""))
+        .env_remove("TI_ASSET_PATH")
+        .assert()
+        .success();
+
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("fontpack")
+        .arg("-d")
+        .arg(&pack)
+        .arg("-o")
+        .arg(directory.join("pack.bin"))
+        .arg("--force")
+        .env("TI_ASSET_PATH", fixture(""))
+        .assert()
+        .success();
+}
+
+#[test]
+fn check_schema_newer() {
+    let directory = output_dir("check_schema_newer");
+    let pack = directory.join("pack.toml");
+    std::fs::write(
+        &pack,
+        "schema = 99\n\n[pack]\nfonts = []\n\n[pack.metadata]\n",
+    )
+    .unwrap();
+
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("check")
+        .arg(&pack)
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("Please upgrade ti-asset-builder"));
+}
+
+#[test]
+fn init_fontpack() {
+    let directory = output_dir("init_fontpack");
+    std::fs::remove_dir_all(&directory).unwrap();
+
+    let init = || {
+        let mut command = Command::cargo_bin("ti-asset-builder").unwrap();
+        command
+            .arg("init")
+            .arg("fontpack")
+            .arg(&directory)
+            .arg("--glyphs")
+            .arg("a..c");
+        command
+    };
+
+    init().assert().success();
+    assert!(directory.join("glyphs/98.png").exists());
+
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("--strict")
+        .arg("check")
+        .arg(directory.join("pack.toml"))
+        .assert()
+        .success();
+
+    init()
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Refusing to overwrite"));
+    init().arg("--force").assert().success();
+}
+
+#[test]
+fn init_sprite() {
+    let directory = output_dir("init_sprite");
+    std::fs::remove_dir_all(&directory).unwrap();
+
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("init")
+        .arg("sprite")
+        .arg(&directory)
+        .arg("--placeholder")
+        .arg("box")
+        .assert()
+        .success();
+
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("--strict")
+        .arg("check")
+        .arg(directory.join("sprite.toml"))
+        .assert()
+        .success();
+}
+
+#[test]
+fn fontpack_diff() {
+    let directory = copy_font_fixture("fontpack_diff");
+    let old = directory.join("old.bin");
+    let new = directory.join("pack.bin");
+
+    build_cached(&directory, &[]);
+    std::fs::copy(&new, &old).unwrap();
+    std::fs::copy(
+        directory.join("glyphs/a.png"),
+        directory.join("glyphs/b.png"),
+    )
+    .unwrap();
+    build_cached(&directory, &[]);
+
+    let output = Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("fontpack")
+        .arg("diff")
+        .arg(&old)
+        .arg(&new)
+        .arg("--exit-code")
+        .assert()
+        .code(6)
+        .get_output()
+        .stdout
+        .clone();
+    let output = String::from_utf8(output).unwrap();
+
+    assert!(output.starts_with("Font 0:\n"), "{output}");
+    assert!(output.contains("glyph 0x62 ('b')"), "{output}");
+    assert!(!output.contains("0x61"), "{output}");
+
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("fontpack")
+        .arg("diff")
+        .arg(&new)
+        .arg(&new)
+        .arg("--exit-code")
+        .assert()
+        .success()
+        .stdout("Font packs are identical\n");
+}
+
+#[test]
+fn fontpack_invalid_var_name() {
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("fontpack")
+        .arg("-d")
+        .arg(fixture("font/pack.toml"))
+        .arg("-o")
+        .arg(output_dir("fontpack_invalid_var_name").join("pack.bin"))
+        .arg("--var-name")
+        .arg("TOOLONGNAME")
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("Invalid variable name"));
+}
+
+#[test]
+fn fontpack_over_budget() {
+    Command::cargo_bin("ti-asset-builder")
+        .unwrap()
+        .arg("fontpack")
+        .arg("-d")
+        .arg(fixture("font/pack.toml"))
+        .arg("-o")
+        .arg(output_dir("fontpack_over_budget").join("pack.bin"))
+        .arg("--budget")
+        .arg("16")
+        .assert()
+        .code(5)
+        .stderr(predicate::str::contains("Font pack is over budget"));
+}